@@ -26,7 +26,9 @@ class ExportArmatureToJSON(bpy.types.Operator):
             armatureJSON = {
                 'actions': {},
                 'inverse_bind_poses': [],
-                'joint_index': {}
+                'joint_index': {},
+                'bone_parents': [],
+                'local_rest_poses': []
             }
 
             # Get the armature that is currently active. We will be parsing it's actions
@@ -110,6 +112,20 @@ class ExportArmatureToJSON(bpy.types.Operator):
 
                 armatureJSON['inverse_bind_poses'].append({'Matrix': matrixToArray(boneInverseBind)})
 
+            # Record each bone's parent and its rest pose relative to that parent so that poses can
+            # be converted between local and armature space
+            for boneName in allBoneNames:
+                bone = activeArmature.pose.bones[boneName].bone
+
+                if bone.parent is None:
+                    armatureJSON['bone_parents'].append(None)
+                    localRestMatrix = bone.matrix_local
+                else:
+                    armatureJSON['bone_parents'].append(allBoneNames.index(bone.parent.name))
+                    localRestMatrix = bone.parent.matrix_local.inverted() @ bone.matrix_local
+
+                armatureJSON['local_rest_poses'].append({'Matrix': matrixToArray(localRestMatrix)})
+
             # Now we create the JSON for the joint name indices. The bind poses and keyframe poses are
             # arrays of index 0...numBones - 1. To look up a bone in this array you use its joint name index
             for index, boneName in enumerate(allBoneNames):
@@ -0,0 +1,261 @@
+//! Interpolating dual quaternion bones.
+//!
+//! Our dual quaternions are stored as `[w, x, y, z, dual_w, dual_x, dual_y, dual_z]`, with the
+//! rotation (real part) first and the translation (dual part) second.
+//!
+//! @see https://www.cs.utah.edu/~ladislav/kavan07skinning/kavan07skinning.pdf
+
+use nalgebra::{Quaternion, Vector3};
+
+/// How dual quaternion bones get interpolated.
+///
+/// Both methods take the shortest path between the two bones.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DualQuatInterpolation {
+    /// Dual quaternion linear blending (DLB). The bones are lerped and then normalized, which is
+    /// fast and very close to screw linear interpolation for nearby keyframes.
+    #[default]
+    Linear,
+    /// Screw linear interpolation (ScLERP). The bone moves along the screw motion between the
+    /// two bones at a constant speed, at the cost of some trigonometry.
+    ScrewLinear,
+}
+
+/// Interpolate `amount` of the way from `start` to `end`
+pub fn interpolate(
+    start: &[f32; 8],
+    end: &[f32; 8],
+    amount: f32,
+    method: DualQuatInterpolation,
+) -> [f32; 8] {
+    let end = shortest_path(start, end);
+
+    match method {
+        DualQuatInterpolation::Linear => {
+            let mut interpolated = [0.0; 8];
+
+            for index in 0..8 {
+                interpolated[index] = (end[index] - start[index]) * amount + start[index];
+            }
+
+            normalize(&interpolated)
+        }
+        DualQuatInterpolation::ScrewLinear => screw_linear(start, &end, amount),
+    }
+}
+
+/// `q` and `-q` are the same transform. If the two rotations are in opposite hemispheres we
+/// negate the end dual quaternion so that we interpolate along the shortest path.
+/// http://www.xbdev.net/misc_demos/demos/dual_quaternions_beyond/paper.pdf
+pub fn shortest_path(start: &[f32; 8], end: &[f32; 8]) -> [f32; 8] {
+    let mut end = *end;
+
+    if real(start).dot(&real(&end)) < 0.0 {
+        for component in end.iter_mut() {
+            *component = -*component;
+        }
+    }
+
+    end
+}
+
+/// Give the real part unit length and make the dual part orthogonal to it, so that the dual
+/// quaternion is a rigid transform again.
+///
+/// Dual quaternions with no rotation can't be normalized, so they are returned as is.
+pub fn normalize(dual_quat: &[f32; 8]) -> [f32; 8] {
+    let real = real(dual_quat);
+    let length = real.norm();
+
+    if length == 0.0 {
+        return *dual_quat;
+    }
+
+    let real = real / length;
+    let dual = dual(dual_quat) / length;
+    let dual = dual - real * real.dot(&dual);
+
+    from_parts(&real, &dual)
+}
+
+/// `start * (start⁻¹ * end) ^ amount`, raising the difference between the two to a power
+/// through its screw parameters
+fn screw_linear(start: &[f32; 8], end: &[f32; 8], amount: f32) -> [f32; 8] {
+    let start = normalize(start);
+    let end = normalize(end);
+
+    let (real_difference, dual_difference) = multiply(&conjugate(&start), &end);
+
+    let (real_power, dual_power) = match screw(&real_difference, &dual_difference) {
+        Some(screw) => screw.power(amount),
+        // Without any rotation the difference is a pure translation, which scales linearly
+        None => (Quaternion::identity(), dual_difference * amount),
+    };
+
+    let (real_start, dual_start) = (real(&start), dual(&start));
+    let real_end = real_start * real_power;
+    let dual_end = real_start * dual_power + dual_start * real_power;
+
+    from_parts(&real_end, &dual_end)
+}
+
+/// A rotation of `angle` radians around the line through `moment` with direction `axis`,
+/// combined with a translation of `pitch` along it
+struct Screw {
+    angle: f32,
+    pitch: f32,
+    axis: Vector3<f32>,
+    moment: Vector3<f32>,
+}
+
+/// The screw motion of a unit dual quaternion, or `None` if it doesn't rotate
+fn screw(real: &Quaternion<f32>, dual: &Quaternion<f32>) -> Option<Screw> {
+    let sin_half_angle = real.vector().norm();
+    if sin_half_angle < 1.0e-6 {
+        return None;
+    }
+
+    let angle = 2.0 * real.w.clamp(-1.0, 1.0).acos();
+    let axis = real.vector() / sin_half_angle;
+
+    let translation = (dual * real.conjugate() * 2.0).vector().into_owned();
+    let pitch = translation.dot(&axis);
+    let moment =
+        (translation.cross(&axis) + (translation - axis * pitch) * (real.w / sin_half_angle)) * 0.5;
+
+    Some(Screw {
+        angle,
+        pitch,
+        axis,
+        moment,
+    })
+}
+
+impl Screw {
+    /// The real and dual parts of the dual quaternion that moves `amount` of the way along the
+    /// screw
+    fn power(&self, amount: f32) -> (Quaternion<f32>, Quaternion<f32>) {
+        let half_angle = self.angle * amount / 2.0;
+        let half_pitch = self.pitch * amount / 2.0;
+        let (sin, cos) = half_angle.sin_cos();
+
+        let real = Quaternion::from_parts(cos, self.axis * sin);
+        let dual = Quaternion::from_parts(
+            -half_pitch * sin,
+            self.moment * sin + self.axis * (half_pitch * cos),
+        );
+
+        (real, dual)
+    }
+}
+
+fn multiply(lhs: &[f32; 8], rhs: &[f32; 8]) -> (Quaternion<f32>, Quaternion<f32>) {
+    (
+        real(lhs) * real(rhs),
+        real(lhs) * dual(rhs) + dual(lhs) * real(rhs),
+    )
+}
+
+/// The inverse of a unit dual quaternion
+fn conjugate(dual_quat: &[f32; 8]) -> [f32; 8] {
+    from_parts(&real(dual_quat).conjugate(), &dual(dual_quat).conjugate())
+}
+
+fn real(dual_quat: &[f32; 8]) -> Quaternion<f32> {
+    Quaternion::new(dual_quat[0], dual_quat[1], dual_quat[2], dual_quat[3])
+}
+
+fn dual(dual_quat: &[f32; 8]) -> Quaternion<f32> {
+    Quaternion::new(dual_quat[4], dual_quat[5], dual_quat[6], dual_quat[7])
+}
+
+fn from_parts(real: &Quaternion<f32>, dual: &Quaternion<f32>) -> [f32; 8] {
+    [
+        real.w, real.i, real.j, real.k, dual.w, dual.i, dual.j, dual.k,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlenderArmature, Bone};
+    use nalgebra::{Matrix4, Vector3};
+    use std::f32::consts::PI;
+
+    /// A rotation around `axis`, followed by a translation
+    fn rigid(axis: Vector3<f32>, angle: f32, translation: Vector3<f32>) -> [f32; 8] {
+        let matrix4 = Matrix4::from_axis_angle(&nalgebra::Unit::new_normalize(axis), angle)
+            .append_translation(&translation);
+
+        let mut matrix = [0.0; 16];
+        matrix.copy_from_slice(matrix4.as_slice());
+
+        match BlenderArmature::matrix_to_dual_quat(&Bone::Matrix(matrix)) {
+            Bone::DualQuat(dual_quat) => dual_quat,
+            Bone::Matrix(_) => unreachable!(),
+        }
+    }
+
+    fn assert_dual_quat_eq(actual: &[f32; 8], expected: &[f32; 8]) {
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1.0e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    /// Halfway through a quarter turn around z (while moving along z) is an eighth of a turn
+    /// with half of the translation
+    #[test]
+    fn screw_linear_halfway() {
+        let start = rigid(Vector3::z(), 0.0, Vector3::new(0.0, 0.0, 0.0));
+        let end = rigid(Vector3::z(), PI / 2.0, Vector3::new(0.0, 0.0, 2.0));
+
+        let halfway = interpolate(&start, &end, 0.5, DualQuatInterpolation::ScrewLinear);
+
+        assert_dual_quat_eq(
+            &halfway,
+            &rigid(Vector3::z(), PI / 4.0, Vector3::new(0.0, 0.0, 1.0)),
+        );
+    }
+
+    /// A pure translation is interpolated linearly
+    #[test]
+    fn screw_linear_translation() {
+        let start = rigid(Vector3::z(), 0.0, Vector3::new(0.0, 0.0, 0.0));
+        let end = rigid(Vector3::z(), 0.0, Vector3::new(4.0, 0.0, 0.0));
+
+        for method in [
+            DualQuatInterpolation::Linear,
+            DualQuatInterpolation::ScrewLinear,
+        ]
+        .iter()
+        {
+            assert_dual_quat_eq(
+                &interpolate(&start, &end, 0.25, *method),
+                &rigid(Vector3::z(), 0.0, Vector3::new(1.0, 0.0, 0.0)),
+            );
+        }
+    }
+
+    /// `q` and `-q` are the same transform, so interpolating between them shouldn't move
+    #[test]
+    fn shortest_path_between_opposite_signs() {
+        let start = rigid(
+            Vector3::new(1.0, 2.0, 3.0),
+            1.0,
+            Vector3::new(1.0, 2.0, 3.0),
+        );
+        let mut end = start;
+        for component in end.iter_mut() {
+            *component = -*component;
+        }
+
+        for method in [
+            DualQuatInterpolation::Linear,
+            DualQuatInterpolation::ScrewLinear,
+        ]
+        .iter()
+        {
+            assert_dual_quat_eq(&interpolate(&start, &end, 0.5, *method), &start);
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! The parent / child relationships between an armature's bones, and converting poses between
+//! the spaces that they can be expressed in.
+//!
+//! - **Local space** - relative to the bone's parent (or to the armature for root bones). This
+//!   is the space to make procedural edits or layer additive animation in, since moving a bone
+//!   carries its children along with it.
+//! - **Armature space** - relative to the armature's origin. The keyframes exported from Blender
+//!   are in armature space.
+//! - **World space** - armature space moved by the armature's own world transform.
+//!
+//! Matrix bones are expected to be column major, as they are after
+//! [`BlenderArmature.transpose_actions`].
+//!
+//! [`BlenderArmature.transpose_actions`]: struct.BlenderArmature.html#method.transpose_actions
+
+use crate::BlenderArmature;
+use crate::Bone;
+use nalgebra::Matrix4;
+
+/// Something is wrong with an armature's bone hierarchy
+#[derive(Debug, Fail)]
+pub enum HierarchyError {
+    /// A joint's parent isn't one of the bones being converted
+    #[fail(
+        display = "Joint {} has parent {} but there are only {} bones",
+        joint_index, parent_index, bone_count
+    )]
+    ParentOutOfRange {
+        joint_index: u8,
+        parent_index: u8,
+        bone_count: usize,
+    },
+    /// Following a joint's parents leads back to the joint
+    #[fail(display = "Joint {} is its own ancestor", joint_index)]
+    Cycle { joint_index: u8 },
+    /// A joint's parent has a zero scale, so the joint can't be expressed relative to it
+    #[fail(display = "The parent of joint {} can't be inverted", joint_index)]
+    SingularParent { joint_index: u8 },
+    /// The world matrix has a zero scale, so world space bones can't be brought back into
+    /// armature space
+    #[fail(display = "The world matrix can't be inverted")]
+    SingularWorldMatrix,
+}
+
+impl BlenderArmature {
+    /// The joint index of a bone's parent, or `None` for a root bone.
+    ///
+    /// Armatures exported before parents were recorded treat every bone as a root.
+    pub fn parent(&self, joint_index: u8) -> Option<u8> {
+        self.bone_parents
+            .get(joint_index as usize)
+            .cloned()
+            .unwrap_or(None)
+    }
+
+    /// The joint indices of a bone's direct children
+    pub fn children(&self, joint_index: u8) -> Vec<u8> {
+        (0..self.bone_parents.len())
+            .map(|child| child as u8)
+            .filter(|child| self.parent(*child) == Some(joint_index))
+            .collect()
+    }
+
+    /// The armature space rest pose, built up from the local rest poses
+    pub fn rest_pose(&self) -> Result<Vec<Bone>, HierarchyError> {
+        self.local_to_armature_space(&self.local_rest_poses)
+    }
+
+    /// Convert a pose (one bone per joint) from local space into armature space.
+    ///
+    /// Dual quaternion bones stay dual quaternions.
+    pub fn local_to_armature_space(&self, local: &[Bone]) -> Result<Vec<Bone>, HierarchyError> {
+        let mut armature = local.iter().map(bone_to_matrix).collect::<Vec<_>>();
+
+        for joint in self.parents_first(local.len())? {
+            if let Some(parent) = self.parent(joint as u8) {
+                armature[joint] = armature[parent as usize] * armature[joint];
+            }
+        }
+
+        Ok(local
+            .iter()
+            .zip(armature.iter())
+            .map(|(bone, matrix)| matrix_to_bone(matrix, bone))
+            .collect())
+    }
+
+    /// Convert a pose (one bone per joint) from armature space into local space, such as an
+    /// action's keyframe bones.
+    ///
+    /// Dual quaternion bones stay dual quaternions.
+    pub fn armature_to_local_space(&self, armature: &[Bone]) -> Result<Vec<Bone>, HierarchyError> {
+        let armature_matrices = armature.iter().map(bone_to_matrix).collect::<Vec<_>>();
+        let mut local = armature_matrices.clone();
+
+        for joint in self.parents_first(armature.len())? {
+            if let Some(parent) = self.parent(joint as u8) {
+                let parent_inverse = armature_matrices[parent as usize].try_inverse().ok_or(
+                    HierarchyError::SingularParent {
+                        joint_index: joint as u8,
+                    },
+                )?;
+
+                local[joint] = parent_inverse * armature_matrices[joint];
+            }
+        }
+
+        Ok(armature
+            .iter()
+            .zip(local.iter())
+            .map(|(bone, matrix)| matrix_to_bone(matrix, bone))
+            .collect())
+    }
+
+    /// Move armature space bones by the armature's (column major) world matrix
+    pub fn armature_to_world_space(armature: &[Bone], world_matrix: &[f32; 16]) -> Vec<Bone> {
+        let mut world = Matrix4::identity();
+        world.copy_from_slice(world_matrix);
+
+        armature
+            .iter()
+            .map(|bone| matrix_to_bone(&(world * bone_to_matrix(bone)), bone))
+            .collect()
+    }
+
+    /// Bring world space bones back into armature space, given the armature's (column major)
+    /// world matrix
+    pub fn world_to_armature_space(
+        world: &[Bone],
+        world_matrix: &[f32; 16],
+    ) -> Result<Vec<Bone>, HierarchyError> {
+        let mut world_matrix4 = Matrix4::identity();
+        world_matrix4.copy_from_slice(world_matrix);
+
+        let world_inverse = world_matrix4
+            .try_inverse()
+            .ok_or(HierarchyError::SingularWorldMatrix)?;
+
+        Ok(world
+            .iter()
+            .map(|bone| matrix_to_bone(&(world_inverse * bone_to_matrix(bone)), bone))
+            .collect())
+    }
+
+    /// The joints of a pose with `bone_count` bones, ordered so that every parent comes before
+    /// its children
+    fn parents_first(&self, bone_count: usize) -> Result<Vec<usize>, HierarchyError> {
+        let mut depths = Vec::with_capacity(bone_count);
+
+        for joint in 0..bone_count {
+            let mut depth = 0;
+            let mut ancestor = joint as u8;
+
+            while let Some(parent) = self.parent(ancestor) {
+                if parent as usize >= bone_count {
+                    return Err(HierarchyError::ParentOutOfRange {
+                        joint_index: ancestor,
+                        parent_index: parent,
+                        bone_count,
+                    });
+                }
+
+                depth += 1;
+                if depth > bone_count {
+                    return Err(HierarchyError::Cycle {
+                        joint_index: joint as u8,
+                    });
+                }
+
+                ancestor = parent;
+            }
+
+            depths.push(depth);
+        }
+
+        let mut joints = (0..bone_count).collect::<Vec<_>>();
+        joints.sort_by_key(|joint| depths[*joint]);
+
+        Ok(joints)
+    }
+}
+
+/// A bone as a column major matrix
+pub(crate) fn bone_to_matrix(bone: &Bone) -> Matrix4<f32> {
+    let mut matrix = Matrix4::identity();
+    matrix.copy_from_slice(BlenderArmature::dual_quat_to_matrix(bone).as_slice());
+    matrix
+}
+
+/// The matrix as the same kind of bone as `like`
+pub(crate) fn matrix_to_bone(matrix: &Matrix4<f32>, like: &Bone) -> Bone {
+    let mut array = [0.0; 16];
+    array.copy_from_slice(matrix.as_slice());
+
+    match like {
+        Bone::Matrix(_) => Bone::Matrix(array),
+        Bone::DualQuat(_) => BlenderArmature::matrix_to_dual_quat(&Bone::Matrix(array)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+    use std::f32::consts::PI;
+
+    /// A rotation around z followed by a translation
+    fn matrix(angle: f32, translation: [f32; 3]) -> [f32; 16] {
+        let matrix4 = Matrix4::from_axis_angle(&Vector3::z_axis(), angle).append_translation(
+            &Vector3::new(translation[0], translation[1], translation[2]),
+        );
+
+        let mut matrix = [0.0; 16];
+        matrix.copy_from_slice(matrix4.as_slice());
+        matrix
+    }
+
+    fn assert_bones_eq(actual: &[Bone], expected: &[Bone]) {
+        assert_eq!(actual.len(), expected.len());
+
+        for (actual, expected) in actual.iter().zip(expected.iter()) {
+            for (a, e) in actual.as_slice().iter().zip(expected.as_slice().iter()) {
+                assert!((a - e).abs() < 1.0e-5, "{:?} != {:?}", actual, expected);
+            }
+        }
+    }
+
+    /// A three bone arm with the bones listed children first. Each bone is one unit along its
+    /// parent's x axis and turned a quarter turn from its parent.
+    fn arm() -> BlenderArmature {
+        BlenderArmature {
+            bone_parents: vec![Some(1), Some(2), None],
+            local_rest_poses: vec![
+                Bone::Matrix(matrix(PI / 2.0, [1.0, 0.0, 0.0])),
+                Bone::Matrix(matrix(PI / 2.0, [1.0, 0.0, 0.0])),
+                Bone::Matrix(matrix(0.0, [0.0, 0.0, 0.0])),
+            ],
+            ..BlenderArmature::default()
+        }
+    }
+
+    #[test]
+    fn parents_and_children() {
+        let armature = arm();
+
+        assert_eq!(armature.parent(0), Some(1));
+        assert_eq!(armature.parent(2), None);
+        assert_eq!(armature.children(2), vec![1]);
+        assert!(armature.children(0).is_empty());
+    }
+
+    #[test]
+    fn rest_pose_follows_parents() {
+        let rest_pose = arm().rest_pose().unwrap();
+
+        assert_bones_eq(
+            &rest_pose,
+            &[
+                Bone::Matrix(matrix(PI, [1.0, 1.0, 0.0])),
+                Bone::Matrix(matrix(PI / 2.0, [1.0, 0.0, 0.0])),
+                Bone::Matrix(matrix(0.0, [0.0, 0.0, 0.0])),
+            ],
+        );
+    }
+
+    #[test]
+    fn armature_to_local_and_back() {
+        let armature = arm();
+        let rest_pose = armature.rest_pose().unwrap();
+
+        let local = armature.armature_to_local_space(&rest_pose).unwrap();
+        assert_bones_eq(&local, &armature.local_rest_poses);
+
+        assert_bones_eq(
+            &armature.local_to_armature_space(&local).unwrap(),
+            &rest_pose,
+        );
+    }
+
+    #[test]
+    fn dual_quats_stay_dual_quats() {
+        let armature = arm();
+        let local = armature
+            .local_rest_poses
+            .iter()
+            .map(BlenderArmature::matrix_to_dual_quat)
+            .collect::<Vec<_>>();
+
+        let rest_pose = armature.local_to_armature_space(&local).unwrap();
+
+        let expected = armature
+            .rest_pose()
+            .unwrap()
+            .iter()
+            .map(BlenderArmature::matrix_to_dual_quat)
+            .collect::<Vec<_>>();
+        assert_bones_eq(&rest_pose, &expected);
+    }
+
+    #[test]
+    fn armature_to_world_and_back() {
+        let rest_pose = arm().rest_pose().unwrap();
+        let world_matrix = matrix(PI / 2.0, [0.0, 0.0, 3.0]);
+
+        let world = BlenderArmature::armature_to_world_space(&rest_pose, &world_matrix);
+        assert_bones_eq(&world[2..], &[Bone::Matrix(world_matrix)]);
+
+        assert_bones_eq(
+            &BlenderArmature::world_to_armature_space(&world, &world_matrix).unwrap(),
+            &rest_pose,
+        );
+    }
+
+    /// Armatures exported without parents treat every bone as a root, so local space is the
+    /// same as armature space
+    #[test]
+    fn no_parents() {
+        let armature = BlenderArmature::default();
+        let pose = vec![Bone::Matrix(matrix(1.0, [1.0, 2.0, 3.0]))];
+
+        assert_eq!(armature.parent(0), None);
+        assert_eq!(armature.armature_to_local_space(&pose).unwrap(), pose);
+    }
+
+    #[test]
+    fn bad_hierarchies() {
+        let pose = vec![
+            Bone::Matrix(matrix(0.0, [0.0, 0.0, 0.0])),
+            Bone::Matrix(matrix(0.0, [0.0, 0.0, 0.0])),
+        ];
+
+        let cycle = BlenderArmature {
+            bone_parents: vec![Some(1), Some(0)],
+            ..BlenderArmature::default()
+        };
+        match cycle.local_to_armature_space(&pose) {
+            Err(HierarchyError::Cycle { joint_index: 0 }) => {}
+            _ => unreachable!(),
+        };
+
+        let out_of_range = BlenderArmature {
+            bone_parents: vec![None, Some(5)],
+            ..BlenderArmature::default()
+        };
+        match out_of_range.armature_to_local_space(&pose) {
+            Err(HierarchyError::ParentOutOfRange {
+                joint_index: 1,
+                parent_index: 5,
+                bone_count: 2,
+            }) => {}
+            _ => unreachable!(),
+        };
+
+        let singular = vec![Bone::Matrix([0.0; 16])];
+        match BlenderArmature::world_to_armature_space(&singular, &[0.0; 16]) {
+            Err(HierarchyError::SingularWorldMatrix) => {}
+            _ => unreachable!(),
+        };
+    }
+}
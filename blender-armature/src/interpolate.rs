@@ -1,7 +1,7 @@
 //! Methods and configuration for interpolating keyframed poses, useful for skeletal animation.
 //!
-//! BlenderArmature currently supports dual quaternion interpolation, but could support 4x4 matrix
-//! interpolation if you open an issue/PR.
+//! BlenderArmature supports interpolating both dual quaternion and 4x4 matrix bones. Matrices are
+//! decomposed into their translation, rotation and scale, which get interpolated separately.
 //!
 //! The initial implementation and tests are based off of [skeletal-animation-system](https://github.com/chinedufn/skeletal-animation-system/blob/master/test/skeletal-animation-system.js)
 //!
@@ -28,9 +28,11 @@
 //! // ...
 //! ```
 
+use crate::dual_quat::{self, DualQuatInterpolation};
 use crate::BlenderArmature;
 use crate::Bone;
 use crate::Keyframe;
+use nalgebra::{Matrix3, Matrix4, Rotation3, UnitQuaternion, Vector3, U3};
 use std::collections::HashMap;
 
 /// Settings for how to interpolate your BlenderArmature's bone data. These can be used to do
@@ -77,6 +79,9 @@ pub struct InterpolationSettings<'a> {
     /// let blend_fn = |delta_seconds: f32| 0.2 * delta_seconds;
     /// ```
     pub blend_fn: Option<fn(f32) -> f32>,
+    /// How dual quaternion bones get interpolated, both between keyframes and when blending
+    /// the previous action into the current one.
+    pub dual_quat_interpolation: DualQuatInterpolation,
     /// Settings for the current action (animation) of this armature.
     pub current_action: ActionSettings<'a>,
     /// Optional settings for the previous action of this armature. This is useful for blending
@@ -121,6 +126,50 @@ impl<'a> ActionSettings<'a> {
     }
 }
 
+/// Something went wrong while interpolating an armature's bones
+#[derive(Debug, Fail)]
+pub enum InterpolationError {
+    /// The armature doesn't have an action with this name
+    #[fail(display = "The armature does not have an action named {}", action_name)]
+    UnknownAction { action_name: String },
+    /// The action doesn't have any keyframes to sample
+    #[fail(display = "The action {} does not have any keyframes", action_name)]
+    EmptyAction { action_name: String },
+    /// A joint index that one of the action's keyframes doesn't have a bone for
+    #[fail(
+        display = "Joint {} is out of range for a keyframe of {} with {} bones",
+        joint_index, action_name, bone_count
+    )]
+    JointOutOfRange {
+        action_name: String,
+        joint_index: u8,
+        bone_count: usize,
+    },
+    /// The previous and current actions animate a different number of joints, so they can't
+    /// belong to the same armature
+    #[fail(
+        display = "The previous action has {} joints but the current action has {}",
+        previous_joint_count, current_joint_count
+    )]
+    MismatchedJoints {
+        previous_joint_count: usize,
+        current_joint_count: usize,
+    },
+    /// A joint is a matrix in one of the poses being interpolated and a dual quaternion in the
+    /// other. Convert every action to the same bone type before interpolating.
+    #[fail(
+        display = "Joint {} mixes matrix and dual quaternion bones. Convert your actions to one type before interpolating",
+        joint_index
+    )]
+    MixedBoneTypes { joint_index: u8 },
+    /// The current time is before the action started
+    #[fail(
+        display = "The action {} started {} seconds after the current time",
+        action_name, seconds
+    )]
+    NegativeElapsedTime { action_name: String, seconds: f32 },
+}
+
 impl BlenderArmature {
     /// Interpolate in between the keyframes of your BlenderArmature. This is useful for
     /// skeletal animation.
@@ -128,23 +177,34 @@ impl BlenderArmature {
     /// We return a hashmap so that you can easily merge the results of interpolating
     /// different sets of bone groups.
     ///
-    /// # Panics
+    /// Matrix bones are expected to be column major, as they are after
+    /// [`BlenderArmature.transpose_actions`].
     ///
-    /// We don't yet support interpolating matrix bones, so we panic if your bones aren't
-    /// dual quaternions.
+    /// [`BlenderArmature.transpose_actions`]: struct.BlenderArmature.html#method.transpose_actions
     ///
-    /// Panics if you pass in previous actions that do not have the exact same joint indices
-    /// as your current action.
+    /// # Errors
     ///
-    /// # TODO
+    /// Bad actions or settings return an [`InterpolationError`] instead of panicking, so one
+    /// broken asset can't take down your game.
     ///
-    /// - [ ] Return Result<HashMap<u8, Bone>, InterpolationError>
-    /// - [ ] error if clock time is negative
-    pub fn interpolate_bones(&self, opts: &InterpolationSettings) -> HashMap<u8, Bone> {
-        let mut interpolated_bones = self.interpolate_action(&opts, &opts.current_action);
+    /// [`InterpolationError`]: enum.InterpolationError.html
+    pub fn interpolate_bones(
+        &self,
+        opts: &InterpolationSettings,
+    ) -> Result<HashMap<u8, Bone>, InterpolationError> {
+        let mut interpolated_bones = self.interpolate_action(&opts, &opts.current_action)?;
 
         if let Some(ref previous_action) = opts.previous_action {
-            let previous_bones = self.interpolate_action(&opts, &previous_action);
+            let previous_bones = self.interpolate_action(&opts, &previous_action)?;
+
+            let previous_joint_count = self.keyframes(previous_action)?[0].bones.len();
+            let current_joint_count = self.keyframes(&opts.current_action)?[0].bones.len();
+            if previous_joint_count != current_joint_count {
+                return Err(InterpolationError::MismatchedJoints {
+                    previous_joint_count,
+                    current_joint_count,
+                });
+            }
 
             let cur_anim_elapsed_time = opts.current_time - opts.current_action.start_time;
 
@@ -153,66 +213,52 @@ impl BlenderArmature {
             } else {
                 |dt_seconds: f32| (2.0 as f32 * dt_seconds).min(1.0)
             };
-
-            interpolated_bones = interpolated_bones
-                .iter()
-                .zip(previous_bones.iter())
-                .map(
-                    |((cur_joint_idx, cur_action_bone), (prev_joint_idx, prev_action_bone))| {
-                        if prev_joint_idx != cur_joint_idx {
-                            panic!("We do not currently support the current action having different joints than the previous action");
-                        }
-
-                        // FIXME: Ditch clones
-                        let prev = prev_action_bone.as_slice();
-                        let mut prev_action_bone: [f32; 8] = [0.0; 8];
-                        prev_action_bone.copy_from_slice(prev);
-
-                        // Get the dot product of the start and end rotation quaternions. If the
-                        // dot product is negative we negative the rotation portion of the first
-                        // dual quaternion in order to ensure the shortest path rotation.
-                        // http://www.xbdev.net/misc_demos/demos/dual_quaternions_beyond/paper.pdf
-                        if dot_product(&prev_action_bone, cur_action_bone.as_slice()) < 0.0 {
-                            prev_action_bone[0] = -prev_action_bone[0];
-                            prev_action_bone[1] = -prev_action_bone[1];
-                            prev_action_bone[2] = -prev_action_bone[2];
-                            prev_action_bone[3] = -prev_action_bone[3];
-                        }
-
-                        let _new_bone = [0.0; 8];
-
-                        let interpolation_amount = blend_func(cur_anim_elapsed_time);
-                        let new_bone = interpolate_bones(&Bone::DualQuat(prev_action_bone), &cur_action_bone, interpolation_amount);
-
-                        (*cur_joint_idx, new_bone)
-                    },
+            let interpolation_amount = blend_func(cur_anim_elapsed_time);
+
+            for (joint_index, cur_action_bone) in interpolated_bones.iter_mut() {
+                *cur_action_bone = interpolate_bones(
+                    &previous_bones[joint_index],
+                    cur_action_bone,
+                    interpolation_amount,
+                    opts.dual_quat_interpolation,
                 )
-                .collect()
+                .ok_or(InterpolationError::MixedBoneTypes {
+                    joint_index: *joint_index,
+                })?;
+            }
         };
 
-        interpolated_bones
+        Ok(interpolated_bones)
     }
 
     fn interpolate_action(
         &self,
         opts: &InterpolationSettings,
         action: &ActionSettings,
-    ) -> HashMap<u8, Bone> {
+    ) -> Result<HashMap<u8, Bone>, InterpolationError> {
         let mut interpolated_bones = HashMap::new();
 
-        let keyframes = self.actions.get(action.action_name).unwrap();
+        let keyframes = self.keyframes(action)?;
 
-        let lowest_keyframe = self.find_lowest_keyframe(action);
-        let highest_keyframe = self.find_highest_keyframe(action);
+        let lowest_keyframe = find_lowest_keyframe(keyframes);
+        let highest_keyframe = find_highest_keyframe(keyframes);
 
         let mut time_elapsed_since_first_keyframe = opts.current_time - action.start_time;
+        if time_elapsed_since_first_keyframe < 0.0 {
+            return Err(InterpolationError::NegativeElapsedTime {
+                action_name: action.action_name.to_string(),
+                seconds: time_elapsed_since_first_keyframe,
+            });
+        }
+
         let mut key_time_to_sample =
             lowest_keyframe.frame_time_secs + time_elapsed_since_first_keyframe;
 
         let action_duration = highest_keyframe.frame_time_secs - lowest_keyframe.frame_time_secs;
 
         if time_elapsed_since_first_keyframe > action_duration {
-            if action.should_loop {
+            // Actions with a single keyframe have no duration to loop over
+            if action.should_loop && action_duration > 0.0 {
                 time_elapsed_since_first_keyframe =
                     time_elapsed_since_first_keyframe % action_duration;
             } else {
@@ -236,77 +282,196 @@ impl BlenderArmature {
         for joint_index in &opts.joint_indices {
             let joint_index = *joint_index;
 
-            let lower_bone = &action_lower_keyframe.bones[joint_index as usize];
-            let upper_bone = &action_upper_keyframe.bones[joint_index as usize];
+            let lower_bone = joint_bone(action, action_lower_keyframe, joint_index)?;
+            let upper_bone = joint_bone(action, action_upper_keyframe, joint_index)?;
 
-            let interpolated_bone =
-                interpolate_bones(&lower_bone, &upper_bone, percent_elapsed_into_keyframe);
+            let interpolated_bone = interpolate_bones(
+                lower_bone,
+                upper_bone,
+                percent_elapsed_into_keyframe,
+                opts.dual_quat_interpolation,
+            )
+            .ok_or(InterpolationError::MixedBoneTypes { joint_index })?;
             interpolated_bones.insert(joint_index, interpolated_bone);
         }
 
-        interpolated_bones
+        Ok(interpolated_bones)
     }
 
-    fn find_lowest_keyframe<'a>(&'a self, action: &ActionSettings) -> &'a Keyframe {
-        let keyframes = self.actions.get(action.action_name).unwrap();
+    /// The keyframes of an action, which has at least one keyframe
+    pub(crate) fn keyframes(
+        &self,
+        action: &ActionSettings,
+    ) -> Result<&[Keyframe], InterpolationError> {
+        let keyframes = self.actions.get(action.action_name).ok_or_else(|| {
+            InterpolationError::UnknownAction {
+                action_name: action.action_name.to_string(),
+            }
+        })?;
 
-        let mut lowest_keyframe = std::f32::INFINITY;
-        let mut lowest_keyframe_idx = 0;
+        if keyframes.is_empty() {
+            return Err(InterpolationError::EmptyAction {
+                action_name: action.action_name.to_string(),
+            });
+        }
 
-        for (index, keyframe) in keyframes.iter().enumerate() {
-            if keyframe.frame_time_secs < lowest_keyframe {
-                lowest_keyframe = keyframe.frame_time_secs;
-                lowest_keyframe_idx = index;
-            }
+        Ok(keyframes)
+    }
+}
+
+fn find_lowest_keyframe(keyframes: &[Keyframe]) -> &Keyframe {
+    let mut lowest_keyframe = std::f32::INFINITY;
+    let mut lowest_keyframe_idx = 0;
+
+    for (index, keyframe) in keyframes.iter().enumerate() {
+        if keyframe.frame_time_secs < lowest_keyframe {
+            lowest_keyframe = keyframe.frame_time_secs;
+            lowest_keyframe_idx = index;
         }
+    }
 
-        &keyframes[lowest_keyframe_idx]
+    &keyframes[lowest_keyframe_idx]
+}
+
+fn find_highest_keyframe(keyframes: &[Keyframe]) -> &Keyframe {
+    let mut highest_keyframe = -std::f32::INFINITY;
+    let mut highest_keyframe_idx = 0;
+
+    for (index, keyframe) in keyframes.iter().enumerate() {
+        if keyframe.frame_time_secs > highest_keyframe {
+            highest_keyframe = keyframe.frame_time_secs;
+            highest_keyframe_idx = index;
+        }
     }
 
-    fn find_highest_keyframe<'a>(&'a self, action: &ActionSettings) -> &'a Keyframe {
-        let keyframes = self.actions.get(action.action_name).unwrap();
+    &keyframes[highest_keyframe_idx]
+}
 
-        let mut highest_keyframe = -std::f32::INFINITY;
-        let mut highest_keyframe_idx = 0;
+/// The bone of a joint at a keyframe
+fn joint_bone<'a>(
+    action: &ActionSettings,
+    keyframe: &'a Keyframe,
+    joint_index: u8,
+) -> Result<&'a Bone, InterpolationError> {
+    keyframe
+        .bones
+        .get(joint_index as usize)
+        .ok_or_else(|| InterpolationError::JointOutOfRange {
+            action_name: action.action_name.to_string(),
+            joint_index,
+            bone_count: keyframe.bones.len(),
+        })
+}
 
-        for (index, keyframe) in keyframes.iter().enumerate() {
-            if keyframe.frame_time_secs > highest_keyframe {
-                highest_keyframe = keyframe.frame_time_secs;
-                highest_keyframe_idx = index;
-            }
+/// Interpolate between two bones of the same type, or `None` if one is a matrix and the other a
+/// dual quaternion
+pub(crate) fn interpolate_bones(
+    start_bone: &Bone,
+    end_bone: &Bone,
+    amount: f32,
+    dual_quat_interpolation: DualQuatInterpolation,
+) -> Option<Bone> {
+    match (start_bone, end_bone) {
+        (Bone::DualQuat(start_dual_quat), Bone::DualQuat(end_dual_quat)) => {
+            Some(Bone::DualQuat(dual_quat::interpolate(
+                start_dual_quat,
+                end_dual_quat,
+                amount,
+                dual_quat_interpolation,
+            )))
         }
+        (Bone::Matrix(start_matrix), Bone::Matrix(end_matrix)) => {
+            let start = Decomposed::from_matrix(start_matrix);
+            let end = Decomposed::from_matrix(end_matrix);
 
-        &keyframes[highest_keyframe_idx]
+            Some(Bone::Matrix(start.interpolate(&end, amount).to_matrix()))
+        }
+        _ => None,
     }
 }
 
-fn interpolate_bones(start_bone: &Bone, end_bone: &Bone, amount: f32) -> Bone {
-    match start_bone {
-        &Bone::DualQuat(ref start_dual_quat) => match end_bone {
-            &Bone::DualQuat(ref end_dual_quat) => {
-                let mut interpolated_dual_quat: [f32; 8] = [0.0; 8];
+/// A column major transformation matrix, split into the translation, rotation and scale that
+/// it applies (in that order)
+#[derive(Debug, PartialEq)]
+struct Decomposed {
+    translation: Vector3<f32>,
+    rotation: UnitQuaternion<f32>,
+    scale: Vector3<f32>,
+}
+
+impl Decomposed {
+    fn from_matrix(matrix: &[f32; 16]) -> Decomposed {
+        let mut matrix4: Matrix4<f32> = Matrix4::identity();
+        matrix4.copy_from_slice(matrix);
 
-                for index in 0..8 {
-                    let start = start_dual_quat[index];
-                    let end = end_dual_quat[index];
-                    interpolated_dual_quat[index] = (end - start) * amount + start;
-                }
+        let translation = Vector3::new(matrix4[(0, 3)], matrix4[(1, 3)], matrix4[(2, 3)]);
 
-                Bone::DualQuat(interpolated_dual_quat)
+        let mut linear: Matrix3<f32> = matrix4.fixed_slice::<U3, U3>(0, 0).into_owned();
+        let mut scale = Vector3::new(
+            linear.column(0).norm(),
+            linear.column(1).norm(),
+            linear.column(2).norm(),
+        );
+
+        // A mirrored matrix can't be represented by a rotation, so we fold the mirroring into
+        // the scale
+        if linear.determinant() < 0.0 {
+            scale.x = -scale.x;
+        }
+
+        for axis in 0..3 {
+            if scale[axis] != 0.0 {
+                let mut column = linear.column_mut(axis);
+                column /= scale[axis];
             }
-            _ => panic!(
-                "You may only interpolate bones of the same type. Please convert\
-                 your end bone into a dual quaternion before interpolating"
-            ),
-        },
-        &Bone::Matrix(ref _matrix) => unimplemented!(),
+        }
+
+        Decomposed {
+            translation,
+            rotation: UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(
+                linear,
+            )),
+            scale,
+        }
+    }
+
+    /// Lerp the translation and scale and slerp the rotation, along the shortest path
+    fn interpolate(&self, end: &Decomposed, amount: f32) -> Decomposed {
+        let mut end_rotation = end.rotation;
+        if self.rotation.coords.dot(&end_rotation.coords) < 0.0 {
+            end_rotation = UnitQuaternion::new_unchecked(-end_rotation.into_inner());
+        }
+
+        // Nearly identical rotations can't be slerped, but lerping them is just as accurate
+        let rotation = self
+            .rotation
+            .try_slerp(&end_rotation, amount, 1.0e-6)
+            .unwrap_or_else(|| self.rotation.nlerp(&end_rotation, amount));
+
+        Decomposed {
+            translation: self.translation + (end.translation - self.translation) * amount,
+            rotation,
+            scale: self.scale + (end.scale - self.scale) * amount,
+        }
+    }
+
+    fn to_matrix(&self) -> [f32; 16] {
+        let matrix4 = self
+            .rotation
+            .to_homogeneous()
+            .prepend_nonuniform_scaling(&self.scale)
+            .append_translation(&self.translation);
+
+        let mut matrix = [0.0; 16];
+        matrix.copy_from_slice(matrix4.as_slice());
+        matrix
     }
 }
 
 // If you're sampling time 1.5seconds and there are three keyframes, 0.0s, 1.8s, 2.2s the
 // surrounding keyframes are 0.0s and 1.8s
 fn get_surrounding_keyframes(
-    keyframes: &Vec<Keyframe>,
+    keyframes: &[Keyframe],
     key_time_to_sample: f32,
 ) -> (&Keyframe, &Keyframe) {
     let mut action_lower_keyframe = 0;
@@ -335,16 +500,14 @@ fn get_surrounding_keyframes(
     )
 }
 
-fn dot_product(a: &[f32], b: &[f32]) -> f32 {
-    a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + a[3] * b[3]
-}
-
 // Tests originally ported from:
 //  https://github.com/chinedufn/skeletal-animation-system/tree/8cc52d69f2e4e3f64540a4b6274bcd5fc3c00eee/test
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::Keyframe;
+    use nalgebra::Quaternion;
+    use std::f32::consts::PI;
 
     struct DualQuatTestCase<'a> {
         description: String,
@@ -378,6 +541,7 @@ mod tests {
                 // TODO: armature.get_group_indices(BlenderArmature::BONE_GROUPS_ALL)
                 joint_indices: vec![0],
                 blend_fn: None,
+                dual_quat_interpolation: DualQuatInterpolation::default(),
                 current_action: ActionSettings::new("test", 0.0, true),
                 previous_action: None,
             },
@@ -405,6 +569,7 @@ mod tests {
                 current_time: 4.0,
                 joint_indices: vec![0],
                 blend_fn: None,
+                dual_quat_interpolation: DualQuatInterpolation::default(),
                 current_action: ActionSettings::new("test", 0.0, true),
                 previous_action: None,
             },
@@ -436,6 +601,7 @@ mod tests {
                 current_time: 2.5,
                 joint_indices: vec![0],
                 blend_fn: None,
+                dual_quat_interpolation: DualQuatInterpolation::default(),
                 current_action: ActionSettings::new("test", 0.0, true),
                 previous_action: None,
             },
@@ -463,6 +629,7 @@ mod tests {
                 current_time: 7.0,
                 joint_indices: vec![0],
                 blend_fn: None,
+                dual_quat_interpolation: DualQuatInterpolation::default(),
                 current_action: ActionSettings::new("test", 0.0, false),
                 previous_action: None,
             },
@@ -497,6 +664,7 @@ mod tests {
                 current_time: 10.0,
                 joint_indices: vec![0],
                 blend_fn: None,
+                dual_quat_interpolation: DualQuatInterpolation::default(),
                 current_action: ActionSettings::new("test", 10.0, true),
                 previous_action: Some(ActionSettings::new("test", 0.0, false)),
             },
@@ -531,6 +699,7 @@ mod tests {
                 current_time: 10.0,
                 joint_indices: vec![0],
                 blend_fn: Some(two_second_blend_func),
+                dual_quat_interpolation: DualQuatInterpolation::default(),
                 current_action: ActionSettings::new("test", 9.0, true),
                 previous_action: Some(ActionSettings::new("test", 5.0, false)),
             },
@@ -562,11 +731,12 @@ mod tests {
             // TODO: self.get_bone_group(BlenderArmature::ALL_BONES)
             joint_indices: vec![0],
             blend_fn: None,
+            dual_quat_interpolation: DualQuatInterpolation::default(),
             current_action: ActionSettings::new("Twist", 0.0, true),
             previous_action: None,
         };
         // Just making sure that this no longer panics..
-        armature.interpolate_bones(&interp_opts);
+        armature.interpolate_bones(&interp_opts).unwrap();
     }
 
     #[test]
@@ -591,6 +761,7 @@ mod tests {
                 // TODO: armature.get_group_indices(BlenderArmature::BONE_GROUPS_ALL)
                 joint_indices: vec![0],
                 blend_fn: None,
+                dual_quat_interpolation: DualQuatInterpolation::default(),
                 current_action: ActionSettings::new("test", 0.0, true),
                 previous_action: None,
             },
@@ -617,15 +788,25 @@ mod tests {
                 ..BlenderArmature::default()
             };
 
-            let interpolated_bones = armature.interpolate_bones(&self.interp_settings);
+            let interpolated_bones = armature.interpolate_bones(&self.interp_settings).unwrap();
             let interpolated_bone = interpolated_bones.get(&0).unwrap();
 
-            assert_eq!(
-                interpolated_bone.as_slice(),
-                &self.expected_bone,
-                "{}",
-                self.description
-            );
+            // Interpolated dual quaternions get normalized, so we compare against our expected
+            // bone after normalizing it too
+            let expected_bone = dual_quat::normalize(&self.expected_bone);
+            for (actual, expected) in interpolated_bone
+                .as_slice()
+                .iter()
+                .zip(expected_bone.iter())
+            {
+                assert!(
+                    (actual - expected).abs() < 1e-6,
+                    "{}: {:?} != {:?}",
+                    self.description,
+                    interpolated_bone,
+                    expected_bone
+                );
+            }
         }
     }
 
@@ -633,6 +814,320 @@ mod tests {
         (0.5 as f32 * dt_seconds).min(1.0)
     }
 
+    /// Rigid transforms stay rigid (unit length, with orthogonal real and dual parts) no matter
+    /// how long an action runs or how it is interpolated, even when neighbouring keyframes are
+    /// in opposite hemispheres
+    #[test]
+    fn dual_quats_stay_unit_length_across_long_actions() {
+        let keyframes: Vec<Keyframe> = (0..12)
+            .map(|frame| {
+                let frame = frame as f32;
+                let axis = nalgebra::Unit::new_normalize(Vector3::new(1.0, frame, 2.0 - frame));
+                let matrix4 = Matrix4::from_axis_angle(&axis, frame * 1.3)
+                    .append_translation(&Vector3::new(frame, -2.0 * frame, 0.5));
+
+                let mut matrix = [0.0; 16];
+                matrix.copy_from_slice(matrix4.as_slice());
+
+                let mut bone = match BlenderArmature::matrix_to_dual_quat(&Bone::Matrix(matrix)) {
+                    Bone::DualQuat(dual_quat) => dual_quat,
+                    Bone::Matrix(_) => unreachable!(),
+                };
+                // `q` and `-q` are the same transform
+                if frame as u32 % 2 == 1 {
+                    for component in bone.iter_mut() {
+                        *component = -*component;
+                    }
+                }
+
+                Keyframe {
+                    frame_time_secs: frame * 0.25,
+                    bones: vec![Bone::DualQuat(bone)],
+                }
+            })
+            .collect();
+
+        let mut actions = HashMap::new();
+        actions.insert("test".to_string(), keyframes);
+        let armature = BlenderArmature {
+            actions,
+            ..BlenderArmature::default()
+        };
+
+        for method in [
+            DualQuatInterpolation::Linear,
+            DualQuatInterpolation::ScrewLinear,
+        ]
+        .iter()
+        {
+            for step in 0..1000 {
+                let bones = armature
+                    .interpolate_bones(&InterpolationSettings {
+                        current_time: step as f32 * 0.0173,
+                        joint_indices: vec![0],
+                        blend_fn: None,
+                        dual_quat_interpolation: *method,
+                        current_action: ActionSettings::new("test", 0.0, true),
+                        previous_action: Some(ActionSettings::new("test", -1.1, true)),
+                    })
+                    .unwrap();
+
+                let bone = bones[&0].as_slice();
+                let real = Quaternion::new(bone[0], bone[1], bone[2], bone[3]);
+                let dual = Quaternion::new(bone[4], bone[5], bone[6], bone[7]);
+
+                assert!((real.norm() - 1.0).abs() < 1e-5, "{:?} {:?}", method, bone);
+                assert!(real.dot(&dual).abs() < 1e-5, "{:?} {:?}", method, bone);
+            }
+        }
+    }
+
+    /// A rotation of `angle` radians around the z axis, scaled uniformly and then translated
+    fn matrix(angle: f32, scale: f32, translation: Vector3<f32>) -> [f32; 16] {
+        let matrix4 = Matrix4::from_axis_angle(&Vector3::z_axis(), angle)
+            .prepend_scaling(scale)
+            .append_translation(&translation);
+
+        let mut matrix = [0.0; 16];
+        matrix.copy_from_slice(matrix4.as_slice());
+        matrix
+    }
+
+    fn matrix_armature(keyframes: Vec<(f32, [f32; 16])>) -> BlenderArmature {
+        let keyframes = keyframes
+            .into_iter()
+            .map(|(frame_time_secs, matrix)| Keyframe {
+                frame_time_secs,
+                bones: vec![Bone::Matrix(matrix)],
+            })
+            .collect();
+
+        let mut actions = HashMap::new();
+        actions.insert("test".to_string(), keyframes);
+
+        BlenderArmature {
+            actions,
+            ..BlenderArmature::default()
+        }
+    }
+
+    fn assert_matrix_eq(actual: &Bone, expected: &[f32; 16]) {
+        for (actual, expected) in actual.as_slice().iter().zip(expected.iter()) {
+            assert!(
+                (actual - expected).abs() < 1e-5,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    /// Matrices get their rotations slerped and their translations and scales lerped, so
+    /// halfway between no rotation and a quarter turn is an eighth of a turn
+    #[test]
+    fn interpolate_matrices() {
+        let armature = matrix_armature(vec![
+            (0.0, matrix(0.0, 1.0, Vector3::new(0.0, 0.0, 0.0))),
+            (2.0, matrix(PI / 2.0, 3.0, Vector3::new(2.0, 4.0, 0.0))),
+        ]);
+
+        let bones = armature
+            .interpolate_bones(&InterpolationSettings {
+                current_time: 1.0,
+                joint_indices: vec![0],
+                blend_fn: None,
+                dual_quat_interpolation: DualQuatInterpolation::default(),
+                current_action: ActionSettings::new("test", 0.0, true),
+                previous_action: None,
+            })
+            .unwrap();
+
+        assert_matrix_eq(
+            &bones[&0],
+            &matrix(PI / 4.0, 2.0, Vector3::new(1.0, 2.0, 0.0)),
+        );
+    }
+
+    /// The previous action gets blended into the current one for matrices too
+    #[test]
+    fn blend_out_previous_matrix_action() {
+        let armature = matrix_armature(vec![
+            (0.0, matrix(0.0, 1.0, Vector3::new(0.0, 0.0, 0.0))),
+            (2.0, matrix(PI / 2.0, 1.0, Vector3::new(2.0, 0.0, 0.0))),
+        ]);
+
+        let bones = armature
+            .interpolate_bones(&InterpolationSettings {
+                current_time: 10.0,
+                joint_indices: vec![0],
+                blend_fn: Some(two_second_blend_func),
+                dual_quat_interpolation: DualQuatInterpolation::default(),
+                // Sampled at 1 second, an eighth of a turn
+                current_action: ActionSettings::new("test", 9.0, false),
+                // Sampled at 2 seconds, a quarter turn
+                previous_action: Some(ActionSettings::new("test", 8.0, false)),
+            })
+            .unwrap();
+
+        // One second in, our blend function is halfway through blending
+        assert_matrix_eq(
+            &bones[&0],
+            &matrix(3.0 * PI / 8.0, 1.0, Vector3::new(1.5, 0.0, 0.0)),
+        );
+    }
+
+    /// Decomposing and recomposing a matrix gives back the same matrix, even when it's mirrored
+    #[test]
+    fn decompose_and_recompose_matrix() {
+        let rotated = matrix(1.0, 0.5, Vector3::new(1.0, 2.0, 3.0));
+        assert_matrix_eq(
+            &Bone::Matrix(Decomposed::from_matrix(&rotated).to_matrix()),
+            &rotated,
+        );
+
+        let mut mirrored = rotated;
+        for component in mirrored[0..3].iter_mut() {
+            *component = -*component;
+        }
+        let decomposed = Decomposed::from_matrix(&mirrored);
+        assert!(decomposed.scale.x < 0.0);
+        assert_matrix_eq(&Bone::Matrix(decomposed.to_matrix()), &mirrored);
+    }
+
+    fn dual_quat_armature(actions: Vec<(&str, Vec<Vec<Bone>>)>) -> BlenderArmature {
+        let actions = actions
+            .into_iter()
+            .map(|(name, keyframes)| {
+                let keyframes = keyframes
+                    .into_iter()
+                    .enumerate()
+                    .map(|(frame, bones)| Keyframe {
+                        frame_time_secs: frame as f32,
+                        bones,
+                    })
+                    .collect();
+
+                (name.to_string(), keyframes)
+            })
+            .collect();
+
+        BlenderArmature {
+            actions,
+            ..BlenderArmature::default()
+        }
+    }
+
+    fn settings<'a>(
+        current_time: f32,
+        current_action: &'a str,
+        previous_action: Option<&'a str>,
+    ) -> InterpolationSettings<'a> {
+        InterpolationSettings {
+            current_time,
+            joint_indices: vec![0, 1],
+            blend_fn: None,
+            dual_quat_interpolation: DualQuatInterpolation::default(),
+            current_action: ActionSettings::new(current_action, 0.0, true),
+            previous_action: previous_action.map(|action| ActionSettings::new(action, 0.0, true)),
+        }
+    }
+
+    fn identity() -> Bone {
+        Bone::DualQuat([1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0])
+    }
+
+    #[test]
+    fn unknown_action() {
+        let armature = dual_quat_armature(vec![]);
+
+        match armature.interpolate_bones(&settings(0.0, "Walk", None)) {
+            Err(InterpolationError::UnknownAction { ref action_name }) if action_name == "Walk" => {
+            }
+            result => panic!("{:?}", result),
+        };
+    }
+
+    #[test]
+    fn empty_action() {
+        let armature = dual_quat_armature(vec![("Walk", vec![])]);
+
+        match armature.interpolate_bones(&settings(0.0, "Walk", None)) {
+            Err(InterpolationError::EmptyAction { ref action_name }) if action_name == "Walk" => {}
+            result => panic!("{:?}", result),
+        };
+    }
+
+    /// Every keyframe needs a bone for every joint that we interpolate
+    #[test]
+    fn joint_out_of_range() {
+        let armature = dual_quat_armature(vec![(
+            "Walk",
+            vec![vec![identity(), identity()], vec![identity()]],
+        )]);
+
+        match armature.interpolate_bones(&settings(0.5, "Walk", None)) {
+            Err(InterpolationError::JointOutOfRange {
+                joint_index: 1,
+                bone_count: 1,
+                ..
+            }) => {}
+            result => panic!("{:?}", result),
+        };
+    }
+
+    #[test]
+    fn mismatched_joints() {
+        let armature = dual_quat_armature(vec![
+            ("Walk", vec![vec![identity(), identity()]]),
+            ("Wave", vec![vec![identity(), identity(), identity()]]),
+        ]);
+
+        match armature.interpolate_bones(&settings(0.0, "Walk", Some("Wave"))) {
+            Err(InterpolationError::MismatchedJoints {
+                previous_joint_count: 3,
+                current_joint_count: 2,
+            }) => {}
+            result => panic!("{:?}", result),
+        };
+    }
+
+    #[test]
+    fn mixed_bone_types() {
+        let matrix_bone = Bone::Matrix(matrix(0.0, 1.0, Vector3::zeros()));
+        let armature = dual_quat_armature(vec![(
+            "Walk",
+            vec![vec![identity(), identity()], vec![identity(), matrix_bone]],
+        )]);
+
+        match armature.interpolate_bones(&settings(0.5, "Walk", None)) {
+            Err(InterpolationError::MixedBoneTypes { joint_index: 1 }) => {}
+            result => panic!("{:?}", result),
+        };
+    }
+
+    /// The current time can't be before an action started
+    #[test]
+    fn negative_elapsed_time() {
+        let armature = dual_quat_armature(vec![("Walk", vec![vec![identity(), identity()]])]);
+
+        match armature.interpolate_bones(&settings(-2.0, "Walk", None)) {
+            Err(InterpolationError::NegativeElapsedTime { seconds: -2.0, .. }) => {}
+            result => panic!("{:?}", result),
+        };
+    }
+
+    /// An action with a single keyframe holds that pose, even when it loops
+    #[test]
+    fn single_keyframe_action() {
+        let armature = dual_quat_armature(vec![("Walk", vec![vec![identity(), identity()]])]);
+
+        let bones = armature
+            .interpolate_bones(&settings(5.0, "Walk", None))
+            .unwrap();
+
+        assert_eq!(bones[&1], identity());
+    }
+
     #[test]
     fn surrounding_keyframes() {
         let keyframes = vec![
@@ -0,0 +1,489 @@
+//! Blending any number of actions together into one pose, such as an upper body attack on top of
+//! a walk cycle, with a breathing layer and a hit reaction added on top of that.
+//!
+//! Layers are applied from first to last. Every layer samples its action and blends it into the
+//! pose built up by the layers below it, by the layer's weight and (optionally) a per bone mask.
+//! Blending happens in local space, so a masked arm still follows the shoulder that a lower layer
+//! moved.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! let upper_body = armature.bone_mask(&[spine_joint]);
+//!
+//! let settings = LayeredPoseSettings {
+//!     current_time: 12.0,
+//!     dual_quat_interpolation: DualQuatInterpolation::default(),
+//!     layers: vec![
+//!         PoseLayer {
+//!             action: ActionSettings::new("Walk", 0.0, true),
+//!             weight: 1.0,
+//!             bone_mask: None,
+//!             blend_mode: LayerBlendMode::Override,
+//!         },
+//!         PoseLayer {
+//!             action: ActionSettings::new("Attack", 11.5, false),
+//!             weight: 1.0,
+//!             bone_mask: Some(upper_body),
+//!             blend_mode: LayerBlendMode::Override,
+//!         },
+//!         PoseLayer {
+//!             action: ActionSettings::new("Breathe", 0.0, true),
+//!             weight: 0.5,
+//!             bone_mask: None,
+//!             blend_mode: LayerBlendMode::Additive,
+//!         },
+//!     ],
+//! };
+//!
+//! let pose = armature.blend_layers(&settings)?;
+//! ```
+
+use crate::dual_quat::DualQuatInterpolation;
+use crate::hierarchy::{bone_to_matrix, matrix_to_bone};
+use crate::interpolate::interpolate_bones;
+use crate::{ActionSettings, BlenderArmature, Bone, HierarchyError, InterpolationError};
+use crate::{InterpolationSettings, Keyframe};
+use nalgebra::Matrix4;
+
+/// Settings for blending a stack of actions into one pose
+#[derive(Debug)]
+pub struct LayeredPoseSettings<'a> {
+    /// The time that every layer's action is sampled at
+    pub current_time: f32,
+    /// How dual quaternion bones get interpolated, both between keyframes and between layers
+    pub dual_quat_interpolation: DualQuatInterpolation,
+    /// The layers to blend, from the bottom of the stack to the top
+    pub layers: Vec<PoseLayer<'a>>,
+}
+
+/// One action in a stack of layers
+#[derive(Debug, Clone)]
+pub struct PoseLayer<'a> {
+    /// The action to sample for this layer
+    pub action: ActionSettings<'a>,
+    /// How much of this layer to blend in, between `0.0` and `1.0`
+    pub weight: f32,
+    /// A weight between `0.0` and `1.0` for every joint, which gets multiplied by the layer's
+    /// `weight`. `None` blends in every joint.
+    ///
+    /// [`BlenderArmature.bone_mask`] creates a mask for part of the armature, such as the upper
+    /// body.
+    ///
+    /// [`BlenderArmature.bone_mask`]: struct.BlenderArmature.html#method.bone_mask
+    pub bone_mask: Option<Vec<f32>>,
+    /// How the layer gets combined with the layers below it
+    pub blend_mode: LayerBlendMode,
+}
+
+/// How a layer gets combined with the layers below it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayerBlendMode {
+    /// Blend from the pose below towards this layer's pose. At full weight this layer replaces
+    /// the pose below it.
+    Override,
+    /// Add this layer's difference from the rest pose on top of the pose below it. Useful for
+    /// breathing or hit reactions that should work on top of any other action.
+    ///
+    /// Armatures exported without local rest poses treat the action itself as the difference.
+    Additive,
+}
+
+/// Something went wrong while blending a stack of layers
+#[derive(Debug, Fail)]
+pub enum LayerError {
+    /// There has to be at least one layer to sample a pose from
+    #[fail(display = "There are no layers to blend")]
+    NoLayers,
+    /// A layer's action has a different number of joints than the rest of the armature
+    #[fail(
+        display = "The action {} has {} joints but the armature has {}",
+        action_name, joint_count, expected_joint_count
+    )]
+    MismatchedJoints {
+        action_name: String,
+        joint_count: usize,
+        expected_joint_count: usize,
+    },
+    /// A layer's bone mask doesn't have one weight per joint
+    #[fail(
+        display = "The bone mask for {} has {} weights but the armature has {} joints",
+        action_name, mask_length, joint_count
+    )]
+    MaskLength {
+        action_name: String,
+        mask_length: usize,
+        joint_count: usize,
+    },
+    /// A joint's rest pose has a zero scale, so an additive layer can't be made relative to it
+    #[fail(display = "The rest pose of joint {} can't be inverted", joint_index)]
+    SingularRestPose { joint_index: u8 },
+    /// A layer's action couldn't be sampled
+    #[fail(display = "Could not sample a layer: {}", _0)]
+    Interpolation(#[cause] InterpolationError),
+    /// The sampled poses couldn't be converted between local and armature space
+    #[fail(display = "Could not convert a layer's pose: {}", _0)]
+    Hierarchy(#[cause] HierarchyError),
+}
+
+impl From<InterpolationError> for LayerError {
+    fn from(error: InterpolationError) -> Self {
+        LayerError::Interpolation(error)
+    }
+}
+
+impl From<HierarchyError> for LayerError {
+    fn from(error: HierarchyError) -> Self {
+        LayerError::Hierarchy(error)
+    }
+}
+
+impl BlenderArmature {
+    /// Sample and blend every layer, returning the armature space pose of every joint (indexed by
+    /// joint index).
+    ///
+    /// Joints start out in their local rest pose, so a joint that no layer covers stays at rest.
+    ///
+    /// Matrix bones are expected to be column major, as they are after
+    /// [`BlenderArmature.transpose_actions`].
+    ///
+    /// [`BlenderArmature.transpose_actions`]: struct.BlenderArmature.html#method.transpose_actions
+    pub fn blend_layers(&self, settings: &LayeredPoseSettings) -> Result<Vec<Bone>, LayerError> {
+        let first_layer = settings.layers.first().ok_or(LayerError::NoLayers)?;
+
+        let joint_count = if self.local_rest_poses.is_empty() {
+            first_keyframe(self, &first_layer.action)?.bones.len()
+        } else {
+            self.local_rest_poses.len()
+        };
+
+        let mut pose: Vec<Bone> = vec![];
+
+        for layer in settings.layers.iter() {
+            let layer_pose = self.sample_layer(settings, layer, joint_count)?;
+
+            // The rest pose takes on the bone type of the actions so that they can be blended
+            if pose.is_empty() {
+                pose = layer_pose
+                    .iter()
+                    .enumerate()
+                    .map(|(joint, bone)| matrix_to_bone(&self.local_rest_matrix(joint), bone))
+                    .collect();
+            }
+
+            for (joint, layer_bone) in layer_pose.iter().enumerate() {
+                let amount = match &layer.bone_mask {
+                    Some(mask) => layer.weight * mask[joint],
+                    None => layer.weight,
+                };
+                if amount == 0.0 {
+                    continue;
+                }
+
+                let target = match layer.blend_mode {
+                    LayerBlendMode::Override => bone_to_matrix(layer_bone),
+                    LayerBlendMode::Additive => {
+                        let rest_inverse = self.local_rest_matrix(joint).try_inverse().ok_or(
+                            LayerError::SingularRestPose {
+                                joint_index: joint as u8,
+                            },
+                        )?;
+
+                        bone_to_matrix(&pose[joint]) * rest_inverse * bone_to_matrix(layer_bone)
+                    }
+                };
+
+                pose[joint] = interpolate_bones(
+                    &pose[joint],
+                    &matrix_to_bone(&target, layer_bone),
+                    amount,
+                    settings.dual_quat_interpolation,
+                )
+                .ok_or(InterpolationError::MixedBoneTypes {
+                    joint_index: joint as u8,
+                })?;
+            }
+        }
+
+        Ok(self.local_to_armature_space(&pose)?)
+    }
+
+    /// A mask with a weight of `1.0` for the given joints and all of their descendants, and `0.0`
+    /// for every other joint. For example, pass in the spine to get a mask for the upper body.
+    pub fn bone_mask(&self, roots: &[u8]) -> Vec<f32> {
+        let joint_count = self.bone_parents.len();
+
+        (0..joint_count)
+            .map(|joint| {
+                let mut ancestor = Some(joint as u8);
+
+                // Stepping at most `joint_count` times keeps us from looping around a cycle
+                for _ in 0..=joint_count {
+                    match ancestor {
+                        Some(joint) if roots.contains(&joint) => return 1.0,
+                        Some(joint) => ancestor = self.parent(joint),
+                        None => break,
+                    };
+                }
+
+                0.0
+            })
+            .collect()
+    }
+
+    /// The local space pose of a layer's action at the current time
+    fn sample_layer(
+        &self,
+        settings: &LayeredPoseSettings,
+        layer: &PoseLayer,
+        joint_count: usize,
+    ) -> Result<Vec<Bone>, LayerError> {
+        let action_name = layer.action.action_name.to_string();
+
+        let layer_joint_count = first_keyframe(self, &layer.action)?.bones.len();
+        if layer_joint_count != joint_count {
+            return Err(LayerError::MismatchedJoints {
+                action_name,
+                joint_count: layer_joint_count,
+                expected_joint_count: joint_count,
+            });
+        }
+
+        if let Some(mask) = &layer.bone_mask {
+            if mask.len() != joint_count {
+                return Err(LayerError::MaskLength {
+                    action_name,
+                    mask_length: mask.len(),
+                    joint_count,
+                });
+            }
+        }
+
+        let sampled = self.interpolate_bones(&InterpolationSettings {
+            current_time: settings.current_time,
+            joint_indices: (0..joint_count).map(|joint| joint as u8).collect(),
+            blend_fn: None,
+            dual_quat_interpolation: settings.dual_quat_interpolation,
+            current_action: layer.action,
+            previous_action: None,
+        })?;
+
+        let mut sampled: Vec<(u8, Bone)> = sampled.into_iter().collect();
+        sampled.sort_by_key(|(joint, _)| *joint);
+        let armature_pose: Vec<Bone> = sampled.into_iter().map(|(_, bone)| bone).collect();
+
+        Ok(self.armature_to_local_space(&armature_pose)?)
+    }
+
+    fn local_rest_matrix(&self, joint: usize) -> Matrix4<f32> {
+        self.local_rest_poses
+            .get(joint)
+            .map(bone_to_matrix)
+            .unwrap_or_else(Matrix4::identity)
+    }
+}
+
+fn first_keyframe<'a>(
+    armature: &'a BlenderArmature,
+    action: &ActionSettings,
+) -> Result<&'a Keyframe, LayerError> {
+    Ok(&armature.keyframes(action)?[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+    use std::collections::HashMap;
+    use std::f32::consts::PI;
+
+    /// A rotation around z followed by a translation
+    fn matrix(angle: f32, translation: [f32; 3]) -> Bone {
+        let matrix4 = Matrix4::from_axis_angle(&Vector3::z_axis(), angle).append_translation(
+            &Vector3::new(translation[0], translation[1], translation[2]),
+        );
+
+        let mut matrix = [0.0; 16];
+        matrix.copy_from_slice(matrix4.as_slice());
+        Bone::Matrix(matrix)
+    }
+
+    fn assert_bones_eq(actual: &[Bone], expected: &[Bone]) {
+        assert_eq!(actual.len(), expected.len());
+
+        for (actual, expected) in actual.iter().zip(expected.iter()) {
+            for (a, e) in actual.as_slice().iter().zip(expected.as_slice().iter()) {
+                assert!((a - e).abs() < 1.0e-5, "{:?} != {:?}", actual, expected);
+            }
+        }
+    }
+
+    /// A root bone with a child one unit along its x axis, and actions that hold a single
+    /// (armature space) pose
+    fn armature() -> BlenderArmature {
+        let mut actions = HashMap::new();
+
+        let mut action = |name: &str, bones: Vec<Bone>| {
+            actions.insert(
+                name.to_string(),
+                vec![Keyframe {
+                    frame_time_secs: 0.0,
+                    bones,
+                }],
+            );
+        };
+
+        // The whole arm turns a quarter turn
+        action(
+            "Walk",
+            vec![
+                matrix(PI / 2.0, [0.0, 0.0, 0.0]),
+                matrix(PI / 2.0, [0.0, 1.0, 0.0]),
+            ],
+        );
+        // The child turns a quarter turn
+        action(
+            "Wave",
+            vec![
+                matrix(0.0, [0.0, 0.0, 0.0]),
+                matrix(PI / 2.0, [1.0, 0.0, 0.0]),
+            ],
+        );
+        // The arm moves up from its rest pose
+        action(
+            "Breathe",
+            vec![matrix(0.0, [0.0, 0.0, 1.0]), matrix(0.0, [1.0, 0.0, 1.0])],
+        );
+        action("Root Only", vec![matrix(0.0, [0.0, 0.0, 0.0])]);
+
+        BlenderArmature {
+            bone_parents: vec![None, Some(0)],
+            local_rest_poses: vec![matrix(0.0, [0.0, 0.0, 0.0]), matrix(0.0, [1.0, 0.0, 0.0])],
+            actions,
+            ..BlenderArmature::default()
+        }
+    }
+
+    fn layer(action_name: &str, weight: f32, blend_mode: LayerBlendMode) -> PoseLayer<'_> {
+        PoseLayer {
+            action: ActionSettings::new(action_name, 0.0, true),
+            weight,
+            bone_mask: None,
+            blend_mode,
+        }
+    }
+
+    fn settings(layers: Vec<PoseLayer<'_>>) -> LayeredPoseSettings<'_> {
+        LayeredPoseSettings {
+            current_time: 0.0,
+            dual_quat_interpolation: DualQuatInterpolation::default(),
+            layers,
+        }
+    }
+
+    /// A masked layer only overrides the child, which still follows the root from the layer below
+    #[test]
+    fn masked_override() {
+        let armature = armature();
+
+        let wave = PoseLayer {
+            bone_mask: Some(armature.bone_mask(&[1])),
+            ..layer("Wave", 1.0, LayerBlendMode::Override)
+        };
+        let settings = settings(vec![
+            layer("Walk", 1.0, LayerBlendMode::Override),
+            wave,
+            layer("Breathe", 0.0, LayerBlendMode::Override),
+        ]);
+
+        assert_bones_eq(
+            &armature.blend_layers(&settings).unwrap(),
+            &[
+                matrix(PI / 2.0, [0.0, 0.0, 0.0]),
+                matrix(PI, [0.0, 1.0, 0.0]),
+            ],
+        );
+    }
+
+    /// An additive layer adds a weighted amount of its difference from the rest pose
+    #[test]
+    fn additive() {
+        let armature = armature();
+
+        let settings = settings(vec![
+            layer("Walk", 1.0, LayerBlendMode::Override),
+            layer("Breathe", 0.5, LayerBlendMode::Additive),
+        ]);
+
+        assert_bones_eq(
+            &armature.blend_layers(&settings).unwrap(),
+            &[
+                matrix(PI / 2.0, [0.0, 0.0, 0.5]),
+                matrix(PI / 2.0, [0.0, 1.0, 0.5]),
+            ],
+        );
+    }
+
+    /// Joints start at rest, so a partial override blends from the rest pose
+    #[test]
+    fn blend_from_rest_pose() {
+        let armature = armature();
+
+        let settings = settings(vec![layer("Breathe", 0.5, LayerBlendMode::Override)]);
+
+        assert_bones_eq(
+            &armature.blend_layers(&settings).unwrap(),
+            &[matrix(0.0, [0.0, 0.0, 0.5]), matrix(0.0, [1.0, 0.0, 0.5])],
+        );
+    }
+
+    #[test]
+    fn bone_masks() {
+        let armature = BlenderArmature {
+            bone_parents: vec![None, Some(0), Some(1), Some(0)],
+            ..BlenderArmature::default()
+        };
+
+        assert_eq!(armature.bone_mask(&[1]), vec![0.0, 1.0, 1.0, 0.0]);
+        assert_eq!(armature.bone_mask(&[0]), vec![1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn layer_errors() {
+        let armature = armature();
+
+        match armature.blend_layers(&settings(vec![])) {
+            Err(LayerError::NoLayers) => {}
+            _ => unreachable!(),
+        };
+
+        let bad_mask = PoseLayer {
+            bone_mask: Some(vec![1.0]),
+            ..layer("Wave", 1.0, LayerBlendMode::Override)
+        };
+        match armature.blend_layers(&settings(vec![bad_mask])) {
+            Err(LayerError::MaskLength {
+                mask_length: 1,
+                joint_count: 2,
+                ..
+            }) => {}
+            _ => unreachable!(),
+        };
+
+        let root_only = layer("Root Only", 1.0, LayerBlendMode::Override);
+        match armature.blend_layers(&settings(vec![root_only])) {
+            Err(LayerError::MismatchedJoints {
+                joint_count: 1,
+                expected_joint_count: 2,
+                ..
+            }) => {}
+            _ => unreachable!(),
+        };
+
+        let unknown = layer("Run", 1.0, LayerBlendMode::Override);
+        match armature.blend_layers(&settings(vec![unknown])) {
+            Err(LayerError::Interpolation(InterpolationError::UnknownAction { .. })) => {}
+            _ => unreachable!(),
+        };
+    }
+}
@@ -24,14 +24,21 @@ extern crate serde_derive;
 
 use std::collections::HashMap;
 
+pub use self::dual_quat::DualQuatInterpolation;
 pub use self::export::*;
+pub use self::hierarchy::HierarchyError;
 pub use crate::interpolate::ActionSettings;
+pub use crate::interpolate::InterpolationError;
 pub use crate::interpolate::InterpolationSettings;
+pub use crate::layer::{LayerBlendMode, LayerError, LayeredPoseSettings, PoseLayer};
 use nalgebra::Matrix4;
 
 mod convert;
+pub mod dual_quat;
 mod export;
+mod hierarchy;
 mod interpolate;
+mod layer;
 
 /// Something went wrong in the Blender child process that was trying to parse your armature data.
 #[derive(Debug, Fail)]
@@ -75,6 +82,12 @@ pub enum Bone {
 pub struct BlenderArmature {
     pub joint_index: HashMap<String, u8>,
     pub inverse_bind_poses: Vec<Bone>,
+    /// The joint index of each bone's parent, or `None` for root bones
+    #[serde(default)]
+    pub bone_parents: Vec<Option<u8>>,
+    /// Each bone's rest pose relative to its parent (or to the armature for root bones)
+    #[serde(default)]
+    pub local_rest_poses: Vec<Bone>,
     // TODO: Generic type instead of string for your action names so that you can have an enum
     // for your action names ... ?
     // TODO: Inner HashMap should have a float key not a string since it is a time in seconds
@@ -91,6 +104,18 @@ pub struct Keyframe {
     bones: Vec<Bone>,
 }
 
+impl Keyframe {
+    /// The time of this keyframe, in seconds
+    pub fn frame_time_secs(&self) -> f32 {
+        self.frame_time_secs
+    }
+
+    /// The pose of every bone at this keyframe, indexed by joint index
+    pub fn bones(&self) -> &[Bone] {
+        &self.bones
+    }
+}
+
 // TODO: These methods can be abstracted into calling a method that takes a callback
 impl BlenderArmature {
     /// Iterate over all of the action bones and apply and multiply in the inverse bind pose.
@@ -110,6 +135,8 @@ impl BlenderArmature {
     /// Tranpose all of the bone matrices in our armature's action keyframes.
     /// Blender uses row major matrices, but OpenGL uses column major matrices so you'll
     /// usually want to transpose your matrices before using them.
+    ///
+    /// The local rest poses get transposed too, so that they can be combined with the actions.
     pub fn transpose_actions(&mut self) {
         for (_name, action) in self.actions.iter_mut() {
             for keyframe in action.iter_mut() {
@@ -118,6 +145,10 @@ impl BlenderArmature {
                 }
             }
         }
+
+        for bone in self.local_rest_poses.iter_mut() {
+            bone.transpose();
+        }
     }
 }
 
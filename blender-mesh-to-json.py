@@ -40,6 +40,8 @@ class MeshToJSON(bpy.types.Operator):
         mesh_json = {
             'vertex_positions': [],
             'num_vertices_in_each_face': [],
+            'smooth_faces': [],
+            'face_materials': [],
             'vertex_position_indices': [],
             'vertex_normals': [],
             'vertex_normal_indices': [],
@@ -55,7 +57,9 @@ class MeshToJSON(bpy.types.Operator):
                 'max_corner': []
             },
             'materials': {
-            }
+            },
+            'material_slots': [],
+            'morph_targets': []
         }
 
         # We maintain a list of all of the parent armature's bone names so that when exporting bone indices / weights
@@ -83,6 +87,8 @@ class MeshToJSON(bpy.types.Operator):
         for face in mesh.data.polygons:
             num_vertices_in_face = len(face.vertices)
             mesh_json['num_vertices_in_each_face'].append(num_vertices_in_face)
+            mesh_json['smooth_faces'].append(face.use_smooth)
+            mesh_json['face_materials'].append(face.material_index)
 
             for i in range(num_vertices_in_face):
                 mesh_json['vertex_position_indices'].append(face.vertices[i])
@@ -126,6 +132,32 @@ class MeshToJSON(bpy.types.Operator):
                 mesh_json['vertex_uvs'].append(loop.uv.x)
                 mesh_json['vertex_uvs'].append(loop.uv.y)
 
+        # Shape keys are exported as deltas from the reference key (Basis). We export one
+        # normal per face, so each shape key gets one normal delta per face.
+        shape_keys = mesh.data.shape_keys
+        if shape_keys is not None:
+            reference_key = shape_keys.reference_key
+            reference_normals = reference_key.normals_polygon_get()
+
+            for key in shape_keys.key_blocks:
+                if key == reference_key:
+                    continue
+
+                position_deltas = []
+                for (vert, reference) in zip(key.data, reference_key.data):
+                    delta = vert.co - reference.co
+                    position_deltas.extend([delta.x, delta.y, delta.z])
+
+                normals = key.normals_polygon_get()
+                normal_deltas = [normal - reference for (normal, reference) in zip(normals, reference_normals)]
+
+                mesh_json['morph_targets'].append({
+                    'name': key.name,
+                    'default_weight': key.value,
+                    'position_deltas': position_deltas,
+                    'normal_deltas': normal_deltas
+                })
+
         if mesh_json['armature_name'] == None:
             mesh_json['vertex_group_indices'] = None
             mesh_json['vertex_group_weights'] = None
@@ -175,7 +207,9 @@ class MeshToJSON(bpy.types.Operator):
         mesh_json['bounding_box']['max_corner'] = max_corner
 
         for material in mesh.data.materials:
-            if material.node_tree == None:
+            mesh_json['material_slots'].append(material.name if material else None)
+
+            if material == None or material.node_tree == None:
                 continue;
 
             # Iterate over the nodes until we find the Principled BSDF node. Then
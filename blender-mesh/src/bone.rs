@@ -1,12 +1,14 @@
-use crate::BlenderMesh;
+use crate::combine_indices::CreateSingleIndexConfig;
+use crate::quantize::{quantize_weights, WeightEncoding};
+use crate::vertex_data::BoneInfluences;
 
 /// The number of bones that influence each uniform.
 ///
 /// When exported from Blender this is non uniform, but becomes uniform when
 /// we call `.set_groups_per_vertex` to make every vertex have the same number
 /// of influences.
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub(crate) enum BoneInfluencesPerVertex {
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum BoneInfluencesPerVertex {
     NonUniform(Vec<u8>),
     Uniform(u8),
 }
@@ -23,7 +25,7 @@ impl From<Vec<u8>> for BoneInfluencesPerVertex {
     }
 }
 
-impl BlenderMesh {
+impl BoneInfluences {
     /// Different vertices might have different numbers of bones that influence them.
     /// A vertex near the shoulder might be influenced by the neck and upper arm and sternum,
     /// while a vertex in a toe might only be influenced by a toe bone.
@@ -38,51 +40,104 @@ impl BlenderMesh {
         let mut normalized_group_indices = vec![];
         let mut normalized_group_weights = vec![];
 
-        let mut current_index: u32 = 0;
+        for mut influences in self.influences_of_each_vertex() {
+            influences.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+            influences.resize(count as usize, (0, 0.0));
 
+            normalized_group_indices.extend(influences.iter().map(|(bone, _)| *bone));
+            normalized_group_weights.extend(influences.iter().map(|(_, weight)| *weight));
+        }
+
+        self.bones_per_vertex = BoneInfluencesPerVertex::Uniform(count);
+
+        self.bone_indices = normalized_group_indices;
+        self.bone_weights = normalized_group_weights;
+    }
+
+    /// Prune, renormalize and quantize each vertex's weights, as set in the config.
+    ///
+    /// Expects every vertex to have `count` influences, sorted by weight, which is what
+    /// `set_bone_influences_per_vertex` leaves behind.
+    pub(crate) fn adjust_bone_weights(&mut self, count: u8, config: &CreateSingleIndexConfig) {
+        let count = count as usize;
+        if count == 0 {
+            return;
+        }
+
+        for (indices, weights) in self
+            .bone_indices
+            .chunks_mut(count)
+            .zip(self.bone_weights.chunks_mut(count))
         {
-            let indices = self.vertex_group_indices.as_mut().unwrap();
-            let weights = self.vertex_group_weights.as_mut().unwrap();
-
-            if let Some(BoneInfluencesPerVertex::NonUniform(bone_influences_per_vertex)) =
-                &self.bone_influences_per_vertex
-            {
-                for group_count in bone_influences_per_vertex.iter() {
-                    let mut vertex_indices = vec![];
-                    let mut vertex_weights = vec![];
-
-                    for index in current_index..(current_index + *group_count as u32) {
-                        vertex_indices.push(index);
-                        vertex_weights.push(weights[index as usize]);
-                    }
-
-                    vertex_weights.sort_by(|a, b| b.partial_cmp(a).unwrap());
-                    vertex_indices.sort_by(|a, b| {
-                        weights[*b as usize]
-                            .partial_cmp(&weights[*a as usize])
-                            .unwrap()
-                    });
-
-                    let mut vertex_indices: Vec<u8> = vertex_indices
-                        .iter()
-                        .map(|i| indices[*i as usize])
-                        .collect();
-
-                    vertex_indices.resize(count as usize, 0);
-                    vertex_weights.resize(count as usize, 0.0);
-
-                    normalized_group_indices.append(&mut vertex_indices);
-                    normalized_group_weights.append(&mut vertex_weights);
-
-                    current_index += *group_count as u32;
+            // The strongest bone is always kept, so that no vertex ends up without any bones
+            for (index, weight) in indices.iter_mut().zip(weights.iter_mut()).skip(1) {
+                if *weight < config.min_bone_weight {
+                    *index = 0;
+                    *weight = 0.0;
+                }
+            }
+
+            let quantize = config
+                .bone_weight_quantization
+                .filter(|encoding| *encoding != WeightEncoding::F32);
+
+            let sum: f32 = weights.iter().sum();
+            if (config.renormalize_bone_weights || quantize.is_some()) && sum > 0.0 {
+                for weight in weights.iter_mut() {
+                    *weight /= sum;
                 }
             }
+
+            let max = match quantize {
+                Some(WeightEncoding::U8) => u8::MAX as u32,
+                Some(WeightEncoding::U16) => u16::MAX as u32,
+                _ => continue,
+            };
+            let quantized = quantize_weights(weights, count, max);
+            for (weight, quantized) in weights.iter_mut().zip(quantized) {
+                *weight = quantized as f32 / max as f32;
+            }
         }
+    }
 
-        self.bone_influences_per_vertex = Some(BoneInfluencesPerVertex::Uniform(count));
+    /// The (bone index, weight) pairs that influence each vertex.
+    ///
+    /// Stops early if there are fewer bone indices or weights than `bones_per_vertex` says.
+    pub(crate) fn influences_of_each_vertex(&self) -> Vec<Vec<(u8, f32)>> {
+        let counts: Vec<usize> = match &self.bones_per_vertex {
+            BoneInfluencesPerVertex::NonUniform(counts) => {
+                counts.iter().map(|count| *count as usize).collect()
+            }
+            BoneInfluencesPerVertex::Uniform(0) => vec![],
+            BoneInfluencesPerVertex::Uniform(count) => {
+                vec![*count as usize; self.bone_indices.len() / *count as usize]
+            }
+        };
 
-        self.vertex_group_indices = Some(normalized_group_indices);
-        self.vertex_group_weights = Some(normalized_group_weights);
+        let mut start = 0;
+        let mut vertices = Vec::with_capacity(counts.len());
+
+        for count in counts {
+            let end = start + count;
+            let (indices, weights) = match (
+                self.bone_indices.get(start..end),
+                self.bone_weights.get(start..end),
+            ) {
+                (Some(indices), Some(weights)) => (indices, weights),
+                _ => break,
+            };
+
+            vertices.push(
+                indices
+                    .iter()
+                    .cloned()
+                    .zip(weights.iter().cloned())
+                    .collect(),
+            );
+            start = end;
+        }
+
+        vertices
     }
 }
 
@@ -92,23 +147,93 @@ mod tests {
 
     #[test]
     fn set_joints_per_vert() {
-        let mut start_mesh = BlenderMesh {
-            vertex_group_indices: Some(vec![0, 2, 3, 4, 0, 1, 3, 2]),
-            bone_influences_per_vertex: Some(vec![1, 3, 4].into()),
-            vertex_group_weights: Some(vec![1.0, 0.5, 0.2, 0.3, 0.6, 0.15, 0.1, 0.15]),
-            ..BlenderMesh::default()
+        let mut start_influences = BoneInfluences {
+            bone_indices: vec![0, 2, 3, 4, 0, 1, 3, 2],
+            bones_per_vertex: vec![1, 3, 4].into(),
+            bone_weights: vec![1.0, 0.5, 0.2, 0.3, 0.6, 0.15, 0.1, 0.15],
+        };
+
+        start_influences.set_bone_influences_per_vertex(3);
+        let three_joints_per_vert = start_influences;
+
+        let expected_influences = BoneInfluences {
+            bone_indices: vec![0, 0, 0, 2, 4, 3, 0, 1, 2],
+            bones_per_vertex: BoneInfluencesPerVertex::Uniform(3),
+            bone_weights: vec![1.0, 0.0, 0.0, 0.5, 0.3, 0.2, 0.6, 0.15, 0.15],
+        };
+
+        assert_eq!(three_joints_per_vert, expected_influences);
+    }
+
+    /// Influences that are already uniform are resized instead of being thrown away
+    #[test]
+    fn set_joints_per_vert_when_already_uniform() {
+        let mut influences = BoneInfluences {
+            bone_indices: vec![4, 2, 1, 3],
+            bones_per_vertex: BoneInfluencesPerVertex::Uniform(2),
+            bone_weights: vec![0.25, 0.75, 0.5, 0.5],
+        };
+
+        influences.set_bone_influences_per_vertex(3);
+
+        assert_eq!(
+            influences,
+            BoneInfluences {
+                bone_indices: vec![2, 4, 0, 1, 3, 0],
+                bones_per_vertex: BoneInfluencesPerVertex::Uniform(3),
+                bone_weights: vec![0.75, 0.25, 0.0, 0.5, 0.5, 0.0],
+            }
+        );
+    }
+
+    /// Weights below the minimum are pruned, and what is left is renormalized
+    #[test]
+    fn prune_and_renormalize_weights() {
+        let mut influences = BoneInfluences {
+            bone_indices: vec![0, 1, 2, 3, 5, 6, 7, 8],
+            bones_per_vertex: vec![4, 4].into(),
+            bone_weights: vec![0.4, 0.3, 0.05, 0.2, 0.01, 0.02, 0.03, 0.01],
+        };
+        let config = CreateSingleIndexConfig {
+            renormalize_bone_weights: true,
+            min_bone_weight: 0.1,
+            ..CreateSingleIndexConfig::default()
         };
 
-        start_mesh.set_bone_influences_per_vertex(3);
-        let three_joints_per_vert = start_mesh;
+        influences.set_bone_influences_per_vertex(3);
+        influences.adjust_bone_weights(3, &config);
 
-        let expected_mesh = BlenderMesh {
-            vertex_group_indices: Some(vec![0, 0, 0, 2, 4, 3, 0, 1, 2]),
-            bone_influences_per_vertex: Some(BoneInfluencesPerVertex::Uniform(3)),
-            vertex_group_weights: Some(vec![1.0, 0.0, 0.0, 0.5, 0.3, 0.2, 0.6, 0.15, 0.15]),
-            ..BlenderMesh::default()
+        assert_eq!(influences.bone_indices, vec![0, 1, 3, 7, 0, 0]);
+        let expected_weights = [4.0 / 9.0, 3.0 / 9.0, 2.0 / 9.0, 1.0, 0.0, 0.0];
+        for (weight, expected) in influences.bone_weights.iter().zip(expected_weights.iter()) {
+            assert!((weight - expected).abs() < 1e-6);
+        }
+    }
+
+    /// Quantized weights are multiples of 1 / 255 that add up to exactly 255 / 255
+    #[test]
+    fn quantize_weights_to_exactly_one() {
+        let mut influences = BoneInfluences {
+            bone_indices: vec![0, 1, 2],
+            bones_per_vertex: vec![3].into(),
+            bone_weights: vec![0.2, 0.2, 0.2],
+        };
+        let config = CreateSingleIndexConfig {
+            bone_weight_quantization: Some(WeightEncoding::U8),
+            ..CreateSingleIndexConfig::default()
         };
 
-        assert_eq!(three_joints_per_vert, expected_mesh);
+        influences.set_bone_influences_per_vertex(3);
+        influences.adjust_bone_weights(3, &config);
+
+        let quantized: Vec<f32> = influences
+            .bone_weights
+            .iter()
+            .map(|weight| weight * 255.0)
+            .collect();
+        for weight in quantized.iter() {
+            assert_eq!(weight.round(), *weight);
+        }
+        assert_eq!(quantized.iter().sum::<f32>(), 255.0);
     }
 }
@@ -0,0 +1,377 @@
+//! Shaders can only hold so many bone matrices (or dual quaternions) in their uniforms, so a mesh
+//! that is influenced by more bones than that needs to be drawn in pieces, where each piece only
+//! uses a small palette of bones.
+
+use crate::draw_group::{draw_groups, DrawGroup};
+use crate::optimize::{reorder, reorder_attribute};
+use crate::vertex_data::{BoneAttributes, SingleIndexVertexData, VertexDataError};
+use crate::BlenderMesh;
+use blender_armature::BlenderArmature;
+use std::collections::{BTreeSet, HashMap};
+
+/// Configuration for [`BlenderMesh.split_by_bone_palette`]
+///
+/// [`BlenderMesh.split_by_bone_palette`]: struct.BlenderMesh.html#method.split_by_bone_palette
+#[derive(Debug)]
+pub struct BonePaletteConfig {
+    /// The most bones that one sub mesh can use, i.e. the number of bones that your shader
+    /// has room for
+    pub max_bones: usize,
+}
+
+impl Default for BonePaletteConfig {
+    fn default() -> Self {
+        BonePaletteConfig { max_bones: 64 }
+    }
+}
+
+/// Part of a mesh that is influenced by at most [`BonePaletteConfig.max_bones`] bones.
+///
+/// [`BonePaletteConfig.max_bones`]: struct.BonePaletteConfig.html#structfield.max_bones
+#[derive(Debug, PartialEq)]
+pub struct BonePaletteMesh {
+    /// The sub mesh's own vertices. Its bone indices are indices into the `palette`.
+    pub vertex_data: SingleIndexVertexData,
+    /// The armature's bone index for each of the sub mesh's bones, so the bone at
+    /// `palette[0]` in [`BlenderArmature.joint_index`] goes into the shader's first uniform.
+    ///
+    /// [`BlenderArmature.joint_index`]: ../blender_armature/struct.BlenderArmature.html#structfield.joint_index
+    pub palette: Vec<u8>,
+    /// The range of the sub mesh's index buffer that uses each material
+    pub draw_groups: Vec<DrawGroup>,
+}
+
+impl BonePaletteMesh {
+    /// The name of each bone in the palette, or `None` for bones that the armature doesn't have
+    pub fn joint_names<'a>(&self, armature: &'a BlenderArmature) -> Vec<Option<&'a str>> {
+        let names: HashMap<u8, &str> = armature
+            .joint_index
+            .iter()
+            .map(|(name, index)| (*index, name.as_str()))
+            .collect();
+
+        self.palette
+            .iter()
+            .map(|bone| names.get(bone).cloned())
+            .collect()
+    }
+}
+
+/// An error while splitting a mesh by bone palette
+#[derive(Debug, Fail)]
+pub enum BonePaletteError {
+    #[fail(display = "Could not split the mesh by bone palette: {}", _0)]
+    VertexData(#[cause] VertexDataError),
+    #[fail(display = "The mesh does not have any bone influences")]
+    NoBoneInfluences,
+    #[fail(
+        display = "Face {} is influenced by {} bones, but a palette can only have {}",
+        face, bone_count, max_bones
+    )]
+    TooManyBonesInFace {
+        face: usize,
+        bone_count: usize,
+        max_bones: usize,
+    },
+}
+
+impl From<VertexDataError> for BonePaletteError {
+    fn from(error: VertexDataError) -> Self {
+        BonePaletteError::VertexData(error)
+    }
+}
+
+impl BlenderMesh {
+    /// Split a skinned mesh into sub meshes that each use at most `config.max_bones` bones.
+    ///
+    /// Each face goes into the sub mesh that already has the most of its bones (as long as
+    /// there is room for the rest), so faces that share bones end up together. Faces keep their
+    /// order within each sub mesh, and vertices that are used by more than one sub mesh are
+    /// copied into each of them.
+    ///
+    /// Bones with a weight of zero don't count towards a face's bones.
+    pub fn split_by_bone_palette(
+        &self,
+        config: &BonePaletteConfig,
+    ) -> Result<Vec<BonePaletteMesh>, BonePaletteError> {
+        let single = self.single_index_vertex_data_or_err()?;
+        let bones = single
+            .bones
+            .as_ref()
+            .ok_or(BonePaletteError::NoBoneInfluences)?;
+        let bones_per_vertex = bones.bones_per_vertex as usize;
+
+        let mut palettes: Vec<BTreeSet<u8>> = vec![];
+        let mut palette_faces: Vec<Vec<usize>> = vec![];
+
+        for (face, corners) in faces(&single.vertices_in_each_face).into_iter().enumerate() {
+            let mut face_bones = BTreeSet::new();
+            for index in single.indices[corners].iter() {
+                let vertex = *index as usize;
+                let influences = vertex * bones_per_vertex..(vertex + 1) * bones_per_vertex;
+
+                for (bone, weight) in bones.bone_indices[influences.clone()]
+                    .iter()
+                    .zip(bones.bone_weights[influences].iter())
+                {
+                    if *weight != 0.0 {
+                        face_bones.insert(*bone);
+                    }
+                }
+            }
+
+            if face_bones.len() > config.max_bones {
+                return Err(BonePaletteError::TooManyBonesInFace {
+                    face,
+                    bone_count: face_bones.len(),
+                    max_bones: config.max_bones,
+                });
+            }
+
+            let best = palettes
+                .iter()
+                .enumerate()
+                .map(|(idx, palette)| (idx, face_bones.difference(palette).count()))
+                .filter(|(idx, new_bones)| palettes[*idx].len() + new_bones <= config.max_bones)
+                .min_by_key(|(_, new_bones)| *new_bones)
+                .map(|(idx, _)| idx);
+
+            let palette = match best {
+                Some(palette) => palette,
+                None => {
+                    palettes.push(BTreeSet::new());
+                    palette_faces.push(vec![]);
+                    palettes.len() - 1
+                }
+            };
+
+            palettes[palette].extend(face_bones);
+            palette_faces[palette].push(face);
+        }
+
+        Ok(palettes
+            .into_iter()
+            .zip(palette_faces)
+            .map(|(palette, faces)| {
+                let palette: Vec<u8> = palette.into_iter().collect();
+                let vertex_data = single.sub_mesh(&faces, bones, &palette);
+
+                BonePaletteMesh {
+                    draw_groups: draw_groups(
+                        &vertex_data.vertices_in_each_face,
+                        vertex_data.face_materials.as_deref(),
+                        &self.material_slots,
+                    ),
+                    vertex_data,
+                    palette,
+                }
+            })
+            .collect())
+    }
+}
+
+impl SingleIndexVertexData {
+    /// A copy of some of the faces with their own vertices, with bone indices into the palette
+    fn sub_mesh(
+        &self,
+        faces_to_keep: &[usize],
+        bones: &BoneAttributes,
+        palette: &[u8],
+    ) -> SingleIndexVertexData {
+        let face_corners = faces(&self.vertices_in_each_face);
+
+        let mut new_indices: HashMap<u32, u32> = HashMap::new();
+        let mut old_vertices = vec![];
+        let mut indices = vec![];
+
+        for face in faces_to_keep {
+            for index in self.indices[face_corners[*face].clone()].iter() {
+                let new_index = new_indices.entry(*index).or_insert_with(|| {
+                    old_vertices.push(*index as usize);
+                    (old_vertices.len() - 1) as u32
+                });
+                indices.push(*new_index);
+            }
+        }
+
+        let local_bones: HashMap<u8, u8> = palette
+            .iter()
+            .enumerate()
+            .map(|(local, bone)| (*bone, local as u8))
+            .collect();
+        let bones_per_vertex = bones.bones_per_vertex as usize;
+
+        let bone_weights = reorder(&bones.bone_weights, bones_per_vertex, &old_vertices);
+        let bone_indices = reorder(&bones.bone_indices, bones_per_vertex, &old_vertices)
+            .iter()
+            .zip(bone_weights.iter())
+            // Bones without any weight might not be in the palette, but they don't matter
+            .map(|(bone, weight)| match local_bones.get(bone) {
+                Some(local) => *local,
+                None if *weight == 0.0 => 0,
+                None => unreachable!("Every weighted bone is in the face's palette"),
+            })
+            .collect();
+
+        SingleIndexVertexData {
+            vertices_in_each_face: faces_to_keep
+                .iter()
+                .map(|face| self.vertices_in_each_face[*face])
+                .collect(),
+            indices,
+            index_width: self.index_width,
+            positions: reorder_attribute(&self.positions, &old_vertices),
+            normals: reorder_attribute(&self.normals, &old_vertices),
+            uvs: self
+                .uvs
+                .as_ref()
+                .map(|uvs| reorder_attribute(uvs, &old_vertices)),
+            tangents: self
+                .tangents
+                .as_ref()
+                .map(|tangents| reorder_attribute(tangents, &old_vertices)),
+            bones: Some(BoneAttributes {
+                bones_per_vertex: bones.bones_per_vertex,
+                bone_indices,
+                bone_weights,
+            }),
+            face_materials: self
+                .face_materials
+                .as_ref()
+                .map(|materials| faces_to_keep.iter().map(|face| materials[*face]).collect()),
+            morph_targets: self
+                .morph_targets
+                .iter()
+                .map(|target| target.reorder(&old_vertices))
+                .collect(),
+        }
+    }
+}
+
+/// The range of the index buffer that each face uses
+fn faces(vertices_in_each_face: &[u8]) -> Vec<std::ops::Range<usize>> {
+    let mut start = 0;
+
+    vertices_in_each_face
+        .iter()
+        .map(|vertex_count| {
+            let end = start + *vertex_count as usize;
+            let face = start..end;
+            start = end;
+            face
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::vertex_data::VertexData;
+
+    /// A strip of four triangles where each triangle is influenced by two bones, with each
+    /// neighboring pair of triangles sharing a bone
+    fn bone_strip() -> BlenderMesh {
+        let mut positions = vec![];
+        for vertex in 0..6 {
+            positions.extend_from_slice(&[vertex as f32, (vertex % 2) as f32, 0.0]);
+        }
+
+        BlenderMesh {
+            material_slots: vec![Some("Skin".to_string())],
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                vertices_in_each_face: vec![3, 3, 3, 3],
+                indices: vec![0, 1, 2, 2, 1, 3, 2, 3, 4, 4, 3, 5],
+                positions: attrib3(positions),
+                normals: attrib3(vec![0.0; 18]),
+                bones: Some(BoneAttributes {
+                    bones_per_vertex: 2,
+                    bone_indices: vec![10, 11, 10, 0, 11, 12, 11, 12, 12, 13, 13, 3],
+                    bone_weights: vec![0.5, 0.5, 1.0, 0.0, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 1.0, 0.0],
+                }),
+                face_materials: Some(vec![0; 4]),
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        }
+    }
+
+    #[test]
+    fn split_into_palettes() {
+        let mesh = bone_strip();
+
+        let sub_meshes = mesh
+            .split_by_bone_palette(&BonePaletteConfig { max_bones: 3 })
+            .unwrap();
+
+        assert_eq!(sub_meshes.len(), 2);
+
+        assert_eq!(sub_meshes[0].palette, vec![10, 11, 12]);
+        assert_eq!(sub_meshes[0].vertex_data.indices, vec![0, 1, 2, 2, 1, 3]);
+        assert_eq!(
+            sub_meshes[0]
+                .vertex_data
+                .bones
+                .as_ref()
+                .unwrap()
+                .bone_indices,
+            vec![0, 1, 0, 0, 1, 2, 1, 2]
+        );
+
+        assert_eq!(sub_meshes[1].palette, vec![11, 12, 13]);
+        assert_eq!(sub_meshes[1].vertex_data.indices, vec![0, 1, 2, 2, 1, 3]);
+        assert_eq!(sub_meshes[1].vertex_data.positions.vertex_count(), 4);
+        assert_eq!(
+            sub_meshes[1]
+                .vertex_data
+                .bones
+                .as_ref()
+                .unwrap()
+                .bone_indices,
+            vec![0, 1, 0, 1, 1, 2, 2, 0]
+        );
+        assert_eq!(sub_meshes[1].draw_groups[0].count, 6);
+    }
+
+    /// Everything fits into one palette when the limit is high enough
+    #[test]
+    fn one_palette_for_few_bones() {
+        let mesh = bone_strip();
+
+        let sub_meshes = mesh
+            .split_by_bone_palette(&BonePaletteConfig::default())
+            .unwrap();
+
+        assert_eq!(sub_meshes.len(), 1);
+        assert_eq!(sub_meshes[0].palette, vec![10, 11, 12, 13]);
+        assert_eq!(sub_meshes[0].vertex_data.indices.len(), 12);
+
+        let armature = BlenderArmature {
+            joint_index: vec![("Hip".to_string(), 10), ("Knee".to_string(), 11)]
+                .into_iter()
+                .collect(),
+            inverse_bind_poses: vec![],
+            bone_parents: vec![],
+            local_rest_poses: vec![],
+            actions: HashMap::new(),
+        };
+        assert_eq!(
+            sub_meshes[0].joint_names(&armature),
+            vec![Some("Hip"), Some("Knee"), None, None]
+        );
+    }
+
+    #[test]
+    fn face_with_too_many_bones() {
+        let mesh = bone_strip();
+
+        match mesh.split_by_bone_palette(&BonePaletteConfig { max_bones: 2 }) {
+            Err(BonePaletteError::TooManyBonesInFace {
+                face: 0,
+                bone_count: 3,
+                max_bones: 2,
+            }) => {}
+            _ => unreachable!(),
+        };
+    }
+}
@@ -1,11 +1,22 @@
-use nalgebra::Point3;
+use crate::vertex_data::VertexData;
+use crate::BlenderMesh;
+use blender_armature::{
+    ActionSettings, BlenderArmature, Bone, DualQuatInterpolation, InterpolationError,
+    InterpolationSettings, Keyframe,
+};
+use nalgebra::{Matrix3, Matrix4, Point3, Rotation3, Vector3, U3};
 
 /// The bounding box that encompasses a mesh. This will usually come from Blender as a z_up
 /// coordinate system bounding box that you'll later convert to be y_up.
 ///
 /// If your mesh is parented to an armature then this is the bounding box of your mesh in its
 /// bind pose.
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+///
+/// The bounding box from Blender isn't updated when you change the mesh's positions, so call
+/// [`BlenderMesh.recompute_bounding_box`] after doing that.
+///
+/// [`BlenderMesh.recompute_bounding_box`]: struct.BlenderMesh.html#method.recompute_bounding_box
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct BoundingBox {
     /// The corner with the lowest x, y and z values
     pub min_corner: Point3<f32>,
@@ -21,3 +32,981 @@ impl Default for BoundingBox {
         }
     }
 }
+
+impl BoundingBox {
+    /// The smallest bounding box that contains every position, where `positions` is
+    /// `[x, y, z, x, y, z, ...]`.
+    ///
+    /// Returns `None` if there are no positions.
+    pub fn from_positions(positions: &[f32]) -> Option<BoundingBox> {
+        let mut positions = positions
+            .chunks(3)
+            .map(|position| Point3::new(position[0], position[1], position[2]));
+
+        let first = positions.next()?;
+        let mut bounding_box = BoundingBox {
+            min_corner: first,
+            max_corner: first,
+        };
+
+        for position in positions {
+            bounding_box.include_point(&position);
+        }
+
+        Some(bounding_box)
+    }
+
+    /// The point in the middle of the bounding box
+    pub fn center(&self) -> Point3<f32> {
+        nalgebra::center(&self.min_corner, &self.max_corner)
+    }
+
+    /// Whether or not the point is inside of (or on the edge of) the bounding box
+    pub fn contains_point(&self, point: &Point3<f32>) -> bool {
+        (0..3).all(|axis| {
+            point[axis] >= self.min_corner[axis] && point[axis] <= self.max_corner[axis]
+        })
+    }
+
+    /// The smallest bounding box that contains both bounding boxes
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min_corner: Point3::from(
+                self.min_corner
+                    .coords
+                    .zip_map(&other.min_corner.coords, f32::min),
+            ),
+            max_corner: Point3::from(
+                self.max_corner
+                    .coords
+                    .zip_map(&other.max_corner.coords, f32::max),
+            ),
+        }
+    }
+
+    /// The bounding box of this bounding box after it has been transformed by the matrix.
+    ///
+    /// Rotating a box makes it bigger, so this is a loose fit around the transformed geometry.
+    /// Transform the positions and call `BoundingBox::from_positions` if you need a tight fit.
+    ///
+    /// @see Graphics Gems, "Transforming Axis-Aligned Bounding Boxes" (Arvo)
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> BoundingBox {
+        let mut min_corner = Point3::new(matrix[(0, 3)], matrix[(1, 3)], matrix[(2, 3)]);
+        let mut max_corner = min_corner;
+
+        for row in 0..3 {
+            for column in 0..3 {
+                let a = matrix[(row, column)] * self.min_corner[column];
+                let b = matrix[(row, column)] * self.max_corner[column];
+
+                min_corner[row] += a.min(b);
+                max_corner[row] += a.max(b);
+            }
+        }
+
+        BoundingBox {
+            min_corner,
+            max_corner,
+        }
+    }
+
+    /// The bounding box of every point in this bounding box plus every point in `other`
+    fn sum(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min_corner: self.min_corner + other.min_corner.coords,
+            max_corner: self.max_corner + other.max_corner.coords,
+        }
+    }
+
+    /// Grow the bounding box by `amount` in every direction
+    fn padded(&self, amount: f32) -> BoundingBox {
+        let amount = Vector3::new(amount, amount, amount);
+
+        BoundingBox {
+            min_corner: self.min_corner - amount,
+            max_corner: self.max_corner + amount,
+        }
+    }
+
+    pub(crate) fn include_point(&mut self, point: &Point3<f32>) {
+        for axis in 0..3 {
+            self.min_corner[axis] = self.min_corner[axis].min(point[axis]);
+            self.max_corner[axis] = self.max_corner[axis].max(point[axis]);
+        }
+    }
+}
+
+/// Configuration for [`BlenderMesh.sampled_skinned_bounding_box`]
+///
+/// [`BlenderMesh.sampled_skinned_bounding_box`]: struct.BlenderMesh.html#method.sampled_skinned_bounding_box
+#[derive(Debug)]
+pub struct SkinnedBoundsConfig {
+    /// The number of poses to check in between each pair of neighboring keyframes.
+    ///
+    /// Bones can swing outside of the bounds of both of their keyframes while they're being
+    /// interpolated, so more samples give more accurate bounds.
+    pub samples_between_keyframes: u32,
+    /// How the poses in between keyframes are interpolated. Use the same method that you pass
+    /// to `BlenderArmature.interpolate_bones` at runtime.
+    pub dual_quat_interpolation: DualQuatInterpolation,
+}
+
+impl Default for SkinnedBoundsConfig {
+    fn default() -> Self {
+        SkinnedBoundsConfig {
+            samples_between_keyframes: 4,
+            dual_quat_interpolation: DualQuatInterpolation::default(),
+        }
+    }
+}
+
+/// An error while finding the bounds of a skinned mesh
+#[derive(Debug, Fail)]
+pub enum SkinnedBoundsError {
+    #[fail(display = "The mesh has no bone influences, so it can't be skinned")]
+    NoBoneInfluences,
+    #[fail(display = "The armature does not have an action named {}", _0)]
+    UnknownAction(String),
+    #[fail(
+        display = "A vertex is influenced by bone {} but the action only has {} bones",
+        bone, bone_count
+    )]
+    MissingBone { bone: u8, bone_count: usize },
+    #[fail(display = "Could not interpolate the action: {}", _0)]
+    Interpolation(#[cause] InterpolationError),
+}
+
+impl From<InterpolationError> for SkinnedBoundsError {
+    fn from(error: InterpolationError) -> Self {
+        SkinnedBoundsError::Interpolation(error)
+    }
+}
+
+impl BlenderMesh {
+    /// Replace the mesh's `bounding_box` with the smallest bounding box that contains all of the
+    /// mesh's current positions.
+    ///
+    /// After combining indices only the vertices that the indices use are included.
+    ///
+    /// Meshes without any positions get an empty bounding box at the origin.
+    pub fn recompute_bounding_box(&mut self) {
+        let bounding_box = match &self.vertex_data {
+            VertexData::Multi(multi) => BoundingBox::from_positions(multi.positions().data()),
+            VertexData::Single(single) => BoundingBox::from_positions(&single.indexed_positions()),
+        };
+
+        self.bounding_box = bounding_box.unwrap_or_default();
+    }
+
+    /// An approximate bounding box around the mesh as it plays one of its armature's actions,
+    /// useful for frustum culling a skinned mesh without skinning it on the CPU.
+    ///
+    /// For each bone we find the bounding box of the vertices that it influences, and then
+    /// transform that box by the bone at every keyframe and at
+    /// `config.samples_between_keyframes` poses in between each pair of keyframes. The poses in
+    /// between keyframes are interpolated by `BlenderArmature.interpolate_bones`.
+    ///
+    /// This is a sampled bound, not a conservative one, so use
+    /// [`BlenderMesh.conservative_skinned_bounding_box`] if you need to be sure:
+    ///
+    /// - Bones move along arcs between the samples, so a bone that rotates quickly can swing
+    ///   outside of the bounds. More samples get closer to the true bounds.
+    /// - A linear blend skinned vertex is a weighted average of where its bones would put it, so
+    ///   at the sampled poses it's always inside of the bounds. Dual quaternion skinning blends
+    ///   rotations instead, so a vertex that is influenced by several bones can bulge outside.
+    ///
+    /// The action's bones are expected to be in the same space as the mesh's positions (i.e. the
+    /// inverse bind poses have been applied), and matrix bones need to be column major so call
+    /// `BlenderArmature.transpose_actions` first.
+    ///
+    /// [`BlenderMesh.conservative_skinned_bounding_box`]: struct.BlenderMesh.html#method.conservative_skinned_bounding_box
+    pub fn sampled_skinned_bounding_box(
+        &self,
+        armature: &BlenderArmature,
+        action_name: &str,
+        config: &SkinnedBoundsConfig,
+    ) -> Result<BoundingBox, SkinnedBoundsError> {
+        let bone_bounds = self.bounds_of_each_bone()?;
+        let joint_indices = influencing_bones(&bone_bounds);
+        let keyframes = sorted_keyframes(armature, action_name, &joint_indices)?;
+
+        let mut skinned: Option<BoundingBox> = None;
+        let mut include = |bone: u8, pose: &Bone| {
+            let bounds = match &bone_bounds[bone as usize] {
+                Some(bounds) => bounds.transform(&bone_matrix(pose)),
+                None => return,
+            };
+
+            skinned = Some(match skinned.as_ref() {
+                Some(skinned) => skinned.union(&bounds),
+                None => bounds,
+            });
+        };
+
+        let start_time = keyframes.first().map(|keyframe| keyframe.frame_time_secs());
+        for (idx, keyframe) in keyframes.iter().enumerate() {
+            for bone in joint_indices.iter() {
+                include(*bone, &keyframe.bones()[*bone as usize]);
+            }
+
+            let next_time = match keyframes.get(idx + 1) {
+                Some(next) => next.frame_time_secs(),
+                None => continue,
+            };
+
+            let samples = config.samples_between_keyframes;
+            for sample in 1..=samples {
+                let amount = sample as f32 / (samples + 1) as f32;
+                let time =
+                    keyframe.frame_time_secs() + (next_time - keyframe.frame_time_secs()) * amount;
+
+                let pose = armature.interpolate_bones(&InterpolationSettings {
+                    current_time: time,
+                    joint_indices: joint_indices.clone(),
+                    blend_fn: None,
+                    dual_quat_interpolation: config.dual_quat_interpolation,
+                    current_action: ActionSettings::new(
+                        action_name,
+                        start_time.unwrap_or(0.0),
+                        false,
+                    ),
+                    previous_action: None,
+                })?;
+                for (bone, pose) in pose.iter() {
+                    include(*bone, pose);
+                }
+            }
+        }
+
+        Ok(skinned.unwrap_or_else(|| self.bounding_box.clone()))
+    }
+
+    /// A bounding box that contains the mesh at every moment of one of its armature's actions,
+    /// for frustum culling a skinned mesh without ever culling it by mistake.
+    ///
+    /// For each bone we put a sphere around the vertices that it influences. In between two
+    /// keyframes the bone's rotation takes the shortest path, so the center of the sphere moves
+    /// along a circular arc of at most half a turn (plus a straight line for the bone's
+    /// translation). That arc stays within the ball whose diameter is the arc's chord, so we
+    /// bound the arc by that ball and then pad it by the sphere's radius. This holds for matrix
+    /// bones as well as both ways of interpolating dual quaternion bones, which follow the same
+    /// screw motion.
+    ///
+    /// A linear blend skinned vertex is a weighted average of where its bones would put it, so it
+    /// stays within these bounds. So does any vertex that is influenced by a single bone. Dual
+    /// quaternion skinning blends rotations instead, so a vertex that is influenced by several
+    /// bones can still bulge outside of the bounds.
+    ///
+    /// This is looser than [`BlenderMesh.sampled_skinned_bounding_box`], especially for long
+    /// bones that rotate a lot.
+    ///
+    /// The action's bones are expected to be in the same space as the mesh's positions (i.e. the
+    /// inverse bind poses have been applied), and matrix bones need to be column major so call
+    /// `BlenderArmature.transpose_actions` first.
+    ///
+    /// [`BlenderMesh.sampled_skinned_bounding_box`]: struct.BlenderMesh.html#method.sampled_skinned_bounding_box
+    pub fn conservative_skinned_bounding_box(
+        &self,
+        armature: &BlenderArmature,
+        action_name: &str,
+    ) -> Result<BoundingBox, SkinnedBoundsError> {
+        let bone_bounds = self.bounds_of_each_bone()?;
+        let joint_indices = influencing_bones(&bone_bounds);
+        let keyframes = sorted_keyframes(armature, action_name, &joint_indices)?;
+
+        let mut skinned: Option<BoundingBox> = None;
+
+        for (bone, bounds) in bone_bounds.iter().enumerate() {
+            let bounds = match bounds {
+                Some(bounds) => bounds,
+                None => continue,
+            };
+            let center = bounds.center();
+            let radius = (bounds.max_corner - bounds.min_corner).norm() / 2.0;
+
+            // An action with a single keyframe holds that pose the whole time
+            let pairs = keyframes
+                .windows(2)
+                .map(|pair| (pair[0], pair[1]))
+                .chain(keyframes.first().map(|first| (*first, *first)));
+
+            for (start, end) in pairs {
+                let (swept, scale) = swept_point(&start.bones()[bone], &end.bones()[bone], &center);
+                let swept = swept.padded(radius * scale);
+
+                skinned = Some(match skinned.as_ref() {
+                    Some(skinned) => skinned.union(&swept),
+                    None => swept,
+                });
+            }
+        }
+
+        Ok(skinned.unwrap_or_else(|| self.bounding_box.clone()))
+    }
+
+    /// The bounding box of the positions that each bone influences, indexed by bone
+    fn bounds_of_each_bone(&self) -> Result<Vec<Option<BoundingBox>>, SkinnedBoundsError> {
+        let (positions, influences) = match &self.vertex_data {
+            VertexData::Multi(multi) => {
+                let influences = multi
+                    .bone_influences()
+                    .ok_or(SkinnedBoundsError::NoBoneInfluences)?;
+                (multi.positions(), influences.influences_of_each_vertex())
+            }
+            VertexData::Single(single) => {
+                let bones = single.bones().ok_or(SkinnedBoundsError::NoBoneInfluences)?;
+                let influences = bones
+                    .bone_indices()
+                    .chunks(bones.bones_per_vertex.max(1) as usize)
+                    .zip(
+                        bones
+                            .bone_weights()
+                            .chunks(bones.bones_per_vertex.max(1) as usize),
+                    )
+                    .map(|(indices, weights)| {
+                        indices
+                            .iter()
+                            .cloned()
+                            .zip(weights.iter().cloned())
+                            .collect()
+                    })
+                    .collect();
+                (single.positions(), influences)
+            }
+        };
+
+        let mut bone_bounds: Vec<Option<BoundingBox>> = vec![];
+
+        for (position, influences) in positions.data().chunks(3).zip(influences.iter()) {
+            let position = Point3::new(position[0], position[1], position[2]);
+
+            for (bone, weight) in influences.iter() {
+                if *weight <= 0.0 {
+                    continue;
+                }
+
+                let bone = *bone as usize;
+                if bone_bounds.len() <= bone {
+                    bone_bounds.resize(bone + 1, None);
+                }
+
+                match bone_bounds[bone].as_mut() {
+                    Some(bounds) => bounds.include_point(&position),
+                    None => {
+                        bone_bounds[bone] = Some(BoundingBox {
+                            min_corner: position,
+                            max_corner: position,
+                        })
+                    }
+                };
+            }
+        }
+
+        Ok(bone_bounds)
+    }
+}
+
+fn bone_matrix(bone: &Bone) -> Matrix4<f32> {
+    let matrix = match bone {
+        Bone::Matrix(_) => bone.as_slice().to_vec(),
+        Bone::DualQuat(_) => BlenderArmature::dual_quat_to_matrix(bone)
+            .as_slice()
+            .to_vec(),
+    };
+
+    Matrix4::from_column_slice(&matrix)
+}
+
+/// The bones that influence at least one vertex
+fn influencing_bones(bone_bounds: &[Option<BoundingBox>]) -> Vec<u8> {
+    bone_bounds
+        .iter()
+        .enumerate()
+        .filter(|(_, bounds)| bounds.is_some())
+        .map(|(bone, _)| bone as u8)
+        .collect()
+}
+
+/// The action's keyframes in the order that they're played, making sure that each of them has
+/// all of the `joint_indices`
+fn sorted_keyframes<'a>(
+    armature: &'a BlenderArmature,
+    action_name: &str,
+    joint_indices: &[u8],
+) -> Result<Vec<&'a Keyframe>, SkinnedBoundsError> {
+    let keyframes = armature
+        .actions
+        .get(action_name)
+        .ok_or_else(|| SkinnedBoundsError::UnknownAction(action_name.to_string()))?;
+
+    let mut keyframes: Vec<_> = keyframes.iter().collect();
+    keyframes.sort_by(|a, b| {
+        a.frame_time_secs()
+            .partial_cmp(&b.frame_time_secs())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    for keyframe in keyframes.iter() {
+        let bone_count = keyframe.bones().len();
+        if let Some(bone) = joint_indices
+            .iter()
+            .find(|bone| **bone as usize >= bone_count)
+        {
+            return Err(SkinnedBoundsError::MissingBone {
+                bone: *bone,
+                bone_count,
+            });
+        }
+    }
+
+    Ok(keyframes)
+}
+
+/// A bounding box around every position that `point` is moved to while a bone is interpolated
+/// from `start` to `end`, along with the largest scale that the bone applies along the way.
+fn swept_point(start: &Bone, end: &Bone, point: &Point3<f32>) -> (BoundingBox, f32) {
+    match (start, end) {
+        (Bone::Matrix(_), Bone::Matrix(_)) => {
+            swept_by_matrices(&bone_matrix(start), &bone_matrix(end), point)
+        }
+        _ => (
+            swept_by_screw(&bone_matrix(start), &bone_matrix(end), point),
+            1.0,
+        ),
+    }
+}
+
+/// Matrix bones are decomposed and then their translations and scales are lerped while their
+/// rotations are slerped.
+///
+/// So the point ends up at `translation + rotation * (scale * point)`. The translation moves
+/// along a line, and the scaled point is a blend of the point scaled by each bone, each of which
+/// the rotation moves along an arc.
+fn swept_by_matrices(
+    start: &Matrix4<f32>,
+    end: &Matrix4<f32>,
+    point: &Point3<f32>,
+) -> (BoundingBox, f32) {
+    let (start_translation, start_rotation, start_scale) = decompose(start);
+    let (end_translation, end_rotation, end_scale) = decompose(end);
+
+    let translated = BoundingBox::from_positions(&[
+        start_translation.x,
+        start_translation.y,
+        start_translation.z,
+        end_translation.x,
+        end_translation.y,
+        end_translation.z,
+    ])
+    .unwrap_or_default();
+
+    let start_scaled = start_scale.component_mul(&point.coords);
+    let end_scaled = end_scale.component_mul(&point.coords);
+    let rotated = arc_bounds(
+        &Point3::from(start_rotation * start_scaled),
+        &Point3::from(end_rotation * start_scaled),
+    )
+    .union(&arc_bounds(
+        &Point3::from(start_rotation * end_scaled),
+        &Point3::from(end_rotation * end_scaled),
+    ));
+
+    let scale = start_scale.amax().max(end_scale.amax());
+
+    (translated.sum(&rotated), scale)
+}
+
+/// The same translation, rotation and scale that `BlenderArmature.interpolate_bones` decomposes
+/// matrix bones into before interpolating them
+fn decompose(matrix: &Matrix4<f32>) -> (Vector3<f32>, Matrix3<f32>, Vector3<f32>) {
+    let translation = Vector3::new(matrix[(0, 3)], matrix[(1, 3)], matrix[(2, 3)]);
+
+    let mut rotation: Matrix3<f32> = matrix.fixed_slice::<U3, U3>(0, 0).into_owned();
+    let mut scale = Vector3::new(
+        rotation.column(0).norm(),
+        rotation.column(1).norm(),
+        rotation.column(2).norm(),
+    );
+
+    if rotation.determinant() < 0.0 {
+        scale.x = -scale.x;
+    }
+
+    for axis in 0..3 {
+        if scale[axis] != 0.0 {
+            let mut column = rotation.column_mut(axis);
+            column /= scale[axis];
+        }
+    }
+
+    (translation, rotation, scale)
+}
+
+/// Dual quaternion bones move along the screw motion between the two bones (lerping and then
+/// normalizing follows the same path as screw linear interpolation, just at a different speed).
+///
+/// That's a rotation around the screw's axis, which moves the point along an arc, plus a
+/// translation along the axis.
+fn swept_by_screw(start: &Matrix4<f32>, end: &Matrix4<f32>, point: &Point3<f32>) -> BoundingBox {
+    let difference = start.try_inverse().unwrap_or_else(Matrix4::identity) * end;
+
+    let rotation: Matrix3<f32> = difference.fixed_slice::<U3, U3>(0, 0).into_owned();
+    let along_axis = match Rotation3::from_matrix_unchecked(rotation).axis_angle() {
+        Some((axis, _)) => {
+            let translation =
+                Vector3::new(difference[(0, 3)], difference[(1, 3)], difference[(2, 3)]);
+            start.transform_vector(&(axis.into_inner() * translation.dot(&axis)))
+        }
+        None => Vector3::zeros(),
+    };
+
+    let arc = arc_bounds(
+        &start.transform_point(point),
+        &(end.transform_point(point) - along_axis),
+    );
+    let translated =
+        BoundingBox::from_positions(&[0.0, 0.0, 0.0, along_axis.x, along_axis.y, along_axis.z])
+            .unwrap_or_default();
+
+    arc.sum(&translated)
+}
+
+/// An arc of at most half a turn between `start` and `end` is within the ball whose diameter is
+/// the line between them.
+fn arc_bounds(start: &Point3<f32>, end: &Point3<f32>) -> BoundingBox {
+    let radius = (end - start).norm() / 2.0;
+
+    BoundingBox {
+        min_corner: nalgebra::center(start, end),
+        max_corner: nalgebra::center(start, end),
+    }
+    .padded(radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combine_indices::EASILY_RECOGNIZABLE_NUMBER;
+    use crate::test_utils::*;
+    use crate::vertex_data::{BoneAttributes, MultiIndexVertexData, SingleIndexVertexData};
+    use crate::{SkinConfig, SkinningMethod};
+    use std::collections::HashMap;
+
+    #[test]
+    fn recompute_bounding_box() {
+        let mut mesh = BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                positions: attrib3(vec![1.0, -2.0, 3.0, -4.0, 5.0, 0.5, 0.0, 0.0, 9.0]),
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        mesh.recompute_bounding_box();
+
+        assert_eq!(
+            mesh.bounding_box,
+            BoundingBox {
+                min_corner: Point3::new(-4.0, -2.0, 0.5),
+                max_corner: Point3::new(1.0, 5.0, 9.0),
+            }
+        );
+    }
+
+    /// Unused vertices that were left behind when combining indices aren't included
+    #[test]
+    fn recompute_bounding_box_of_indexed_vertices() {
+        let mut mesh = BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                positions: attrib3(vec![
+                    1.0,
+                    -2.0,
+                    3.0,
+                    EASILY_RECOGNIZABLE_NUMBER,
+                    EASILY_RECOGNIZABLE_NUMBER,
+                    EASILY_RECOGNIZABLE_NUMBER,
+                    -4.0,
+                    5.0,
+                    0.5,
+                    0.0,
+                    0.0,
+                    9.0,
+                ]),
+                indices: vec![0, 2, 3],
+                vertices_in_each_face: vec![3],
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        mesh.recompute_bounding_box();
+
+        assert_eq!(
+            mesh.bounding_box,
+            BoundingBox {
+                min_corner: Point3::new(-4.0, -2.0, 0.5),
+                max_corner: Point3::new(1.0, 5.0, 9.0),
+            }
+        );
+    }
+
+    /// Transforming a bounding box contains all of its transformed corners
+    #[test]
+    fn transform_bounding_box() {
+        let bounding_box = BoundingBox {
+            min_corner: Point3::new(-1.0, -2.0, -3.0),
+            max_corner: Point3::new(1.0, 2.0, 3.0),
+        };
+
+        let matrix = Matrix4::new_translation(&Vector3::new(10.0, 0.0, 0.0))
+            * Rotation3::from_axis_angle(&Vector3::z_axis(), std::f32::consts::FRAC_PI_2)
+                .to_homogeneous()
+            * Matrix4::new_scaling(2.0);
+
+        let transformed = bounding_box.transform(&matrix);
+
+        assert_close(&transformed.min_corner, &Point3::new(6.0, -2.0, -6.0));
+        assert_close(&transformed.max_corner, &Point3::new(14.0, 2.0, 6.0));
+
+        let corners = [-1.0, -2.0, -3.0, 1.0, 2.0, 3.0];
+        for corner in 0..8 {
+            let corner = Point3::new(
+                corners[(corner & 1) * 3],
+                corners[((corner >> 1) & 1) * 3 + 1],
+                corners[((corner >> 2) & 1) * 3 + 2],
+            );
+            let corner = matrix.transform_point(&corner);
+
+            let padded = BoundingBox {
+                min_corner: transformed.min_corner - Vector3::repeat(1e-5),
+                max_corner: transformed.max_corner + Vector3::repeat(1e-5),
+            };
+            assert!(padded.contains_point(&corner));
+        }
+    }
+
+    /// A mesh with one vertex for each of two bones, and an action where the second bone moves
+    /// up and then rotates around the origin
+    fn skinned_mesh() -> (BlenderMesh, BlenderArmature) {
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                positions: attrib3(vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0]),
+                bones: Some(BoneAttributes {
+                    bones_per_vertex: 2,
+                    bone_indices: vec![0, 1, 1, 0],
+                    bone_weights: vec![1.0, 0.0, 1.0, 0.0],
+                }),
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        let identity = || Bone::Matrix(array16(Matrix4::identity()));
+        let raised = Matrix4::new_translation(&Vector3::new(0.0, 0.0, 2.0));
+        let rotated =
+            Rotation3::from_axis_angle(&Vector3::z_axis(), 160f32.to_radians()).to_homogeneous();
+
+        let keyframes = vec![
+            keyframe(2.0, vec![identity(), Bone::Matrix(array16(rotated))]),
+            keyframe(0.0, vec![identity(), identity()]),
+            keyframe(1.0, vec![identity(), Bone::Matrix(array16(raised))]),
+        ];
+
+        let mut actions = HashMap::new();
+        actions.insert("Wave".to_string(), keyframes);
+
+        let armature = BlenderArmature {
+            actions,
+            joint_index: HashMap::new(),
+            inverse_bind_poses: vec![],
+            bone_parents: vec![],
+            local_rest_poses: vec![],
+        };
+
+        (mesh, armature)
+    }
+
+    /// The bounds contain every keyframe's pose and the poses in between them
+    #[test]
+    fn skinned_bounds_contain_every_pose() {
+        let (mesh, armature) = skinned_mesh();
+
+        let bounds = mesh
+            .sampled_skinned_bounding_box(&armature, "Wave", &SkinnedBoundsConfig::default())
+            .unwrap();
+
+        let rotated = (160f32.to_radians().cos(), 160f32.to_radians().sin());
+
+        // Raised at the second keyframe and rotated most of the way around at the last
+        assert!(bounds.contains_point(&Point3::new(1.0, 0.0, 2.0)));
+        assert!(bounds.contains_point(&Point3::new(rotated.0, rotated.1, 0.0)));
+        // Swings through +y when rotating from the raised pose to the rotated pose
+        assert!(bounds.max_corner.y > 0.9);
+        assert_close(&bounds.min_corner, &Point3::new(rotated.0, 0.0, 0.0));
+        assert_eq!(bounds.max_corner.z, 2.0);
+
+        let keyframes_only = SkinnedBoundsConfig {
+            samples_between_keyframes: 0,
+            ..SkinnedBoundsConfig::default()
+        };
+        let bounds = mesh
+            .sampled_skinned_bounding_box(&armature, "Wave", &keyframes_only)
+            .unwrap();
+        assert_close(&bounds.max_corner, &Point3::new(1.0, rotated.1, 2.0));
+    }
+
+    /// A scaled bone that turns 180 degrees between two keyframes is in the same place along the
+    /// y axis at both keyframes, so only the poses in between keyframes show it swinging around.
+    #[test]
+    fn skinned_bounds_of_a_half_turn() {
+        let (mesh, mut armature) = skinned_mesh();
+
+        let scaled = Matrix4::new_scaling(2.0);
+        let half_turn = Matrix4::from_axis_angle(&Vector3::z_axis(), std::f32::consts::PI)
+            * Matrix4::new_scaling(2.0);
+        armature.actions.insert(
+            "Turn".to_string(),
+            vec![
+                keyframe(
+                    0.0,
+                    vec![scaled, scaled].into_iter().map(matrix_bone).collect(),
+                ),
+                keyframe(
+                    1.0,
+                    vec![scaled, half_turn]
+                        .into_iter()
+                        .map(matrix_bone)
+                        .collect(),
+                ),
+            ],
+        );
+
+        let keyframes_only = SkinnedBoundsConfig {
+            samples_between_keyframes: 0,
+            ..SkinnedBoundsConfig::default()
+        };
+        let bounds = mesh
+            .sampled_skinned_bounding_box(&armature, "Turn", &keyframes_only)
+            .unwrap();
+        assert_close(&bounds.min_corner, &Point3::new(-2.0, 0.0, 0.0));
+        assert_close(&bounds.max_corner, &Point3::new(2.0, 0.0, 0.0));
+
+        // Halfway through the turn the vertex is a quarter turn around, and the bone's scale is
+        // kept while it's interpolated
+        let midpoint = SkinnedBoundsConfig {
+            samples_between_keyframes: 1,
+            ..SkinnedBoundsConfig::default()
+        };
+        let bounds = mesh
+            .sampled_skinned_bounding_box(&armature, "Turn", &midpoint)
+            .unwrap();
+        assert!((bounds.max_corner.y - bounds.min_corner.y - 2.0).abs() < 1e-4);
+        assert_eq!(bounds.max_corner.x, 2.0);
+        assert!((bounds.min_corner.x + 2.0).abs() < 1e-4);
+    }
+
+    /// Every pose of the action, interpolated or not, is skinned within the conservative bounds,
+    /// whether the bones are matrices or dual quaternions
+    #[test]
+    fn conservative_skinned_bounds_contain_every_pose() {
+        let (_, mut armature) = skinned_mesh();
+
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                positions: attrib3(vec![
+                    0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.5, 0.5, -0.5, 2.0, -1.0, 1.0,
+                ]),
+                normals: attrib3([0.0, 0.0, 1.0].repeat(4)),
+                bones: Some(BoneAttributes {
+                    bones_per_vertex: 2,
+                    bone_indices: vec![0, 1, 1, 0, 0, 1, 1, 0],
+                    bone_weights: vec![1.0, 0.0, 1.0, 0.0, 0.5, 0.5, 0.7, 0.3],
+                }),
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        let scaled_turn = Matrix4::new_translation(&Vector3::new(1.0, -2.0, 0.5))
+            * Matrix4::from_axis_angle(&Vector3::x_axis(), 2.5)
+            * Matrix4::new_nonuniform_scaling(&Vector3::new(1.5, 0.5, 1.0));
+        let twisted = Matrix4::new_translation(&Vector3::new(-1.0, 0.0, 3.0))
+            * Matrix4::from_axis_angle(&Vector3::y_axis(), -2.0);
+        armature.actions.insert(
+            "Dance".to_string(),
+            vec![
+                keyframe(
+                    0.0,
+                    vec![Matrix4::identity(), Matrix4::identity()]
+                        .into_iter()
+                        .map(matrix_bone)
+                        .collect(),
+                ),
+                keyframe(
+                    1.0,
+                    vec![twisted, scaled_turn]
+                        .into_iter()
+                        .map(matrix_bone)
+                        .collect(),
+                ),
+                keyframe(
+                    1.5,
+                    vec![scaled_turn, twisted]
+                        .into_iter()
+                        .map(matrix_bone)
+                        .collect(),
+                ),
+            ],
+        );
+
+        let rigid_turn = Matrix4::new_translation(&Vector3::new(1.0, -2.0, 0.5))
+            * Matrix4::from_axis_angle(
+                &nalgebra::Unit::new_normalize(Vector3::new(1.0, 1.0, 0.0)),
+                3.0,
+            );
+        let dual_quat = |matrix| BlenderArmature::matrix_to_dual_quat(&matrix_bone(matrix));
+        armature.actions.insert(
+            "DualQuatDance".to_string(),
+            vec![
+                keyframe(
+                    0.0,
+                    vec![dual_quat(Matrix4::identity()), dual_quat(twisted)],
+                ),
+                keyframe(1.0, vec![dual_quat(twisted), dual_quat(rigid_turn)]),
+                keyframe(2.0, vec![dual_quat(rigid_turn), dual_quat(rigid_turn)]),
+            ],
+        );
+
+        for (action, duration) in [("Wave", 2.0), ("Dance", 1.5), ("DualQuatDance", 2.0)].iter() {
+            let bounds = mesh
+                .conservative_skinned_bounding_box(&armature, action)
+                .unwrap()
+                .padded(1e-4);
+
+            for interpolation in [
+                DualQuatInterpolation::Linear,
+                DualQuatInterpolation::ScrewLinear,
+            ]
+            .iter()
+            {
+                for sample in 0..=300 {
+                    let bones = armature
+                        .interpolate_bones(&InterpolationSettings {
+                            current_time: duration * sample as f32 / 300.0,
+                            joint_indices: vec![0, 1],
+                            blend_fn: None,
+                            dual_quat_interpolation: *interpolation,
+                            current_action: ActionSettings::new(action, 0.0, false),
+                            previous_action: None,
+                        })
+                        .unwrap();
+                    let config = SkinConfig {
+                        method: SkinningMethod::LinearBlend,
+                    };
+                    let skinned = mesh.skin(&bones, &config).unwrap();
+
+                    for position in skinned.positions.data().chunks(3) {
+                        let position = Point3::new(position[0], position[1], position[2]);
+                        assert!(
+                            bounds.contains_point(&position),
+                            "{} {:?} {}",
+                            action,
+                            bounds,
+                            position
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// A scaled bone that turns 180 degrees could swing around either way, so the conservative
+    /// bounds cover both
+    #[test]
+    fn conservative_skinned_bounds_of_a_half_turn() {
+        let (mesh, mut armature) = skinned_mesh();
+
+        let scaled = Matrix4::new_scaling(2.0);
+        let half_turn = Matrix4::from_axis_angle(&Vector3::z_axis(), std::f32::consts::PI)
+            * Matrix4::new_scaling(2.0);
+        armature.actions.insert(
+            "Turn".to_string(),
+            vec![
+                keyframe(
+                    0.0,
+                    vec![scaled, scaled].into_iter().map(matrix_bone).collect(),
+                ),
+                keyframe(
+                    1.0,
+                    vec![scaled, half_turn]
+                        .into_iter()
+                        .map(matrix_bone)
+                        .collect(),
+                ),
+            ],
+        );
+
+        let bounds = mesh
+            .conservative_skinned_bounding_box(&armature, "Turn")
+            .unwrap();
+        assert_close(&bounds.min_corner, &Point3::new(-2.0, -2.0, -2.0));
+        assert_close(&bounds.max_corner, &Point3::new(2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn skinned_bounds_errors() {
+        let (mesh, mut armature) = skinned_mesh();
+
+        match mesh.sampled_skinned_bounding_box(&armature, "Run", &SkinnedBoundsConfig::default()) {
+            Err(SkinnedBoundsError::UnknownAction(action)) => assert_eq!(action, "Run"),
+            _ => unreachable!(),
+        };
+
+        armature.actions.insert(
+            "Wave".to_string(),
+            vec![keyframe(
+                0.0,
+                vec![Bone::Matrix(array16(Matrix4::identity()))],
+            )],
+        );
+        match mesh.sampled_skinned_bounding_box(&armature, "Wave", &SkinnedBoundsConfig::default())
+        {
+            Err(SkinnedBoundsError::MissingBone { bone, bone_count }) => {
+                assert_eq!((bone, bone_count), (1, 1))
+            }
+            _ => unreachable!(),
+        };
+
+        let unskinned = BlenderMesh::default();
+        match unskinned.sampled_skinned_bounding_box(
+            &armature,
+            "Wave",
+            &SkinnedBoundsConfig::default(),
+        ) {
+            Err(SkinnedBoundsError::NoBoneInfluences) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    fn keyframe(frame_time_secs: f32, bones: Vec<Bone>) -> Keyframe {
+        serde_json::from_value(serde_json::json!({
+            "frame_time_secs": frame_time_secs,
+            "bones": bones,
+        }))
+        .unwrap()
+    }
+
+    fn matrix_bone(matrix: Matrix4<f32>) -> Bone {
+        Bone::Matrix(array16(matrix))
+    }
+
+    fn array16(matrix: Matrix4<f32>) -> [f32; 16] {
+        let mut array = [0.0; 16];
+        array.copy_from_slice(matrix.as_slice());
+        array
+    }
+
+    fn assert_close(actual: &Point3<f32>, expected: &Point3<f32>) {
+        assert!((actual - expected).norm() < 1e-4, "{} {}", actual, expected);
+    }
+}
@@ -0,0 +1,359 @@
+//! The smallest sphere that contains a mesh, which is cheaper than a bounding box to test against
+//! a view frustum and doesn't change size when the mesh rotates.
+
+use crate::vertex_data::VertexData;
+use crate::BlenderMesh;
+use nalgebra::{Matrix3, Matrix4, Point3, Vector3};
+
+/// A sphere that contains every position in a mesh
+#[derive(Debug, PartialEq, Clone)]
+pub struct BoundingSphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// The smallest sphere that contains every position, where `positions` is
+    /// `[x, y, z, x, y, z, ...]`.
+    ///
+    /// Returns `None` if there are no positions.
+    ///
+    /// This is Welzl's algorithm, written as the nested loops from the "minidisk" version
+    /// instead of recursion so that large meshes don't overflow the stack.
+    ///
+    /// @see https://en.wikipedia.org/wiki/Smallest-circle_problem#Welzl's_algorithm
+    pub fn from_positions(positions: &[f32]) -> Option<BoundingSphere> {
+        let mut points: Vec<Point3<f64>> = positions
+            .chunks(3)
+            .map(|p| Point3::new(p[0] as f64, p[1] as f64, p[2] as f64))
+            .collect();
+
+        if points.is_empty() {
+            return None;
+        }
+
+        // Welzl's algorithm is only expected linear time when the points are in a random order,
+        // and mesh positions tend to be sorted spatially.
+        shuffle(&mut points);
+
+        let mut sphere = Sphere::from_points(&[points[0]]);
+        for i in 1..points.len() {
+            if !sphere.contains(&points[i]) {
+                sphere = smallest_with_boundary(&points[..i], &[points[i]]);
+            }
+        }
+
+        Some(BoundingSphere {
+            center: Point3::new(
+                sphere.center.x as f32,
+                sphere.center.y as f32,
+                sphere.center.z as f32,
+            ),
+            // Make sure that rounding to f32 doesn't leave any positions slightly outside
+            radius: (sphere.radius_squared.sqrt() * (1.0 + 1e-6)) as f32,
+        })
+    }
+
+    /// Whether or not the point is inside of (or on the edge of) the sphere
+    pub fn contains_point(&self, point: &Point3<f32>) -> bool {
+        (point - self.center).norm() <= self.radius
+    }
+
+    /// The bounding sphere of this sphere after it has been transformed by the matrix.
+    ///
+    /// A non uniform scale would turn the sphere into an ellipsoid, so we scale the radius by
+    /// the largest scale.
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> BoundingSphere {
+        let max_scale = (0..3)
+            .map(|column| {
+                matrix
+                    .fixed_slice::<nalgebra::U3, nalgebra::U1>(0, column)
+                    .norm()
+            })
+            .fold(0.0, f32::max);
+
+        BoundingSphere {
+            center: matrix.transform_point(&self.center),
+            radius: self.radius * max_scale,
+        }
+    }
+}
+
+impl BlenderMesh {
+    /// The smallest sphere that contains all of the mesh's current positions.
+    ///
+    /// After combining indices only the vertices that the indices use are included.
+    ///
+    /// Returns `None` if the mesh has no positions.
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        match &self.vertex_data {
+            VertexData::Multi(multi) => BoundingSphere::from_positions(multi.positions().data()),
+            VertexData::Single(single) => {
+                BoundingSphere::from_positions(&single.indexed_positions())
+            }
+        }
+    }
+}
+
+/// A sphere in double precision, since finding spheres through 3 or 4 points loses a lot of
+/// precision
+#[derive(Debug, Clone, Copy)]
+struct Sphere {
+    center: Point3<f64>,
+    radius_squared: f64,
+}
+
+impl Sphere {
+    /// The smallest sphere with every one of the (up to 4) points on its surface
+    fn from_points(points: &[Point3<f64>]) -> Sphere {
+        match points {
+            [a] => Sphere {
+                center: *a,
+                radius_squared: 0.0,
+            },
+            [a, b] => {
+                let center = nalgebra::center(a, b);
+                Sphere {
+                    center,
+                    radius_squared: (a - center).norm_squared(),
+                }
+            }
+            [a, b, c] => circumcircle(a, b, c).unwrap_or_else(|| widest_pair(points)),
+            [a, b, c, d] => circumsphere(a, b, c, d).unwrap_or_else(|| {
+                // The points are coplanar, so the smallest sphere that passes through three of
+                // them and contains the fourth is the one we want.
+                let triples = [[*a, *b, *c], [*a, *b, *d], [*a, *c, *d], [*b, *c, *d]];
+                triples
+                    .iter()
+                    .map(|triple| Sphere::from_points(triple))
+                    .filter(|sphere| points.iter().all(|point| sphere.contains(point)))
+                    .min_by(|x, y| x.radius_squared.partial_cmp(&y.radius_squared).unwrap())
+                    .unwrap_or_else(|| widest_pair(points))
+            }),
+            _ => unreachable!("Spheres are defined by 1 to 4 points"),
+        }
+    }
+
+    fn contains(&self, point: &Point3<f64>) -> bool {
+        let tolerance = 1e-9 * self.radius_squared.max(1.0);
+        (point - self.center).norm_squared() <= self.radius_squared + tolerance
+    }
+}
+
+/// The smallest sphere that contains `points` with every point in `boundary` on its surface
+fn smallest_with_boundary(points: &[Point3<f64>], boundary: &[Point3<f64>]) -> Sphere {
+    let mut sphere = Sphere::from_points(boundary);
+
+    if boundary.len() == 4 {
+        return sphere;
+    }
+
+    for (idx, point) in points.iter().enumerate() {
+        if !sphere.contains(point) {
+            let mut boundary = boundary.to_vec();
+            boundary.push(*point);
+            sphere = smallest_with_boundary(&points[..idx], &boundary);
+        }
+    }
+
+    sphere
+}
+
+/// The sphere whose diameter is the two points that are furthest apart, for degenerate cases
+fn widest_pair(points: &[Point3<f64>]) -> Sphere {
+    let mut widest = Sphere::from_points(&[points[0]]);
+
+    for (idx, a) in points.iter().enumerate() {
+        for b in points[idx + 1..].iter() {
+            let sphere = Sphere::from_points(&[*a, *b]);
+            if sphere.radius_squared > widest.radius_squared {
+                widest = sphere;
+            }
+        }
+    }
+
+    widest
+}
+
+/// The sphere centered on the circle that passes through all three points, or `None` if they're
+/// in a line
+///
+/// @see https://en.wikipedia.org/wiki/Circumscribed_circle#Higher_dimensions
+fn circumcircle(a: &Point3<f64>, b: &Point3<f64>, c: &Point3<f64>) -> Option<Sphere> {
+    let ab = b - a;
+    let ac = c - a;
+    let normal = ab.cross(&ac);
+    let denominator = 2.0 * normal.norm_squared();
+
+    if denominator <= f64::EPSILON * ab.norm_squared() * ac.norm_squared() {
+        return None;
+    }
+
+    let offset = (normal.cross(&ab) * ac.norm_squared() + ac.cross(&normal) * ab.norm_squared())
+        / denominator;
+
+    Some(Sphere {
+        center: a + offset,
+        radius_squared: offset.norm_squared(),
+    })
+}
+
+/// The sphere that passes through all four points, or `None` if they're on a plane
+fn circumsphere(
+    a: &Point3<f64>,
+    b: &Point3<f64>,
+    c: &Point3<f64>,
+    d: &Point3<f64>,
+) -> Option<Sphere> {
+    let (ab, ac, ad) = (b - a, c - a, d - a);
+    let rows = Matrix3::from_rows(&[ab.transpose(), ac.transpose(), ad.transpose()]);
+    let rhs = Vector3::new(ab.norm_squared(), ac.norm_squared(), ad.norm_squared()) / 2.0;
+
+    let scale = ab.norm() * ac.norm() * ad.norm();
+    if rows.determinant().abs() <= 1e-12 * scale {
+        return None;
+    }
+
+    let offset = rows.lu().solve(&rhs)?;
+
+    Some(Sphere {
+        center: a + offset,
+        radius_squared: offset.norm_squared(),
+    })
+}
+
+/// A deterministic shuffle, so that the same mesh always gives the same sphere
+fn shuffle<T>(items: &mut [T]) {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+
+    for idx in (1..items.len()).rev() {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+
+        items.swap(idx, (state % (idx as u64 + 1)) as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combine_indices::EASILY_RECOGNIZABLE_NUMBER;
+    use crate::test_utils::*;
+    use crate::vertex_data::{MultiIndexVertexData, SingleIndexVertexData};
+
+    /// Unused vertices that were left behind when combining indices aren't included
+    #[test]
+    fn sphere_around_indexed_vertices() {
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                positions: attrib3(vec![
+                    -1.0,
+                    0.0,
+                    0.0,
+                    1.0,
+                    0.0,
+                    0.0,
+                    EASILY_RECOGNIZABLE_NUMBER,
+                    0.0,
+                    0.0,
+                    0.0,
+                    1.0,
+                    0.0,
+                ]),
+                indices: vec![0, 1, 3],
+                vertices_in_each_face: vec![3],
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        let sphere = mesh.bounding_sphere().unwrap();
+
+        assert!(sphere.center.coords.norm() < 1e-5);
+        assert!((sphere.radius - 1.0).abs() < 1e-5);
+    }
+
+    /// The corners of a cube, plus points inside of it
+    #[test]
+    fn sphere_around_a_cube() {
+        let mut positions = vec![];
+        for corner in 0..8 {
+            positions.push(if corner & 1 == 0 { -1.0 } else { 1.0 });
+            positions.push(if corner & 2 == 0 { -1.0 } else { 1.0 });
+            positions.push(if corner & 4 == 0 { 3.0 } else { 5.0 });
+        }
+        positions.extend_from_slice(&[0.5, 0.2, 4.0, -0.3, 0.9, 3.5]);
+
+        let sphere = BoundingSphere::from_positions(&positions).unwrap();
+
+        assert!((sphere.center - Point3::new(0.0, 0.0, 4.0)).norm() < 1e-5);
+        assert!((sphere.radius - 3f32.sqrt()).abs() < 1e-5);
+    }
+
+    /// The sphere contains every position, and is as tight as the sphere through the two
+    /// furthest positions when they're on opposite sides of it
+    #[test]
+    fn tight_sphere_around_many_positions() {
+        let mut positions = vec![];
+        for idx in 0..1000 {
+            let angle = idx as f32 * 2.4;
+            let height = (idx as f32 / 500.0) - 1.0;
+            let radius = (1.0 - height * height).sqrt() * (0.5 + (idx as f32 * 0.37).fract() / 2.0);
+            positions.extend_from_slice(&[radius * angle.cos(), height, radius * angle.sin()]);
+        }
+        // The two furthest points, and the only points on the surface of the smallest sphere
+        positions.extend_from_slice(&[0.0, -2.0, 0.0, 0.0, 2.0, 0.0]);
+
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                positions: attrib3(positions.clone()),
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+        let sphere = mesh.bounding_sphere().unwrap();
+
+        for position in positions.chunks(3) {
+            let position = Point3::new(position[0], position[1], position[2]);
+            assert!(sphere.contains_point(&position));
+        }
+        assert!((sphere.radius - 2.0).abs() < 1e-5);
+        assert!(sphere.center.coords.norm() < 1e-5);
+    }
+
+    /// Three points in a line and four points on a plane don't define a sphere
+    #[test]
+    fn degenerate_positions() {
+        let in_a_line = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 3.0, 0.0, 0.0];
+        let sphere = BoundingSphere::from_positions(&in_a_line).unwrap();
+        assert!((sphere.center - Point3::new(1.5, 0.0, 0.0)).norm() < 1e-5);
+        assert!((sphere.radius - 1.5).abs() < 1e-5);
+
+        let square = [
+            0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0, 2.0, 2.0, 0.0, 1.0, 1.0, 0.0,
+        ];
+        let sphere = BoundingSphere::from_positions(&square).unwrap();
+        assert!((sphere.center - Point3::new(1.0, 1.0, 0.0)).norm() < 1e-5);
+        assert!((sphere.radius - 2f32.sqrt()).abs() < 1e-5);
+
+        assert_eq!(BoundingSphere::from_positions(&[]), None);
+    }
+
+    #[test]
+    fn transform_sphere() {
+        let sphere = BoundingSphere {
+            center: Point3::new(1.0, 0.0, 0.0),
+            radius: 2.0,
+        };
+
+        let matrix = Matrix4::new_translation(&Vector3::new(0.0, 5.0, 0.0))
+            * Matrix4::new_nonuniform_scaling(&Vector3::new(1.0, 3.0, 2.0));
+
+        let transformed = sphere.transform(&matrix);
+
+        assert_eq!(transformed.center, Point3::new(1.0, 5.0, 0.0));
+        assert_eq!(transformed.radius, 6.0);
+    }
+}
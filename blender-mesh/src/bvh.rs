@@ -0,0 +1,885 @@
+//! A bounding volume hierarchy over a mesh's triangles, for answering questions such as "which
+//! triangle is under the mouse?" or "which triangles does this decal touch?" without testing
+//! every triangle.
+//!
+//! The hierarchy is built using the surface area heuristic, which splits each node wherever the
+//! two halves are cheapest to trace rays through.
+//!
+//! @see http://www.sci.utah.edu/~wald/Publications/2007/ParallelBVHBuild/fastbuild.pdf
+
+use crate::bounding_box::BoundingBox;
+use crate::vertex_data::{ensure_triangulated, VertexData, VertexDataError};
+use crate::BlenderMesh;
+use nalgebra::{Point3, Vector2, Vector3};
+use std::cmp::Ordering;
+
+/// Configuration for building a [`Bvh`]
+///
+/// [`Bvh`]: struct.Bvh.html
+#[derive(Debug)]
+pub struct BvhConfig {
+    /// Nodes with this many triangles or fewer become leaves
+    pub max_triangles_per_leaf: usize,
+    /// The number of places along each axis where we consider splitting a node. More buckets
+    /// build a slightly better tree, slightly slower.
+    pub sah_buckets: usize,
+}
+
+impl Default for BvhConfig {
+    fn default() -> Self {
+        BvhConfig {
+            max_triangles_per_leaf: 4,
+            sah_buckets: 12,
+        }
+    }
+}
+
+/// An error while building a [`Bvh`]
+///
+/// [`Bvh`]: struct.Bvh.html
+#[derive(Debug, Fail)]
+pub enum BvhError {
+    #[fail(display = "Could not build a BVH for the mesh: {}", _0)]
+    VertexData(#[cause] VertexDataError),
+}
+
+impl From<VertexDataError> for BvhError {
+    fn from(error: VertexDataError) -> Self {
+        BvhError::VertexData(error)
+    }
+}
+
+/// A ray starting at `origin` and travelling in `direction`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    /// Always normalized, so that distances along the ray are in the same units as the mesh
+    direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// Create a ray. The direction doesn't need to be normalized.
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Ray {
+        Ray {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// The normalized direction of the ray
+    pub fn direction(&self) -> &Vector3<f32> {
+        &self.direction
+    }
+}
+
+/// Where a ray or sphere touches one of the mesh's triangles
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriangleHit {
+    /// The index of the triangle, which is the index of the face in the mesh
+    pub triangle: usize,
+    /// The point on the triangle
+    pub point: Point3<f32>,
+    /// How much each of the triangle's three vertices contributes to the point. These add up to
+    /// one.
+    pub barycentric: Vector3<f32>,
+    /// For rays, the distance along the ray. For spheres, the distance from the center of the
+    /// sphere.
+    pub distance: f32,
+    /// The triangle's vertex normals interpolated to the point and normalized
+    pub normal: Vector3<f32>,
+    /// The triangle's vertex uvs interpolated to the point, if the mesh has uvs
+    pub uv: Option<Vector2<f32>>,
+}
+
+/// A bounding volume hierarchy over a mesh's triangles.
+///
+/// The BVH keeps its own copy of the triangles, so it stays valid if the mesh is changed (but
+/// won't see those changes).
+#[derive(Debug)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    /// The indices of the triangles, ordered so that every leaf's triangles are next to each
+    /// other
+    triangle_order: Vec<usize>,
+    triangles: Vec<Triangle>,
+}
+
+#[derive(Debug)]
+struct BvhNode {
+    bounds: BoundingBox,
+    kind: BvhNodeKind,
+}
+
+#[derive(Debug)]
+enum BvhNodeKind {
+    /// A range of `triangle_order`
+    Leaf {
+        first: usize,
+        count: usize,
+    },
+    Interior {
+        left: usize,
+        right: usize,
+    },
+}
+
+#[derive(Debug)]
+struct Triangle {
+    positions: [Point3<f32>; 3],
+    normals: [Vector3<f32>; 3],
+    uvs: Option<[Vector2<f32>; 3]>,
+}
+
+impl BlenderMesh {
+    /// Build a bounding volume hierarchy over the mesh's triangles so that you can quickly find
+    /// which triangles a ray hits or a sphere overlaps.
+    pub fn build_bvh(&self, config: &BvhConfig) -> Result<Bvh, BvhError> {
+        let triangles = self.bvh_triangles()?;
+        Ok(Bvh::new(triangles, config))
+    }
+
+    /// The positions, normals and uvs of the corners of every triangle
+    fn bvh_triangles(&self) -> Result<Vec<Triangle>, BvhError> {
+        let (vertices_in_each_face, corner_indices, positions, normals, uvs) =
+            match &self.vertex_data {
+                VertexData::Multi(multi) => (
+                    &multi.vertices_in_each_face,
+                    (
+                        &multi.position_indices,
+                        &multi.normal_indices,
+                        multi.uv_indices.as_ref(),
+                    ),
+                    &multi.positions,
+                    &multi.normals,
+                    multi.uvs.as_ref(),
+                ),
+                VertexData::Single(single) => (
+                    &single.vertices_in_each_face,
+                    (
+                        &single.indices,
+                        &single.indices,
+                        single.uvs.as_ref().map(|_| &single.indices),
+                    ),
+                    &single.positions,
+                    &single.normals,
+                    single.uvs.as_ref(),
+                ),
+            };
+
+        let (position_indices, normal_indices, uv_indices) = corner_indices;
+
+        let vec3 = |data: &[f32], idx: u32| {
+            let idx = idx as usize * 3;
+            Vector3::new(data[idx], data[idx + 1], data[idx + 2])
+        };
+
+        ensure_triangulated(vertices_in_each_face)?;
+
+        let mut triangles = Vec::with_capacity(vertices_in_each_face.len());
+
+        for face in 0..vertices_in_each_face.len() {
+            let corners = [face * 3, face * 3 + 1, face * 3 + 2];
+
+            let uvs = match (uvs, uv_indices) {
+                (Some(uvs), Some(uv_indices)) => {
+                    let uv = |corner: usize| {
+                        let idx = uv_indices[corner] as usize * 2;
+                        Vector2::new(uvs.data()[idx], uvs.data()[idx + 1])
+                    };
+                    Some([uv(corners[0]), uv(corners[1]), uv(corners[2])])
+                }
+                _ => None,
+            };
+
+            let position =
+                |corner: usize| Point3::from(vec3(positions.data(), position_indices[corner]));
+            let normal = |corner: usize| vec3(normals.data(), normal_indices[corner]);
+
+            triangles.push(Triangle {
+                positions: [
+                    position(corners[0]),
+                    position(corners[1]),
+                    position(corners[2]),
+                ],
+                normals: [normal(corners[0]), normal(corners[1]), normal(corners[2])],
+                uvs,
+            });
+        }
+
+        Ok(triangles)
+    }
+}
+
+impl Bvh {
+    fn new(triangles: Vec<Triangle>, config: &BvhConfig) -> Bvh {
+        let mut bvh = Bvh {
+            nodes: vec![],
+            triangle_order: (0..triangles.len()).collect(),
+            triangles,
+        };
+
+        if !bvh.triangles.is_empty() {
+            let triangle_bounds: Vec<(BoundingBox, Point3<f32>)> = bvh
+                .triangles
+                .iter()
+                .map(|triangle| {
+                    let bounds = triangle.bounds();
+                    let centroid = bounds.center();
+                    (bounds, centroid)
+                })
+                .collect();
+
+            let triangle_count = bvh.triangles.len();
+            bvh.build_node(0, triangle_count, &triangle_bounds, config);
+        }
+
+        bvh
+    }
+
+    /// The number of triangles in the BVH
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    /// The bounding box of every triangle, or `None` if there are no triangles
+    pub fn bounds(&self) -> Option<&BoundingBox> {
+        self.nodes.first().map(|root| &root.bounds)
+    }
+
+    /// The first triangle that the ray hits, no further than `max_distance` along the ray.
+    ///
+    /// Both sides of a triangle can be hit.
+    pub fn closest_hit(&self, ray: &Ray, max_distance: f32) -> Option<TriangleHit> {
+        let mut closest: Option<(usize, f32, Vector3<f32>)> = None;
+        let mut max_distance = max_distance;
+
+        self.traverse_ray(ray, |triangle| {
+            if let Some((distance, barycentric)) = self.triangles[triangle].intersect(ray) {
+                if distance <= max_distance {
+                    max_distance = distance;
+                    closest = Some((triangle, distance, barycentric));
+                }
+            }
+
+            (max_distance, false)
+        });
+
+        closest.map(|(triangle, distance, barycentric)| {
+            self.hit(
+                triangle,
+                ray.origin + ray.direction * distance,
+                barycentric,
+                distance,
+            )
+        })
+    }
+
+    /// Any triangle that the ray hits no further than `max_distance` along the ray. This is
+    /// faster than `closest_hit` when you only need to know whether something is in the way,
+    /// such as for shadow rays.
+    pub fn any_hit(&self, ray: &Ray, max_distance: f32) -> Option<TriangleHit> {
+        let mut found = None;
+
+        self.traverse_ray(ray, |triangle| {
+            if let Some((distance, barycentric)) = self.triangles[triangle].intersect(ray) {
+                if distance <= max_distance {
+                    found = Some((triangle, distance, barycentric));
+                    return (max_distance, true);
+                }
+            }
+
+            (max_distance, false)
+        });
+
+        found.map(|(triangle, distance, barycentric)| {
+            self.hit(
+                triangle,
+                ray.origin + ray.direction * distance,
+                barycentric,
+                distance,
+            )
+        })
+    }
+
+    /// Every triangle that is within `radius` of `center`, at the point on the triangle that is
+    /// closest to `center`. Sorted from closest to furthest.
+    pub fn sphere_overlap(&self, center: &Point3<f32>, radius: f32) -> Vec<TriangleHit> {
+        let mut hits = vec![];
+
+        if self.nodes.is_empty() {
+            return hits;
+        }
+
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if distance_to_box(center, &node.bounds) > radius {
+                continue;
+            }
+
+            match node.kind {
+                BvhNodeKind::Leaf { first, count } => {
+                    for triangle in self.triangle_order[first..first + count].iter() {
+                        let (point, barycentric) = self.triangles[*triangle].closest_point(center);
+                        let distance = (point - center).norm();
+
+                        if distance <= radius {
+                            hits.push(self.hit(*triangle, point, barycentric, distance));
+                        }
+                    }
+                }
+                BvhNodeKind::Interior { left, right } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            };
+        }
+
+        hits.sort_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .unwrap_or(Ordering::Equal)
+                .then(a.triangle.cmp(&b.triangle))
+        });
+        hits
+    }
+
+    /// Visit the triangles in every leaf that the ray passes through, nearest leaves first.
+    ///
+    /// `visit` returns the furthest distance that we still care about, and whether to stop.
+    fn traverse_ray<F: FnMut(usize) -> (f32, bool)>(&self, ray: &Ray, mut visit: F) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let inverse_direction = ray.direction.map(|component| 1.0 / component);
+        let mut max_distance = f32::INFINITY;
+
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+
+            match ray_box(ray, &inverse_direction, &node.bounds) {
+                Some(entry) if entry <= max_distance => {}
+                _ => continue,
+            };
+
+            match node.kind {
+                BvhNodeKind::Leaf { first, count } => {
+                    for triangle in self.triangle_order[first..first + count].iter() {
+                        let (distance, stop) = visit(*triangle);
+                        if stop {
+                            return;
+                        }
+                        max_distance = distance;
+                    }
+                }
+                BvhNodeKind::Interior { left, right } => {
+                    let left_entry = ray_box(ray, &inverse_direction, &self.nodes[left].bounds);
+                    let right_entry = ray_box(ray, &inverse_direction, &self.nodes[right].bounds);
+
+                    // Push the further child first so that we visit the nearer one first
+                    match (left_entry, right_entry) {
+                        (Some(l), Some(r)) if l <= r => {
+                            stack.push(right);
+                            stack.push(left);
+                        }
+                        (Some(_), Some(_)) => {
+                            stack.push(left);
+                            stack.push(right);
+                        }
+                        (Some(_), None) => stack.push(left),
+                        (None, Some(_)) => stack.push(right),
+                        (None, None) => {}
+                    };
+                }
+            };
+        }
+    }
+
+    fn hit(
+        &self,
+        triangle: usize,
+        point: Point3<f32>,
+        barycentric: Vector3<f32>,
+        distance: f32,
+    ) -> TriangleHit {
+        let corners = &self.triangles[triangle];
+
+        let normal = corners.normals[0] * barycentric[0]
+            + corners.normals[1] * barycentric[1]
+            + corners.normals[2] * barycentric[2];
+        let normal = normal
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(|| corners.face_normal());
+
+        let uv = corners
+            .uvs
+            .map(|uvs| uvs[0] * barycentric[0] + uvs[1] * barycentric[1] + uvs[2] * barycentric[2]);
+
+        TriangleHit {
+            triangle,
+            point,
+            barycentric,
+            distance,
+            normal,
+            uv,
+        }
+    }
+
+    /// Build the node for `triangle_order[start..end]`, returning its index
+    fn build_node(
+        &mut self,
+        start: usize,
+        end: usize,
+        triangle_bounds: &[(BoundingBox, Point3<f32>)],
+        config: &BvhConfig,
+    ) -> usize {
+        let node_idx = self.nodes.len();
+
+        let mut bounds = triangle_bounds[self.triangle_order[start]].0.clone();
+        let mut centroid_bounds = BoundingBox {
+            min_corner: triangle_bounds[self.triangle_order[start]].1,
+            max_corner: triangle_bounds[self.triangle_order[start]].1,
+        };
+        for triangle in self.triangle_order[start..end].iter() {
+            bounds = bounds.union(&triangle_bounds[*triangle].0);
+            centroid_bounds.include_point(&triangle_bounds[*triangle].1);
+        }
+
+        self.nodes.push(BvhNode {
+            bounds,
+            kind: BvhNodeKind::Leaf {
+                first: start,
+                count: end - start,
+            },
+        });
+
+        if end - start <= config.max_triangles_per_leaf.max(1) {
+            return node_idx;
+        }
+
+        let split = self.sah_split(start, end, &centroid_bounds, triangle_bounds, config);
+
+        let left = self.build_node(start, split, triangle_bounds, config);
+        let right = self.build_node(split, end, triangle_bounds, config);
+        self.nodes[node_idx].kind = BvhNodeKind::Interior { left, right };
+
+        node_idx
+    }
+
+    /// Find the cheapest place to split `triangle_order[start..end]` and partition the
+    /// triangles around it, returning the index of the first triangle in the second half.
+    fn sah_split(
+        &mut self,
+        start: usize,
+        end: usize,
+        centroid_bounds: &BoundingBox,
+        triangle_bounds: &[(BoundingBox, Point3<f32>)],
+        config: &BvhConfig,
+    ) -> usize {
+        let bucket_count = config.sah_buckets.max(2);
+        let extent = centroid_bounds.max_corner - centroid_bounds.min_corner;
+
+        // Every centroid is in the same place, so we can't split by position
+        if extent.iter().all(|axis| *axis <= 0.0) {
+            return (start + end) / 2;
+        }
+
+        let bucket_of = |centroid: &Point3<f32>, axis: usize| {
+            let offset = (centroid[axis] - centroid_bounds.min_corner[axis]) / extent[axis];
+            ((offset * bucket_count as f32) as usize).min(bucket_count - 1)
+        };
+
+        let mut best: Option<(f32, usize, usize)> = None;
+
+        for axis in 0..3 {
+            if extent[axis] <= 0.0 {
+                continue;
+            }
+
+            let mut buckets: Vec<Option<(BoundingBox, usize)>> = vec![None; bucket_count];
+            for triangle in self.triangle_order[start..end].iter() {
+                let (bounds, centroid) = &triangle_bounds[*triangle];
+                let bucket = &mut buckets[bucket_of(centroid, axis)];
+                *bucket = Some(match bucket.take() {
+                    Some((bucket_bounds, count)) => (bucket_bounds.union(bounds), count + 1),
+                    None => (bounds.clone(), 1),
+                });
+            }
+
+            // The cost of putting the first `split` buckets on the left and the rest on the right
+            for split in 1..bucket_count {
+                let side = |buckets: &[Option<(BoundingBox, usize)>]| {
+                    let mut bounds: Option<BoundingBox> = None;
+                    let mut count = 0;
+                    for (bucket_bounds, bucket_count) in buckets.iter().flatten() {
+                        bounds = Some(match bounds {
+                            Some(bounds) => bounds.union(bucket_bounds),
+                            None => bucket_bounds.clone(),
+                        });
+                        count += bucket_count;
+                    }
+                    bounds.map_or(0.0, |bounds| surface_area(&bounds) * count as f32)
+                };
+
+                let cost = side(&buckets[..split]) + side(&buckets[split..]);
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, split));
+                }
+            }
+        }
+
+        let (_, axis, split) = match best {
+            Some(best) => best,
+            None => return (start + end) / 2,
+        };
+
+        let mut triangles = self.triangle_order[start..end].to_vec();
+        triangles.sort_by_key(|triangle| bucket_of(&triangle_bounds[*triangle].1, axis) >= split);
+        let left_count = triangles
+            .iter()
+            .filter(|triangle| bucket_of(&triangle_bounds[**triangle].1, axis) < split)
+            .count();
+        self.triangle_order[start..end].copy_from_slice(&triangles);
+
+        if left_count == 0 || left_count == end - start {
+            return (start + end) / 2;
+        }
+
+        start + left_count
+    }
+}
+
+impl Triangle {
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox {
+            min_corner: self.positions[0],
+            max_corner: self.positions[0],
+        };
+        bounds.include_point(&self.positions[1]);
+        bounds.include_point(&self.positions[2]);
+        bounds
+    }
+
+    fn face_normal(&self) -> Vector3<f32> {
+        let [a, b, c] = self.positions;
+        (b - a)
+            .cross(&(c - a))
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector3::z)
+    }
+
+    /// The distance along the ray to the triangle and the barycentric coordinates of the hit
+    ///
+    /// @see https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
+    fn intersect(&self, ray: &Ray) -> Option<(f32, Vector3<f32>)> {
+        let [a, b, c] = self.positions;
+        let ab = b - a;
+        let ac = c - a;
+
+        let p = ray.direction.cross(&ac);
+        let determinant = ab.dot(&p);
+        if determinant.abs() < f32::EPSILON * ab.norm() * ac.norm() {
+            return None;
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        let to_origin = ray.origin - a;
+
+        let u = to_origin.dot(&p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = to_origin.cross(&ab);
+        let v = ray.direction.dot(&q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = ac.dot(&q) * inverse_determinant;
+        if distance < 0.0 {
+            return None;
+        }
+
+        Some((distance, Vector3::new(1.0 - u - v, u, v)))
+    }
+
+    /// The point on the triangle closest to `point`, and its barycentric coordinates
+    ///
+    /// @see Real-Time Collision Detection (Ericson), 5.1.5
+    fn closest_point(&self, point: &Point3<f32>) -> (Point3<f32>, Vector3<f32>) {
+        let [a, b, c] = self.positions;
+        let ab = b - a;
+        let ac = c - a;
+
+        let ap = point - a;
+        let d1 = ab.dot(&ap);
+        let d2 = ac.dot(&ap);
+        if d1 <= 0.0 && d2 <= 0.0 {
+            return (a, Vector3::new(1.0, 0.0, 0.0));
+        }
+
+        let bp = point - b;
+        let d3 = ab.dot(&bp);
+        let d4 = ac.dot(&bp);
+        if d3 >= 0.0 && d4 <= d3 {
+            return (b, Vector3::new(0.0, 1.0, 0.0));
+        }
+
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+            let v = d1 / (d1 - d3);
+            return (a + ab * v, Vector3::new(1.0 - v, v, 0.0));
+        }
+
+        let cp = point - c;
+        let d5 = ab.dot(&cp);
+        let d6 = ac.dot(&cp);
+        if d6 >= 0.0 && d5 <= d6 {
+            return (c, Vector3::new(0.0, 0.0, 1.0));
+        }
+
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+            let w = d2 / (d2 - d6);
+            return (a + ac * w, Vector3::new(1.0 - w, 0.0, w));
+        }
+
+        let va = d3 * d6 - d5 * d4;
+        if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+            let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+            return (b + (c - b) * w, Vector3::new(0.0, 1.0 - w, w));
+        }
+
+        let denominator = 1.0 / (va + vb + vc);
+        let v = vb * denominator;
+        let w = vc * denominator;
+        (a + ab * v + ac * w, Vector3::new(1.0 - v - w, v, w))
+    }
+}
+
+/// The distance along the ray where it enters the box, if it hits the box at all
+///
+/// @see https://tavianator.com/fast-branchless-raybounding-box-intersections/
+fn ray_box(ray: &Ray, inverse_direction: &Vector3<f32>, bounds: &BoundingBox) -> Option<f32> {
+    let mut entry: f32 = 0.0;
+    let mut exit = f32::INFINITY;
+
+    for axis in 0..3 {
+        let near = (bounds.min_corner[axis] - ray.origin[axis]) * inverse_direction[axis];
+        let far = (bounds.max_corner[axis] - ray.origin[axis]) * inverse_direction[axis];
+
+        // A ray that is parallel to this axis and starts on the box's face gives NaN, which
+        // these comparisons ignore.
+        entry = entry.max(near.min(far));
+        exit = exit.min(near.max(far));
+    }
+
+    if entry <= exit {
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+fn distance_to_box(point: &Point3<f32>, bounds: &BoundingBox) -> f32 {
+    let mut distance_squared = 0.0;
+
+    for axis in 0..3 {
+        let outside = (bounds.min_corner[axis] - point[axis])
+            .max(point[axis] - bounds.max_corner[axis])
+            .max(0.0);
+        distance_squared += outside * outside;
+    }
+
+    distance_squared.sqrt()
+}
+
+fn surface_area(bounds: &BoundingBox) -> f32 {
+    let extent = bounds.max_corner - bounds.min_corner;
+    2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::vertex_data::{MultiIndexVertexData, SingleIndexVertexData};
+
+    /// Two grids, one above the other
+    fn stacked_grids() -> BlenderMesh {
+        let lower = grid(10, |_, _| 0.0);
+        let upper = grid(10, |_, _| 5.0);
+
+        let vertex_count = lower.positions.vertex_count() as u32;
+        let mut indices = lower.indices.clone();
+        indices.extend(upper.indices.iter().map(|idx| idx + vertex_count));
+
+        let concat = |a: &[f32], b: &[f32]| a.iter().chain(b.iter()).cloned().collect();
+
+        BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                vertices_in_each_face: vec![3; indices.len() / 3],
+                indices,
+                positions: attrib3(concat(lower.positions.data(), upper.positions.data())),
+                normals: attrib3(concat(lower.normals.data(), upper.normals.data())),
+                uvs: Some(attrib2(concat(
+                    lower.uvs.as_ref().unwrap().data(),
+                    upper.uvs.as_ref().unwrap().data(),
+                ))),
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        }
+    }
+
+    /// A ray pointing down hits the upper grid, and the hit is interpolated across the triangle
+    #[test]
+    fn closest_hit() {
+        let bvh = stacked_grids().build_bvh(&BvhConfig::default()).unwrap();
+
+        let ray = Ray::new(Point3::new(2.25, 7.5, 10.0), Vector3::new(0.0, 0.0, -2.0));
+        let hit = bvh.closest_hit(&ray, f32::INFINITY).unwrap();
+
+        assert!((hit.distance - 5.0).abs() < 1e-5);
+        assert!((hit.point - Point3::new(2.25, 7.5, 5.0)).norm() < 1e-5);
+        assert!(hit.triangle >= 200);
+        assert_eq!(hit.normal, Vector3::new(0.0, 0.0, 1.0));
+
+        let uv = hit.uv.unwrap();
+        assert!((uv - Vector2::new(0.225, 0.75)).norm() < 1e-5);
+        assert!((hit.barycentric.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+
+        // Pointing up from between the grids
+        let ray = Ray::new(Point3::new(2.25, 7.5, 1.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = bvh.closest_hit(&ray, f32::INFINITY).unwrap();
+        assert!((hit.distance - 4.0).abs() < 1e-5);
+        assert_eq!(bvh.closest_hit(&ray, 3.0), None);
+
+        // Missing the grids
+        let ray = Ray::new(Point3::new(20.0, 7.5, 10.0), Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(bvh.closest_hit(&ray, f32::INFINITY), None);
+    }
+
+    #[test]
+    fn any_hit() {
+        let bvh = stacked_grids().build_bvh(&BvhConfig::default()).unwrap();
+
+        let ray = Ray::new(Point3::new(3.3, 3.6, 10.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = bvh.any_hit(&ray, f32::INFINITY).unwrap();
+        assert!((hit.distance - 5.0).abs() < 1e-5 || (hit.distance - 10.0).abs() < 1e-5);
+
+        assert!(bvh.any_hit(&ray, 4.0).is_none());
+    }
+
+    /// The BVH finds the same closest hits as testing every triangle
+    #[test]
+    fn closest_hit_matches_brute_force() {
+        let mesh = stacked_grids();
+        let config = BvhConfig {
+            max_triangles_per_leaf: 1,
+            sah_buckets: 4,
+        };
+        let bvh = mesh.build_bvh(&config).unwrap();
+        let mut hits = 0;
+
+        for idx in 0..200 {
+            let origin = Point3::new(
+                (idx as f32 * 0.37).fract() * 12.0 - 1.0,
+                (idx as f32 * 0.71).fract() * 12.0 - 1.0,
+                (idx as f32 * 0.13).fract() * 8.0 - 1.0,
+            );
+            let direction = Vector3::new(
+                (idx as f32 * 0.53).fract() - 0.5,
+                (idx as f32 * 0.29).fract() - 0.5,
+                (idx as f32 * 0.91).fract() - 0.5,
+            );
+            let ray = Ray::new(origin, direction);
+
+            let brute_force = bvh
+                .triangles
+                .iter()
+                .filter_map(|triangle| triangle.intersect(&ray))
+                .map(|(distance, _)| distance)
+                .fold(None, |closest: Option<f32>, distance| {
+                    Some(closest.map_or(distance, |closest| closest.min(distance)))
+                });
+
+            assert_eq!(
+                bvh.closest_hit(&ray, f32::INFINITY).map(|hit| hit.distance),
+                brute_force
+            );
+            hits += brute_force.is_some() as usize;
+        }
+
+        assert!(hits > 50, "{}", hits);
+    }
+
+    #[test]
+    fn sphere_overlap() {
+        let bvh = stacked_grids().build_bvh(&BvhConfig::default()).unwrap();
+
+        // Touches the two triangles of one quad in the upper grid
+        let hits = bvh.sphere_overlap(&Point3::new(4.6, 4.5, 5.5), 0.6);
+        assert_eq!(hits.len(), 2);
+        assert!((hits[0].distance - 0.5).abs() < 1e-5);
+        assert!((hits[0].point - Point3::new(4.6, 4.5, 5.0)).norm() < 1e-5);
+
+        // Closer to an edge of the quad than to the quad
+        let hits = bvh.sphere_overlap(&Point3::new(4.6, 10.5, 5.0), 0.6);
+        assert_eq!(hits.len(), 1);
+        assert!((hits[0].point - Point3::new(4.6, 10.0, 5.0)).norm() < 1e-5);
+        assert!((hits[0].distance - 0.5).abs() < 1e-5);
+
+        assert!(bvh
+            .sphere_overlap(&Point3::new(4.6, 4.3, 2.5), 2.0)
+            .is_empty());
+    }
+
+    /// Multi indexed meshes look up each corner with its own indices
+    #[test]
+    fn multi_indexed_mesh() {
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                vertices_in_each_face: vec![3],
+                positions: attrib3(vec![0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0]),
+                position_indices: vec![0, 1, 2],
+                normals: attrib3(vec![0.0, 0.0, 1.0, 1.0, 0.0, 0.0]),
+                normal_indices: vec![0, 0, 1],
+                uvs: Some(attrib2(vec![0.0, 0.0, 1.0, 1.0])),
+                uv_indices: Some(vec![0, 1, 1]),
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        let bvh = mesh.build_bvh(&BvhConfig::default()).unwrap();
+        let ray = Ray::new(Point3::new(0.5, 1.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = bvh.closest_hit(&ray, f32::INFINITY).unwrap();
+
+        assert_eq!(hit.barycentric, Vector3::new(0.25, 0.25, 0.5));
+        assert_eq!(hit.uv, Some(Vector2::new(0.75, 0.75)));
+        assert_eq!(hit.normal, Vector3::new(1.0, 0.0, 1.0).normalize());
+    }
+
+    #[test]
+    fn meshes_need_to_be_triangulated() {
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                vertices_in_each_face: vec![3, 4],
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        match mesh.build_bvh(&BvhConfig::default()) {
+            Err(BvhError::VertexData(VertexDataError::NotTriangulated {
+                face: 1,
+                vertex_count: 4,
+            })) => {}
+            _ => unreachable!(),
+        };
+    }
+}
@@ -1,3 +1,7 @@
+use super::{CombineIndicesError, IndexWidth};
+use crate::quantize::WeightEncoding;
+use crate::tangent::TangentAlgorithm;
+
 /// Configuration for combining multiple indices into a single index
 #[derive(Debug, Default)]
 pub struct CreateSingleIndexConfig {
@@ -5,8 +9,56 @@ pub struct CreateSingleIndexConfig {
     ///
     /// If unset then no bone influences will be included in the final single indexed VertexData.
     pub bone_influences_per_vertex: Option<u8>,
+    /// Scale each vertex's bone weights so that they add up to one.
+    ///
+    /// Without this, vertices that lost some of their bones to `bone_influences_per_vertex` or
+    /// `min_bone_weight` have weights that add up to less than one, and get pulled towards the
+    /// origin when they're skinned.
+    pub renormalize_bone_weights: bool,
+    /// Bones that influence a vertex less than this are removed from it (their weight is set
+    /// to zero). The bone with the largest weight is always kept.
+    pub min_bone_weight: f32,
+    /// Round the bone weights so that they can be stored exactly as normalized integers, with
+    /// each vertex's weights adding up to exactly one (i.e. `255` or `65535`).
+    ///
+    /// This renormalizes the weights first, even if `renormalize_bone_weights` isn't set.
+    /// `None` and `Some(WeightEncoding::F32)` leave the weights as they are.
+    pub bone_weight_quantization: Option<WeightEncoding>,
     /// Whether or not to calculate the tangents for each vertex.
     ///
     /// You'll want to do this when you plan to use normal mapping in your rendering pipeline.
     pub calculate_vertex_tangents: bool,
+    /// How vertex tangents are calculated when `calculate_vertex_tangents` is set.
+    pub tangent_algorithm: TangentAlgorithm,
+    /// The integer type to store the combined index buffer as.
+    ///
+    /// If unset we'll use `u16` indices when every vertex can be addressed by one, and `u32`
+    /// indices otherwise.
+    ///
+    /// If you force `u16` indices on a mesh that ends up with more than 65,536 vertices,
+    /// combining will fail with [`CombineIndicesError::IndicesDoNotFitInU16`].
+    ///
+    /// [`CombineIndicesError::IndicesDoNotFitInU16`]: enum.CombineIndicesError.html#variant.IndicesDoNotFitInU16
+    pub index_width: Option<IndexWidth>,
+}
+
+impl CreateSingleIndexConfig {
+    /// The index width to use for a combined mesh with this many vertices
+    pub(crate) fn index_width_for_vertex_count(
+        &self,
+        vertex_count: usize,
+    ) -> Result<IndexWidth, CombineIndicesError> {
+        let index_width = self
+            .index_width
+            .unwrap_or_else(|| IndexWidth::smallest_for_vertex_count(vertex_count));
+
+        if index_width == IndexWidth::U16 && vertex_count > index_width.max_vertex_count() {
+            return Err(CombineIndicesError::IndicesDoNotFitInU16 {
+                vertex_count,
+                max_vertex_count: index_width.max_vertex_count(),
+            });
+        }
+
+        Ok(index_width)
+    }
 }
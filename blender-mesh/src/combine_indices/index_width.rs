@@ -0,0 +1,87 @@
+/// The integer type that a mesh's single index buffer gets stored as.
+///
+/// `u16` indices use half of the memory but can only address 65,536 vertices, so larger meshes
+/// need `u32` indices.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+pub enum IndexWidth {
+    #[default]
+    U16,
+    U32,
+}
+
+impl IndexWidth {
+    /// The smallest index width that can address `vertex_count` vertices
+    pub fn smallest_for_vertex_count(vertex_count: usize) -> IndexWidth {
+        if vertex_count <= IndexWidth::U16.max_vertex_count() {
+            IndexWidth::U16
+        } else {
+            IndexWidth::U32
+        }
+    }
+
+    /// The largest number of vertices that an index of this width can address
+    pub fn max_vertex_count(&self) -> usize {
+        match self {
+            IndexWidth::U16 => u16::MAX as usize + 1,
+            IndexWidth::U32 => (u32::MAX as usize).saturating_add(1),
+        }
+    }
+}
+
+/// A single index buffer, stored as the integer type that it was created with.
+///
+/// Most graphics APIs let you pick either of these when you upload your index buffer.
+#[derive(Debug, PartialEq, Clone)]
+pub enum IndexBuffer {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl IndexBuffer {
+    /// Store indices as the given integer type
+    pub(crate) fn new(indices: &[u32], index_width: IndexWidth) -> IndexBuffer {
+        match index_width {
+            IndexWidth::U16 => IndexBuffer::U16(indices.iter().map(|idx| *idx as u16).collect()),
+            IndexWidth::U32 => IndexBuffer::U32(indices.to_vec()),
+        }
+    }
+
+    /// The number of indices in the buffer
+    pub fn len(&self) -> usize {
+        match self {
+            IndexBuffer::U16(indices) => indices.len(),
+            IndexBuffer::U32(indices) => indices.len(),
+        }
+    }
+
+    /// Whether or not there are any indices in the buffer
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The width of each index in the buffer
+    pub fn index_width(&self) -> IndexWidth {
+        match self {
+            IndexBuffer::U16(_) => IndexWidth::U16,
+            IndexBuffer::U32(_) => IndexWidth::U32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smallest_index_width() {
+        assert_eq!(IndexWidth::smallest_for_vertex_count(0), IndexWidth::U16);
+        assert_eq!(
+            IndexWidth::smallest_for_vertex_count(65_536),
+            IndexWidth::U16
+        );
+        assert_eq!(
+            IndexWidth::smallest_for_vertex_count(65_537),
+            IndexWidth::U32
+        );
+    }
+}
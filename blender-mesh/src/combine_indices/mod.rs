@@ -1,11 +1,18 @@
 pub use self::create_single_index_config::CreateSingleIndexConfig;
-use crate::vertex_data::{AttributeSize, VertexAttribute};
+pub use self::index_width::{IndexBuffer, IndexWidth};
+use crate::morph_target::MorphTarget;
+use crate::tangent::{TangentAlgorithm, TangentError};
+use crate::vertex_data::{
+    AttributeSize, BoneAttributes, MultiIndexVertexData, SingleIndexVertexData, VertexAttribute,
+    VertexData,
+};
 use crate::BlenderMesh;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::ops::{Deref, DerefMut};
 
 mod create_single_index_config;
+mod index_width;
 
 /// Used to set temporary data that should get overwritten.
 ///
@@ -13,7 +20,43 @@ mod create_single_index_config;
 /// data was improperly generated somehow.
 ///
 /// Our unit tests should prevemt this, so this is a safety precaution.
-const EASILY_RECOGNIZABLE_NUMBER: f32 = 123456789.;
+pub(crate) const EASILY_RECOGNIZABLE_NUMBER: f32 = 123456789.;
+
+/// Something went wrong while combining a mesh's indices into a single index
+#[derive(Debug, Fail)]
+pub enum CombineIndicesError {
+    /// `u16` indices were requested but the combined mesh has more vertices than a `u16` can
+    /// address.
+    #[fail(
+        display = "The combined mesh has {} vertices but u16 indices can only address {}. Use IndexWidth::U32",
+        vertex_count, max_vertex_count
+    )]
+    IndicesDoNotFitInU16 {
+        vertex_count: usize,
+        max_vertex_count: usize,
+    },
+    /// The mesh's indices have already been combined
+    #[fail(display = "The mesh's vertex data already has a single index")]
+    AlreadySingleIndexed,
+    /// Every position needs bone influences, but the bone indices or weights ran out early
+    #[fail(
+        display = "The mesh has {} positions but only {} of them have bone influences",
+        position_count, influence_count
+    )]
+    MissingBoneInfluences {
+        position_count: usize,
+        influence_count: usize,
+    },
+    /// Vertex tangents were requested but could not be calculated
+    #[fail(display = "Could not calculate vertex tangents: {}", _0)]
+    Tangents(#[cause] TangentError),
+}
+
+impl From<TangentError> for CombineIndicesError {
+    fn from(error: TangentError) -> Self {
+        CombineIndicesError::Tangents(error)
+    }
+}
 
 impl BlenderMesh {
     /// We export our models with indices for positions, normals and uvs because
@@ -26,296 +69,275 @@ impl BlenderMesh {
     /// from having three indices to having one. This usually requires some duplication of
     /// vertex data. We duplicate the minimum amount of vertex data necessary.
     ///
-    /// TODO: Need to continue refactoring
-    ///
-    /// TODO: Make this function set BlenderMesh.vertex_data = VertexData::SingleIndexVertexData
-    ///
-    /// TODO: Don't work on additionally functionality until we've broken up these tests
-    /// and implementation into smaller, specific pieces.
-    pub fn combine_vertex_indices(&mut self, config: &CreateSingleIndexConfig) {
-        if let Some(bone_influences_per_vertex) = config.bone_influences_per_vertex {
-            self.set_bone_influences_per_vertex(bone_influences_per_vertex);
-        }
-
-        if config.calculate_vertex_tangents {
-            self.calculate_face_tangents().unwrap();
-        }
-
-        let has_uvs = self.vertex_uvs.is_some();
+    /// Our `VertexData::Multi` is replaced with the `VertexData::Single` that we generate.
+    /// If this fails the mesh is left untouched.
+    pub fn combine_vertex_indices(
+        &mut self,
+        config: &CreateSingleIndexConfig,
+    ) -> Result<(), CombineIndicesError> {
+        let single = match &self.vertex_data {
+            VertexData::Multi(multi) => multi.combine_indices(config)?,
+            VertexData::Single(_) => return Err(CombineIndicesError::AlreadySingleIndexed),
+        };
 
-        let mut largest_vert_id = *self.vertex_position_indices.iter().max().unwrap() as usize;
+        self.vertex_data = VertexData::Single(single);
 
-        let mut encountered_vert_data = EncounteredIndexCombinations::default();
+        Ok(())
+    }
+}
 
-        let mut encountered_vert_ids = HashSet::new();
+impl MultiIndexVertexData {
+    /// Generate single indexed vertex data, duplicating a vertex position for every unique
+    /// combination of position, normal and uv indices that uses it.
+    ///
+    /// The first combination that we see for a position index keeps that position index, every
+    /// other combination becomes a new vertex at the end of our vertex data.
+    pub(crate) fn combine_indices(
+        &self,
+        config: &CreateSingleIndexConfig,
+    ) -> Result<SingleIndexVertexData, CombineIndicesError> {
+        // Check that our indices will fit before doing any work
+        let vertex_count = self.combined_vertex_count();
+        let index_width = config.index_width_for_vertex_count(vertex_count)?;
+
+        let face_tangents = match (config.calculate_vertex_tangents, config.tangent_algorithm) {
+            (true, TangentAlgorithm::FaceTangents) => Some(self.calculate_face_tangents()?),
+            _ => None,
+        };
 
-        let mut expanded_positions = vec![];
-        expanded_positions.resize((largest_vert_id + 1) * 3, EASILY_RECOGNIZABLE_NUMBER);
-        let mut expanded_positions = VertexAttribute::new(expanded_positions, AttributeSize::Three);
-
-        let mut expanded_normals = vec![];
-        expanded_normals.resize((largest_vert_id + 1) * 3, EASILY_RECOGNIZABLE_NUMBER);
-        let mut expanded_normals = VertexAttribute::new(expanded_normals, AttributeSize::Three);
-
-        let mut expanded_uvs = vec![];
-        expanded_uvs.resize((largest_vert_id + 1) * 2, EASILY_RECOGNIZABLE_NUMBER);
-        let mut expanded_uvs = VertexAttribute::new(expanded_uvs, AttributeSize::Two);
-
-        let mut expanded_pos_indices = vec![];
-
-        let mut new_group_indices = self.vertex_group_indices.clone();
-        let mut new_group_weights = self.vertex_group_weights.clone();
-
-        expanded_pos_indices.resize(self.vertex_position_indices.len(), 0);
-
-        let mut face_idx = 0;
-        let mut vertices_until_next_face = self.num_vertices_in_each_face[0];
-
-        let mut expanded_tangents = vec![];
-        expanded_tangents.resize((largest_vert_id + 1) * 3, EASILY_RECOGNIZABLE_NUMBER);
-        let mut expanded_tangents = VertexAttribute::new(expanded_tangents, AttributeSize::Three);
-
-        let total_indices: usize = self
-            .num_vertices_in_each_face
-            .iter()
-            .map(|x| *x as usize)
-            .sum();
-
-        // FIXME: Split this loop into a function
-        for (elem_array_index, start_vert_id) in self.vertex_position_indices.iter().enumerate() {
-            let start_vert_id = *start_vert_id;
-            let normal_index = self.vertex_normal_indices.as_ref().unwrap()[elem_array_index];
-            let uv_index = match self.vertex_uv_indices.as_ref() {
-                Some(uvs) => Some(uvs[elem_array_index]),
-                None => None,
-            };
-
-            let vert_id_to_reuse =
-                encountered_vert_data.get(&(start_vert_id, normal_index, uv_index));
-
-            // If we've already seen this combination of vertex indices we'll re-use the index
-            if vert_id_to_reuse.is_some() {
-                expanded_pos_indices[elem_array_index] = *vert_id_to_reuse.unwrap();
-
-                if let Some(face_tangents) = &self.face_tangents {
-                    if face_tangents.len() > 0 {
-                        let (x, y, z) = self.face_tangent_at_idx(face_idx);
-                        expanded_tangents.increment_three_components(
-                            *vert_id_to_reuse.unwrap() as usize,
-                            x,
-                            y,
-                            z,
-                        );
-                    }
+        let bone_influences = match (config.bone_influences_per_vertex, &self.bone_influences) {
+            (Some(bone_influences_per_vertex), Some(bone_influences)) => {
+                let position_count = self.positions.vertex_count();
+                let influence_count = bone_influences.influences_of_each_vertex().len();
+                if influence_count < position_count {
+                    return Err(CombineIndicesError::MissingBoneInfluences {
+                        position_count,
+                        influence_count,
+                    });
                 }
-            } else if !encountered_vert_ids.contains(&start_vert_id) {
-                // If this is our first time seeing this vertex index of vertex indices we'll insert
-                // the expanded data
-
-                encountered_vert_ids.insert(start_vert_id);
-
-                // TODO: Use a data structure that holds some of this stuff so we don't need
-                // to pass it around everywhere ..
-                self.handle_first_vertex_encounter(
-                    &mut encountered_vert_data,
-                    &mut expanded_pos_indices,
-                    start_vert_id,
-                    elem_array_index,
-                    &mut expanded_positions,
-                    &mut expanded_normals,
-                    &mut expanded_uvs,
-                    &mut expanded_tangents,
-                    normal_index,
-                    uv_index,
-                    face_idx,
-                );
-            } else {
-                // If we've encountered an existing position index but the normal / uv indices for this
-                // vertex aren't the same as ones that we've previously encountered we'll need to
-                // create a new vertex index with this new combination of data.
-
-                largest_vert_id += 1;
-
-                expanded_pos_indices[elem_array_index] = largest_vert_id as u16;
-
-                self.push_generated_vertex_data(
-                    start_vert_id,
-                    normal_index,
-                    uv_index,
-                    config.bone_influences_per_vertex,
-                    new_group_indices.as_mut(),
-                    new_group_weights.as_mut(),
-                    &mut expanded_positions,
-                    &mut expanded_normals,
-                    &mut expanded_uvs,
-                    &mut expanded_tangents,
-                    face_idx,
-                );
-
-                encountered_vert_data.insert(
-                    (start_vert_id as u16, normal_index, uv_index),
-                    largest_vert_id as u16,
-                );
-            }
 
-            if face_idx + 1 < self.num_vertices_in_each_face.len() {
-                vertices_until_next_face -= 1;
-            }
-
-            if vertices_until_next_face == 0 {
-                face_idx += 1;
-                if face_idx < self.num_vertices_in_each_face.len() {
-                    vertices_until_next_face = self.num_vertices_in_each_face[face_idx];
-                }
+                let mut bone_influences = bone_influences.clone();
+                bone_influences.set_bone_influences_per_vertex(bone_influences_per_vertex);
+                bone_influences.adjust_bone_weights(bone_influences_per_vertex, config);
+                Some(bone_influences)
             }
-        }
-
-        self.vertex_position_indices = expanded_pos_indices;
-
-        self.vertex_normals = expanded_normals.data().clone();
-        self.vertex_positions = expanded_positions.data().clone();
-
-        self.vertex_group_indices = new_group_indices;
-        self.vertex_group_weights = new_group_weights;
-
-        if config.calculate_vertex_tangents {
-            self.per_vertex_tangents = Some(expanded_tangents);
-        }
-
-        if has_uvs {
-            self.vertex_uvs = Some(expanded_uvs.data().clone());
-        }
+            _ => None,
+        };
 
-        self.vertex_normal_indices = None;
-        self.vertex_uv_indices = None;
-    }
+        let mut combined = SingleIndexVertexData {
+            vertices_in_each_face: self.vertices_in_each_face.clone(),
+            indices: vec![0; self.position_indices.len()],
+            index_width,
+            positions: placeholder_attribute(vertex_count, AttributeSize::Three),
+            normals: placeholder_attribute(vertex_count, AttributeSize::Three),
+            uvs: self
+                .uvs
+                .as_ref()
+                .map(|_| placeholder_attribute(vertex_count, AttributeSize::Two)),
+            tangents: face_tangents
+                .as_ref()
+                .map(|_| placeholder_attribute(vertex_count, AttributeSize::Three)),
+            bones: config
+                .bone_influences_per_vertex
+                .filter(|_| bone_influences.is_some())
+                .map(|bones_per_vertex| BoneAttributes {
+                    bones_per_vertex,
+                    bone_indices: vec![0; vertex_count * bones_per_vertex as usize],
+                    bone_weights: vec![0.0; vertex_count * bones_per_vertex as usize],
+                }),
+            face_materials: self.face_materials.clone(),
+            morph_targets: self
+                .morph_targets
+                .iter()
+                .map(|target| MorphTarget {
+                    name: target.name.clone(),
+                    default_weight: target.default_weight,
+                    position_deltas: placeholder_attribute(vertex_count, AttributeSize::Three),
+                    normal_deltas: placeholder_attribute(vertex_count, AttributeSize::Three),
+                })
+                .collect(),
+        };
 
-    // TODO: Way too many parameters - just working on splitting things up into smaller functions..
-    fn handle_first_vertex_encounter(
-        &self,
-        encountered_vert_data: &mut EncounteredIndexCombinations,
-        expanded_pos_indices: &mut Vec<u16>,
-        start_vert_id: u16,
-        elem_array_index: usize,
-        expanded_positions: &mut VertexAttribute,
-        expanded_normals: &mut VertexAttribute,
-        expanded_uvs: &mut VertexAttribute,
-        expanded_tangents: &mut VertexAttribute,
-        normal_index: u16,
-        uv_index: Option<u16>,
-        face_idx: usize,
-    ) {
-        let has_uvs = self.vertex_uvs.is_some();
+        let mut largest_vert_id = match self.position_indices.iter().max() {
+            Some(largest_vert_id) => *largest_vert_id,
+            None => return Ok(combined),
+        };
 
-        expanded_pos_indices[elem_array_index] = start_vert_id;
+        let mut encountered_vert_data = EncounteredIndexCombinations::default();
+        let mut encountered_vert_ids = HashSet::new();
 
-        let start_vert_id = start_vert_id as usize;
+        let mut elem_array_index = 0;
+
+        for (face_idx, vertices_in_face) in self.vertices_in_each_face.iter().enumerate() {
+            let face_tangent = face_tangents.as_ref().map(|face_tangents| {
+                (
+                    face_tangents[face_idx * 3],
+                    face_tangents[face_idx * 3 + 1],
+                    face_tangents[face_idx * 3 + 2],
+                )
+            });
+
+            for _ in 0..*vertices_in_face {
+                let pos_idx = self.position_indices[elem_array_index];
+                let normal_idx = self.normal_indices[elem_array_index];
+                let uv_idx = self
+                    .uv_indices
+                    .as_ref()
+                    .map(|uv_indices| uv_indices[elem_array_index]);
 
-        // TODO: Six methods to get and set the normal, pos, and uv for a vertex_num
-        let (x, y, z) = self.vertex_pos_at_idx(start_vert_id as u16);
-        expanded_positions.set_three_components(start_vert_id, x, y, z);
+                let vertex_indices = (pos_idx, normal_idx, uv_idx);
 
-        let (x, y, z) = self.vertex_normal_at_idx(normal_index);
-        expanded_normals.set_three_components(start_vert_id, x, y, z);
+                if let Some(vert_id_to_reuse) = encountered_vert_data.get(&vertex_indices) {
+                    // If we've already seen this combination of vertex indices we'll re-use the
+                    // index, adding this face's tangent into the vertex's tangent
+                    combined.indices[elem_array_index] = *vert_id_to_reuse;
 
-        if has_uvs {
-            let uv_index = uv_index.unwrap();
-            let (u, v) = self.vertex_uv_at_idx(uv_index);
-            expanded_uvs.set_two_components(start_vert_id, u, v);
-        }
+                    if let (Some(tangents), Some((x, y, z))) =
+                        (combined.tangents.as_mut(), face_tangent)
+                    {
+                        tangents.increment_three_components(*vert_id_to_reuse as usize, x, y, z);
+                    }
+                } else {
+                    // If this is our first time seeing this position index the vertex keeps the
+                    // position index. Otherwise the normal / uv indices for this position
+                    // aren't ones that we've previously encountered, so we'll need to create a
+                    // new vertex with this new combination of data.
+                    let vert_id = if encountered_vert_ids.insert(pos_idx) {
+                        pos_idx
+                    } else {
+                        largest_vert_id += 1;
+                        largest_vert_id
+                    };
+
+                    self.set_combined_vertex(
+                        &mut combined,
+                        vert_id,
+                        vertex_indices,
+                        face_tangent,
+                        bone_influences
+                            .as_ref()
+                            .map(|b| (&b.bone_indices, &b.bone_weights)),
+                    );
+
+                    combined.indices[elem_array_index] = vert_id;
+                    encountered_vert_data.insert(vertex_indices, vert_id);
+                }
 
-        if let Some(face_tangents) = &self.face_tangents {
-            if face_tangents.len() > 0 {
-                let (x, y, z) = self.face_tangent_at_idx(face_idx);
-                expanded_tangents.set_three_components(start_vert_id, x, y, z);
+                elem_array_index += 1;
             }
         }
 
-        let start_vert_id = start_vert_id as u16;
+        if let (true, TangentAlgorithm::MikkTSpace) =
+            (config.calculate_vertex_tangents, config.tangent_algorithm)
+        {
+            combined.calculate_mikktspace_tangents()?;
+
+            // Splitting vertices with mirrored uvs might have pushed us past what our indices
+            // can address
+            combined.index_width =
+                config.index_width_for_vertex_count(combined.positions.vertex_count())?;
+        }
 
-        encountered_vert_data.insert((start_vert_id, normal_index, uv_index), start_vert_id);
+        Ok(combined)
     }
 
-    // TODO: Way too many parameters - just working on splitting things up into smaller functions..
-    fn push_generated_vertex_data(
+    /// Copy the data for a combination of position, normal and uv indices into a vertex of our
+    /// single indexed data.
+    fn set_combined_vertex(
         &self,
-        pos_idx: u16,
-        normal_idx: u16,
-        uv_idx: Option<u16>,
-        bone_influences_per_vertex: Option<u8>,
-        new_group_indices: Option<&mut Vec<u8>>,
-        new_group_weights: Option<&mut Vec<f32>>,
-        expanded_positions: &mut VertexAttribute,
-        expanded_normals: &mut VertexAttribute,
-        expanded_uvs: &mut VertexAttribute,
-        expanded_tangents: &mut VertexAttribute,
-        face_idx: usize,
+        combined: &mut SingleIndexVertexData,
+        vert_id: u32,
+        (pos_idx, normal_idx, uv_idx): (PosIndex, NormalIndex, UvIndex),
+        face_tangent: Option<(f32, f32, f32)>,
+        bones: Option<(&Vec<u8>, &Vec<f32>)>,
     ) {
-        let has_uvs = self.vertex_uvs.is_some();
+        let vert_id = vert_id as usize;
 
         let (x, y, z) = self.vertex_pos_at_idx(pos_idx);
-        expanded_positions.push(x);
-        expanded_positions.push(y);
-        expanded_positions.push(z);
+        combined.positions.set_three_components(vert_id, x, y, z);
 
         let (x, y, z) = self.vertex_normal_at_idx(normal_idx);
-        expanded_normals.push(x);
-        expanded_normals.push(y);
-        expanded_normals.push(z);
-
-        if has_uvs {
-            let uv_index = uv_idx.unwrap();
-            let (u, v) = self.vertex_uv_at_idx(uv_index);
-            expanded_uvs.push(u);
-            expanded_uvs.push(v);
+        combined.normals.set_three_components(vert_id, x, y, z);
+
+        if let (Some(uvs), Some(uv_idx)) = (combined.uvs.as_mut(), uv_idx) {
+            let (u, v) = self.vertex_uv_at_idx(uv_idx);
+            uvs.set_two_components(vert_id, u, v);
         }
 
-        if let Some(face_tangents) = &self.face_tangents {
-            if face_tangents.len() > 0 {
-                let (x, y, z) = self.face_tangent_at_idx(face_idx);
-                expanded_tangents.push(x);
-                expanded_tangents.push(y);
-                expanded_tangents.push(z);
-            }
+        if let (Some(tangents), Some((x, y, z))) = (combined.tangents.as_mut(), face_tangent) {
+            tangents.set_three_components(vert_id, x, y, z);
+        }
+
+        for (combined_target, target) in combined
+            .morph_targets
+            .iter_mut()
+            .zip(self.morph_targets.iter())
+        {
+            let (x, y, z) = target.position_deltas.three_components(pos_idx as usize);
+            combined_target
+                .position_deltas
+                .set_three_components(vert_id, x, y, z);
+
+            let (x, y, z) = target.normal_deltas.three_components(normal_idx as usize);
+            combined_target
+                .normal_deltas
+                .set_three_components(vert_id, x, y, z);
         }
 
-        // If the mesh has bone influences append bone data to the end of the bone vectors
-        // to account for this newly generated vertex.
-        if let Some(bone_influences_per_vertex) = bone_influences_per_vertex {
-            self.push_bone_data_for_generated_vertex(
-                pos_idx as usize,
-                bone_influences_per_vertex,
-                new_group_indices.unwrap(),
-                new_group_weights.unwrap(),
-            );
+        // Every vertex that shares a position is influenced by the same bones
+        if let (Some(combined_bones), Some((bone_indices, bone_weights))) =
+            (combined.bones.as_mut(), bones)
+        {
+            let bones_per_vertex = combined_bones.bones_per_vertex as usize;
+            let from = pos_idx as usize * bones_per_vertex;
+            let to = vert_id * bones_per_vertex;
+
+            combined_bones.bone_indices[to..to + bones_per_vertex]
+                .copy_from_slice(&bone_indices[from..from + bones_per_vertex]);
+            combined_bones.bone_weights[to..to + bones_per_vertex]
+                .copy_from_slice(&bone_weights[from..from + bones_per_vertex]);
         }
     }
 
-    // TODO: Way too many parameters - just working on splitting things up into smaller functions..
-    fn push_bone_data_for_generated_vertex(
-        &self,
-        vert_idx: usize,
-        bone_influences_per_vertex: u8,
-        new_group_indices: &mut Vec<u8>,
-        new_group_weights: &mut Vec<f32>,
-    ) {
-        // Where in our vector of group indices / weights does this vertex start?
-        let group_data_start_idx = vert_idx * bone_influences_per_vertex as usize;
+    /// The number of vertices that the mesh will have after combining its indices.
+    ///
+    /// Every position index keeps its slot, and every additional combination of normal and uv
+    /// indices that we see for a position index becomes a new vertex at the end.
+    fn combined_vertex_count(&self) -> usize {
+        let largest_vert_id = match self.position_indices.iter().max() {
+            Some(largest_vert_id) => *largest_vert_id as usize,
+            None => return 0,
+        };
+
+        let mut encountered_vert_ids = HashSet::new();
+        let mut encountered_combinations = HashSet::new();
 
-        for i in 0..bone_influences_per_vertex {
-            let group_data_idx = group_data_start_idx + i as usize;
-            let weight = new_group_weights[group_data_idx];
-            new_group_weights.push(weight);
+        for (elem_array_index, pos_idx) in self.position_indices.iter().enumerate() {
+            let normal_idx = self.normal_indices[elem_array_index];
+            let uv_idx = self
+                .uv_indices
+                .as_ref()
+                .map(|indices| indices[elem_array_index]);
 
-            let index = new_group_indices[group_data_idx];
-            new_group_indices.push(index);
+            encountered_vert_ids.insert(*pos_idx);
+            encountered_combinations.insert((*pos_idx, normal_idx, uv_idx));
         }
+
+        largest_vert_id + 1 + encountered_combinations.len() - encountered_vert_ids.len()
     }
 }
 
-type PosIndex = u16;
-type NormalIndex = u16;
-type UvIndex = Option<u16>;
+/// Vertex data that every combined vertex will overwrite
+fn placeholder_attribute(vertex_count: usize, size: AttributeSize) -> VertexAttribute {
+    VertexAttribute::new(
+        vec![EASILY_RECOGNIZABLE_NUMBER; vertex_count * size as usize],
+        size,
+    )
+}
+
+type PosIndex = u32;
+type NormalIndex = u32;
+type UvIndex = Option<u32>;
 #[derive(Debug, Default)]
 struct EncounteredIndexCombinations {
     encountered: HashMap<(PosIndex, NormalIndex, UvIndex), PosIndex>,
@@ -343,9 +365,9 @@ impl DerefMut for EncounteredIndexCombinations {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::bone::BoneInfluencesPerVertex;
     use crate::concat_vecs;
     use crate::test_utils::*;
+    use crate::vertex_data::BoneInfluences;
 
     struct CombineIndicesTest {
         mesh_to_combine: BlenderMesh,
@@ -355,11 +377,13 @@ mod tests {
 
     impl CombineIndicesTest {
         fn test(&mut self) {
-            self.mesh_to_combine.combine_vertex_indices(
-                self.create_single_idx_config
-                    .as_ref()
-                    .unwrap_or(&CreateSingleIndexConfig::default()),
-            );
+            self.mesh_to_combine
+                .combine_vertex_indices(
+                    self.create_single_idx_config
+                        .as_ref()
+                        .unwrap_or(&CreateSingleIndexConfig::default()),
+                )
+                .unwrap();
             let combined_mesh = &self.mesh_to_combine;
             assert_eq!(combined_mesh, &self.expected_combined_mesh);
         }
@@ -373,6 +397,9 @@ mod tests {
         let create_single_idx_config = Some(CreateSingleIndexConfig {
             bone_influences_per_vertex: Some(3),
             calculate_vertex_tangents: false,
+            tangent_algorithm: TangentAlgorithm::FaceTangents,
+            index_width: None,
+            ..CreateSingleIndexConfig::default()
         });
 
         CombineIndicesTest {
@@ -391,25 +418,67 @@ mod tests {
     // vector at the time.
     #[test]
     fn combine_mesh_with_non_sequential_indices() {
-        let mesh_to_combine = BlenderMesh {
-            vertex_positions: concat_vecs!(v(5), v(6), v(7)),
-            vertex_normals: concat_vecs!(v(10), v(11), v(12)),
-            vertex_uvs: Some(concat_vecs!(v2(15), v2(16), v2(17))),
-            num_vertices_in_each_face: vec![3],
-            vertex_position_indices: vec![2, 1, 0],
-            vertex_normal_indices: Some(vec![2, 1, 0]),
-            vertex_uv_indices: Some(vec![2, 1, 0]),
-            ..BlenderMesh::default()
-        };
+        let mesh_to_combine = multi(MultiIndexVertexData {
+            positions: attrib3(concat_vecs!(v(5), v(6), v(7))),
+            normals: attrib3(concat_vecs!(v(10), v(11), v(12))),
+            uvs: Some(attrib2(concat_vecs!(v2(15), v2(16), v2(17)))),
+            vertices_in_each_face: vec![3],
+            position_indices: vec![2, 1, 0],
+            normal_indices: vec![2, 1, 0],
+            uv_indices: Some(vec![2, 1, 0]),
+            ..MultiIndexVertexData::default()
+        });
 
-        let expected_combined_mesh = BlenderMesh {
-            vertex_position_indices: vec![2, 1, 0],
-            vertex_positions: concat_vecs!(v(5), v(6), v(7)),
-            vertex_normals: concat_vecs!(v(10), v(11), v(12)),
-            vertex_uvs: Some(concat_vecs!(v2(15), v2(16), v2(17))),
-            num_vertices_in_each_face: vec![3],
-            ..BlenderMesh::default()
-        };
+        let expected_combined_mesh = single(SingleIndexVertexData {
+            indices: vec![2, 1, 0],
+            positions: attrib3(concat_vecs!(v(5), v(6), v(7))),
+            normals: attrib3(concat_vecs!(v(10), v(11), v(12))),
+            uvs: Some(attrib2(concat_vecs!(v2(15), v2(16), v2(17)))),
+            vertices_in_each_face: vec![3],
+            index_width: IndexWidth::U16,
+            ..SingleIndexVertexData::default()
+        });
+
+        CombineIndicesTest {
+            mesh_to_combine,
+            expected_combined_mesh,
+            create_single_idx_config: None,
+        }
+        .test();
+    }
+
+    /// Morph target deltas are duplicated along with the positions and normals that they move
+    #[test]
+    fn combine_morph_targets() {
+        let mesh_to_combine = multi(MultiIndexVertexData {
+            positions: attrib3(concat_vecs!(v(0), v(1), v(2))),
+            position_indices: vec![0, 1, 2, 0, 2, 1],
+            normals: attrib3(concat_vecs!(v(3), v(4))),
+            normal_indices: vec![0, 0, 0, 1, 1, 0],
+            vertices_in_each_face: vec![3, 3],
+            morph_targets: vec![MorphTarget::new(
+                "Smile".to_string(),
+                0.5,
+                concat_vecs!(v(5), v(6), v(7)),
+                concat_vecs!(v(8), v(9)),
+            )],
+            ..MultiIndexVertexData::default()
+        });
+
+        let expected_combined_mesh = single(SingleIndexVertexData {
+            indices: vec![0, 1, 2, 3, 4, 1],
+            positions: attrib3(concat_vecs!(v(0), v(1), v(2), v(0), v(2))),
+            normals: attrib3(concat_vecs!(v(3), v(3), v(3), v(4), v(4))),
+            vertices_in_each_face: vec![3, 3],
+            index_width: IndexWidth::U16,
+            morph_targets: vec![MorphTarget::new(
+                "Smile".to_string(),
+                0.5,
+                concat_vecs!(v(5), v(6), v(7), v(5), v(7)),
+                concat_vecs!(v(8), v(8), v(8), v(9), v(9)),
+            )],
+            ..SingleIndexVertexData::default()
+        });
 
         CombineIndicesTest {
             mesh_to_combine,
@@ -424,22 +493,34 @@ mod tests {
     // modifier in Blender.
     #[test]
     fn combine_already_triangulated_mesh() {
-        let mesh_to_combine = BlenderMesh {
-            vertex_positions: concat_vecs!(v(5), v(6), v(7), v(8)),
-            vertex_normals: concat_vecs!(v(10), v(11), v(12), v(13), v(14), v(15), v(16), v(17)),
-            num_vertices_in_each_face: vec![3, 3, 3],
-            vertex_position_indices: concat_vecs!(vec![0, 1, 2], vec![0, 2, 3], vec![0, 2, 3]),
-            vertex_normal_indices: Some(concat_vecs!(vec![0, 1, 2], vec![0, 2, 3], vec![4, 5, 6])),
-            ..BlenderMesh::default()
-        };
+        let mesh_to_combine = multi(MultiIndexVertexData {
+            positions: attrib3(concat_vecs!(v(5), v(6), v(7), v(8))),
+            normals: attrib3(concat_vecs!(
+                v(10),
+                v(11),
+                v(12),
+                v(13),
+                v(14),
+                v(15),
+                v(16),
+                v(17)
+            )),
+            vertices_in_each_face: vec![3, 3, 3],
+            position_indices: concat_vecs!(vec![0, 1, 2], vec![0, 2, 3], vec![0, 2, 3]),
+            normal_indices: concat_vecs!(vec![0, 1, 2], vec![0, 2, 3], vec![4, 5, 6]),
+            face_materials: Some(vec![0, 1, 1]),
+            ..MultiIndexVertexData::default()
+        });
 
-        let expected_combined_mesh = BlenderMesh {
-            vertex_positions: concat_vecs!(v3_x3(5, 6, 7), v(8), v3_x3(5, 7, 8)),
-            vertex_position_indices: concat_vecs![vec![0, 1, 2], vec![0, 2, 3], vec![4, 5, 6]],
-            num_vertices_in_each_face: vec![3, 3, 3],
-            vertex_normals: concat_vecs!(v3_x3(10, 11, 12), v(13), v3_x3(14, 15, 16)),
-            ..BlenderMesh::default()
-        };
+        let expected_combined_mesh = single(SingleIndexVertexData {
+            positions: attrib3(concat_vecs!(v3_x3(5, 6, 7), v(8), v3_x3(5, 7, 8))),
+            indices: concat_vecs![vec![0, 1, 2], vec![0, 2, 3], vec![4, 5, 6]],
+            vertices_in_each_face: vec![3, 3, 3],
+            normals: attrib3(concat_vecs!(v3_x3(10, 11, 12), v(13), v3_x3(14, 15, 16))),
+            index_width: IndexWidth::U16,
+            face_materials: Some(vec![0, 1, 1]),
+            ..SingleIndexVertexData::default()
+        });
 
         CombineIndicesTest {
             mesh_to_combine,
@@ -465,24 +546,24 @@ mod tests {
     }
 
     fn mesh_to_combine_pos_norm_uv_indices() -> BlenderMesh {
-        BlenderMesh {
-            vertex_positions: concat_vecs!(v(0), v(1), v(2), v(3)),
-            vertex_normals: concat_vecs!(v(4), v(5), v(6)),
-            num_vertices_in_each_face: vec![4, 4, 4, 4],
-            vertex_position_indices: concat_vecs!(
+        multi(MultiIndexVertexData {
+            positions: attrib3(concat_vecs!(v(0), v(1), v(2), v(3))),
+            normals: attrib3(concat_vecs!(v(4), v(5), v(6))),
+            vertices_in_each_face: vec![4, 4, 4, 4],
+            position_indices: concat_vecs!(
                 vec![0, 1, 2, 3],
                 vec![0, 1, 2, 3],
                 vec![0, 1, 2, 3],
                 vec![0, 1, 2, 3]
             ),
-            vertex_normal_indices: Some(concat_vecs!(
+            normal_indices: concat_vecs!(
                 vec![0, 1, 0, 1],
                 vec![2, 2, 2, 2],
                 vec![2, 2, 2, 2],
                 vec![2, 2, 2, 2]
-            )),
-            vertex_uvs: Some(concat_vecs!(v2(7), v2(8), v2(9), v2(10))),
-            vertex_uv_indices: Some(concat_vecs!(
+            ),
+            uvs: Some(attrib2(concat_vecs!(v2(7), v2(8), v2(9), v2(10)))),
+            uv_indices: Some(concat_vecs!(
                 vec![0, 1, 0, 1],
                 vec![2, 2, 2, 2],
                 vec![3, 3, 3, 3],
@@ -490,14 +571,18 @@ mod tests {
             )),
             // We already tested vertex group indices / weights about so not bothering setting up
             // more test data
-            ..BlenderMesh::default()
-        }
+            ..MultiIndexVertexData::default()
+        })
     }
 
     fn expected_mesh_to_combine_pos_norm_uv_indices() -> BlenderMesh {
-        BlenderMesh {
-            vertex_positions: concat_vecs!(v3_x4(0, 1, 2, 3), v3_x4(0, 1, 2, 3), v3_x4(0, 1, 2, 3)),
-            vertex_position_indices: concat_vecs![
+        single(SingleIndexVertexData {
+            positions: attrib3(concat_vecs!(
+                v3_x4(0, 1, 2, 3),
+                v3_x4(0, 1, 2, 3),
+                v3_x4(0, 1, 2, 3)
+            )),
+            indices: concat_vecs![
                 // First Triangle
                 vec![0, 1, 2, 3,],
                 // Second Triangle
@@ -507,15 +592,20 @@ mod tests {
                 // Fourth Triangle
                 vec![8, 9, 10, 11]
             ],
-            num_vertices_in_each_face: vec![4, 4, 4, 4],
-            vertex_normals: concat_vecs!(v3_x4(4, 5, 4, 5), v3_x4(6, 6, 6, 6), v3_x4(6, 6, 6, 6)),
-            vertex_uvs: Some(concat_vecs!(
+            vertices_in_each_face: vec![4, 4, 4, 4],
+            normals: attrib3(concat_vecs!(
+                v3_x4(4, 5, 4, 5),
+                v3_x4(6, 6, 6, 6),
+                v3_x4(6, 6, 6, 6)
+            )),
+            uvs: Some(attrib2(concat_vecs!(
                 v2_x4(7, 8, 7, 8),
                 v2_x4(9, 9, 9, 9),
                 v2_x4(10, 10, 10, 10)
-            )),
-            ..BlenderMesh::default()
-        }
+            ))),
+            index_width: IndexWidth::U16,
+            ..SingleIndexVertexData::default()
+        })
     }
 
     /// Verify that when we re-use a vertex we add in the tangent of the second vertex that we're
@@ -526,59 +616,67 @@ mod tests {
     /// that the rendered models looked visually correct (meaning that our test values are also correct).
     #[test]
     fn calculate_per_vertex_tangents_encountered_duplicate_data() {
-        let mut mesh_to_combine = BlenderMesh {
-            vertex_positions: concat_vecs!(
+        let mesh_to_combine = multi(MultiIndexVertexData {
+            positions: attrib3(concat_vecs!(
                 v(0),
                 vec![1.0, 0.0, 0.0],
                 vec![1.0, 1.0, 0.0],
                 vec![0., 1., 0.]
-            ),
-            vertex_normals: concat_vecs!(v(4), v(5), v(6), v(7)),
-            num_vertices_in_each_face: vec![4, 4, 4, 4],
-            vertex_position_indices: concat_vecs!(
+            )),
+            normals: attrib3(concat_vecs!(v(4), v(5), v(6), v(7))),
+            vertices_in_each_face: vec![4, 4, 4, 4],
+            position_indices: concat_vecs!(
                 vec![0, 1, 2, 3],
                 vec![0, 1, 2, 3],
                 vec![0, 1, 2, 3],
                 vec![0, 1, 2, 3]
             ),
-            vertex_normal_indices: Some(concat_vecs!(
+            normal_indices: concat_vecs!(
                 vec![0, 1, 2, 3],
                 vec![0, 1, 2, 3],
                 vec![0, 1, 2, 3],
                 vec![0, 1, 2, 3]
-            )),
-            vertex_uvs: Some(concat_vecs!(v2(0), vec![0.5, 0.0], v2(1), vec![0., 1.])),
-            vertex_uv_indices: Some(concat_vecs!(
+            ),
+            uvs: Some(attrib2(concat_vecs!(
+                v2(0),
+                vec![0.5, 0.0],
+                v2(1),
+                vec![0., 1.]
+            ))),
+            uv_indices: Some(concat_vecs!(
                 vec![0, 1, 2, 3], // .
                 vec![0, 1, 2, 3], // .
                 vec![0, 1, 2, 3], // .
                 vec![0, 1, 2, 3]  // .
             )),
-            ..BlenderMesh::default()
-        };
-
-        let expected_combined_mesh = BlenderMesh {
-            vertex_positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+            ..MultiIndexVertexData::default()
+        });
 
-            vertex_position_indices: vec![0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3],
-            num_vertices_in_each_face: vec![4, 4, 4, 4],
-            vertex_normals: vec![4.0, 4.0, 4.0, 5.0, 5.0, 5.0, 6.0, 6.0, 6.0, 7.0, 7.0, 7.0],
-            vertex_uvs: Some(vec![0.0, 0.0, 0.5, 0.0, 1.0, 1.0, 0.0, 1.0]),
-            face_tangents: Some(vec![
-                2.0, 0.0, 0.0, 2.0, 0.0, 0.0, 2.0, 0.0, 0.0, 2.0, 0.0, 0.0,
+        let expected_combined_mesh = single(SingleIndexVertexData {
+            positions: attrib3(vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0,
+            ]),
+            indices: vec![0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3],
+            vertices_in_each_face: vec![4, 4, 4, 4],
+            normals: attrib3(vec![
+                4.0, 4.0, 4.0, 5.0, 5.0, 5.0, 6.0, 6.0, 6.0, 7.0, 7.0, 7.0,
             ]),
+            uvs: Some(attrib2(vec![0.0, 0.0, 0.5, 0.0, 1.0, 1.0, 0.0, 1.0])),
             // 4 duplicate vertices, each with [2., 0., 0.] as the tangent
             // When combined we get [8., 0., 0.]
-            per_vertex_tangents: Some(VertexAttribute::new(
-                vec![8.0, 0.0, 0.0, 8.0, 0.0, 0.0, 8.0, 0.0, 0.0, 8.0, 0.0, 0.0],
-                AttributeSize::Three,
-            )),
-            ..BlenderMesh::default()
-        };
+            tangents: Some(attrib3(vec![
+                8.0, 0.0, 0.0, 8.0, 0.0, 0.0, 8.0, 0.0, 0.0, 8.0, 0.0, 0.0,
+            ])),
+            index_width: IndexWidth::U16,
+            ..SingleIndexVertexData::default()
+        });
 
         let create_single_idx_config = Some(CreateSingleIndexConfig {
             bone_influences_per_vertex: None,
             calculate_vertex_tangents: true,
+            tangent_algorithm: TangentAlgorithm::FaceTangents,
+            index_width: None,
+            ..CreateSingleIndexConfig::default()
         });
 
         CombineIndicesTest {
@@ -589,47 +687,238 @@ mod tests {
         .test();
     }
 
+    /// MikkTSpace tangents are unit length with their handedness in `w`
+    #[test]
+    fn mikktspace_tangents() {
+        let mut mesh = multi(MultiIndexVertexData {
+            positions: attrib3(vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0,
+            ]),
+            position_indices: vec![0, 1, 2, 3],
+            normals: attrib3(vec![0.0, 0.0, 1.0]),
+            normal_indices: vec![0, 0, 0, 0],
+            uvs: Some(attrib2(vec![0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0])),
+            uv_indices: Some(vec![0, 1, 2, 3]),
+            vertices_in_each_face: vec![4],
+            ..MultiIndexVertexData::default()
+        });
+        let config = CreateSingleIndexConfig {
+            calculate_vertex_tangents: true,
+            tangent_algorithm: TangentAlgorithm::MikkTSpace,
+            ..CreateSingleIndexConfig::default()
+        };
+
+        mesh.combine_vertex_indices(&config).unwrap();
+
+        let tangents = mesh.single_index_vertex_data().unwrap().tangents().unwrap();
+        assert_eq!(tangents.size(), AttributeSize::Four);
+        for tangent in tangents.data().chunks(4) {
+            for (actual, expected) in tangent.iter().zip([1.0, 0.0, 0.0, 1.0].iter()) {
+                assert!((actual - expected).abs() < 1e-6);
+            }
+        }
+    }
+
+    /// Meshes with more vertices than a u16 can address get u32 indices by default
+    #[test]
+    fn large_mesh_is_promoted_to_u32_indices() {
+        let mut mesh = mesh_with_more_vertices_than_u16_can_address();
+
+        mesh.combine_vertex_indices(&CreateSingleIndexConfig::default())
+            .unwrap();
+
+        let single = mesh.single_index_vertex_data().unwrap();
+        assert_eq!(single.index_width(), IndexWidth::U32);
+        assert_eq!(
+            *single.indices().last().unwrap(),
+            LARGE_MESH_VERTEX_COUNT - 1
+        );
+        match single.index_buffer() {
+            IndexBuffer::U32(indices) => assert_eq!(&indices, single.indices()),
+            IndexBuffer::U16(_) => unreachable!(),
+        };
+    }
+
+    /// Forcing u16 indices on a mesh that doesn't fit in them is an error rather than silently
+    /// truncating the indices
+    #[test]
+    fn forced_u16_indices_that_do_not_fit() {
+        let mut mesh = mesh_with_more_vertices_than_u16_can_address();
+        let config = CreateSingleIndexConfig {
+            index_width: Some(IndexWidth::U16),
+            ..CreateSingleIndexConfig::default()
+        };
+
+        match mesh.combine_vertex_indices(&config) {
+            Err(CombineIndicesError::IndicesDoNotFitInU16 {
+                vertex_count,
+                max_vertex_count,
+            }) => {
+                assert_eq!(vertex_count, LARGE_MESH_VERTEX_COUNT as usize);
+                assert_eq!(max_vertex_count, 65_536);
+            }
+            _ => unreachable!(),
+        };
+
+        // The mesh is left untouched
+        assert!(mesh.multi_index_vertex_data().is_some());
+    }
+
+    /// Small meshes can still be forced to use u32 indices
+    #[test]
+    fn forced_u32_indices() {
+        let mut mesh = mesh_to_combine_pos_norm_uv_indices();
+        let config = CreateSingleIndexConfig {
+            index_width: Some(IndexWidth::U32),
+            ..CreateSingleIndexConfig::default()
+        };
+
+        mesh.combine_vertex_indices(&config).unwrap();
+
+        let single = mesh.single_index_vertex_data().unwrap();
+        assert_eq!(single.index_buffer().index_width(), IndexWidth::U32);
+    }
+
+    /// Combining duplicates vertices, so we count the vertices that we'll generate rather than
+    /// just the largest position index.
+    #[test]
+    fn combined_vertex_count() {
+        let mesh = mesh_to_combine_pos_norm_uv_indices();
+        assert_eq!(
+            mesh.multi_index_vertex_data()
+                .unwrap()
+                .combined_vertex_count(),
+            12
+        );
+    }
+
+    /// Indices can only be combined once
+    #[test]
+    fn already_single_indexed() {
+        let mut mesh = expected_mesh_to_combine_pos_norm_uv_indices();
+
+        match mesh.combine_vertex_indices(&CreateSingleIndexConfig::default()) {
+            Err(CombineIndicesError::AlreadySingleIndexed) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    /// Tangents can't be calculated without uvs
+    #[test]
+    fn tangents_without_uvs() {
+        let mut mesh = make_mesh_to_combine_without_uvs();
+        let config = CreateSingleIndexConfig {
+            calculate_vertex_tangents: true,
+            ..CreateSingleIndexConfig::default()
+        };
+
+        match mesh.combine_vertex_indices(&config) {
+            Err(CombineIndicesError::Tangents(TangentError::NoVertexUvs)) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    /// Bone influences that stop before the last position are an error rather than a panic
+    #[test]
+    fn missing_bone_influences() {
+        let mut mesh = make_mesh_to_combine_without_uvs();
+        match &mut mesh.vertex_data {
+            VertexData::Multi(multi) => {
+                let bone_influences = multi.bone_influences.as_mut().unwrap();
+                bone_influences.bone_indices.truncate(10);
+                bone_influences.bone_weights.truncate(10);
+            }
+            VertexData::Single(_) => unreachable!(),
+        };
+        let config = CreateSingleIndexConfig {
+            bone_influences_per_vertex: Some(3),
+            ..CreateSingleIndexConfig::default()
+        };
+
+        match mesh.combine_vertex_indices(&config) {
+            Err(CombineIndicesError::MissingBoneInfluences {
+                position_count,
+                influence_count,
+            }) => assert_eq!((position_count, influence_count), (4, 3)),
+            _ => unreachable!(),
+        };
+
+        // The mesh is left untouched
+        assert!(mesh.multi_index_vertex_data().is_some());
+    }
+
+    const LARGE_MESH_VERTEX_COUNT: u32 = 70_002;
+
+    fn mesh_with_more_vertices_than_u16_can_address() -> BlenderMesh {
+        let vertex_count = LARGE_MESH_VERTEX_COUNT as usize;
+
+        multi(MultiIndexVertexData {
+            positions: attrib3(vec![1.0; vertex_count * 3]),
+            position_indices: (0..LARGE_MESH_VERTEX_COUNT).collect(),
+            vertices_in_each_face: vec![3; vertex_count / 3],
+            normals: attrib3(v(0)),
+            normal_indices: vec![0; vertex_count],
+            ..MultiIndexVertexData::default()
+        })
+    }
+
     fn make_mesh_to_combine_without_uvs() -> BlenderMesh {
         let start_positions = concat_vecs!(v(0), v(1), v(2), v(3));
         let start_normals = concat_vecs!(v(4), v(5), v(6));
 
-        BlenderMesh {
-            vertex_positions: start_positions,
-            vertex_position_indices: vec![0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3],
-            num_vertices_in_each_face: vec![4, 4, 4],
-            vertex_normals: start_normals,
+        multi(MultiIndexVertexData {
+            positions: attrib3(start_positions),
+            position_indices: vec![0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3],
+            vertices_in_each_face: vec![4, 4, 4],
+            normals: attrib3(start_normals),
             // Our last 4 vertices already exist so our expected mesh will generate
             // position indices 4, 5, 6 and 7 and use those for the second to last 4 and
             // then last 4 indices
-            vertex_normal_indices: Some(vec![0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2]),
-            bone_influences_per_vertex: Some(vec![3, 2, 5, 1].into()),
-            vertex_group_indices: Some(vec![0, 1, 2, 0, 3, 4, 5, 6, 7, 8, 11]),
-            vertex_group_weights: Some(vec![
-                0.05, 0.8, 0.15, 0.5, 0.5, 0.1, 0.2, 0.2, 0.2, 0.3, 0.999,
-            ]),
-            ..BlenderMesh::default()
-        }
+            normal_indices: vec![0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+            bone_influences: Some(BoneInfluences {
+                bones_per_vertex: vec![3, 2, 5, 1].into(),
+                bone_indices: vec![0, 1, 2, 0, 3, 4, 5, 6, 7, 8, 11],
+                bone_weights: vec![0.05, 0.8, 0.15, 0.5, 0.5, 0.1, 0.2, 0.2, 0.2, 0.3, 0.999],
+            }),
+            ..MultiIndexVertexData::default()
+        })
     }
 
     fn make_expected_combined_mesh() -> BlenderMesh {
         let end_positions = concat_vecs!(v(0), v(1), v(2), v(3), v(0), v(1), v(2), v(3));
         let end_normals = concat_vecs!(v(4), v(5), v(4), v(5), v(6), v(6), v(6), v(6));
 
+        single(SingleIndexVertexData {
+            positions: attrib3(end_positions),
+            indices: vec![0, 1, 2, 3, 4, 5, 6, 7, 4, 5, 6, 7],
+            vertices_in_each_face: vec![4, 4, 4],
+            normals: attrib3(end_normals),
+            bones: Some(BoneAttributes {
+                // Config.bone_influences_per_vertex = 3
+                bones_per_vertex: 3,
+                bone_indices: vec![
+                    1, 2, 0, 0, 3, 0, 8, 5, 6, 11, 0, 0, 1, 2, 0, 0, 3, 0, 8, 5, 6, 11, 0, 0,
+                ],
+                bone_weights: vec![
+                    0.8, 0.15, 0.05, 0.5, 0.5, 0.0, 0.3, 0.2, 0.2, 0.999, 0.0, 0.0, 0.8, 0.15,
+                    0.05, 0.5, 0.5, 0.0, 0.3, 0.2, 0.2, 0.999, 0.0, 0.0,
+                ],
+            }),
+            index_width: IndexWidth::U16,
+            ..SingleIndexVertexData::default()
+        })
+    }
+
+    fn multi(vertex_data: MultiIndexVertexData) -> BlenderMesh {
         BlenderMesh {
-            vertex_positions: end_positions,
-            vertex_position_indices: vec![0, 1, 2, 3, 4, 5, 6, 7, 4, 5, 6, 7],
-            num_vertices_in_each_face: vec![4, 4, 4],
-            vertex_normals: end_normals,
-            bone_influences_per_vertex: Some(BoneInfluencesPerVertex::Uniform(3)),
-            // Config.bone_influences_per_vertex = 3
-            vertex_group_indices: Some(vec![
-                1, 2, 0, 0, 3, 0, 8, 5, 6, 11, 0, 0, 1, 2, 0, 0, 3, 0, 8, 5, 6, 11, 0, 0,
-            ]),
-            // Config.bone_influences_per_vertex = 3
-            vertex_group_weights: Some(vec![
-                0.8, 0.15, 0.05, 0.5, 0.5, 0.0, 0.3, 0.2, 0.2, 0.999, 0.0, 0.0, 0.8, 0.15, 0.05,
-                0.5, 0.5, 0.0, 0.3, 0.2, 0.2, 0.999, 0.0, 0.0,
-            ]),
+            vertex_data: VertexData::Multi(vertex_data),
+            ..BlenderMesh::default()
+        }
+    }
+
+    fn single(vertex_data: SingleIndexVertexData) -> BlenderMesh {
+        BlenderMesh {
+            vertex_data: VertexData::Single(vertex_data),
             ..BlenderMesh::default()
         }
     }
@@ -0,0 +1,242 @@
+//! Meshes with several materials are drawn one material at a time, using a different range of the
+//! same index buffer for each material.
+
+use crate::vertex_data::{SingleIndexVertexData, VertexDataError};
+use crate::BlenderMesh;
+
+/// A range of a mesh's index buffer where every face uses the same material.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DrawGroup {
+    /// The material slot that these faces use, as an index into [`BlenderMesh.material_slots`]
+    ///
+    /// [`BlenderMesh.material_slots`]: struct.BlenderMesh.html#method.material_slots
+    pub material_slot: u16,
+    /// The name of the material in that slot, if there is one.
+    ///
+    /// Look this up in [`BlenderMesh.materials`] to get the material's data.
+    ///
+    /// [`BlenderMesh.materials`]: struct.BlenderMesh.html#method.materials
+    pub material: Option<String>,
+    /// The first index in the index buffer
+    pub start: usize,
+    /// The number of indices in the group
+    pub count: usize,
+}
+
+/// An error while splitting a mesh into draw groups
+#[derive(Debug, Fail)]
+pub enum DrawGroupError {
+    #[fail(display = "Could not split the mesh into draw groups: {}", _0)]
+    VertexData(#[cause] VertexDataError),
+}
+
+impl From<VertexDataError> for DrawGroupError {
+    fn from(error: VertexDataError) -> Self {
+        DrawGroupError::VertexData(error)
+    }
+}
+
+impl BlenderMesh {
+    /// Reorder the mesh's faces so that all of the faces that use the same material are next to
+    /// each other, and return one draw group per material in material slot order.
+    ///
+    /// Faces that use the same material keep their order, so call this after
+    /// [`BlenderMesh.optimize`] (which also keeps faces grouped by material) or
+    /// [`BlenderMesh.simplify`].
+    ///
+    /// [`BlenderMesh.optimize`]: struct.BlenderMesh.html#method.optimize
+    /// [`BlenderMesh.simplify`]: struct.BlenderMesh.html#method.simplify
+    pub fn split_by_material(&mut self) -> Result<Vec<DrawGroup>, DrawGroupError> {
+        let single = self.vertex_data.single_index_mut_or_err()?;
+        single.sort_faces_by_material();
+
+        Ok(single.draw_groups(&self.material_slots))
+    }
+
+    /// One draw group for every run of faces in the index buffer that use the same material.
+    ///
+    /// Unless [`BlenderMesh.split_by_material`] was called there might be more than one group
+    /// per material.
+    ///
+    /// [`BlenderMesh.split_by_material`]: struct.BlenderMesh.html#method.split_by_material
+    pub fn draw_groups(&self) -> Result<Vec<DrawGroup>, DrawGroupError> {
+        let single = self.single_index_vertex_data_or_err()?;
+
+        Ok(single.draw_groups(&self.material_slots))
+    }
+}
+
+impl SingleIndexVertexData {
+    /// Every face, sorted by material slot. Faces in the same slot keep their order.
+    pub(crate) fn faces_by_material(&self) -> Vec<usize> {
+        let mut faces: Vec<usize> = (0..self.vertices_in_each_face.len()).collect();
+
+        if let Some(face_materials) = self.face_materials.as_ref() {
+            faces.sort_by_key(|face| face_materials[*face]);
+        }
+
+        faces
+    }
+
+    pub(crate) fn sort_faces_by_material(&mut self) {
+        let faces = self.faces_by_material();
+
+        let mut face_starts = Vec::with_capacity(faces.len());
+        let mut start = 0;
+        for vertex_count in self.vertices_in_each_face.iter() {
+            face_starts.push(start);
+            start += *vertex_count as usize;
+        }
+
+        self.indices = faces
+            .iter()
+            .flat_map(|face| {
+                let start = face_starts[*face];
+                let end = start + self.vertices_in_each_face[*face] as usize;
+                self.indices[start..end].to_vec()
+            })
+            .collect();
+        self.vertices_in_each_face = faces
+            .iter()
+            .map(|face| self.vertices_in_each_face[*face])
+            .collect();
+        if let Some(face_materials) = self.face_materials.as_ref() {
+            self.face_materials = Some(faces.iter().map(|face| face_materials[*face]).collect());
+        }
+    }
+
+    fn draw_groups(&self, material_slots: &[Option<String>]) -> Vec<DrawGroup> {
+        draw_groups(
+            &self.vertices_in_each_face,
+            self.face_materials.as_deref(),
+            material_slots,
+        )
+    }
+}
+
+/// One draw group for every run of faces that use the same material slot
+pub(crate) fn draw_groups(
+    vertices_in_each_face: &[u8],
+    face_materials: Option<&[u16]>,
+    material_slots: &[Option<String>],
+) -> Vec<DrawGroup> {
+    let mut groups: Vec<DrawGroup> = vec![];
+    let mut start = 0;
+
+    for (face, vertex_count) in vertices_in_each_face.iter().enumerate() {
+        let material_slot = face_materials.map_or(0, |materials| materials[face]);
+        let count = *vertex_count as usize;
+
+        match groups.last_mut() {
+            Some(group) if group.material_slot == material_slot => group.count += count,
+            _ => groups.push(DrawGroup {
+                material_slot,
+                material: material_slots
+                    .get(material_slot as usize)
+                    .cloned()
+                    .flatten(),
+                start,
+                count,
+            }),
+        };
+
+        start += count;
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::vertex_data::VertexData;
+
+    /// Four faces that alternate between two materials
+    fn alternating_materials() -> BlenderMesh {
+        BlenderMesh {
+            material_slots: vec![Some("Red".to_string()), Some("Blue".to_string())],
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                vertices_in_each_face: vec![3, 3, 4, 3],
+                indices: vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12],
+                positions: attrib3(vec![0.0; 39]),
+                normals: attrib3(vec![0.0; 39]),
+                face_materials: Some(vec![1, 0, 1, 0]),
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        }
+    }
+
+    /// Runs of faces with the same material are grouped together
+    #[test]
+    fn draw_groups_follow_the_index_buffer() {
+        let mesh = alternating_materials();
+
+        let groups = mesh.draw_groups().unwrap();
+
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[1].material, Some("Red".to_string()));
+        assert_eq!((groups[2].start, groups[2].count), (6, 4));
+    }
+
+    /// Splitting by material leaves one draw group per material, and faces keep their order
+    /// within each material
+    #[test]
+    fn split_by_material() {
+        let mut mesh = alternating_materials();
+
+        let groups = mesh.split_by_material().unwrap();
+
+        assert_eq!(
+            groups,
+            vec![
+                DrawGroup {
+                    material_slot: 0,
+                    material: Some("Red".to_string()),
+                    start: 0,
+                    count: 6,
+                },
+                DrawGroup {
+                    material_slot: 1,
+                    material: Some("Blue".to_string()),
+                    start: 6,
+                    count: 7,
+                },
+            ]
+        );
+
+        let single = mesh.single_index_vertex_data().unwrap();
+        assert_eq!(
+            single.indices(),
+            &vec![3, 4, 5, 10, 11, 12, 0, 1, 2, 6, 7, 8, 9]
+        );
+        assert_eq!(single.vertices_in_each_face(), &vec![3, 3, 3, 4]);
+        assert_eq!(single.face_materials(), Some(&vec![0, 0, 1, 1]));
+    }
+
+    /// Meshes without per face materials are drawn with their first material slot
+    #[test]
+    fn one_group_without_face_materials() {
+        let mut mesh = alternating_materials();
+        if let VertexData::Single(single) = &mut mesh.vertex_data {
+            single.face_materials = None;
+        }
+
+        let groups = mesh.split_by_material().unwrap();
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].material, Some("Red".to_string()));
+        assert_eq!(groups[0].count, 13);
+    }
+
+    #[test]
+    fn multi_indexed_meshes_can_not_be_split() {
+        let mut mesh = BlenderMesh::default();
+
+        match mesh.split_by_material() {
+            Err(DrawGroupError::VertexData(VertexDataError::MultipleIndices)) => {}
+            _ => unreachable!(),
+        };
+    }
+}
@@ -0,0 +1,360 @@
+//! `blender-mesh-to-json.py` writes every mesh as one flat JSON object of vertex data, and
+//! `BlenderMesh` used to be (de)serialized the same way.
+//!
+//! We now store vertex data in a `VertexData`, so when deserializing from a human readable format
+//! such as JSON we accept both the flat format and the current one.
+//!
+//! Non human readable formats (such as bincode) aren't self describing, so they can only hold
+//! the current format.
+
+use crate::bone::BoneInfluencesPerVertex;
+use crate::bounding_box::BoundingBox;
+use crate::material::PrincipledBSDF;
+use crate::morph_target::MorphTarget;
+use crate::vertex_data::{
+    AttributeSize, BoneAttributes, BoneInfluences, MultiIndexVertexData, SingleIndexVertexData,
+    VertexAttribute, VertexData,
+};
+use crate::{BlenderMesh, IndexWidth};
+use serde::de::{Deserialize, Deserializer, Error, IgnoredAny};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The current format, exactly as `BlenderMesh` is serialized
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CurrentFormat {
+    armature_name: Option<String>,
+    bounding_box: BoundingBox,
+    materials: HashMap<String, PrincipledBSDF>,
+    #[serde(default)]
+    material_slots: Vec<Option<String>>,
+    vertex_data: VertexData,
+}
+
+/// The format that `blender-mesh-to-json.py` exports
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FlatFormat {
+    vertex_positions: Vec<f32>,
+    vertex_position_indices: Vec<u32>,
+    num_vertices_in_each_face: Vec<u8>,
+    vertex_normals: Vec<f32>,
+    vertex_normal_indices: Option<Vec<u32>>,
+    vertex_uvs: Option<Vec<f32>>,
+    vertex_uv_indices: Option<Vec<u32>>,
+    smooth_faces: Option<Vec<bool>>,
+    face_materials: Option<Vec<u16>>,
+    armature_name: Option<String>,
+    vertex_group_indices: Option<Vec<u8>>,
+    vertex_group_weights: Option<Vec<f32>>,
+    bone_influences_per_vertex: Option<BoneInfluencesPerVertex>,
+    bounding_box: BoundingBox,
+    materials: HashMap<String, PrincipledBSDF>,
+    #[serde(default)]
+    material_slots: Vec<Option<String>>,
+    #[serde(default)]
+    morph_targets: Vec<FlatMorphTarget>,
+    // Older versions of `BlenderMesh` also serialized these fields
+    per_vertex_tangents: Option<VertexAttribute>,
+    index_width: Option<IndexWidth>,
+    #[serde(default, rename = "face_tangents")]
+    _face_tangents: IgnoredAny,
+    #[serde(default, rename = "vertex_data")]
+    _vertex_data: IgnoredAny,
+}
+
+/// A shape key as `blender-mesh-to-json.py` exports it, with its deltas indexed like the
+/// positions and normals
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FlatMorphTarget {
+    name: String,
+    default_weight: f32,
+    position_deltas: Vec<f32>,
+    normal_deltas: Vec<f32>,
+}
+
+impl From<FlatMorphTarget> for MorphTarget {
+    fn from(flat: FlatMorphTarget) -> Self {
+        MorphTarget::new(
+            flat.name,
+            flat.default_weight,
+            flat.position_deltas,
+            flat.normal_deltas,
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for BlenderMesh {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if !deserializer.is_human_readable() {
+            return CurrentFormat::deserialize(deserializer).map(BlenderMesh::from);
+        }
+
+        let value = Value::deserialize(deserializer)?;
+
+        if value.get("vertex_positions").is_some() {
+            let flat: FlatFormat = serde_json::from_value(value).map_err(D::Error::custom)?;
+            flat.into_blender_mesh().map_err(D::Error::custom)
+        } else {
+            let current: CurrentFormat = serde_json::from_value(value).map_err(D::Error::custom)?;
+            Ok(BlenderMesh::from(current))
+        }
+    }
+}
+
+impl From<CurrentFormat> for BlenderMesh {
+    fn from(current: CurrentFormat) -> Self {
+        BlenderMesh {
+            armature_name: current.armature_name,
+            bounding_box: current.bounding_box,
+            materials: current.materials,
+            material_slots: current.material_slots,
+            vertex_data: current.vertex_data,
+        }
+    }
+}
+
+impl FlatFormat {
+    /// Meshes with normal indices are multi indexed. Meshes without them had their indices
+    /// combined before they were serialized.
+    fn into_blender_mesh(self) -> Result<BlenderMesh, &'static str> {
+        let positions = VertexAttribute::new(self.vertex_positions, AttributeSize::Three);
+        let normals = VertexAttribute::new(self.vertex_normals, AttributeSize::Three);
+        let uvs = self
+            .vertex_uvs
+            .map(|uvs| VertexAttribute::new(uvs, AttributeSize::Two));
+
+        let bone_influences = match (
+            self.bone_influences_per_vertex,
+            self.vertex_group_indices,
+            self.vertex_group_weights,
+        ) {
+            (Some(bones_per_vertex), Some(bone_indices), Some(bone_weights)) => {
+                Some(BoneInfluences {
+                    bones_per_vertex,
+                    bone_indices,
+                    bone_weights,
+                })
+            }
+            _ => None,
+        };
+
+        let morph_targets = self
+            .morph_targets
+            .into_iter()
+            .map(MorphTarget::from)
+            .collect();
+
+        let vertex_data = match self.vertex_normal_indices {
+            Some(normal_indices) => VertexData::Multi(MultiIndexVertexData {
+                vertices_in_each_face: self.num_vertices_in_each_face,
+                bone_influences,
+                positions,
+                position_indices: self.vertex_position_indices,
+                normals,
+                normal_indices,
+                uvs,
+                uv_indices: self.vertex_uv_indices,
+                smooth_faces: self.smooth_faces,
+                face_materials: self.face_materials,
+                morph_targets,
+            }),
+            None => {
+                if self.vertex_uv_indices.is_some() {
+                    return Err("vertex_uv_indices requires vertex_normal_indices");
+                }
+
+                let bones = match bone_influences {
+                    Some(BoneInfluences {
+                        bones_per_vertex: BoneInfluencesPerVertex::Uniform(bones_per_vertex),
+                        bone_indices,
+                        bone_weights,
+                    }) => Some(BoneAttributes {
+                        bones_per_vertex,
+                        bone_indices,
+                        bone_weights,
+                    }),
+                    Some(_) => {
+                        return Err("Meshes without vertex_normal_indices need a uniform number of bone influences per vertex")
+                    }
+                    None => None,
+                };
+
+                let vertex_count = self
+                    .vertex_position_indices
+                    .iter()
+                    .max()
+                    .map_or(0, |largest| *largest as usize + 1);
+
+                VertexData::Single(SingleIndexVertexData {
+                    vertices_in_each_face: self.num_vertices_in_each_face,
+                    indices: self.vertex_position_indices,
+                    index_width: self
+                        .index_width
+                        .unwrap_or_else(|| IndexWidth::smallest_for_vertex_count(vertex_count)),
+                    positions,
+                    normals,
+                    uvs,
+                    tangents: self.per_vertex_tangents,
+                    bones,
+                    face_materials: self.face_materials,
+                    morph_targets,
+                })
+            }
+        };
+
+        Ok(BlenderMesh {
+            armature_name: self.armature_name,
+            bounding_box: self.bounding_box,
+            materials: self.materials,
+            material_slots: self.material_slots,
+            vertex_data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn flat_quad() -> Value {
+        json!({
+            "vertex_positions": [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+            "vertex_position_indices": [0, 1, 2, 3],
+            "num_vertices_in_each_face": [4],
+            "smooth_faces": [true],
+            "face_materials": [1],
+            "vertex_normals": [0.0, 0.0, 1.0],
+            "vertex_normal_indices": [0, 0, 0, 0],
+            "vertex_uvs": [0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0],
+            "vertex_uv_indices": [0, 1, 2, 3],
+            "armature_name": "Armature",
+            "vertex_group_indices": [0, 0, 1, 1, 0],
+            "vertex_group_weights": [1.0, 0.5, 0.5, 1.0, 1.0],
+            "bone_influences_per_vertex": {"NonUniform": [1, 2, 1, 1]},
+            "bounding_box": {
+                "min_corner": [0.0, 0.0, 0.0],
+                "max_corner": [1.0, 1.0, 0.0]
+            },
+            "materials": {},
+            "material_slots": [null, "Material"]
+        })
+    }
+
+    /// The JSON that blender-mesh-to-json.py exports becomes multi indexed vertex data
+    #[test]
+    fn flat_json_is_multi_indexed() {
+        let mesh: BlenderMesh = serde_json::from_value(flat_quad()).unwrap();
+
+        let multi = mesh.multi_index_vertex_data().unwrap();
+        assert!(mesh.single_index_vertex_data().is_none());
+
+        assert_eq!(mesh.armature_name, Some("Armature".to_string()));
+        assert_eq!(multi.vertices_in_each_face(), &vec![4]);
+        assert_eq!(multi.position_indices(), &vec![0, 1, 2, 3]);
+        assert_eq!(multi.normals().data(), &vec![0.0, 0.0, 1.0]);
+        assert_eq!(multi.normal_indices(), &vec![0, 0, 0, 0]);
+        assert_eq!(multi.uvs().unwrap().size(), AttributeSize::Two);
+        assert_eq!(multi.uv_indices(), Some(&vec![0, 1, 2, 3]));
+        assert_eq!(multi.smooth_faces(), Some(&vec![true]));
+        assert_eq!(multi.face_materials(), Some(&vec![1]));
+        assert_eq!(
+            mesh.material_slots(),
+            &vec![None, Some("Material".to_string())]
+        );
+
+        let bones = multi.bone_influences().unwrap();
+        assert_eq!(
+            bones.bones_per_vertex(),
+            &BoneInfluencesPerVertex::NonUniform(vec![1, 2, 1, 1])
+        );
+        assert_eq!(bones.bone_indices(), &vec![0, 0, 1, 1, 0]);
+    }
+
+    /// Shape keys are exported with flat delta vectors
+    #[test]
+    fn flat_json_morph_targets() {
+        let mut flat = flat_quad();
+        flat["morph_targets"] = json!([{
+            "name": "Smile",
+            "default_weight": 0.25,
+            "position_deltas": vec![0.0; 12],
+            "normal_deltas": [0.0, 1.0, 0.0]
+        }]);
+
+        let mesh: BlenderMesh = serde_json::from_value(flat).unwrap();
+
+        let target = &mesh.morph_targets()[0];
+        assert_eq!(target.name(), "Smile");
+        assert_eq!(target.default_weight(), 0.25);
+        assert_eq!(target.position_deltas().vertex_count(), 4);
+        assert_eq!(target.normal_deltas().data(), &vec![0.0, 1.0, 0.0]);
+    }
+
+    /// Older versions of BlenderMesh serialized combined meshes in the flat format, with no
+    /// normal indices.
+    #[test]
+    fn flat_json_without_normal_indices_is_single_indexed() {
+        let mut flat = flat_quad();
+        flat["vertex_normals"] =
+            json!([0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+        flat["vertex_normal_indices"] = Value::Null;
+        flat["vertex_uv_indices"] = Value::Null;
+        flat["vertex_group_indices"] = json!([0, 0, 0, 1, 1, 0, 0, 0]);
+        flat["vertex_group_weights"] = json!([1.0, 0.0, 0.5, 0.5, 1.0, 0.0, 1.0, 0.0]);
+        flat["bone_influences_per_vertex"] = json!({"Uniform": 2});
+        flat["face_tangents"] = Value::Null;
+        flat["per_vertex_tangents"] = Value::Null;
+        flat["vertex_data"] = json!({"Multi": {}});
+
+        let mesh: BlenderMesh = serde_json::from_value(flat).unwrap();
+
+        let single = mesh.single_index_vertex_data().unwrap();
+        assert!(mesh.multi_index_vertex_data().is_none());
+
+        assert_eq!(single.indices(), &vec![0, 1, 2, 3]);
+        assert_eq!(single.index_width(), IndexWidth::U16);
+        assert_eq!(single.normals().vertex_count(), 4);
+        assert_eq!(single.bones().unwrap().bones_per_vertex(), 2);
+        assert_eq!(single.face_materials(), Some(&vec![1]));
+    }
+
+    /// Single indexed meshes can't have a different number of bones for each vertex
+    #[test]
+    fn flat_json_without_normal_indices_needs_uniform_bones() {
+        let mut flat = flat_quad();
+        flat["vertex_normal_indices"] = Value::Null;
+        flat["vertex_uv_indices"] = Value::Null;
+
+        assert!(serde_json::from_value::<BlenderMesh>(flat).is_err());
+    }
+
+    #[test]
+    fn unknown_flat_fields_are_rejected() {
+        let mut flat = flat_quad();
+        flat["not_a_field"] = json!(5);
+
+        assert!(serde_json::from_value::<BlenderMesh>(flat).is_err());
+    }
+
+    /// The current format survives a round trip through JSON
+    #[test]
+    fn current_format_json_round_trip() {
+        let mut mesh: BlenderMesh = serde_json::from_value(flat_quad()).unwrap();
+
+        let json = serde_json::to_string(&mesh).unwrap();
+        assert_eq!(serde_json::from_str::<BlenderMesh>(&json).unwrap(), mesh);
+
+        mesh.combine_vertex_indices(&Default::default()).unwrap();
+
+        let json = serde_json::to_value(&mesh).unwrap();
+        assert!(json.get("vertex_positions").is_none());
+        assert_eq!(serde_json::from_value::<BlenderMesh>(json).unwrap(), mesh);
+    }
+}
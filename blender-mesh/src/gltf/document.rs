@@ -0,0 +1,139 @@
+//! The subset of the glTF 2.0 JSON schema that we write out.
+//!
+//! @see https://github.com/KhronosGroup/glTF/tree/master/specification/2.0#properties-reference
+
+use std::collections::BTreeMap;
+
+/// https://github.com/KhronosGroup/glTF/tree/master/specification/2.0#accessor-element-size
+pub(super) const COMPONENT_TYPE_UNSIGNED_SHORT: u32 = 5123;
+pub(super) const COMPONENT_TYPE_UNSIGNED_INT: u32 = 5125;
+pub(super) const COMPONENT_TYPE_FLOAT: u32 = 5126;
+
+/// https://github.com/KhronosGroup/glTF/tree/master/specification/2.0#buffers-and-buffer-views
+pub(super) const TARGET_ARRAY_BUFFER: u32 = 34962;
+pub(super) const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// https://github.com/KhronosGroup/glTF/tree/master/specification/2.0#primitivemode
+pub(super) const MODE_TRIANGLES: u32 = 4;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Document {
+    pub asset: Asset,
+    pub scene: usize,
+    pub scenes: Vec<Scene>,
+    pub nodes: Vec<Node>,
+    pub meshes: Vec<Mesh>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub materials: Vec<Material>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub textures: Vec<Texture>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<Image>,
+    pub accessors: Vec<Accessor>,
+    pub buffer_views: Vec<BufferView>,
+    pub buffers: Vec<Buffer>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(super) struct Asset {
+    pub version: String,
+    pub generator: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(super) struct Scene {
+    pub nodes: Vec<usize>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(super) struct Node {
+    pub name: String,
+    pub mesh: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(super) struct Mesh {
+    pub name: String,
+    pub primitives: Vec<Primitive>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(super) struct Primitive {
+    /// Attribute semantic (POSITION, NORMAL, TEXCOORD_0..) to accessor index
+    pub attributes: BTreeMap<&'static str, usize>,
+    pub indices: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub material: Option<usize>,
+    pub mode: u32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Material {
+    pub name: String,
+    pub pbr_metallic_roughness: PbrMetallicRoughness,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normal_texture: Option<TextureInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extras: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct PbrMetallicRoughness {
+    pub base_color_factor: [f32; 4],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_color_texture: Option<TextureInfo>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metallic_roughness_texture: Option<TextureInfo>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(super) struct TextureInfo {
+    pub index: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(super) struct Texture {
+    pub source: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(super) struct Image {
+    pub uri: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Accessor {
+    pub buffer_view: usize,
+    pub component_type: u32,
+    pub count: usize,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct BufferView {
+    pub buffer: usize,
+    pub byte_offset: usize,
+    pub byte_length: usize,
+    pub target: u32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Buffer {
+    pub byte_length: usize,
+    /// Omitted when the buffer is stored in the binary chunk of a `.glb`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+}
@@ -0,0 +1,42 @@
+//! The binary glTF container.
+//!
+//! A 12 byte header followed by a JSON chunk and a BIN chunk, each of which is 4 byte aligned.
+//!
+//! @see https://github.com/KhronosGroup/glTF/tree/master/specification/2.0#glb-file-format-specification
+
+use super::{padded_to_four_bytes, Gltf};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const CHUNK_TYPE_JSON: &[u8; 4] = b"JSON";
+const CHUNK_TYPE_BIN: &[u8; 4] = b"BIN\0";
+
+impl Gltf {
+    /// A self contained `.glb` file with the buffer embedded in its binary chunk
+    pub fn to_glb(&self) -> Vec<u8> {
+        let mut json = serde_json::to_vec(&self.document).unwrap();
+        // The JSON chunk must be padded with spaces
+        json.resize(padded_to_four_bytes(json.len()), b' ');
+
+        let mut bin = self.bin.clone();
+        bin.resize(padded_to_four_bytes(bin.len()), 0);
+
+        let total_length = 12 + 8 + json.len() + 8 + bin.len();
+
+        let mut glb = Vec::with_capacity(total_length);
+
+        glb.extend_from_slice(GLB_MAGIC);
+        glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+        glb.extend_from_slice(&(total_length as u32).to_le_bytes());
+
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(CHUNK_TYPE_JSON);
+        glb.extend_from_slice(&json);
+
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(CHUNK_TYPE_BIN);
+        glb.extend_from_slice(&bin);
+
+        glb
+    }
+}
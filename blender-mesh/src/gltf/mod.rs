@@ -0,0 +1,1000 @@
+//! Write a processed `BlenderMesh` out as glTF 2.0, either as one self contained `.glb` file or
+//! as a `.gltf` JSON file that sits alongside a `.bin` buffer.
+//!
+//! glTF expects one index buffer made up of triangles, so you'll want to call
+//! `combine_vertex_indices`, `triangulate` and `y_up` before exporting.
+//!
+//! @see https://github.com/KhronosGroup/glTF/tree/master/specification/2.0
+
+use self::document::*;
+use crate::draw_group::draw_groups;
+use crate::material::PrincipledBSDF;
+use crate::vertex_data::{ensure_triangulated, SingleIndexVertexData, VertexDataError};
+use crate::{AttributeSize, BlenderMesh, Channel, IndexBuffer, MaterialInput};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+mod document;
+mod glb;
+
+/// A mesh that has been converted into a glTF document and its binary buffer.
+///
+/// Use [`Gltf.to_glb`] for a single embedded file or [`Gltf.to_gltf_json`] when you want to
+/// store the buffer in a separate `.bin` file.
+///
+/// [`Gltf.to_glb`]: struct.Gltf.html#method.to_glb
+/// [`Gltf.to_gltf_json`]: struct.Gltf.html#method.to_gltf_json
+#[derive(Debug)]
+pub struct Gltf {
+    document: Document,
+    bin: Vec<u8>,
+}
+
+/// An error while converting a `BlenderMesh` into glTF
+#[derive(Debug, Fail)]
+pub enum GltfError {
+    #[fail(display = "Could not export the mesh to glTF: {}", _0)]
+    VertexData(#[cause] VertexDataError),
+}
+
+impl From<VertexDataError> for GltfError {
+    fn from(error: VertexDataError) -> Self {
+        GltfError::VertexData(error)
+    }
+}
+
+impl BlenderMesh {
+    /// Convert this mesh into a glTF 2.0 document with one node, one mesh and a PBR metallic
+    /// roughness material for each of the mesh's `PrincipledBSDF` materials.
+    ///
+    /// Blender's uv origin is the bottom left of the texture while glTF's is the top left, so
+    /// every exported `TEXCOORD_0` has its `v` flipped.
+    ///
+    /// MikkTSpace tangents (see [`TangentAlgorithm::MikkTSpace`]) are exported as `TANGENT`.
+    /// Their bitangents still point towards the top of the texture, so they keep their handedness.
+    ///
+    /// [`TangentAlgorithm::MikkTSpace`]: enum.TangentAlgorithm.html#variant.MikkTSpace
+    ///
+    /// Materials are exported in alphabetical order by name. Every material slot that the faces
+    /// use gets its own primitive, and all of the primitives share the same vertex attributes.
+    pub fn to_gltf(&self, mesh_name: &str) -> Result<Gltf, GltfError> {
+        let single = self.single_index_vertex_data_or_err()?;
+        ensure_triangulated(single.vertices_in_each_face())?;
+
+        let mut builder = GltfBuilder::default();
+
+        let mut attributes = BTreeMap::new();
+
+        let positions = builder.push_f32_accessor(&positions_for_bounds(single), "VEC3", 3, true);
+        attributes.insert("POSITION", positions);
+
+        let normals = builder.push_f32_accessor(single.normals().data(), "VEC3", 3, false);
+        attributes.insert("NORMAL", normals);
+
+        if let Some(uvs) = single.uvs() {
+            let flipped: Vec<f32> = uvs
+                .data()
+                .chunks(2)
+                .flat_map(|uv| vec![uv[0], 1.0 - uv[1]])
+                .collect();
+            let uvs = builder.push_f32_accessor(&flipped, "VEC2", 2, false);
+            attributes.insert("TEXCOORD_0", uvs);
+        }
+
+        // glTF tangents need a handedness, which only our MikkTSpace tangents have
+        if let Some(tangents) = single
+            .tangents()
+            .filter(|tangents| tangents.size() == AttributeSize::Four)
+        {
+            let tangents = builder.push_f32_accessor(tangents.data(), "VEC4", 4, false);
+            attributes.insert("TANGENT", tangents);
+        }
+
+        let mut material_names: Vec<&String> = self.materials.keys().collect();
+        material_names.sort();
+        for name in material_names.iter() {
+            let material = builder.material(name, &self.materials[*name]);
+            builder.document.materials.push(material);
+        }
+
+        // Sort the triangles by material so that each primitive uses one range of indices
+        let triangles = single.faces_by_material();
+        let indices: Vec<u32> = triangles
+            .iter()
+            .flat_map(|triangle| single.indices()[triangle * 3..triangle * 3 + 3].to_vec())
+            .collect();
+        let face_materials: Option<Vec<u16>> = single.face_materials().map(|face_materials| {
+            triangles
+                .iter()
+                .map(|triangle| face_materials[*triangle])
+                .collect()
+        });
+        let groups = draw_groups(
+            single.vertices_in_each_face(),
+            face_materials.as_deref(),
+            &self.material_slots,
+        );
+
+        let mut primitives = vec![];
+        for group in groups {
+            let indices = &indices[group.start..group.start + group.count];
+            let indices = builder.push_indices(&IndexBuffer::new(indices, single.index_width()));
+
+            let material = match group.material {
+                Some(name) => material_names.iter().position(|other| **other == name),
+                // Meshes that were exported before we knew their material slots
+                None if self.material_slots.is_empty() && !material_names.is_empty() => Some(0),
+                None => None,
+            };
+
+            primitives.push(Primitive {
+                attributes: attributes.clone(),
+                indices,
+                material,
+                mode: MODE_TRIANGLES,
+            });
+        }
+
+        builder.document.meshes.push(Mesh {
+            name: mesh_name.to_string(),
+            primitives,
+        });
+        builder.document.nodes.push(Node {
+            name: mesh_name.to_string(),
+            mesh: 0,
+        });
+
+        Ok(builder.build())
+    }
+}
+
+impl Gltf {
+    /// The binary buffer that all of the document's buffer views point into.
+    pub fn bin(&self) -> &[u8] {
+        &self.bin
+    }
+
+    /// The glTF JSON for a split `.gltf` + `.bin` export, where `bin_uri` is the path to the
+    /// `.bin` file relative to the `.gltf` file. The uri is used as is, so percent-encode it
+    /// first if it has spaces or other characters that aren't allowed in a uri.
+    pub fn to_gltf_json(&self, bin_uri: &str) -> String {
+        let mut document = self.document.clone();
+        document.buffers[0].uri = Some(bin_uri.to_string());
+
+        serde_json::to_string(&document).unwrap()
+    }
+
+    /// Write `{name}.gltf` and `{name}.bin` next to each other.
+    ///
+    /// The `.bin` file is named after the `.gltf` file, so `gltf_path` of `/tmp/cube.gltf` writes
+    /// `/tmp/cube.gltf` and `/tmp/cube.bin`.
+    ///
+    /// Paths that don't end in a UTF-8 file name are an `io::ErrorKind::InvalidInput` error.
+    pub fn write_gltf<P: AsRef<Path>>(&self, gltf_path: P) -> Result<(), io::Error> {
+        let gltf_path = gltf_path.as_ref();
+        let bin_path = gltf_path.with_extension("bin");
+        let bin_name = bin_path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} does not end in a UTF-8 file name", gltf_path.display()),
+                )
+            })?;
+
+        fs::write(gltf_path, self.to_gltf_json(&percent_encode(bin_name)))?;
+        fs::write(&bin_path, &self.bin)
+    }
+
+    /// Write a self contained `.glb` file
+    pub fn write_glb<P: AsRef<Path>>(&self, glb_path: P) -> Result<(), io::Error> {
+        fs::write(glb_path, self.to_glb())
+    }
+}
+
+/// Combining indices can leave unused vertices behind with placeholder positions. A `POSITION`
+/// accessor's `min` and `max` have to cover every one of its positions, so we move the unused
+/// vertices onto a used one to keep the bounds tight.
+fn positions_for_bounds(single: &SingleIndexVertexData) -> Vec<f32> {
+    let positions = single.positions().data();
+    let indexed = single.indexed_vertices();
+
+    let unused_position = match indexed.iter().position(|indexed| *indexed) {
+        Some(vertex) => positions[vertex * 3..vertex * 3 + 3].to_vec(),
+        None => vec![0.0; 3],
+    };
+
+    positions
+        .chunks(3)
+        .zip(indexed)
+        .flat_map(|(position, indexed)| {
+            if indexed {
+                position.to_vec()
+            } else {
+                unused_position.clone()
+            }
+        })
+        .collect()
+}
+
+/// Percent-encode every byte of a file name that isn't an unreserved uri character (RFC 3986)
+fn percent_encode(file_name: &str) -> String {
+    let mut encoded = String::with_capacity(file_name.len());
+
+    for byte in file_name.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+/// glTF requires buffer views and GLB chunks to start on a 4 byte boundary
+fn padded_to_four_bytes(len: usize) -> usize {
+    len + (4 - len % 4) % 4
+}
+
+#[derive(Debug)]
+struct GltfBuilder {
+    document: Document,
+    bin: Vec<u8>,
+    /// Image uri to texture index, so that textures are shared across materials
+    textures: HashMap<String, usize>,
+}
+
+impl Default for GltfBuilder {
+    fn default() -> Self {
+        GltfBuilder {
+            document: Document {
+                asset: Asset {
+                    version: "2.0".to_string(),
+                    generator: format!("blender-mesh {}", env!("CARGO_PKG_VERSION")),
+                },
+                scene: 0,
+                scenes: vec![Scene { nodes: vec![0] }],
+                nodes: vec![],
+                meshes: vec![],
+                materials: vec![],
+                textures: vec![],
+                images: vec![],
+                accessors: vec![],
+                buffer_views: vec![],
+                buffers: vec![],
+            },
+            bin: vec![],
+            textures: HashMap::new(),
+        }
+    }
+}
+
+impl GltfBuilder {
+    fn build(mut self) -> Gltf {
+        self.document.buffers.push(Buffer {
+            byte_length: self.bin.len(),
+            uri: None,
+        });
+
+        Gltf {
+            document: self.document,
+            bin: self.bin,
+        }
+    }
+
+    /// Append bytes to the binary buffer. Buffer views are kept 4 byte aligned so that every
+    /// accessor's component type is aligned.
+    fn push_buffer_view(&mut self, bytes: &[u8], target: u32) -> usize {
+        let byte_offset = self.bin.len();
+        self.bin.extend_from_slice(bytes);
+
+        let padded_len = padded_to_four_bytes(self.bin.len());
+        self.bin.resize(padded_len, 0);
+
+        self.document.buffer_views.push(BufferView {
+            buffer: 0,
+            byte_offset,
+            byte_length: bytes.len(),
+            target,
+        });
+
+        self.document.buffer_views.len() - 1
+    }
+
+    /// Push a vertex attribute, returning the index of its accessor.
+    ///
+    /// glTF requires `min` and `max` for `POSITION` accessors.
+    fn push_f32_accessor(
+        &mut self,
+        data: &[f32],
+        kind: &'static str,
+        components: usize,
+        with_bounds: bool,
+    ) -> usize {
+        let bytes: Vec<u8> = data.iter().flat_map(|f| f.to_le_bytes().to_vec()).collect();
+        let buffer_view = self.push_buffer_view(&bytes, TARGET_ARRAY_BUFFER);
+
+        let (min, max) = if with_bounds {
+            let mut min = vec![f32::INFINITY; components];
+            let mut max = vec![-f32::INFINITY; components];
+
+            for vertex in data.chunks(components) {
+                for (idx, value) in vertex.iter().enumerate() {
+                    min[idx] = min[idx].min(*value);
+                    max[idx] = max[idx].max(*value);
+                }
+            }
+
+            (Some(min), Some(max))
+        } else {
+            (None, None)
+        };
+
+        self.document.accessors.push(Accessor {
+            buffer_view,
+            component_type: COMPONENT_TYPE_FLOAT,
+            count: data.len() / components,
+            kind,
+            min,
+            max,
+        });
+
+        self.document.accessors.len() - 1
+    }
+
+    /// Push the index buffer using the same integer type that the mesh was combined with
+    fn push_indices(&mut self, indices: &IndexBuffer) -> usize {
+        let (bytes, component_type): (Vec<u8>, u32) = match indices {
+            IndexBuffer::U16(indices) => (
+                indices
+                    .iter()
+                    .flat_map(|i| i.to_le_bytes().to_vec())
+                    .collect(),
+                COMPONENT_TYPE_UNSIGNED_SHORT,
+            ),
+            IndexBuffer::U32(indices) => (
+                indices
+                    .iter()
+                    .flat_map(|i| i.to_le_bytes().to_vec())
+                    .collect(),
+                COMPONENT_TYPE_UNSIGNED_INT,
+            ),
+        };
+        let buffer_view = self.push_buffer_view(&bytes, TARGET_ELEMENT_ARRAY_BUFFER);
+
+        self.document.accessors.push(Accessor {
+            buffer_view,
+            component_type,
+            count: indices.len(),
+            kind: "SCALAR",
+            min: None,
+            max: None,
+        });
+
+        self.document.accessors.len() - 1
+    }
+
+    /// Get the texture for an image, creating the image and texture the first time that we
+    /// see it.
+    fn texture(&mut self, image_uri: &str) -> TextureInfo {
+        if let Some(index) = self.textures.get(image_uri) {
+            return TextureInfo { index: *index };
+        }
+
+        self.document.images.push(Image {
+            uri: image_uri.to_string(),
+        });
+        self.document.textures.push(Texture {
+            source: self.document.images.len() - 1,
+        });
+
+        let index = self.document.textures.len() - 1;
+        self.textures.insert(image_uri.to_string(), index);
+
+        TextureInfo { index }
+    }
+
+    /// Convert a `PrincipledBSDF` into a glTF metallic roughness material.
+    ///
+    /// glTF reads roughness from the green channel and metallic from the blue channel of one
+    /// `metallicRoughnessTexture`. Blender lets you pick any channel (and even different images)
+    /// for each, and we only have the names of the images so we can't repack them. So we only
+    /// use a `metallicRoughnessTexture` when both come from the same image in glTF's layout.
+    ///
+    /// Otherwise viewers would read the wrong channels, so we leave the texture out and keep the
+    /// uniform factors, and record where the data really lives in the material's `extras` for
+    /// loaders that want to wire it up themselves, i.e.
+    ///
+    /// `{"roughness": {"image": "metal.jpg", "channel": "R"}, "metallic": {...}}`
+    fn material(&mut self, name: &str, material: &PrincipledBSDF) -> Material {
+        let mut pbr = PbrMetallicRoughness {
+            base_color_factor: [1.0, 1.0, 1.0, 1.0],
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+        };
+
+        match material.base_color() {
+            MaterialInput::Uniform(color) => {
+                pbr.base_color_factor = [color[0], color[1], color[2], 1.0];
+            }
+            MaterialInput::ImageTexture(image) => {
+                pbr.base_color_texture = Some(self.texture(image));
+            }
+        };
+
+        let roughness = match material.roughness() {
+            MaterialInput::Uniform(roughness) => {
+                pbr.roughness_factor = *roughness;
+                None
+            }
+            MaterialInput::ImageTexture((image, channel)) => Some((image, channel)),
+        };
+        let metallic = match material.metallic() {
+            MaterialInput::Uniform(metallic) => {
+                pbr.metallic_factor = *metallic;
+                None
+            }
+            MaterialInput::ImageTexture((image, channel)) => Some((image, channel)),
+        };
+
+        let mut extras = serde_json::Map::new();
+
+        match (roughness, metallic) {
+            (Some((roughness_image, Channel::Green)), Some((metallic_image, Channel::Blue)))
+                if roughness_image == metallic_image =>
+            {
+                pbr.metallic_roughness_texture = Some(self.texture(roughness_image));
+            }
+            _ => {
+                let inputs = [("roughness", roughness), ("metallic", metallic)];
+                for (input, texture) in inputs.iter() {
+                    if let Some((image, channel)) = texture {
+                        extras.insert(
+                            input.to_string(),
+                            json!({ "image": image, "channel": channel }),
+                        );
+                    }
+                }
+            }
+        };
+
+        Material {
+            name: name.to_string(),
+            pbr_metallic_roughness: pbr,
+            normal_texture: material.normal_map().map(|image| self.texture(image)),
+            extras: if extras.is_empty() {
+                None
+            } else {
+                Some(serde_json::Value::Object(extras))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combine_indices::EASILY_RECOGNIZABLE_NUMBER;
+    use crate::concat_vecs;
+    use crate::test_utils::*;
+    use crate::vertex_data::{MultiIndexVertexData, SingleIndexVertexData, VertexData};
+    use crate::IndexWidth;
+    use serde_json::Value;
+
+    /// Read a `.glb` back into its JSON and binary chunks
+    fn parse_glb(glb: &[u8]) -> (Value, Vec<u8>) {
+        let u32_at = |idx: usize| {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(&glb[idx..idx + 4]);
+            u32::from_le_bytes(bytes) as usize
+        };
+
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(u32_at(4), 2);
+        assert_eq!(u32_at(8), glb.len());
+
+        let json_len = u32_at(12);
+        assert_eq!(&glb[16..20], b"JSON");
+        assert_eq!(json_len % 4, 0);
+        let json = serde_json::from_slice(&glb[20..20 + json_len]).unwrap();
+
+        let bin_start = 20 + json_len;
+        let bin_len = u32_at(bin_start);
+        assert_eq!(&glb[bin_start + 4..bin_start + 8], b"BIN\0");
+        assert_eq!(bin_len % 4, 0);
+
+        (json, glb[bin_start + 8..bin_start + 8 + bin_len].to_vec())
+    }
+
+    /// Read every component of an accessor as an f32
+    fn read_accessor(json: &Value, bin: &[u8], accessor: u64) -> Vec<f32> {
+        let accessor = &json["accessors"][accessor as usize];
+        let view = &json["bufferViews"][accessor["bufferView"].as_u64().unwrap() as usize];
+        let offset = view["byteOffset"].as_u64().unwrap() as usize;
+        let length = view["byteLength"].as_u64().unwrap() as usize;
+        let bytes = &bin[offset..offset + length];
+
+        match accessor["componentType"].as_u64().unwrap() as u32 {
+            COMPONENT_TYPE_FLOAT => bytes
+                .chunks(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+            COMPONENT_TYPE_UNSIGNED_SHORT => bytes
+                .chunks(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]) as f32)
+                .collect(),
+            COMPONENT_TYPE_UNSIGNED_INT => bytes
+                .chunks(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32)
+                .collect(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn attribute(json: &Value, bin: &[u8], name: &str) -> Vec<f32> {
+        let accessor = json["meshes"][0]["primitives"][0]["attributes"][name]
+            .as_u64()
+            .unwrap();
+        read_accessor(json, bin, accessor)
+    }
+
+    /// A fresh directory for one test, so that tests (and test runs) don't share files
+    fn unique_temp_dir(test_name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("blender-mesh-{}-{}", test_name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn triangles() -> SingleIndexVertexData {
+        SingleIndexVertexData {
+            positions: attrib3(concat_vecs!(
+                v(0),
+                vec![1.0, 0.0, 0.0],
+                v(1),
+                vec![0.0, 1.0, 0.0]
+            )),
+            indices: vec![0, 1, 2, 0, 2, 3],
+            vertices_in_each_face: vec![3, 3],
+            normals: attrib3(concat_vecs!(v(4), v(5), v(6), v(7))),
+            uvs: Some(attrib2(concat_vecs!(
+                v2(0),
+                vec![1.0, 0.0],
+                v2(1),
+                vec![0.0, 0.25]
+            ))),
+            ..SingleIndexVertexData::default()
+        }
+    }
+
+    fn single_indexed_triangles() -> BlenderMesh {
+        BlenderMesh {
+            vertex_data: VertexData::Single(triangles()),
+            ..BlenderMesh::default()
+        }
+    }
+
+    /// Verify that our vertex data survives a trip through a `.glb`
+    #[test]
+    fn glb_round_trip() {
+        let mesh = single_indexed_triangles();
+
+        let (json, bin) = parse_glb(&mesh.to_gltf("Triangles").unwrap().to_glb());
+
+        assert_eq!(json["asset"]["version"], "2.0");
+        assert!(json["buffers"][0].get("uri").is_none());
+        assert_eq!(json["buffers"][0]["byteLength"], bin.len());
+        assert_eq!(json["nodes"][0]["name"], "Triangles");
+
+        assert_eq!(
+            &attribute(&json, &bin, "POSITION"),
+            triangles().positions.data()
+        );
+        assert_eq!(
+            &attribute(&json, &bin, "NORMAL"),
+            triangles().normals.data()
+        );
+        assert_eq!(
+            attribute(&json, &bin, "TEXCOORD_0"),
+            vec![0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.75]
+        );
+
+        let indices = json["meshes"][0]["primitives"][0]["indices"]
+            .as_u64()
+            .unwrap();
+        assert_eq!(
+            read_accessor(&json, &bin, indices),
+            vec![0.0, 1.0, 2.0, 0.0, 2.0, 3.0]
+        );
+
+        let positions = &json["accessors"][0];
+        assert_eq!(positions["min"], json!([0.0, 0.0, 0.0]));
+        assert_eq!(positions["max"], json!([1.0, 1.0, 1.0]));
+    }
+
+    /// Unused vertices that were left behind when combining indices don't stretch the bounds
+    #[test]
+    fn position_bounds_skip_unused_vertices() {
+        let mut single = triangles();
+        single
+            .positions
+            .data_mut()
+            .extend_from_slice(&[EASILY_RECOGNIZABLE_NUMBER; 3]);
+        single
+            .normals
+            .data_mut()
+            .extend_from_slice(&[EASILY_RECOGNIZABLE_NUMBER; 3]);
+        single
+            .uvs
+            .as_mut()
+            .unwrap()
+            .data_mut()
+            .extend_from_slice(&[EASILY_RECOGNIZABLE_NUMBER; 2]);
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Single(single),
+            ..BlenderMesh::default()
+        };
+
+        let (json, bin) = parse_glb(&mesh.to_gltf("Triangles").unwrap().to_glb());
+
+        let positions = &json["accessors"][0];
+        assert_eq!(positions["count"], 5);
+        assert_eq!(positions["min"], json!([0.0, 0.0, 0.0]));
+        assert_eq!(positions["max"], json!([1.0, 1.0, 1.0]));
+        assert_eq!(&attribute(&json, &bin, "POSITION")[12..], &[0.0, 0.0, 0.0]);
+    }
+
+    /// Only tangents with a handedness are exported
+    #[test]
+    fn mikktspace_tangents_are_exported() {
+        let mut single = triangles();
+        single.tangents = Some(attrib3(vec![1.0; 12]));
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Single(single),
+            ..BlenderMesh::default()
+        };
+        let (json, _) = parse_glb(&mesh.to_gltf("Triangles").unwrap().to_glb());
+        assert!(json["meshes"][0]["primitives"][0]["attributes"]
+            .get("TANGENT")
+            .is_none());
+
+        let mut single = triangles();
+        single.calculate_mikktspace_tangents().unwrap();
+        let tangents = single.tangents().unwrap().data().clone();
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Single(single),
+            ..BlenderMesh::default()
+        };
+        let (json, bin) = parse_glb(&mesh.to_gltf("Triangles").unwrap().to_glb());
+        assert_eq!(attribute(&json, &bin, "TANGENT"), tangents);
+    }
+
+    /// Verify that the `.gltf` JSON points to the `.bin` and that both files get written
+    #[test]
+    fn split_gltf_round_trip() {
+        let mesh = single_indexed_triangles();
+        let gltf = mesh.to_gltf("Triangles").unwrap();
+
+        let dir = unique_temp_dir("split-gltf-round-trip");
+        gltf.write_gltf(dir.join("triangles.gltf")).unwrap();
+
+        let json: Value =
+            serde_json::from_slice(&fs::read(dir.join("triangles.gltf")).unwrap()).unwrap();
+        let bin = fs::read(dir.join("triangles.bin")).unwrap();
+
+        assert_eq!(json["buffers"][0]["uri"], "triangles.bin");
+        assert_eq!(json["buffers"][0]["byteLength"], bin.len());
+        assert_eq!(bin, gltf.bin());
+
+        assert_eq!(
+            &attribute(&json, &bin, "POSITION"),
+            triangles().positions.data()
+        );
+        assert_eq!(
+            &attribute(&json, &bin, "NORMAL"),
+            triangles().normals.data()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// The `.bin` uri is percent-encoded, and paths without a file name are an error
+    #[test]
+    fn write_gltf_paths() {
+        let gltf = single_indexed_triangles().to_gltf("Triangles").unwrap();
+
+        let dir = unique_temp_dir("write-gltf-paths");
+        gltf.write_gltf(dir.join("two triangles.gltf")).unwrap();
+        let json: Value =
+            serde_json::from_slice(&fs::read(dir.join("two triangles.gltf")).unwrap()).unwrap();
+        assert_eq!(json["buffers"][0]["uri"], "two%20triangles.bin");
+        assert!(dir.join("two triangles.bin").exists());
+
+        for path in ["", "/", ".."].iter() {
+            let error = gltf.write_gltf(path).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn percent_encode_file_names() {
+        assert_eq!(percent_encode("cube-1_a.b~c.bin"), "cube-1_a.b~c.bin");
+        assert_eq!(percent_encode("my cube#1.bin"), "my%20cube%231.bin");
+        assert_eq!(percent_encode("würfel.bin"), "w%C3%BCrfel.bin");
+    }
+
+    /// Verify that meshes that were combined with u32 indices are exported as UNSIGNED_INT
+    #[test]
+    fn u32_indices() {
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                index_width: IndexWidth::U32,
+                ..triangles()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        let (json, bin) = parse_glb(&mesh.to_gltf("Triangles").unwrap().to_glb());
+
+        let indices = json["meshes"][0]["primitives"][0]["indices"]
+            .as_u64()
+            .unwrap();
+        assert_eq!(
+            json["accessors"][indices as usize]["componentType"],
+            COMPONENT_TYPE_UNSIGNED_INT
+        );
+        assert_eq!(
+            read_accessor(&json, &bin, indices),
+            vec![0.0, 1.0, 2.0, 0.0, 2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn buffer_views_are_four_byte_aligned() {
+        // 3 u16 indices is 6 bytes, so the next buffer view needs padding
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                indices: vec![0, 1, 2],
+                vertices_in_each_face: vec![3],
+                ..triangles()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        let (json, _bin) = parse_glb(&mesh.to_gltf("Triangle").unwrap().to_glb());
+
+        for view in json["bufferViews"].as_array().unwrap() {
+            assert_eq!(view["byteOffset"].as_u64().unwrap() % 4, 0);
+        }
+    }
+
+    #[test]
+    fn multi_indexed_mesh_is_rejected() {
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                positions: triangles().positions,
+                position_indices: vec![0, 1, 2],
+                normals: triangles().normals,
+                normal_indices: vec![0, 1, 2],
+                vertices_in_each_face: vec![3],
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        match mesh.to_gltf("Triangles") {
+            Err(GltfError::VertexData(VertexDataError::MultipleIndices)) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn quads_are_rejected() {
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                indices: vec![0, 1, 2, 3],
+                vertices_in_each_face: vec![4],
+                ..triangles()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        match mesh.to_gltf("Quad") {
+            Err(GltfError::VertexData(VertexDataError::NotTriangulated {
+                face: 0,
+                vertex_count: 4,
+            })) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn uniform_material() {
+        let material: PrincipledBSDF = serde_json::from_str(
+            r#"{
+                "base_color": {"Uniform": [0.5, 0.25, 1.0]},
+                "metallic": {"Uniform": 0.2},
+                "roughness": {"Uniform": 0.7},
+                "normal_map": null
+            }"#,
+        )
+        .unwrap();
+
+        let mut builder = GltfBuilder::default();
+        let material = serde_json::to_value(builder.material("Flat", &material)).unwrap();
+
+        assert_eq!(
+            material,
+            json!({
+                "name": "Flat",
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [0.5, 0.25, 1.0, 1.0],
+                    "metallicFactor": 0.2f32,
+                    "roughnessFactor": 0.7f32
+                }
+            })
+        );
+    }
+
+    /// Blender's default roughness / metallic channels are red / green, which isn't glTF's
+    /// green / blue so we leave out the metallic roughness texture and record the real channels
+    /// in the extras.
+    #[test]
+    fn texture_material_records_channel_mapping() {
+        let material: PrincipledBSDF = serde_json::from_str(
+            r#"{
+                "base_color": {"ImageTexture": "metal-material.jpg"},
+                "metallic": {"ImageTexture": ["metal-material.jpg", "G"]},
+                "roughness": {"ImageTexture": ["metal-material.jpg", "R"]},
+                "normal_map": "metal-normal.png"
+            }"#,
+        )
+        .unwrap();
+
+        let mut builder = GltfBuilder::default();
+        let material = serde_json::to_value(builder.material("Metal", &material)).unwrap();
+
+        assert_eq!(
+            material,
+            json!({
+                "name": "Metal",
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [1.0, 1.0, 1.0, 1.0],
+                    "baseColorTexture": {"index": 0},
+                    "metallicFactor": 1.0,
+                    "roughnessFactor": 1.0
+                },
+                "normalTexture": {"index": 1},
+                "extras": {
+                    "roughness": {"image": "metal-material.jpg", "channel": "R"},
+                    "metallic": {"image": "metal-material.jpg", "channel": "G"}
+                }
+            })
+        );
+
+        assert_eq!(builder.document.images.len(), 2);
+        assert_eq!(builder.document.textures.len(), 2);
+    }
+
+    #[test]
+    fn gltf_channel_layout_needs_no_extras() {
+        let material: PrincipledBSDF = serde_json::from_str(
+            r#"{
+                "base_color": {"Uniform": [1.0, 1.0, 1.0]},
+                "metallic": {"ImageTexture": ["orm.png", "B"]},
+                "roughness": {"ImageTexture": ["orm.png", "G"]},
+                "normal_map": null
+            }"#,
+        )
+        .unwrap();
+
+        let mut builder = GltfBuilder::default();
+        let material = builder.material("Packed", &material);
+
+        assert!(material.extras.is_none());
+        assert_eq!(
+            material
+                .pbr_metallic_roughness
+                .metallic_roughness_texture
+                .unwrap()
+                .index,
+            0
+        );
+    }
+
+    /// A single textured input still needs glTF's layout for the other channel, so the uniform
+    /// factor is kept instead
+    #[test]
+    fn one_textured_input_keeps_the_uniform_factors() {
+        let material: PrincipledBSDF = serde_json::from_str(
+            r#"{
+                "base_color": {"Uniform": [1.0, 1.0, 1.0]},
+                "metallic": {"Uniform": 0.0},
+                "roughness": {"ImageTexture": ["rough.png", "G"]},
+                "normal_map": null
+            }"#,
+        )
+        .unwrap();
+
+        let mut builder = GltfBuilder::default();
+        let material = builder.material("Rough", &material);
+
+        let pbr = &material.pbr_metallic_roughness;
+        assert!(pbr.metallic_roughness_texture.is_none());
+        assert_eq!(pbr.metallic_factor, 0.0);
+        assert_eq!(
+            material.extras,
+            Some(json!({"roughness": {"image": "rough.png", "channel": "G"}}))
+        );
+        assert!(builder.document.images.is_empty());
+    }
+
+    #[test]
+    fn primitive_uses_material() {
+        let material: PrincipledBSDF = serde_json::from_str(
+            r#"{
+                "base_color": {"Uniform": [1.0, 0.0, 0.0]},
+                "metallic": {"Uniform": 0.0},
+                "roughness": {"Uniform": 0.5},
+                "normal_map": null
+            }"#,
+        )
+        .unwrap();
+        let mut mesh = single_indexed_triangles();
+        mesh.materials.insert("Red".to_string(), material);
+
+        let (json, _bin) = parse_glb(&mesh.to_gltf("Triangles").unwrap().to_glb());
+
+        assert_eq!(json["meshes"][0]["primitives"][0]["material"], 0);
+        assert_eq!(json["materials"][0]["name"], "Red");
+    }
+
+    /// Every material that the faces use gets its own primitive
+    #[test]
+    fn one_primitive_per_material() {
+        let material: PrincipledBSDF = serde_json::from_str(
+            r#"{
+                "base_color": {"Uniform": [1.0, 0.0, 0.0]},
+                "metallic": {"Uniform": 0.0},
+                "roughness": {"Uniform": 0.5},
+                "normal_map": null
+            }"#,
+        )
+        .unwrap();
+
+        let mut single = triangles();
+        single.face_materials = Some(vec![2, 0]);
+
+        let mut mesh = BlenderMesh {
+            vertex_data: VertexData::Single(single),
+            material_slots: vec![Some("Red".to_string()), None, Some("Missing".to_string())],
+            ..BlenderMesh::default()
+        };
+        mesh.materials.insert("Red".to_string(), material);
+
+        let (json, bin) = parse_glb(&mesh.to_gltf("Triangles").unwrap().to_glb());
+
+        let primitives = json["meshes"][0]["primitives"].as_array().unwrap();
+        assert_eq!(primitives.len(), 2);
+
+        assert_eq!(primitives[0]["material"], 0);
+        assert!(primitives[1].get("material").is_none());
+        assert_eq!(primitives[0]["attributes"], primitives[1]["attributes"]);
+
+        let indices =
+            |primitive: &Value| read_accessor(&json, &bin, primitive["indices"].as_u64().unwrap());
+        assert_eq!(indices(&primitives[0]), vec![0.0, 2.0, 3.0]);
+        assert_eq!(indices(&primitives[1]), vec![0.0, 1.0, 2.0]);
+    }
+}
@@ -0,0 +1,559 @@
+//! Connectivity between a mesh's faces, edges and vertices, stored as half edges.
+//!
+//! Every face is a loop of half edges, one per side of the face. Two faces that share an edge
+//! each have a half edge along it, pointing in opposite directions, and those half edges are
+//! each other's `twin`.
+//!
+//! @see https://www.flipcode.com/archives/The_Half-Edge_Data_Structure.shtml
+
+use crate::vertex_data::{VertexData, VertexDataError};
+use crate::BlenderMesh;
+use std::collections::{HashMap, HashSet};
+
+/// One side of a face, pointing from `origin` to the origin of `next`
+#[derive(Debug, Clone, PartialEq)]
+pub struct HalfEdge {
+    /// The vertex that this half edge starts at
+    pub origin: u32,
+    /// The face that this half edge belongs to
+    pub face: usize,
+    /// The next half edge around the face
+    pub next: usize,
+    /// The previous half edge around the face
+    pub prev: usize,
+    /// The half edge of the neighboring face that runs along the same edge in the opposite
+    /// direction. `None` on the boundary of the mesh and on non manifold edges.
+    pub twin: Option<usize>,
+    /// The index of this half edge's starting corner in the mesh's indices (`position_indices`
+    /// for multi indexed meshes)
+    pub corner: usize,
+}
+
+/// The half edges of a mesh.
+///
+/// Vertices are identified by their position, so for single indexed meshes vertices that were
+/// split along a uv seam are treated as one vertex, identified by the lowest vertex index at
+/// that position. For multi indexed meshes vertices are the position indices.
+#[derive(Debug)]
+pub struct HalfEdgeMesh {
+    half_edges: Vec<HalfEdge>,
+    /// The first half edge of each face
+    face_half_edges: Vec<usize>,
+    /// Every half edge that starts at each vertex
+    outgoing: Vec<Vec<usize>>,
+    /// The vertex at each of the mesh's indices
+    corner_vertices: Vec<u32>,
+    /// The mesh's original indices, used when writing index buffers
+    corner_indices: Vec<u32>,
+    non_manifold_edges: Vec<(u32, u32)>,
+}
+
+/// An error while using a [`HalfEdgeMesh`]
+///
+/// [`HalfEdgeMesh`]: struct.HalfEdgeMesh.html
+#[derive(Debug, Fail)]
+pub enum HalfEdgeError {
+    #[fail(display = "Could not find the triangle adjacency: {}", _0)]
+    VertexData(#[cause] VertexDataError),
+}
+
+impl From<VertexDataError> for HalfEdgeError {
+    fn from(error: VertexDataError) -> Self {
+        HalfEdgeError::VertexData(error)
+    }
+}
+
+impl BlenderMesh {
+    /// Build the half edges of the mesh so that you can find neighboring faces and vertices,
+    /// the mesh's boundaries and any edges that aren't manifold.
+    pub fn build_half_edge_mesh(&self) -> HalfEdgeMesh {
+        let (vertices_in_each_face, indices, positions) = match &self.vertex_data {
+            VertexData::Multi(multi) => (
+                &multi.vertices_in_each_face,
+                &multi.position_indices,
+                &multi.positions,
+            ),
+            VertexData::Single(single) => (
+                &single.vertices_in_each_face,
+                &single.indices,
+                &single.positions,
+            ),
+        };
+
+        let corner_vertices = match &self.vertex_data {
+            VertexData::Multi(_) => indices.clone(),
+            VertexData::Single(_) => {
+                let vertex_ids = positions.first_vertex_at_same_position();
+
+                indices
+                    .iter()
+                    .map(|index| vertex_ids[*index as usize])
+                    .collect()
+            }
+        };
+
+        HalfEdgeMesh::new(vertices_in_each_face, corner_vertices, indices.clone())
+    }
+}
+
+impl HalfEdgeMesh {
+    fn new(
+        vertices_in_each_face: &[u8],
+        corner_vertices: Vec<u32>,
+        corner_indices: Vec<u32>,
+    ) -> HalfEdgeMesh {
+        let vertex_count = corner_vertices
+            .iter()
+            .max()
+            .map_or(0, |max| *max as usize + 1);
+
+        let mut half_edges = Vec::with_capacity(corner_vertices.len());
+        let mut face_half_edges = Vec::with_capacity(vertices_in_each_face.len());
+        let mut outgoing = vec![vec![]; vertex_count];
+
+        let mut start = 0;
+        for (face, vertex_count) in vertices_in_each_face.iter().enumerate() {
+            let count = *vertex_count as usize;
+            face_half_edges.push(start);
+
+            for side in 0..count {
+                let half_edge = start + side;
+                half_edges.push(HalfEdge {
+                    origin: corner_vertices[half_edge],
+                    face,
+                    next: start + (side + 1) % count,
+                    prev: start + (side + count - 1) % count,
+                    twin: None,
+                    corner: half_edge,
+                });
+                outgoing[corner_vertices[half_edge] as usize].push(half_edge);
+            }
+
+            start += count;
+        }
+
+        // The half edges that run along each edge, regardless of direction
+        let mut edges: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+        for (idx, half_edge) in half_edges.iter().enumerate() {
+            let destination = half_edges[half_edge.next].origin;
+            edges
+                .entry(sorted_edge(half_edge.origin, destination))
+                .or_default()
+                .push(idx);
+        }
+
+        let mut non_manifold_edges = vec![];
+        for (edge, edge_half_edges) in edges.iter() {
+            match edge_half_edges.as_slice() {
+                [_] => {}
+                [a, b] if half_edges[*a].origin != half_edges[*b].origin => {
+                    half_edges[*a].twin = Some(*b);
+                    half_edges[*b].twin = Some(*a);
+                }
+                // Shared by more than two faces, or by two faces that face opposite ways
+                _ => non_manifold_edges.push(*edge),
+            };
+        }
+        non_manifold_edges.sort_unstable();
+
+        HalfEdgeMesh {
+            half_edges,
+            face_half_edges,
+            outgoing,
+            corner_vertices,
+            corner_indices,
+            non_manifold_edges,
+        }
+    }
+
+    /// Every half edge in the mesh. The half edges of each face are next to each other, in the
+    /// same order as the face's vertices.
+    pub fn half_edges(&self) -> &[HalfEdge] {
+        &self.half_edges
+    }
+
+    /// The number of faces in the mesh
+    pub fn face_count(&self) -> usize {
+        self.face_half_edges.len()
+    }
+
+    /// The vertices of a face, in order
+    pub fn face_vertices(&self, face: usize) -> Vec<u32> {
+        self.face_loop(face)
+            .map(|half_edge| self.half_edges[half_edge].origin)
+            .collect()
+    }
+
+    /// The faces that share an edge with `face`, in the order of the face's edges. Faces that
+    /// share more than one edge are only listed once.
+    pub fn face_neighbors(&self, face: usize) -> Vec<usize> {
+        let mut neighbors = vec![];
+
+        for half_edge in self.face_loop(face) {
+            if let Some(twin) = self.half_edges[half_edge].twin {
+                let neighbor = self.half_edges[twin].face;
+                if !neighbors.contains(&neighbor) {
+                    neighbors.push(neighbor);
+                }
+            }
+        }
+
+        neighbors
+    }
+
+    /// The vertices that share an edge with `vertex`, in order around the vertex.
+    ///
+    /// For a vertex on the boundary the ring starts and ends at the boundary. A non manifold
+    /// vertex (where several fans of faces meet at one point) has each fan's vertices in turn.
+    pub fn vertex_one_ring(&self, vertex: u32) -> Vec<u32> {
+        let outgoing = match self.outgoing.get(vertex as usize) {
+            Some(outgoing) => outgoing,
+            None => return vec![],
+        };
+
+        let mut ring = vec![];
+        let mut visited = HashSet::new();
+
+        for start in outgoing.iter() {
+            if visited.contains(start) {
+                continue;
+            }
+
+            // Rotate backwards until we reach the boundary (or come back around)
+            let mut first = *start;
+            while let Some(previous) = self.previous_outgoing(first) {
+                if previous == *start {
+                    break;
+                }
+                first = previous;
+            }
+
+            let mut half_edge = first;
+            loop {
+                visited.insert(half_edge);
+                push_unique(&mut ring, self.destination(half_edge));
+
+                match self.next_outgoing(half_edge) {
+                    Some(next) if next == first => break,
+                    Some(next) => half_edge = next,
+                    None => {
+                        // The last edge of a boundary fan comes into the vertex
+                        let incoming = self.half_edges[half_edge].prev;
+                        push_unique(&mut ring, self.half_edges[incoming].origin);
+                        break;
+                    }
+                };
+            }
+        }
+
+        ring
+    }
+
+    /// Every hole in the mesh, as the loop of vertices around it.
+    ///
+    /// Each loop follows the direction of the boundary faces' edges. Non manifold edges don't
+    /// have twins, so the faces around them have boundary loops too.
+    pub fn boundary_loops(&self) -> Vec<Vec<u32>> {
+        let mut visited = HashSet::new();
+        let mut loops = vec![];
+
+        for start in 0..self.half_edges.len() {
+            if self.half_edges[start].twin.is_some() || visited.contains(&start) {
+                continue;
+            }
+
+            let mut boundary_loop = vec![];
+            let mut half_edge = start;
+            loop {
+                visited.insert(half_edge);
+                boundary_loop.push(self.half_edges[half_edge].origin);
+
+                match self.next_boundary(half_edge) {
+                    Some(next) if next != start && !visited.contains(&next) => half_edge = next,
+                    _ => break,
+                };
+            }
+
+            loops.push(boundary_loop);
+        }
+
+        loops
+    }
+
+    /// Edges that are shared by more than two faces, or by two faces that face in opposite
+    /// directions, as pairs of vertices with the lowest vertex first.
+    pub fn non_manifold_edges(&self) -> &[(u32, u32)] {
+        &self.non_manifold_edges
+    }
+
+    /// An index buffer for drawing with adjacency, i.e. `GL_TRIANGLES_ADJACENCY`, where every
+    /// triangle `(a, b, c)` becomes `(a, ab, b, bc, c, ca)` and `ab` is the vertex opposite of
+    /// edge `a b` in the neighboring triangle.
+    ///
+    /// On the boundary there is no neighboring triangle, so we use the triangle's own opposite
+    /// vertex.
+    ///
+    /// The indices are the mesh's indices (`position_indices` for multi indexed meshes).
+    pub fn triangle_adjacency_indices(&self) -> Result<Vec<u32>, HalfEdgeError> {
+        let mut indices = Vec::with_capacity(self.half_edges.len() * 2);
+
+        for face in 0..self.face_count() {
+            let half_edges: Vec<usize> = self.face_loop(face).collect();
+            if half_edges.len() != 3 {
+                return Err(VertexDataError::NotTriangulated {
+                    face,
+                    vertex_count: half_edges.len() as u8,
+                }
+                .into());
+            }
+
+            for half_edge in half_edges {
+                let edge = &self.half_edges[half_edge];
+                indices.push(self.corner_indices[edge.corner]);
+
+                let opposite = match edge.twin {
+                    Some(twin) => self.half_edges[self.half_edges[twin].prev].corner,
+                    None => self.half_edges[edge.prev].corner,
+                };
+                indices.push(self.corner_indices[opposite]);
+            }
+        }
+
+        Ok(indices)
+    }
+
+    /// The vertex that each of the mesh's indices refers to
+    pub fn corner_vertices(&self) -> &[u32] {
+        &self.corner_vertices
+    }
+
+    fn face_loop(&self, face: usize) -> impl Iterator<Item = usize> + '_ {
+        let first = self.face_half_edges[face];
+        let mut current = Some(first);
+
+        std::iter::from_fn(move || {
+            let half_edge = current?;
+            let next = self.half_edges[half_edge].next;
+            current = if next == first { None } else { Some(next) };
+            Some(half_edge)
+        })
+    }
+
+    fn destination(&self, half_edge: usize) -> u32 {
+        self.half_edges[self.half_edges[half_edge].next].origin
+    }
+
+    /// The boundary half edge that follows a boundary half edge, found by rotating around the
+    /// vertex at its end until we reach the boundary again
+    fn next_boundary(&self, half_edge: usize) -> Option<usize> {
+        let mut next = self.half_edges[half_edge].next;
+
+        for _ in 0..self.half_edges.len() {
+            match self.half_edges[next].twin {
+                Some(twin) => next = self.half_edges[twin].next,
+                None => return Some(next),
+            };
+        }
+
+        None
+    }
+
+    /// The next half edge leaving the same vertex, rotating in the direction of the faces'
+    /// winding
+    fn next_outgoing(&self, half_edge: usize) -> Option<usize> {
+        self.half_edges[self.half_edges[half_edge].prev].twin
+    }
+
+    fn previous_outgoing(&self, half_edge: usize) -> Option<usize> {
+        self.half_edges[half_edge]
+            .twin
+            .map(|twin| self.half_edges[twin].next)
+    }
+}
+
+/// An edge's vertices with the smallest first, so that both directions of the edge are equal
+pub(crate) fn sorted_edge(a: u32, b: u32) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+fn push_unique(ring: &mut Vec<u32>, vertex: u32) {
+    if !ring.contains(&vertex) {
+        ring.push(vertex);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::vertex_data::{MultiIndexVertexData, SingleIndexVertexData};
+
+    /// A cube made of quads
+    ///
+    /// ```text
+    ///     7 ---- 6
+    ///    /|     /|
+    ///   4 ---- 5 |
+    ///   | 3 ---|-2
+    ///   |/     |/
+    ///   0 ---- 1
+    /// ```
+    fn cube() -> BlenderMesh {
+        BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                vertices_in_each_face: vec![4; 6],
+                position_indices: vec![
+                    0, 3, 2, 1, // bottom
+                    4, 5, 6, 7, // top
+                    0, 1, 5, 4, // front
+                    1, 2, 6, 5, // right
+                    2, 3, 7, 6, // back
+                    3, 0, 4, 7, // left
+                ],
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        }
+    }
+
+    /// A 2x2 grid of quads
+    ///
+    /// ```text
+    /// 6 - 7 - 8
+    /// |   |   |
+    /// 3 - 4 - 5
+    /// |   |   |
+    /// 0 - 1 - 2
+    /// ```
+    fn grid() -> BlenderMesh {
+        BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                vertices_in_each_face: vec![4; 4],
+                position_indices: grid_quads(2),
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        }
+    }
+
+    #[test]
+    fn closed_cube() {
+        let half_edges = cube().build_half_edge_mesh();
+
+        assert_eq!(half_edges.half_edges().len(), 24);
+        assert_eq!(half_edges.face_vertices(1), vec![4, 5, 6, 7]);
+        assert!(half_edges
+            .half_edges()
+            .iter()
+            .all(|half_edge| half_edge.twin.is_some()));
+
+        // The bottom face touches every face but the top
+        let mut neighbors = half_edges.face_neighbors(0);
+        neighbors.sort_unstable();
+        assert_eq!(neighbors, vec![2, 3, 4, 5]);
+
+        let mut ring = half_edges.vertex_one_ring(0);
+        assert_eq!(ring.len(), 3);
+        ring.sort_unstable();
+        assert_eq!(ring, vec![1, 3, 4]);
+
+        assert!(half_edges.boundary_loops().is_empty());
+        assert!(half_edges.non_manifold_edges().is_empty());
+    }
+
+    #[test]
+    fn grid_boundary_and_one_rings() {
+        let half_edges = grid().build_half_edge_mesh();
+
+        // Neighbors are in order around the vertex
+        let ring = half_edges.vertex_one_ring(4);
+        assert_eq!(ring.len(), 4);
+        let start = ring.iter().position(|vertex| *vertex == 1).unwrap();
+        let rotated: Vec<u32> = ring.iter().cycle().skip(start).take(4).cloned().collect();
+        assert_eq!(rotated, vec![1, 5, 7, 3]);
+
+        // A boundary vertex's ring starts and ends on the boundary
+        assert_eq!(half_edges.vertex_one_ring(1), vec![2, 4, 0]);
+        assert_eq!(half_edges.vertex_one_ring(0), vec![1, 3]);
+
+        let loops = half_edges.boundary_loops();
+        assert_eq!(loops.len(), 1);
+        let boundary = &loops[0];
+        let start = boundary.iter().position(|vertex| *vertex == 0).unwrap();
+        let rotated: Vec<u32> = boundary
+            .iter()
+            .cycle()
+            .skip(start)
+            .take(8)
+            .cloned()
+            .collect();
+        assert_eq!(rotated, vec![0, 1, 2, 5, 8, 7, 6, 3]);
+
+        assert_eq!(half_edges.face_neighbors(0), vec![1, 2]);
+    }
+
+    /// Single indexed vertices that were split along a seam are the same vertex
+    #[test]
+    fn single_indexed_seams_are_welded() {
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                vertices_in_each_face: vec![3, 3],
+                // The second triangle uses copies of vertices 1 and 2
+                indices: vec![0, 1, 2, 3, 4, 5],
+                positions: attrib3(vec![
+                    0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, //
+                    1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0,
+                ]),
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        let half_edges = mesh.build_half_edge_mesh();
+
+        assert_eq!(half_edges.corner_vertices(), &[0, 1, 2, 3, 2, 1]);
+        assert_eq!(half_edges.face_neighbors(0), vec![1]);
+        assert_eq!(half_edges.boundary_loops().len(), 1);
+        assert_eq!(half_edges.boundary_loops()[0].len(), 4);
+
+        // The indices of the mesh, not of the welded vertices
+        assert_eq!(
+            half_edges.triangle_adjacency_indices().unwrap(),
+            vec![0, 2, 1, 3, 2, 1, 3, 5, 4, 0, 5, 4]
+        );
+    }
+
+    /// Three triangles that share one edge
+    #[test]
+    fn non_manifold_edges() {
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                vertices_in_each_face: vec![3, 3, 3],
+                position_indices: vec![0, 1, 2, 1, 0, 3, 0, 1, 4],
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        let half_edges = mesh.build_half_edge_mesh();
+
+        assert_eq!(half_edges.non_manifold_edges(), &[(0, 1)]);
+        assert!(half_edges.face_neighbors(0).is_empty());
+
+        // None of the triangles are joined
+        let loops = half_edges.boundary_loops();
+        assert_eq!(loops.len(), 3);
+        assert!(loops.iter().all(|boundary| boundary.len() == 3));
+    }
+
+    #[test]
+    fn triangle_adjacency_needs_triangles() {
+        match grid().build_half_edge_mesh().triangle_adjacency_indices() {
+            Err(HalfEdgeError::VertexData(VertexDataError::NotTriangulated {
+                face: 0,
+                vertex_count: 4,
+            })) => {}
+            _ => unreachable!(),
+        };
+    }
+}
@@ -0,0 +1,496 @@
+//! Packing a mesh's vertex attributes into one interleaved vertex buffer.
+//!
+//! You describe the attributes that you want, in order, along with how each of their components
+//! should be stored. You get back one buffer of bytes along with where each attribute ended up,
+//! which maps directly onto `vertexAttribPointer` calls (or their equivalent in other graphics
+//! APIs).
+//!
+//! ```ignore
+//! for attribute in interleaved.attributes() {
+//!     gl.vertex_attrib_pointer(
+//!         location_of(attribute.attribute),
+//!         attribute.components as i32,
+//!         attribute.component_type.gl_enum(),
+//!         attribute.normalized,
+//!         interleaved.stride() as i32,
+//!         attribute.offset as i32,
+//!     );
+//! }
+//! ```
+
+use crate::quantize::f32_to_f16;
+use crate::vertex_data::{SingleIndexVertexData, VertexDataError};
+use crate::BlenderMesh;
+
+/// The vertex attributes that can be packed into an interleaved vertex buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeKind {
+    Position,
+    Normal,
+    Uv,
+    /// Either 3 or 4 components, depending on the [`TangentAlgorithm`] that generated them
+    ///
+    /// [`TangentAlgorithm`]: enum.TangentAlgorithm.html
+    Tangent,
+    /// One component for each bone that influences each vertex
+    BoneIndices,
+    /// One component for each bone that influences each vertex
+    BoneWeights,
+}
+
+/// The type that each component of an attribute is stored as.
+///
+/// Everything is stored little endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentType {
+    F32,
+    /// Half floats. WebGL 1 needs the `OES_vertex_half_float` extension for these.
+    F16,
+    I8,
+    U8,
+    I16,
+    U16,
+}
+
+impl ComponentType {
+    /// The number of bytes in one component
+    pub fn size_in_bytes(&self) -> usize {
+        match self {
+            ComponentType::F32 => 4,
+            ComponentType::I8 | ComponentType::U8 => 1,
+            ComponentType::F16 | ComponentType::I16 | ComponentType::U16 => 2,
+        }
+    }
+
+    /// The OpenGL / WebGL enum for this type, such as `GL_FLOAT`.
+    ///
+    /// glTF uses the same values for its accessors' `componentType`.
+    pub fn gl_enum(&self) -> u32 {
+        match self {
+            ComponentType::I8 => 5120,
+            ComponentType::U8 => 5121,
+            ComponentType::I16 => 5122,
+            ComponentType::U16 => 5123,
+            ComponentType::F32 => 5126,
+            ComponentType::F16 => 5131,
+        }
+    }
+}
+
+/// How to store one attribute in an interleaved vertex buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeLayout {
+    pub attribute: AttributeKind,
+    pub component_type: ComponentType,
+    /// Map floats from `-1.0..=1.0` (signed types) or `0.0..=1.0` (unsigned types) onto the whole
+    /// range of an integer component type. Values outside of that range are clamped.
+    ///
+    /// When this is `false` values are rounded to the nearest integer, which is what you want for
+    /// bone indices.
+    ///
+    /// Has no effect on `F32` or `F16` components.
+    pub normalized: bool,
+}
+
+/// A declarative description of an interleaved vertex buffer.
+///
+/// By default positions and normals are stored as `f32`s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexLayout {
+    /// The attributes of each vertex, in the order that they are stored
+    pub attributes: Vec<AttributeLayout>,
+    /// Every attribute starts on a multiple of this many bytes, and the stride is rounded up to
+    /// a multiple of it.
+    ///
+    /// WebGL needs every attribute to be aligned to its component size, so 4 is a safe default.
+    pub alignment: usize,
+    /// The number of bytes between the start of each vertex. `None` packs the vertices as tightly
+    /// as the alignment allows.
+    pub stride: Option<usize>,
+}
+
+impl Default for VertexLayout {
+    fn default() -> Self {
+        VertexLayout {
+            attributes: vec![
+                AttributeLayout {
+                    attribute: AttributeKind::Position,
+                    component_type: ComponentType::F32,
+                    normalized: false,
+                },
+                AttributeLayout {
+                    attribute: AttributeKind::Normal,
+                    component_type: ComponentType::F32,
+                    normalized: false,
+                },
+            ],
+            alignment: 4,
+            stride: None,
+        }
+    }
+}
+
+/// Where an attribute ended up within each vertex of an interleaved vertex buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeDescriptor {
+    pub attribute: AttributeKind,
+    /// The number of components in the attribute, such as 3 for positions
+    pub components: u8,
+    pub component_type: ComponentType,
+    pub normalized: bool,
+    /// The number of bytes from the start of each vertex to the start of this attribute
+    pub offset: usize,
+}
+
+/// Every vertex attribute of a mesh, packed into one buffer
+#[derive(Debug, PartialEq)]
+pub struct InterleavedVertexBuffer {
+    bytes: Vec<u8>,
+    stride: usize,
+    vertex_count: usize,
+    attributes: Vec<AttributeDescriptor>,
+}
+
+impl InterleavedVertexBuffer {
+    /// The packed vertices, ready to be uploaded to the GPU
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The number of bytes between the start of each vertex
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// The number of vertices in the buffer
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    /// Where each attribute is within each vertex, in the order of the layout
+    pub fn attributes(&self) -> &[AttributeDescriptor] {
+        &self.attributes
+    }
+}
+
+/// An error while packing a mesh into an interleaved vertex buffer
+#[derive(Debug, Fail)]
+pub enum InterleaveError {
+    #[fail(display = "Could not interleave the mesh: {}", _0)]
+    VertexData(#[cause] VertexDataError),
+    #[fail(display = "The layout uses {:?} but the mesh doesn't have any", _0)]
+    MissingAttribute(AttributeKind),
+    #[fail(
+        display = "A stride of {} bytes is too small for the layout, which needs {} bytes",
+        stride, required
+    )]
+    StrideTooSmall { stride: usize, required: usize },
+}
+
+impl From<VertexDataError> for InterleaveError {
+    fn from(error: VertexDataError) -> Self {
+        InterleaveError::VertexData(error)
+    }
+}
+
+impl BlenderMesh {
+    /// Pack the mesh's vertex attributes into one interleaved vertex buffer.
+    ///
+    /// The mesh needs to have had its indices combined with
+    /// [`BlenderMesh.combine_vertex_indices`].
+    ///
+    /// [`BlenderMesh.combine_vertex_indices`]: struct.BlenderMesh.html#method.combine_vertex_indices
+    pub fn interleave(
+        &self,
+        layout: &VertexLayout,
+    ) -> Result<InterleavedVertexBuffer, InterleaveError> {
+        self.single_index_vertex_data_or_err()?.interleave(layout)
+    }
+}
+
+impl SingleIndexVertexData {
+    /// Pack the vertex attributes into one interleaved vertex buffer.
+    pub fn interleave(
+        &self,
+        layout: &VertexLayout,
+    ) -> Result<InterleavedVertexBuffer, InterleaveError> {
+        let alignment = layout.alignment.max(1);
+        let vertex_count = self.positions.vertex_count();
+
+        let mut sources = Vec::with_capacity(layout.attributes.len());
+        let mut attributes = Vec::with_capacity(layout.attributes.len());
+        let mut offset = 0;
+
+        for attribute_layout in layout.attributes.iter() {
+            let (data, components) = self.attribute_data(attribute_layout.attribute)?;
+
+            attributes.push(AttributeDescriptor {
+                attribute: attribute_layout.attribute,
+                components: components as u8,
+                component_type: attribute_layout.component_type,
+                normalized: attribute_layout.normalized,
+                offset,
+            });
+            sources.push(data);
+
+            let size = components * attribute_layout.component_type.size_in_bytes();
+            offset = round_up(offset + size, alignment);
+        }
+
+        let stride = match layout.stride {
+            Some(stride) if stride < offset => {
+                return Err(InterleaveError::StrideTooSmall {
+                    stride,
+                    required: offset,
+                })
+            }
+            Some(stride) => stride,
+            None => offset,
+        };
+
+        let mut bytes = vec![0; stride * vertex_count];
+
+        for (attribute, data) in attributes.iter().zip(sources.iter()) {
+            let components = attribute.components as usize;
+            let component_size = attribute.component_type.size_in_bytes();
+
+            for vertex in 0..vertex_count {
+                let values = &data[vertex * components..(vertex + 1) * components];
+
+                for (component, value) in values.iter().enumerate() {
+                    let start = vertex * stride + attribute.offset + component * component_size;
+                    write_component(
+                        &mut bytes[start..start + component_size],
+                        *value,
+                        attribute.component_type,
+                        attribute.normalized,
+                    );
+                }
+            }
+        }
+
+        Ok(InterleavedVertexBuffer {
+            bytes,
+            stride,
+            vertex_count,
+            attributes,
+        })
+    }
+
+    /// Every value of an attribute, and how many components it has per vertex
+    fn attribute_data(
+        &self,
+        attribute: AttributeKind,
+    ) -> Result<(Vec<f32>, usize), InterleaveError> {
+        let missing = || InterleaveError::MissingAttribute(attribute);
+
+        let vertex_attribute = match attribute {
+            AttributeKind::Position => Some(&self.positions),
+            AttributeKind::Normal => Some(&self.normals),
+            AttributeKind::Uv => Some(self.uvs.as_ref().ok_or_else(missing)?),
+            AttributeKind::Tangent => Some(self.tangents.as_ref().ok_or_else(missing)?),
+            AttributeKind::BoneIndices | AttributeKind::BoneWeights => None,
+        };
+
+        if let Some(vertex_attribute) = vertex_attribute {
+            return Ok((
+                vertex_attribute.data().clone(),
+                vertex_attribute.size() as usize,
+            ));
+        }
+
+        let bones = self.bones.as_ref().ok_or_else(missing)?;
+        let data = match attribute {
+            AttributeKind::BoneIndices => {
+                bones.bone_indices.iter().map(|idx| *idx as f32).collect()
+            }
+            _ => bones.bone_weights.clone(),
+        };
+
+        Ok((data, bones.bones_per_vertex as usize))
+    }
+}
+
+fn round_up(value: usize, multiple: usize) -> usize {
+    value.div_ceil(multiple) * multiple
+}
+
+/// Write one component into `bytes`, which is exactly the size of the component type
+fn write_component(bytes: &mut [u8], value: f32, component_type: ComponentType, normalized: bool) {
+    // Integers are either scaled onto their whole range or rounded
+    let integer = |min: f32, max: f32| {
+        if normalized {
+            let lowest = if min < 0.0 { -1.0 } else { 0.0 };
+            (value.clamp(lowest, 1.0) * max).round()
+        } else {
+            value.round().clamp(min, max)
+        }
+    };
+
+    match component_type {
+        ComponentType::F32 => bytes.copy_from_slice(&value.to_le_bytes()),
+        ComponentType::F16 => bytes.copy_from_slice(&f32_to_f16(value).to_le_bytes()),
+        ComponentType::I8 => {
+            bytes.copy_from_slice(&(integer(i8::MIN as f32, i8::MAX as f32) as i8).to_le_bytes())
+        }
+        ComponentType::U8 => bytes[0] = integer(0.0, u8::MAX as f32) as u8,
+        ComponentType::I16 => {
+            bytes.copy_from_slice(&(integer(i16::MIN as f32, i16::MAX as f32) as i16).to_le_bytes())
+        }
+        ComponentType::U16 => {
+            bytes.copy_from_slice(&(integer(0.0, u16::MAX as f32) as u16).to_le_bytes())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::vertex_data::BoneAttributes;
+
+    fn two_vertices() -> SingleIndexVertexData {
+        SingleIndexVertexData {
+            indices: vec![0, 1, 0],
+            vertices_in_each_face: vec![3],
+            positions: attrib3(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]),
+            normals: attrib3(vec![0.0, -1.0, 1.0, 0.5, 2.0, -2.0]),
+            uvs: Some(attrib2(vec![0.0, 1.0, 0.5, 0.25])),
+            bones: Some(BoneAttributes {
+                bones_per_vertex: 2,
+                bone_indices: vec![0, 3, 7, 1],
+                bone_weights: vec![0.75, 0.25, 1.0, 0.0],
+            }),
+            ..SingleIndexVertexData::default()
+        }
+    }
+
+    fn layout(attributes: Vec<(AttributeKind, ComponentType, bool)>) -> VertexLayout {
+        VertexLayout {
+            attributes: attributes
+                .into_iter()
+                .map(|(attribute, component_type, normalized)| AttributeLayout {
+                    attribute,
+                    component_type,
+                    normalized,
+                })
+                .collect(),
+            ..VertexLayout::default()
+        }
+    }
+
+    fn f32_at(bytes: &[u8], start: usize) -> f32 {
+        f32::from_le_bytes([
+            bytes[start],
+            bytes[start + 1],
+            bytes[start + 2],
+            bytes[start + 3],
+        ])
+    }
+
+    /// Attributes are placed one after another on aligned offsets
+    #[test]
+    fn attribute_offsets_and_stride() {
+        let interleaved = two_vertices()
+            .interleave(&layout(vec![
+                (AttributeKind::Position, ComponentType::F32, false),
+                (AttributeKind::BoneIndices, ComponentType::U8, false),
+                (AttributeKind::Uv, ComponentType::U16, true),
+            ]))
+            .unwrap();
+
+        let offsets: Vec<usize> = interleaved
+            .attributes()
+            .iter()
+            .map(|attribute| attribute.offset)
+            .collect();
+        assert_eq!(offsets, vec![0, 12, 16]);
+        assert_eq!(interleaved.stride(), 20);
+        assert_eq!(interleaved.vertex_count(), 2);
+        assert_eq!(interleaved.bytes().len(), 40);
+
+        assert_eq!(interleaved.attributes()[1].components, 2);
+        assert_eq!(interleaved.attributes()[2].component_type.gl_enum(), 5123);
+    }
+
+    /// Every vertex's attributes end up at its offset within the stride
+    #[test]
+    fn vertices_are_interleaved() {
+        let interleaved = two_vertices()
+            .interleave(&layout(vec![
+                (AttributeKind::Position, ComponentType::F32, false),
+                (AttributeKind::BoneIndices, ComponentType::U8, false),
+                (AttributeKind::BoneWeights, ComponentType::F32, false),
+            ]))
+            .unwrap();
+        let bytes = interleaved.bytes();
+
+        assert_eq!(interleaved.stride(), 24);
+
+        let second_vertex = 24;
+        assert_eq!(f32_at(bytes, second_vertex), 4.0);
+        assert_eq!(f32_at(bytes, second_vertex + 8), 6.0);
+        assert_eq!(&bytes[second_vertex + 12..second_vertex + 14], &[7, 1]);
+        assert_eq!(f32_at(bytes, second_vertex + 16), 1.0);
+    }
+
+    /// Normalized integers use their whole range, and clamp values outside of it
+    #[test]
+    fn normalized_integers() {
+        let interleaved = two_vertices()
+            .interleave(&layout(vec![
+                (AttributeKind::Normal, ComponentType::I8, true),
+                (AttributeKind::Uv, ComponentType::U16, true),
+            ]))
+            .unwrap();
+        let bytes = interleaved.bytes();
+
+        let normals: Vec<i8> = bytes[0..3].iter().map(|byte| *byte as i8).collect();
+        assert_eq!(normals, vec![0, -127, 127]);
+        let normals: Vec<i8> = bytes[8..11].iter().map(|byte| *byte as i8).collect();
+        assert_eq!(normals, vec![64, 127, -127]);
+
+        assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), 0);
+        assert_eq!(u16::from_le_bytes([bytes[6], bytes[7]]), u16::MAX);
+    }
+
+    /// A larger stride leaves padding at the end of each vertex
+    #[test]
+    fn explicit_stride() {
+        let config = VertexLayout {
+            stride: Some(32),
+            ..VertexLayout::default()
+        };
+        let interleaved = two_vertices().interleave(&config).unwrap();
+
+        assert_eq!(interleaved.stride(), 32);
+        assert_eq!(f32_at(interleaved.bytes(), 32), 4.0);
+        assert_eq!(f32_at(interleaved.bytes(), 32 + 12), 0.5);
+
+        let config = VertexLayout {
+            stride: Some(20),
+            ..VertexLayout::default()
+        };
+        match two_vertices().interleave(&config) {
+            Err(InterleaveError::StrideTooSmall {
+                stride: 20,
+                required: 24,
+            }) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn missing_attributes() {
+        let config = layout(vec![(AttributeKind::Tangent, ComponentType::F32, false)]);
+        match two_vertices().interleave(&config) {
+            Err(InterleaveError::MissingAttribute(AttributeKind::Tangent)) => {}
+            _ => unreachable!(),
+        };
+
+        match BlenderMesh::default().interleave(&VertexLayout::default()) {
+            Err(InterleaveError::VertexData(VertexDataError::MultipleIndices)) => {}
+            _ => unreachable!(),
+        };
+    }
+}
@@ -23,26 +23,71 @@ extern crate serde_derive;
 #[macro_use]
 extern crate log;
 
-pub use self::combine_indices::CreateSingleIndexConfig;
+pub use self::bone::BoneInfluencesPerVertex;
+pub use self::bone_palette::{BonePaletteConfig, BonePaletteError, BonePaletteMesh};
+pub use self::bounding_box::{BoundingBox, SkinnedBoundsConfig, SkinnedBoundsError};
+pub use self::bounding_sphere::BoundingSphere;
+pub use self::bvh::{Bvh, BvhConfig, BvhError, Ray, TriangleHit};
+pub use self::combine_indices::{
+    CombineIndicesError, CreateSingleIndexConfig, IndexBuffer, IndexWidth,
+};
+pub use self::draw_group::{DrawGroup, DrawGroupError};
 pub use self::export::*;
-use crate::bone::BoneInfluencesPerVertex;
-use crate::bounding_box::BoundingBox;
+pub use self::gltf::{Gltf, GltfError};
+pub use self::half_edge::{HalfEdge, HalfEdgeError, HalfEdgeMesh};
+pub use self::interleave::{
+    AttributeDescriptor, AttributeKind, AttributeLayout, ComponentType, InterleaveError,
+    InterleavedVertexBuffer, VertexLayout,
+};
+pub use self::morph_target::{MorphError, MorphTarget, MorphedVertices};
+pub use self::optimize::{OptimizeConfig, OptimizeError, OptimizeReport};
+pub use self::quantize::{
+    Decoding, DirectionEncoding, PositionEncoding, QuantizeConfig, QuantizeError,
+    QuantizedAttribute, QuantizedData, QuantizedVertexData, UvEncoding, WeightEncoding,
+};
+pub use self::simplify::{Lod, LodTarget, SimplifyConfig, SimplifyError};
+pub use self::skin::{SkinConfig, SkinError, SkinnedVertices, SkinningMethod};
+pub use self::smooth_normals::{NormalWeighting, SmoothNormalsConfig, SmoothNormalsError};
+pub use self::tangent::{TangentAlgorithm, TangentError};
+pub use self::triangulate::TriangulateConfig;
+pub use self::validate::{IssueKind, Severity, ValidateConfig, ValidationIssue};
+pub use self::vertex_data::{
+    AttributeSize, BoneAttributes, BoneInfluences, MultiIndexVertexData, SingleIndexVertexData,
+    VertexAttribute, VertexData, VertexDataError,
+};
+pub use self::weld::{WeldConfig, WeldReport};
 use crate::material::PrincipledBSDF;
-use crate::vertex_data::{VertexAttribute, VertexData};
 pub use material::{Channel, MaterialInput};
 use serde_json;
 use serde_json::Error;
 use std::collections::HashMap;
 
 mod bone;
+mod bone_palette;
 mod bounding_box;
+mod bounding_sphere;
+mod bvh;
 mod combine_indices;
+mod draw_group;
 mod export;
+mod flat_format;
+mod gltf;
+mod half_edge;
 mod individual_vertex;
+mod interleave;
 mod material;
+mod mikktspace;
+mod morph_target;
+mod optimize;
+mod quantize;
+mod simplify;
+mod skin;
+mod smooth_normals;
 mod tangent;
 mod triangulate;
+mod validate;
 mod vertex_data;
+mod weld;
 mod y_up;
 
 #[cfg(test)]
@@ -61,56 +106,64 @@ pub enum BlenderError {
 }
 
 /// All of the data about a Blender mesh
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, PartialEq)]
 #[cfg_attr(test, derive(Default))]
-#[serde(deny_unknown_fields)]
 pub struct BlenderMesh {
-    /// All of the mesh's vertices. Three items in the vector make one vertex.
-    /// So indices 0, 1 and 2 are a vertex, 3, 4 and 5 are a vertex.. etc.
-    /// [v1x, v1y, v1z, v2x, v2y, v2z, ...]
-    pub vertex_positions: Vec<f32>,
-    /// The indices within vertex positions that make up each triangle in our mesh.
-    /// Three vertex position indices correspond to one triangle
-    /// [0, 1, 2, 0, 2, 3, ...]
-    pub vertex_position_indices: Vec<u16>,
-    /// TODO: enum..? if they're all equal we replace the MyEnum::PerVertex(Vec<u8>) with MyEnum::Equal(4)
-    pub num_vertices_in_each_face: Vec<u8>,
-    pub vertex_normals: Vec<f32>,
-    pub vertex_normal_indices: Option<Vec<u16>>,
-    /// If your mesh is textured these will be all of the mesh's vertices' uv coordinates.
-    /// Every vertex has two UV coordinates.
-    /// [v1s, v1t, v2s, v2t, v3s, v3t]
-    /// TODO: Combine vertex_uvs, vertex_uv_indices, texture_name into texture_info
-    pub vertex_uvs: Option<Vec<f32>>,
-    pub vertex_uv_indices: Option<Vec<u16>>,
     pub armature_name: Option<String>,
-    /// TODO: When we move to single index triangulate and add new vertices give those vertices the same group indices / weights
-    /// TODO: A function that trims this down to `n` weights and indices per vertex. Similar to our
-    /// triangulate function
-    /// TODO: Make sure that when we combine vertex indices we expand our group weights
-    pub vertex_group_indices: Option<Vec<u8>>,
-    pub vertex_group_weights: Option<Vec<f32>>,
-    /// TODO: enum..? if they're all equal we replace the MyEnum::PerVertex(Vec<u8>) with MyEnum::Equal(4)
-    bone_influences_per_vertex: Option<BoneInfluencesPerVertex>,
     pub bounding_box: BoundingBox,
     /// A map of material name (in Blender) to the material's data
     materials: HashMap<String, PrincipledBSDF>,
-    /// Tangent vectors per vertex, useful for normal mapping.
+    /// The name of the material in each of the mesh's material slots in Blender, or `None` for
+    /// empty slots. Faces refer to their material by its slot index.
+    material_slots: Vec<Option<String>>,
+    /// The mesh's per vertex data.
     ///
-    /// These get set during [`BlenderMesh.combine_indices`], if there are triangle_tangents.
+    /// Meshes are exported from Blender with multiple indices and end up with a single index
+    /// after [`BlenderMesh.combine_vertex_indices`].
     ///
-    /// Useful for normal mapping.
-    per_vertex_tangents: Option<VertexAttribute>,
-    /// Tangent vector to the vertex, calculated using [`BlenderMesh.calculate_face_tangents`].
-    ///
-    /// [`BlenderMesh.calculate_face_tangents`]: struct.BlenderMesh.html#method.calculate_face_tangents
-    face_tangents: Option<Vec<f32>>,
-    // FIXME: Temporary move all of the vertex data above into VertexData
-    // Then we no longer need default .. it'll be required
-    #[serde(default)]
+    /// [`BlenderMesh.combine_vertex_indices`]: struct.BlenderMesh.html#method.combine_vertex_indices
     vertex_data: VertexData,
 }
 
+impl BlenderMesh {
+    /// The mesh's per vertex data
+    pub fn vertex_data(&self) -> &VertexData {
+        &self.vertex_data
+    }
+
+    /// The mesh's vertex data if it still has multiple indices (one per attribute), or `None`
+    /// if they've been combined.
+    pub fn multi_index_vertex_data(&self) -> Option<&MultiIndexVertexData> {
+        match &self.vertex_data {
+            VertexData::Multi(multi) => Some(multi),
+            VertexData::Single(_) => None,
+        }
+    }
+
+    /// The mesh's vertex data if [`BlenderMesh.combine_vertex_indices`] has been called, or
+    /// `None` if it still has multiple indices.
+    ///
+    /// [`BlenderMesh.combine_vertex_indices`]: struct.BlenderMesh.html#method.combine_vertex_indices
+    pub fn single_index_vertex_data(&self) -> Option<&SingleIndexVertexData> {
+        match &self.vertex_data {
+            VertexData::Multi(_) => None,
+            VertexData::Single(single) => Some(single),
+        }
+    }
+
+    /// The mesh's vertex data if [`BlenderMesh.combine_vertex_indices`] has been called, or a
+    /// [`VertexDataError::MultipleIndices`] if it still has multiple indices.
+    ///
+    /// [`BlenderMesh.combine_vertex_indices`]: struct.BlenderMesh.html#method.combine_vertex_indices
+    /// [`VertexDataError::MultipleIndices`]: enum.VertexDataError.html#variant.MultipleIndices
+    pub fn single_index_vertex_data_or_err(
+        &self,
+    ) -> Result<&SingleIndexVertexData, VertexDataError> {
+        self.single_index_vertex_data()
+            .ok_or(VertexDataError::MultipleIndices)
+    }
+}
+
 impl BlenderMesh {
     // TODO: Delete this.. let the consumer worry about serializing / deserializing
     pub fn from_json(json_str: &str) -> Result<BlenderMesh, Error> {
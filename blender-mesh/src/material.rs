@@ -115,4 +115,11 @@ impl BlenderMesh {
     pub fn materials(&self) -> &HashMap<String, PrincipledBSDF> {
         &self.materials
     }
+
+    /// The name of the material in each of the mesh's material slots, `None` for empty slots.
+    ///
+    /// Each face's material is an index into these slots.
+    pub fn material_slots(&self) -> &Vec<Option<String>> {
+        &self.material_slots
+    }
 }
@@ -0,0 +1,941 @@
+//! MikkTSpace tangent generation.
+//!
+//! A port of the reference implementation that Blender (and most other tools) use when baking
+//! normal maps, so that normal maps baked in Blender render without seams. We follow its default
+//! settings, with an angular threshold of 180 degrees.
+//!
+//! The reference implementation only accepts triangles and quads. Faces with more than four
+//! vertices get ear clipped into triangles first, the same way that
+//! [`BlenderMesh.triangulate`] would split them.
+//!
+//! [`BlenderMesh.triangulate`]: struct.BlenderMesh.html#method.triangulate
+//!
+//! @see http://www.mikktspace.com/
+//! @see https://github.com/mmikk/MikkTSpace
+
+use crate::tangent::TangentError;
+use crate::triangulate::{face_triangles, FaceTriangle, TriangulateConfig};
+use crate::vertex_data::{AttributeSize, SingleIndexVertexData, VertexAttribute};
+use nalgebra::Vector3;
+use std::collections::HashMap;
+
+/// A tangent and the sign of its bitangent, `[x, y, z, w]`
+type Tangent = [f32; 4];
+
+/// The tangent space of a triangle corner, before it gets turned into a [`Tangent`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TangentSpace {
+    /// The direction that `u` increases
+    os: Vector3<f32>,
+    /// The direction that `v` increases
+    ot: Vector3<f32>,
+    orientation_preserving: bool,
+}
+
+/// One triangle of the mesh, along with what the reference implementation tracks about it
+struct Triangle {
+    /// The face that the triangle was cut out of
+    face: usize,
+    /// Where the face's vertices start in `indices`
+    face_start: usize,
+    /// Where each corner's vertex is in `indices`
+    corners: [usize; 3],
+    /// The welded vertex at each corner
+    vertices: [usize; 3],
+    /// The triangle's unit `u` and `v` directions, flipped when the uvs are mirrored
+    os: Vector3<f32>,
+    ot: Vector3<f32>,
+    orientation_preserving: bool,
+    /// Triangles without a usable uv mapping. They join the tangent space of whichever
+    /// neighbor reaches them first.
+    group_with_any: bool,
+    /// Two of the corners are the same welded vertex
+    degenerate: bool,
+    /// Part of a quad whose other triangle is degenerate
+    quad_one_triangle: bool,
+    /// The triangle on the other side of the edge from corner `i` to corner `i + 1`
+    neighbors: [Option<usize>; 3],
+    /// The group that each corner's tangent space comes from
+    groups: [Option<usize>; 3],
+}
+
+/// The triangles around a vertex that share a tangent space. They have the same uv orientation
+/// and can be reached from one another by crossing edges that touch the vertex.
+struct Group {
+    vertex: usize,
+    orientation_preserving: bool,
+    triangles: Vec<usize>,
+}
+
+impl SingleIndexVertexData {
+    /// Generate a unit length tangent with handedness for every vertex.
+    ///
+    /// Vertices whose corners end up with different tangents, such as a vertex on the seam
+    /// between two mirrored halves, get split into one vertex per tangent.
+    pub(crate) fn calculate_mikktspace_tangents(&mut self) -> Result<(), TangentError> {
+        if self.uvs.is_none() {
+            return Err(TangentError::NoVertexUvs);
+        }
+
+        let mut triangles = self.mikktspace_triangles();
+
+        self.force_quad_orientations(&mut triangles);
+        find_neighbors(&mut triangles);
+        let groups = build_groups(&mut triangles);
+
+        let mut corner_spaces: Vec<Option<TangentSpace>> = vec![None; self.indices.len()];
+        self.generate_tangent_spaces(&triangles, &groups, &mut corner_spaces);
+        self.degenerate_epilogue(&triangles, &mut corner_spaces);
+
+        let default_tangent = [1.0, 0.0, 0.0, 1.0];
+        let corner_tangents = corner_spaces
+            .into_iter()
+            .map(|space| match space {
+                Some(space) => {
+                    let w = if space.orientation_preserving {
+                        1.0
+                    } else {
+                        -1.0
+                    };
+                    [space.os.x, space.os.y, space.os.z, w]
+                }
+                None => default_tangent,
+            })
+            .collect::<Vec<_>>();
+
+        let mut vertex_tangents: Vec<Option<Tangent>> = vec![None; self.positions.vertex_count()];
+        let mut split_vertices: HashMap<(u32, [u32; 4]), u32> = HashMap::new();
+
+        for (corner, tangent) in corner_tangents.into_iter().enumerate() {
+            let vertex = self.indices[corner];
+
+            match vertex_tangents[vertex as usize] {
+                None => vertex_tangents[vertex as usize] = Some(tangent),
+                Some(existing) if existing == tangent => {}
+                Some(_) => {
+                    let key = (
+                        vertex,
+                        [
+                            tangent[0].to_bits(),
+                            tangent[1].to_bits(),
+                            tangent[2].to_bits(),
+                            tangent[3].to_bits(),
+                        ],
+                    );
+
+                    let split_vertex = match split_vertices.get(&key) {
+                        Some(split_vertex) => *split_vertex,
+                        None => {
+                            let split_vertex = self.duplicate_vertex(vertex);
+                            vertex_tangents.push(Some(tangent));
+                            split_vertices.insert(key, split_vertex);
+                            split_vertex
+                        }
+                    };
+
+                    self.indices[corner] = split_vertex;
+                }
+            };
+        }
+
+        let mut tangents = Vec::with_capacity(vertex_tangents.len() * 4);
+        for tangent in vertex_tangents.into_iter() {
+            tangents.extend_from_slice(&tangent.unwrap_or(default_tangent));
+        }
+
+        self.tangents = Some(VertexAttribute::new(tangents, AttributeSize::Four));
+
+        Ok(())
+    }
+
+    /// Split the faces into triangles and work out each triangle's tangent directions.
+    ///
+    /// Quads are split along the diagonal that is shorter in uv space, falling back to the
+    /// shorter diagonal in position space when they're the same length.
+    fn mikktspace_triangles(&self) -> Vec<Triangle> {
+        let welded = self.welded_vertices();
+
+        let mut ngon_triangles = face_triangles(
+            &self.vertices_in_each_face,
+            &self.indices,
+            &self.positions,
+            &TriangulateConfig::default(),
+        )
+        .into_iter()
+        .peekable();
+
+        let mut triangles = vec![];
+        let mut face_start = 0;
+
+        for (face, vertex_count) in self.vertices_in_each_face.iter().enumerate() {
+            let vertex_count = *vertex_count as usize;
+
+            let mut face_corners: Vec<[usize; 3]> = match vertex_count {
+                3 => vec![[0, 1, 2]],
+                4 => {
+                    let vertex = |corner: usize| self.indices[face_start + corner];
+
+                    let uv_distance = |a: usize, b: usize| {
+                        let (a, b) = (self.uv(vertex(a)), self.uv(vertex(b)));
+                        (b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)
+                    };
+                    let position_distance = |a: usize, b: usize| {
+                        (self.position(vertex(b)) - self.position(vertex(a))).norm_squared()
+                    };
+
+                    let (uv_02, uv_13) = (uv_distance(0, 2), uv_distance(1, 3));
+                    let diagonal_02 = if uv_02 < uv_13 {
+                        true
+                    } else if uv_13 < uv_02 {
+                        false
+                    } else {
+                        position_distance(1, 3) >= position_distance(0, 2)
+                    };
+
+                    if diagonal_02 {
+                        vec![[0, 1, 2], [0, 2, 3]]
+                    } else {
+                        vec![[0, 1, 3], [1, 2, 3]]
+                    }
+                }
+                _ => vec![],
+            };
+
+            while let Some(FaceTriangle { face: ngon, .. }) = ngon_triangles.peek() {
+                if *ngon > face {
+                    break;
+                }
+
+                let ngon_triangle = ngon_triangles.next().unwrap();
+                if ngon_triangle.face == face && vertex_count > 4 {
+                    face_corners.push(ngon_triangle.corners);
+                }
+            }
+
+            for corners in face_corners {
+                let corners = [
+                    face_start + corners[0],
+                    face_start + corners[1],
+                    face_start + corners[2],
+                ];
+                triangles.push(self.triangle(face, face_start, corners, &welded));
+            }
+
+            face_start += vertex_count;
+        }
+
+        // A quad whose other half is degenerate has one corner that only the degenerate half
+        // touches
+        for pair in 0..triangles.len().saturating_sub(1) {
+            let (first, second) = (&triangles[pair], &triangles[pair + 1]);
+
+            if first.face == second.face && first.degenerate != second.degenerate {
+                triangles[pair].quad_one_triangle = true;
+                triangles[pair + 1].quad_one_triangle = true;
+            }
+        }
+
+        triangles
+    }
+
+    /// The reference implementation treats vertices with the same position, normal and uv as
+    /// the same vertex, even if they have different indices.
+    fn welded_vertices(&self) -> Vec<usize> {
+        let mut welded: HashMap<[u32; 8], usize> = HashMap::new();
+
+        (0..self.positions.vertex_count())
+            .map(|vertex| {
+                let vertex = vertex as u32;
+                let (position, normal, uv) =
+                    (self.position(vertex), self.normal(vertex), self.uv(vertex));
+
+                let key = [
+                    position.x.to_bits(),
+                    position.y.to_bits(),
+                    position.z.to_bits(),
+                    normal.x.to_bits(),
+                    normal.y.to_bits(),
+                    normal.z.to_bits(),
+                    uv.0.to_bits(),
+                    uv.1.to_bits(),
+                ];
+
+                let next_id = welded.len();
+                *welded.entry(key).or_insert(next_id)
+            })
+            .collect()
+    }
+
+    fn triangle(
+        &self,
+        face: usize,
+        face_start: usize,
+        corners: [usize; 3],
+        welded: &[usize],
+    ) -> Triangle {
+        let indices = [
+            self.indices[corners[0]],
+            self.indices[corners[1]],
+            self.indices[corners[2]],
+        ];
+        let vertices = [
+            welded[indices[0] as usize],
+            welded[indices[1] as usize],
+            welded[indices[2] as usize],
+        ];
+
+        let degenerate =
+            vertices[0] == vertices[1] || vertices[0] == vertices[2] || vertices[1] == vertices[2];
+
+        let (u1, v1) = self.uv(indices[0]);
+        let (u2, v2) = self.uv(indices[1]);
+        let (u3, v3) = self.uv(indices[2]);
+        let (t21x, t21y) = (u2 - u1, v2 - v1);
+        let (t31x, t31y) = (u3 - u1, v3 - v1);
+
+        let d1 = self.position(indices[1]) - self.position(indices[0]);
+        let d2 = self.position(indices[2]) - self.position(indices[0]);
+
+        // Twice the triangle's signed area in uv space. Negative when the uvs are mirrored.
+        let signed_uv_area = t21x * t31y - t21y * t31x;
+        let orientation_preserving = signed_uv_area > 0.0;
+
+        let mut os = d1 * t31y - d2 * t21y;
+        let mut ot = d2 * t21x - d1 * t31x;
+        let mut group_with_any = true;
+
+        if not_zero(signed_uv_area) {
+            let sign = if orientation_preserving { 1.0 } else { -1.0 };
+            let (length_os, length_ot) = (os.norm(), ot.norm());
+
+            if not_zero(length_os) {
+                os *= sign / length_os;
+            }
+            if not_zero(length_ot) {
+                ot *= sign / length_ot;
+            }
+
+            group_with_any = !(not_zero(length_os) && not_zero(length_ot));
+        }
+
+        Triangle {
+            face,
+            face_start,
+            corners,
+            vertices,
+            os,
+            ot,
+            orientation_preserving,
+            group_with_any,
+            degenerate,
+            quad_one_triangle: false,
+            neighbors: [None; 3],
+            groups: [None; 3],
+        }
+    }
+
+    /// When the two halves of a quad have mirrored uv orientations, the half with the larger uv
+    /// area decides the orientation of both so that the quad doesn't get split in two.
+    fn force_quad_orientations(&self, triangles: &mut [Triangle]) {
+        for pair in 0..triangles.len().saturating_sub(1) {
+            let (first, second) = (&triangles[pair], &triangles[pair + 1]);
+
+            if first.face != second.face
+                || self.vertices_in_each_face[first.face] != 4
+                || first.degenerate
+                || second.degenerate
+                || first.orientation_preserving == second.orientation_preserving
+            {
+                continue;
+            }
+
+            let choose_first = second.group_with_any || self.uv_area(first) >= self.uv_area(second);
+
+            let orientation_preserving = if choose_first {
+                first.orientation_preserving
+            } else {
+                second.orientation_preserving
+            };
+            triangles[pair].orientation_preserving = orientation_preserving;
+            triangles[pair + 1].orientation_preserving = orientation_preserving;
+        }
+    }
+
+    /// Average the tangents of every group's triangles, weighted by their angle at the group's
+    /// vertex
+    fn generate_tangent_spaces(
+        &self,
+        triangles: &[Triangle],
+        groups: &[Group],
+        corner_spaces: &mut [Option<TangentSpace>],
+    ) {
+        // How many of the triangles that share a corner (the two halves of a quad) have written
+        // to it
+        let mut corner_writes = vec![0; corner_spaces.len()];
+
+        for group in groups.iter() {
+            let mut sub_groups: Vec<(Vec<usize>, TangentSpace)> = vec![];
+
+            for triangle_index in group.triangles.iter() {
+                let triangle = &triangles[*triangle_index];
+                let corner = corner_of(triangle, group.vertex);
+                let normal = self.normal(self.indices[triangle.corners[corner]]);
+
+                let os = project(triangle.os, normal);
+                let ot = project(triangle.ot, normal);
+
+                // With the default 180 degree threshold, only triangles whose tangents point in
+                // exactly opposite directions end up in different sub groups
+                let threshold_cos = -1.0;
+
+                let mut members: Vec<usize> = group
+                    .triangles
+                    .iter()
+                    .cloned()
+                    .filter(|other| {
+                        let other = &triangles[*other];
+
+                        let any = triangle.group_with_any || other.group_with_any;
+                        let same_face = triangle.face == other.face;
+
+                        any || same_face
+                            || (os.dot(&project(other.os, normal)) > threshold_cos
+                                && ot.dot(&project(other.ot, normal)) > threshold_cos)
+                    })
+                    .collect();
+                members.sort_unstable();
+
+                let space = match sub_groups.iter().find(|(existing, _)| *existing == members) {
+                    Some((_, space)) => *space,
+                    None => {
+                        let space = self.evaluate_tangent_space(
+                            triangles,
+                            &members,
+                            group.vertex,
+                            group.orientation_preserving,
+                        );
+                        sub_groups.push((members, space));
+                        space
+                    }
+                };
+
+                let corner = triangle.corners[corner];
+                corner_spaces[corner] = match corner_writes[corner] {
+                    0 => Some(space),
+                    _ => corner_spaces[corner].map(|existing| average(&existing, &space)),
+                };
+                corner_writes[corner] += 1;
+            }
+        }
+    }
+
+    /// The angle weighted average tangent space of some triangles at a vertex
+    fn evaluate_tangent_space(
+        &self,
+        triangles: &[Triangle],
+        members: &[usize],
+        vertex: usize,
+        orientation_preserving: bool,
+    ) -> TangentSpace {
+        let mut os_sum = Vector3::zeros();
+        let mut ot_sum = Vector3::zeros();
+
+        for member in members.iter() {
+            let triangle = &triangles[*member];
+            if triangle.group_with_any {
+                continue;
+            }
+
+            let corner = corner_of(triangle, vertex);
+            let vertex_at = |offset: usize| self.indices[triangle.corners[(corner + offset) % 3]];
+
+            let normal = self.normal(vertex_at(0));
+            let os = project(triangle.os, normal);
+            let ot = project(triangle.ot, normal);
+
+            let position = self.position(vertex_at(0));
+            let to_next = project(self.position(vertex_at(1)) - position, normal);
+            let to_previous = project(self.position(vertex_at(2)) - position, normal);
+
+            let angle = to_next.dot(&to_previous).clamp(-1.0, 1.0).acos();
+
+            os_sum += os * angle;
+            ot_sum += ot * angle;
+        }
+
+        TangentSpace {
+            os: normalize_if_not_zero(os_sum),
+            ot: normalize_if_not_zero(ot_sum),
+            orientation_preserving,
+        }
+    }
+
+    /// Degenerate triangles borrow the tangent space of a good triangle that shares the vertex.
+    ///
+    /// In a quad where only one half is degenerate, the corner that only the degenerate half
+    /// touches sits on top of one of the good half's corners, so it copies that corner.
+    fn degenerate_epilogue(
+        &self,
+        triangles: &[Triangle],
+        corner_spaces: &mut [Option<TangentSpace>],
+    ) {
+        let mut first_good_corner: HashMap<usize, usize> = HashMap::new();
+        for triangle in triangles.iter().filter(|triangle| !triangle.degenerate) {
+            for (vertex, corner) in triangle.vertices.iter().zip(triangle.corners.iter()) {
+                first_good_corner.entry(*vertex).or_insert(*corner);
+            }
+        }
+
+        for triangle in triangles.iter() {
+            if !triangle.degenerate || triangle.quad_one_triangle {
+                continue;
+            }
+
+            for (vertex, corner) in triangle.vertices.iter().zip(triangle.corners.iter()) {
+                if let Some(good_corner) = first_good_corner.get(vertex) {
+                    corner_spaces[*corner] = corner_spaces[*good_corner];
+                }
+            }
+        }
+
+        for triangle in triangles.iter() {
+            if triangle.degenerate || !triangle.quad_one_triangle {
+                continue;
+            }
+
+            let missing = (triangle.face_start..triangle.face_start + 4)
+                .find(|corner| !triangle.corners.contains(corner))
+                .unwrap();
+            let missing_position = self.position(self.indices[missing]);
+
+            if let Some(source) = triangle
+                .corners
+                .iter()
+                .find(|corner| self.position(self.indices[**corner]) == missing_position)
+            {
+                corner_spaces[missing] = corner_spaces[*source];
+            }
+        }
+    }
+
+    /// Half of the absolute area of a triangle in uv space
+    fn uv_area(&self, triangle: &Triangle) -> f32 {
+        let (u1, v1) = self.uv(self.indices[triangle.corners[0]]);
+        let (u2, v2) = self.uv(self.indices[triangle.corners[1]]);
+        let (u3, v3) = self.uv(self.indices[triangle.corners[2]]);
+
+        ((u2 - u1) * (v3 - v1) - (v2 - v1) * (u3 - u1)).abs() * 0.5
+    }
+
+    /// Add a copy of a vertex to the end of our vertex data, returning the copy's index
+    fn duplicate_vertex(&mut self, vertex: u32) -> u32 {
+        let vertex = vertex as usize;
+
+        let mut attributes = vec![&mut self.positions, &mut self.normals];
+        attributes.extend(self.uvs.as_mut());
+        attributes.extend(self.tangents.as_mut());
+        for target in self.morph_targets.iter_mut() {
+            attributes.push(&mut target.position_deltas);
+            attributes.push(&mut target.normal_deltas);
+        }
+
+        for attribute in attributes {
+            let size = attribute.size() as usize;
+            attribute
+                .data_mut()
+                .extend_from_within(vertex * size..(vertex + 1) * size);
+        }
+
+        if let Some(bones) = self.bones.as_mut() {
+            let bones_per_vertex = bones.bones_per_vertex as usize;
+            let range = vertex * bones_per_vertex..(vertex + 1) * bones_per_vertex;
+
+            bones.bone_indices.extend_from_within(range.clone());
+            bones.bone_weights.extend_from_within(range);
+        }
+
+        (self.positions.vertex_count() - 1) as u32
+    }
+
+    fn position(&self, vertex: u32) -> Vector3<f32> {
+        let (x, y, z) = self.positions.three_components(vertex as usize);
+        Vector3::new(x, y, z)
+    }
+
+    fn normal(&self, vertex: u32) -> Vector3<f32> {
+        let (x, y, z) = self.normals.three_components(vertex as usize);
+        Vector3::new(x, y, z)
+    }
+
+    fn uv(&self, vertex: u32) -> (f32, f32) {
+        self.uvs.as_ref().unwrap().two_components(vertex as usize)
+    }
+}
+
+/// Connect triangles that share an edge. Like the reference implementation, an edge only
+/// connects two triangles that wind around it in opposite directions.
+fn find_neighbors(triangles: &mut [Triangle]) {
+    let mut edges: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+
+    for (index, triangle) in triangles.iter().enumerate() {
+        if triangle.degenerate {
+            continue;
+        }
+
+        for edge in 0..3 {
+            let (from, to) = (triangle.vertices[edge], triangle.vertices[(edge + 1) % 3]);
+            edges.entry((from, to)).or_default().push((index, edge));
+        }
+    }
+
+    for index in 0..triangles.len() {
+        if triangles[index].degenerate {
+            continue;
+        }
+
+        for edge in 0..3 {
+            if triangles[index].neighbors[edge].is_some() {
+                continue;
+            }
+
+            let (from, to) = (
+                triangles[index].vertices[edge],
+                triangles[index].vertices[(edge + 1) % 3],
+            );
+
+            let opposite = edges.get(&(to, from)).and_then(|candidates| {
+                candidates
+                    .iter()
+                    .find(|(other, other_edge)| triangles[*other].neighbors[*other_edge].is_none())
+                    .cloned()
+            });
+
+            if let Some((other, other_edge)) = opposite {
+                triangles[index].neighbors[edge] = Some(other);
+                triangles[other].neighbors[other_edge] = Some(index);
+            }
+        }
+    }
+}
+
+/// Group the triangles around each vertex that share a tangent space
+fn build_groups(triangles: &mut [Triangle]) -> Vec<Group> {
+    let mut groups = vec![];
+
+    for index in 0..triangles.len() {
+        let triangle = &triangles[index];
+        if triangle.degenerate || triangle.group_with_any {
+            continue;
+        }
+
+        for corner in 0..3 {
+            if triangles[index].groups[corner].is_some() {
+                continue;
+            }
+
+            groups.push(Group {
+                vertex: triangles[index].vertices[corner],
+                orientation_preserving: triangles[index].orientation_preserving,
+                triangles: vec![],
+            });
+            let group = groups.len() - 1;
+
+            assign_to_group(triangles, &mut groups[group], group, index);
+        }
+    }
+
+    groups
+}
+
+/// Add a triangle to a group, and then spread out to its neighbors across the two edges that
+/// touch the group's vertex
+fn assign_to_group(
+    triangles: &mut [Triangle],
+    group: &mut Group,
+    group_index: usize,
+    index: usize,
+) {
+    let mut stack = vec![index];
+
+    while let Some(index) = stack.pop() {
+        let triangle = &mut triangles[index];
+        let corner = corner_of(triangle, group.vertex);
+
+        if triangle.groups[corner].is_some() {
+            continue;
+        }
+
+        // The first group to reach a triangle without a uv mapping decides its orientation
+        if triangle.group_with_any && triangle.groups.iter().all(Option::is_none) {
+            triangle.orientation_preserving = group.orientation_preserving;
+        }
+
+        if triangle.orientation_preserving != group.orientation_preserving {
+            continue;
+        }
+
+        group.triangles.push(index);
+        triangle.groups[corner] = Some(group_index);
+
+        let left = triangle.neighbors[corner];
+        let right = triangle.neighbors[(corner + 2) % 3];
+        stack.extend(right);
+        stack.extend(left);
+    }
+}
+
+/// Which of a triangle's corners is a welded vertex
+fn corner_of(triangle: &Triangle, vertex: usize) -> usize {
+    triangle
+        .vertices
+        .iter()
+        .position(|corner_vertex| *corner_vertex == vertex)
+        .unwrap()
+}
+
+fn average(first: &TangentSpace, second: &TangentSpace) -> TangentSpace {
+    // Averaging two identical tangent spaces could change them slightly, which would split
+    // the vertex later on
+    if first == second {
+        return *first;
+    }
+
+    TangentSpace {
+        os: normalize_if_not_zero(first.os + second.os),
+        ot: normalize_if_not_zero(first.ot + second.ot),
+        orientation_preserving: second.orientation_preserving,
+    }
+}
+
+/// Remove the part of a vector that points along a unit normal, and normalize what is left
+fn project(vector: Vector3<f32>, normal: Vector3<f32>) -> Vector3<f32> {
+    normalize_if_not_zero(vector - normal * normal.dot(&vector))
+}
+
+fn normalize_if_not_zero(vector: Vector3<f32>) -> Vector3<f32> {
+    if vector.iter().any(|component| not_zero(*component)) {
+        vector / vector.norm()
+    } else {
+        vector
+    }
+}
+
+fn not_zero(value: f32) -> bool {
+    value.abs() > f32::MIN_POSITIVE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concat_vecs;
+    use crate::test_utils::*;
+    use crate::vertex_data::BoneAttributes;
+
+    /// A unit quad facing +z
+    fn quad(uvs: Vec<f32>) -> SingleIndexVertexData {
+        SingleIndexVertexData {
+            vertices_in_each_face: vec![4],
+            indices: vec![0, 1, 2, 3],
+            positions: attrib3(vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0,
+            ]),
+            normals: attrib3(vec![
+                0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0,
+            ]),
+            uvs: Some(attrib2(uvs)),
+            ..SingleIndexVertexData::default()
+        }
+    }
+
+    /// The tangent points in the direction that `u` increases
+    #[test]
+    fn tangents_follow_u() {
+        let mut single = quad(vec![0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0]);
+        single.calculate_mikktspace_tangents().unwrap();
+
+        for vertex in 0..4 {
+            assert_tangent_eq(&single, vertex, [1.0, 0.0, 0.0, 1.0]);
+        }
+
+        // Rotating the uvs rotates the tangents
+        let mut single = quad(vec![0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0]);
+        single.calculate_mikktspace_tangents().unwrap();
+
+        for vertex in 0..4 {
+            assert_tangent_eq(&single, vertex, [0.0, 1.0, 0.0, 1.0]);
+        }
+    }
+
+    /// Mirrored uvs flip the sign of the bitangent
+    #[test]
+    fn mirrored_uvs_have_negative_handedness() {
+        let mut single = quad(vec![1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        single.calculate_mikktspace_tangents().unwrap();
+
+        for vertex in 0..4 {
+            assert_tangent_eq(&single, vertex, [-1.0, 0.0, 0.0, -1.0]);
+        }
+    }
+
+    /// Tangents lie in the plane perpendicular to the vertex normal, even when the normal is
+    /// smoothed and doesn't match the face normal
+    #[test]
+    fn tangents_are_orthogonal_to_the_normal() {
+        let mut single = quad(vec![0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0]);
+        let tilted = 1.0 / 2.0_f32.sqrt();
+        single.normals = attrib3(vec![
+            tilted, 0.0, tilted, tilted, 0.0, tilted, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0,
+        ]);
+
+        single.calculate_mikktspace_tangents().unwrap();
+
+        assert_tangent_eq(&single, 0, [tilted, 0.0, -tilted, 1.0]);
+        assert_tangent_eq(&single, 2, [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    /// A vertex on the seam between two mirrored halves gets split into one vertex for each
+    /// handedness
+    #[test]
+    fn vertices_shared_by_mirrored_faces_are_split() {
+        // Two quads side by side, the right one with its uvs mirrored across the shared edge
+        // between vertices 1 and 2.
+        let mut single = SingleIndexVertexData {
+            vertices_in_each_face: vec![4, 4],
+            indices: vec![0, 1, 2, 3, 1, 4, 5, 2],
+            positions: attrib3(vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 2.0, 0.0, 0.0, 2.0,
+                1.0, 0.0,
+            ]),
+            normals: attrib3(concat_vecs!(
+                vec![0.0, 0.0, 1.0],
+                vec![0.0, 0.0, 1.0],
+                vec![0.0, 0.0, 1.0],
+                vec![0.0, 0.0, 1.0],
+                vec![0.0, 0.0, 1.0],
+                vec![0.0, 0.0, 1.0]
+            )),
+            uvs: Some(attrib2(vec![
+                0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0,
+            ])),
+            bones: Some(BoneAttributes {
+                bones_per_vertex: 1,
+                bone_indices: vec![0, 1, 2, 3, 4, 5],
+                bone_weights: vec![1.0; 6],
+            }),
+            ..SingleIndexVertexData::default()
+        };
+        single.calculate_mikktspace_tangents().unwrap();
+
+        assert_eq!(single.indices(), &vec![0, 1, 2, 3, 6, 4, 5, 7]);
+        assert_eq!(single.positions().vertex_count(), 8);
+        assert_eq!(single.uvs().unwrap().vertex_count(), 8);
+        assert_eq!(
+            single.bones().unwrap().bone_indices(),
+            &vec![0, 1, 2, 3, 4, 5, 1, 2]
+        );
+
+        assert_tangent_eq(&single, 1, [1.0, 0.0, 0.0, 1.0]);
+        assert_tangent_eq(&single, 6, [-1.0, 0.0, 0.0, -1.0]);
+        assert_eq!(
+            single.positions().three_components(6),
+            single.positions().three_components(1)
+        );
+    }
+
+    /// Quads are split along their shorter uv diagonal, even when the position diagonals are
+    /// the same length. Vertex 2 is only a part of the `[1, 2, 3]` half.
+    #[test]
+    fn quads_split_along_the_shorter_uv_diagonal() {
+        let mut single = quad(vec![0.0, 0.0, 1.0, 0.0, 2.0, 2.0, 0.0, 1.0]);
+        single.calculate_mikktspace_tangents().unwrap();
+
+        let sqrt_5 = 5.0_f32.sqrt();
+        assert_tangent_eq(&single, 0, [1.0, 0.0, 0.0, 1.0]);
+        assert_tangent_eq(&single, 2, [2.0 / sqrt_5, -1.0 / sqrt_5, 0.0, 1.0]);
+    }
+
+    /// Two quads hinged along a smooth edge. The right quad's hinge vertices are copies of the
+    /// left quad's, which get welded together since they have the same position, normal and uv,
+    /// so both copies get the average of the two faces' tangents.
+    #[test]
+    fn identical_vertices_are_welded() {
+        let h = 1.0 / 2.0_f32.sqrt();
+        let hinge_normal = Vector3::new(-h, 0.0, 1.0 + h).normalize();
+        let n = hinge_normal;
+
+        let mut single = SingleIndexVertexData {
+            vertices_in_each_face: vec![4, 4],
+            indices: vec![0, 1, 2, 3, 4, 6, 7, 5],
+            positions: attrib3(concat_vecs!(
+                vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+                vec![1.0, 0.0, 0.0, 1.0, 1.0, 0.0],
+                vec![1.0 + h, 0.0, h, 1.0 + h, 1.0, h]
+            )),
+            normals: attrib3(concat_vecs!(
+                vec![0.0, 0.0, 1.0],
+                vec![n.x, n.y, n.z, n.x, n.y, n.z],
+                vec![0.0, 0.0, 1.0],
+                vec![n.x, n.y, n.z, n.x, n.y, n.z],
+                vec![-h, 0.0, h, -h, 0.0, h]
+            )),
+            uvs: Some(attrib2(vec![
+                0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 2.0, 0.0, 2.0, 1.0,
+            ])),
+            ..SingleIndexVertexData::default()
+        };
+        single.calculate_mikktspace_tangents().unwrap();
+
+        let hinge_tangent = Vector3::new(1.0 + h, 0.0, h).normalize();
+        let hinge_tangent = [hinge_tangent.x, hinge_tangent.y, hinge_tangent.z, 1.0];
+        for vertex in 1..=2 {
+            assert_tangent_eq(&single, vertex, hinge_tangent);
+            assert_tangent_eq(&single, vertex + 3, hinge_tangent);
+        }
+        assert_tangent_eq(&single, 0, [1.0, 0.0, 0.0, 1.0]);
+        assert_tangent_eq(&single, 6, [h, 0.0, h, 1.0]);
+    }
+
+    /// A quad with two corners on top of each other has one degenerate half. The corner that only
+    /// the degenerate half touches copies the tangent of the corner that it sits on.
+    #[test]
+    fn quad_with_a_degenerate_half() {
+        let mut single = SingleIndexVertexData {
+            vertices_in_each_face: vec![4],
+            indices: vec![0, 1, 2, 2],
+            positions: attrib3(vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0]),
+            normals: attrib3([0.0, 0.0, 1.0].repeat(3)),
+            uvs: Some(attrib2(vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0])),
+            ..SingleIndexVertexData::default()
+        };
+        single.calculate_mikktspace_tangents().unwrap();
+
+        assert_eq!(single.indices(), &vec![0, 1, 2, 2]);
+        for vertex in 0..3 {
+            assert_tangent_eq(&single, vertex, [0.0, 1.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn tangents_need_uvs() {
+        let mut single = quad(vec![]);
+        single.uvs = None;
+
+        match single.calculate_mikktspace_tangents() {
+            Err(TangentError::NoVertexUvs) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    fn assert_tangent_eq(single: &SingleIndexVertexData, vertex: usize, expected: Tangent) {
+        let tangent = &single.tangents().unwrap().data()[vertex * 4..vertex * 4 + 4];
+
+        for (actual, expected) in tangent.iter().zip(expected.iter()) {
+            assert!(
+                (actual - expected).abs() < 1e-6,
+                "{:?} != {:?}",
+                tangent,
+                expected
+            );
+        }
+    }
+}
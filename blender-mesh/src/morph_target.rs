@@ -0,0 +1,245 @@
+//! Shape keys (morph targets), which move a mesh's vertices towards another shape such as a smile
+//! or a blink.
+//!
+//! Each target stores how far every position and normal moves when the target is fully applied,
+//! so any number of targets can be blended together by adding up their weighted deltas.
+
+use crate::optimize::reorder_attribute;
+use crate::vertex_data::{AttributeSize, VertexAttribute, VertexData};
+use crate::BlenderMesh;
+use nalgebra::Vector3;
+
+/// One of a mesh's shape keys.
+///
+/// The deltas line up with the mesh's vertex data. Before [`BlenderMesh.combine_vertex_indices`]
+/// there is one position delta per position and one normal delta per normal, and afterwards
+/// there is one of each per vertex.
+///
+/// [`BlenderMesh.combine_vertex_indices`]: struct.BlenderMesh.html#method.combine_vertex_indices
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MorphTarget {
+    /// The name of the shape key in Blender
+    pub(crate) name: String,
+    /// The shape key's value in Blender when the mesh was exported
+    pub(crate) default_weight: f32,
+    /// How far each position moves when the target's weight is `1.0`
+    pub(crate) position_deltas: VertexAttribute,
+    /// How much each normal changes when the target's weight is `1.0`
+    pub(crate) normal_deltas: VertexAttribute,
+}
+
+/// A mesh's positions and normals after its morph targets have been applied
+#[derive(Debug, PartialEq)]
+pub struct MorphedVertices {
+    pub positions: VertexAttribute,
+    /// Normalized after the normal deltas are added
+    pub normals: VertexAttribute,
+}
+
+/// An error while applying a mesh's morph targets
+#[derive(Debug, Fail)]
+pub enum MorphError {
+    #[fail(
+        display = "The mesh has {} morph targets but {} weights were given",
+        target_count, weight_count
+    )]
+    WeightCount {
+        target_count: usize,
+        weight_count: usize,
+    },
+}
+
+impl MorphTarget {
+    /// Create a morph target from deltas with three components (x, y, z) per vertex
+    pub fn new(
+        name: String,
+        default_weight: f32,
+        position_deltas: Vec<f32>,
+        normal_deltas: Vec<f32>,
+    ) -> MorphTarget {
+        MorphTarget {
+            name,
+            default_weight,
+            position_deltas: VertexAttribute::new(position_deltas, AttributeSize::Three),
+            normal_deltas: VertexAttribute::new(normal_deltas, AttributeSize::Three),
+        }
+    }
+
+    /// The name of the shape key in Blender
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// The shape key's value in Blender when the mesh was exported
+    pub fn default_weight(&self) -> f32 {
+        self.default_weight
+    }
+
+    /// How far each position moves when the target's weight is `1.0`
+    pub fn position_deltas(&self) -> &VertexAttribute {
+        &self.position_deltas
+    }
+
+    /// How much each normal changes when the target's weight is `1.0`
+    pub fn normal_deltas(&self) -> &VertexAttribute {
+        &self.normal_deltas
+    }
+
+    /// The same target with its vertices rearranged so that the `n`th vertex is the old
+    /// `old_vertices[n]`th vertex
+    pub(crate) fn reorder(&self, old_vertices: &[usize]) -> MorphTarget {
+        MorphTarget {
+            name: self.name.clone(),
+            default_weight: self.default_weight,
+            position_deltas: reorder_attribute(&self.position_deltas, old_vertices),
+            normal_deltas: reorder_attribute(&self.normal_deltas, old_vertices),
+        }
+    }
+}
+
+impl BlenderMesh {
+    /// The mesh's shape keys
+    pub fn morph_targets(&self) -> &[MorphTarget] {
+        match &self.vertex_data {
+            VertexData::Multi(multi) => &multi.morph_targets,
+            VertexData::Single(single) => &single.morph_targets,
+        }
+    }
+
+    /// The default weight of every morph target, in order
+    pub fn default_morph_weights(&self) -> Vec<f32> {
+        self.morph_targets()
+            .iter()
+            .map(|target| target.default_weight)
+            .collect()
+    }
+
+    /// Apply the morph targets to the mesh's positions and normals, with one weight per target.
+    ///
+    /// This is the same sum that a GPU morphing shader does:
+    /// `position + weights[0] * deltas[0] + weights[1] * deltas[1] + ...`
+    pub fn morph(&self, weights: &[f32]) -> Result<MorphedVertices, MorphError> {
+        let targets = self.morph_targets();
+        if weights.len() != targets.len() {
+            return Err(MorphError::WeightCount {
+                target_count: targets.len(),
+                weight_count: weights.len(),
+            });
+        }
+
+        let (positions, normals) = match &self.vertex_data {
+            VertexData::Multi(multi) => (&multi.positions, &multi.normals),
+            VertexData::Single(single) => (&single.positions, &single.normals),
+        };
+
+        let mut morphed_positions = positions.data().clone();
+        let mut morphed_normals = normals.data().clone();
+
+        for (target, weight) in targets.iter().zip(weights.iter()) {
+            if *weight == 0.0 {
+                continue;
+            }
+
+            add_weighted(
+                &mut morphed_positions,
+                target.position_deltas.data(),
+                *weight,
+            );
+            add_weighted(&mut morphed_normals, target.normal_deltas.data(), *weight);
+        }
+
+        for normal in morphed_normals.chunks_mut(3) {
+            let vector = Vector3::new(normal[0], normal[1], normal[2]);
+            if let Some(unit) = vector.try_normalize(f32::EPSILON) {
+                normal.copy_from_slice(unit.as_slice());
+            }
+        }
+
+        Ok(MorphedVertices {
+            positions: VertexAttribute::new(morphed_positions, AttributeSize::Three),
+            normals: VertexAttribute::new(morphed_normals, AttributeSize::Three),
+        })
+    }
+}
+
+fn add_weighted(data: &mut [f32], deltas: &[f32], weight: f32) {
+    for (component, delta) in data.iter_mut().zip(deltas.iter()) {
+        *component += delta * weight;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::vertex_data::MultiIndexVertexData;
+
+    fn two_targets() -> BlenderMesh {
+        BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                positions: attrib3(vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0]),
+                normals: attrib3(vec![0.0, 0.0, 1.0]),
+                morph_targets: vec![
+                    MorphTarget::new(
+                        "Smile".to_string(),
+                        0.0,
+                        vec![0.0, 1.0, 0.0, 0.0, 0.0, 0.0],
+                        vec![0.0, 1.0, -1.0],
+                    ),
+                    MorphTarget::new(
+                        "Blink".to_string(),
+                        0.25,
+                        vec![0.0, 0.0, 0.0, 0.0, 0.0, 2.0],
+                        vec![0.0, 0.0, 0.0],
+                    ),
+                ],
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        }
+    }
+
+    #[test]
+    fn blend_morph_targets() {
+        let mesh = two_targets();
+
+        let morphed = mesh.morph(&[0.5, 1.0]).unwrap();
+
+        assert_eq!(
+            morphed.positions.data(),
+            &vec![0.0, 0.5, 0.0, 1.0, 0.0, 2.0]
+        );
+        let half = 0.5f32.sqrt();
+        for (normal, expected) in morphed.normals.data().iter().zip([0.0, half, half].iter()) {
+            assert!((normal - expected).abs() < 1e-6);
+        }
+    }
+
+    /// The default weights are the shape key values from when the mesh was exported, and zero
+    /// weights leave the mesh alone
+    #[test]
+    fn default_and_zero_weights() {
+        let mesh = two_targets();
+
+        assert_eq!(mesh.default_morph_weights(), vec![0.0, 0.25]);
+
+        let morphed = mesh.morph(&[0.0, 0.0]).unwrap();
+        assert_eq!(
+            morphed.positions.data(),
+            &vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0]
+        );
+        assert_eq!(morphed.normals.data(), &vec![0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn wrong_number_of_weights() {
+        match two_targets().morph(&[1.0]) {
+            Err(MorphError::WeightCount {
+                target_count: 2,
+                weight_count: 1,
+            }) => {}
+            _ => unreachable!(),
+        };
+    }
+}
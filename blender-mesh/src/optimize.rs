@@ -0,0 +1,688 @@
+//! Reordering a mesh's triangles and vertices so that GPUs can render it faster.
+//!
+//! 1. Triangles are reordered so that recently transformed vertices get re-used while they're
+//!    still in the GPU's post transform vertex cache (Tipsify).
+//! 2. Optionally, clusters of those triangles are reordered so that triangles that are likely to
+//!    occlude other triangles are drawn first, reducing overdraw.
+//! 3. Vertices are reordered in the order that the triangles first use them, so that fetching
+//!    vertex attributes walks through memory linearly.
+//!
+//! @see http://gfx.cs.princeton.edu/pubs/Sander_2007_%3ETR/tipsy.pdf
+
+use crate::vertex_data::{
+    ensure_triangulated, SingleIndexVertexData, VertexAttribute, VertexDataError,
+};
+use crate::BlenderMesh;
+use nalgebra::Vector3;
+
+/// Configuration for [`BlenderMesh.optimize`]
+///
+/// [`BlenderMesh.optimize`]: struct.BlenderMesh.html#method.optimize
+#[derive(Debug)]
+pub struct OptimizeConfig {
+    /// The number of vertices that we assume the GPU's post transform cache can hold.
+    ///
+    /// Most GPUs behave like a FIFO cache with somewhere between 16 and 32 entries.
+    pub vertex_cache_size: usize,
+    /// Reorder clusters of triangles to reduce overdraw.
+    ///
+    /// Clusters are split up further wherever that doesn't raise their ACMR by more than this
+    /// factor. For example, `Some(1.05)` allows the vertex cache efficiency to get up to 5% worse
+    /// in exchange for smaller clusters, which can be sorted more effectively.
+    ///
+    /// `None` keeps the order that is best for the vertex cache.
+    pub overdraw_threshold: Option<f32>,
+    /// Reorder the vertices in the order that the triangles first use them
+    pub reorder_vertices: bool,
+}
+
+impl Default for OptimizeConfig {
+    fn default() -> Self {
+        OptimizeConfig {
+            vertex_cache_size: 16,
+            overdraw_threshold: None,
+            reorder_vertices: true,
+        }
+    }
+}
+
+/// How well the mesh used the vertex cache before and after optimizing it.
+///
+/// ACMR (average cache miss ratio) is the number of vertices that need to be transformed per
+/// triangle. It ranges from 3 (no vertex is ever re-used) down to about 0.5 for large, regular
+/// meshes, and lower is better.
+#[derive(Debug, PartialEq)]
+pub struct OptimizeReport {
+    pub acmr_before: f32,
+    pub acmr_after: f32,
+}
+
+/// An error while optimizing a mesh
+#[derive(Debug, Fail)]
+pub enum OptimizeError {
+    #[fail(display = "Could not optimize the mesh: {}", _0)]
+    VertexData(#[cause] VertexDataError),
+}
+
+impl From<VertexDataError> for OptimizeError {
+    fn from(error: VertexDataError) -> Self {
+        OptimizeError::VertexData(error)
+    }
+}
+
+impl BlenderMesh {
+    /// Reorder the mesh's triangles and vertices for faster rendering.
+    ///
+    /// This needs to happen after both [`BlenderMesh.combine_vertex_indices`] and
+    /// [`BlenderMesh.triangulate`].
+    ///
+    /// [`BlenderMesh.combine_vertex_indices`]: struct.BlenderMesh.html#method.combine_vertex_indices
+    /// [`BlenderMesh.triangulate`]: struct.BlenderMesh.html#method.triangulate
+    pub fn optimize(&mut self, config: &OptimizeConfig) -> Result<OptimizeReport, OptimizeError> {
+        self.vertex_data.single_index_mut_or_err()?.optimize(config)
+    }
+}
+
+impl SingleIndexVertexData {
+    pub(crate) fn optimize(
+        &mut self,
+        config: &OptimizeConfig,
+    ) -> Result<OptimizeReport, OptimizeError> {
+        ensure_triangulated(&self.vertices_in_each_face)?;
+
+        let cache_size = config.vertex_cache_size.max(1);
+
+        let acmr_before = acmr(&self.indices, cache_size);
+
+        let vertex_count = self.positions.vertex_count();
+        let clusters = tipsify(&self.indices, vertex_count, cache_size);
+        let mut triangles: Vec<usize> = clusters.iter().flatten().cloned().collect();
+
+        if let Some(threshold) = config.overdraw_threshold {
+            let clusters = split_clusters(&self.indices, &clusters, cache_size, threshold);
+            triangles = self.sort_clusters_outside_in(clusters);
+        }
+
+        // Keep the faces that use each material together, so that they can still be drawn with
+        // one draw call per material
+        if let Some(face_materials) = self.face_materials.as_ref() {
+            triangles.sort_by_key(|triangle| face_materials[*triangle]);
+            self.face_materials = Some(
+                triangles
+                    .iter()
+                    .map(|triangle| face_materials[*triangle])
+                    .collect(),
+            );
+        }
+
+        self.indices = triangles
+            .iter()
+            .flat_map(|triangle| self.indices[triangle * 3..triangle * 3 + 3].to_vec())
+            .collect();
+
+        if config.reorder_vertices {
+            self.reorder_vertices_for_fetch();
+        }
+
+        Ok(OptimizeReport {
+            acmr_before,
+            acmr_after: acmr(&self.indices, cache_size),
+        })
+    }
+
+    /// Draw the clusters that face away from the center of the mesh first, since they're the
+    /// most likely to occlude the rest of the mesh.
+    fn sort_clusters_outside_in(&self, clusters: Vec<Vec<usize>>) -> Vec<usize> {
+        let position = |vertex: u32| {
+            let (x, y, z) = self.positions.three_components(vertex as usize);
+            Vector3::new(x, y, z)
+        };
+
+        // Area weighted centroid and normal of each triangle
+        let triangle_info = |triangle: usize| {
+            let a = position(self.indices[triangle * 3]);
+            let b = position(self.indices[triangle * 3 + 1]);
+            let c = position(self.indices[triangle * 3 + 2]);
+
+            let normal = (b - a).cross(&(c - a));
+            let area = normal.norm() / 2.0;
+            (normal, (a + b + c) / 3.0, area)
+        };
+
+        let mut mesh_centroid = Vector3::zeros();
+        let mut mesh_area = 0.0;
+
+        let mut cluster_info = Vec::with_capacity(clusters.len());
+        for cluster in clusters.iter() {
+            let mut normal = Vector3::zeros();
+            let mut centroid = Vector3::zeros();
+            let mut area = 0.0;
+
+            for triangle in cluster.iter() {
+                let (triangle_normal, triangle_centroid, triangle_area) = triangle_info(*triangle);
+                normal += triangle_normal;
+                centroid += triangle_centroid * triangle_area;
+                area += triangle_area;
+            }
+
+            mesh_centroid += centroid;
+            mesh_area += area;
+
+            if area > 0.0 {
+                centroid /= area;
+            }
+            cluster_info.push((normal, centroid));
+        }
+
+        if mesh_area > 0.0 {
+            mesh_centroid /= mesh_area;
+        }
+
+        let mut sort_keys: Vec<(f32, usize)> = cluster_info
+            .iter()
+            .enumerate()
+            .map(|(cluster, (normal, centroid))| {
+                let normal = normal
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(Vector3::zeros);
+                ((centroid - mesh_centroid).dot(&normal), cluster)
+            })
+            .collect();
+
+        // Stable, so clusters that tie keep their vertex cache friendly order
+        sort_keys.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        sort_keys
+            .into_iter()
+            .flat_map(|(_, cluster)| clusters[cluster].clone())
+            .collect()
+    }
+
+    /// Renumber the vertices in the order that the index buffer first uses them. Vertices that
+    /// no triangle uses are moved to the end.
+    fn reorder_vertices_for_fetch(&mut self) {
+        let vertex_count = self.positions.vertex_count();
+
+        let mut new_indices: Vec<Option<u32>> = vec![None; vertex_count];
+        let mut old_vertices = Vec::with_capacity(vertex_count);
+
+        for index in self.indices.iter_mut() {
+            let new_index = new_indices[*index as usize].get_or_insert_with(|| {
+                old_vertices.push(*index as usize);
+                (old_vertices.len() - 1) as u32
+            });
+            *index = *new_index;
+        }
+
+        for (old_vertex, new_index) in new_indices.iter().enumerate() {
+            if new_index.is_none() {
+                old_vertices.push(old_vertex);
+            }
+        }
+
+        let mut attributes = vec![&mut self.positions, &mut self.normals];
+        attributes.extend(self.uvs.as_mut());
+        attributes.extend(self.tangents.as_mut());
+        for target in self.morph_targets.iter_mut() {
+            attributes.push(&mut target.position_deltas);
+            attributes.push(&mut target.normal_deltas);
+        }
+
+        for attribute in attributes {
+            *attribute = reorder_attribute(attribute, &old_vertices);
+        }
+
+        if let Some(bones) = self.bones.as_mut() {
+            let bones_per_vertex = bones.bones_per_vertex as usize;
+
+            bones.bone_indices = reorder(&bones.bone_indices, bones_per_vertex, &old_vertices);
+            bones.bone_weights = reorder(&bones.bone_weights, bones_per_vertex, &old_vertices);
+        }
+    }
+}
+
+/// Average cache miss ratio - the number of vertices transformed per triangle with a FIFO vertex
+/// cache of the given size.
+pub(crate) fn acmr(indices: &[u32], cache_size: usize) -> f32 {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return 0.0;
+    }
+
+    cache_misses(indices, cache_size).iter().sum::<u32>() as f32 / triangle_count as f32
+}
+
+/// The number of vertices that missed a FIFO vertex cache for each triangle
+fn cache_misses(indices: &[u32], cache_size: usize) -> Vec<u32> {
+    let mut cache = std::collections::VecDeque::with_capacity(cache_size);
+
+    indices
+        .chunks(3)
+        .map(|triangle| {
+            let mut misses = 0;
+
+            for vertex in triangle {
+                if !cache.contains(vertex) {
+                    misses += 1;
+
+                    if cache.len() == cache_size {
+                        cache.pop_front();
+                    }
+                    cache.push_back(*vertex);
+                }
+            }
+
+            misses
+        })
+        .collect()
+}
+
+/// Order triangles for a vertex cache of `cache_size` entries.
+///
+/// We fan around one vertex at a time, emitting all of its remaining triangles, then move on to
+/// whichever of the vertices that we just used will still be in the cache once its remaining
+/// triangles have been emitted.
+///
+/// When none of them will be we have to jump somewhere else in the mesh, which starts a new
+/// cluster of triangles.
+fn tipsify(indices: &[u32], vertex_count: usize, cache_size: usize) -> Vec<Vec<usize>> {
+    let triangle_count = indices.len() / 3;
+
+    let mut vertex_triangles: Vec<Vec<usize>> = vec![vec![]; vertex_count];
+    for (corner, vertex) in indices.iter().enumerate() {
+        vertex_triangles[*vertex as usize].push(corner / 3);
+    }
+
+    // The number of triangles that each vertex is a part of that haven't been emitted yet
+    let mut live_triangles: Vec<usize> = vertex_triangles.iter().map(Vec::len).collect();
+    // When each vertex was last put into the cache
+    let mut cache_time_stamps = vec![0; vertex_count];
+    let mut time_stamp = cache_size + 1;
+
+    let mut emitted = vec![false; triangle_count];
+    let mut dead_end_stack = vec![];
+    let mut cursor = 0;
+
+    let mut clusters: Vec<Vec<usize>> = vec![];
+    let mut cluster = vec![];
+
+    let mut fanning_vertex = match indices.first() {
+        Some(vertex) => *vertex as usize,
+        None => return clusters,
+    };
+
+    loop {
+        let mut candidates = vec![];
+
+        for triangle in vertex_triangles[fanning_vertex].iter() {
+            if emitted[*triangle] {
+                continue;
+            }
+
+            for vertex in indices[triangle * 3..triangle * 3 + 3].iter() {
+                let vertex = *vertex as usize;
+
+                dead_end_stack.push(vertex);
+                candidates.push(vertex);
+                live_triangles[vertex] -= 1;
+
+                if time_stamp - cache_time_stamps[vertex] > cache_size {
+                    cache_time_stamps[vertex] = time_stamp;
+                    time_stamp += 1;
+                }
+            }
+
+            emitted[*triangle] = true;
+            cluster.push(*triangle);
+        }
+
+        // The candidate that will still be in the cache after its remaining triangles have been
+        // emitted, and has been in the cache for the longest.
+        let mut next_vertex = None;
+        let mut best_priority = 0;
+        for candidate in candidates {
+            if live_triangles[candidate] == 0 {
+                continue;
+            }
+
+            let age = time_stamp - cache_time_stamps[candidate];
+            let priority = if age + 2 * live_triangles[candidate] <= cache_size {
+                age
+            } else {
+                0
+            };
+
+            if next_vertex.is_none() || priority > best_priority {
+                best_priority = priority;
+                next_vertex = Some(candidate);
+            }
+        }
+
+        fanning_vertex = match next_vertex {
+            Some(vertex) => vertex,
+            None => {
+                clusters.push(std::mem::take(&mut cluster));
+
+                match skip_dead_end(&live_triangles, &mut dead_end_stack, &mut cursor) {
+                    Some(vertex) => vertex,
+                    None => break,
+                }
+            }
+        };
+    }
+
+    clusters
+}
+
+/// Find a recently used vertex that still has triangles left, or failing that the next vertex
+/// in the mesh that does.
+fn skip_dead_end(
+    live_triangles: &[usize],
+    dead_end_stack: &mut Vec<usize>,
+    cursor: &mut usize,
+) -> Option<usize> {
+    while let Some(vertex) = dead_end_stack.pop() {
+        if live_triangles[vertex] > 0 {
+            return Some(vertex);
+        }
+    }
+
+    while *cursor < live_triangles.len() {
+        if live_triangles[*cursor] > 0 {
+            return Some(*cursor);
+        }
+        *cursor += 1;
+    }
+
+    None
+}
+
+/// Split clusters wherever the part of the cluster so far has an ACMR within `threshold` times
+/// the ACMR of the whole cluster.
+fn split_clusters(
+    indices: &[u32],
+    clusters: &[Vec<usize>],
+    cache_size: usize,
+    threshold: f32,
+) -> Vec<Vec<usize>> {
+    let ordered: Vec<u32> = clusters
+        .iter()
+        .flatten()
+        .flat_map(|triangle| indices[triangle * 3..triangle * 3 + 3].to_vec())
+        .collect();
+    let misses = cache_misses(&ordered, cache_size);
+
+    let mut split = vec![];
+    let mut start = 0;
+
+    for cluster in clusters.iter() {
+        let cluster_misses = &misses[start..start + cluster.len()];
+        start += cluster.len();
+
+        let cluster_acmr = cluster_misses.iter().sum::<u32>() as f32 / cluster.len() as f32;
+
+        let mut sub_cluster = vec![];
+        let mut sub_cluster_misses = 0;
+
+        for (triangle, misses) in cluster.iter().zip(cluster_misses.iter()) {
+            sub_cluster.push(*triangle);
+            sub_cluster_misses += misses;
+
+            if sub_cluster_misses as f32 / sub_cluster.len() as f32 <= cluster_acmr * threshold {
+                split.push(std::mem::take(&mut sub_cluster));
+                sub_cluster_misses = 0;
+            }
+        }
+
+        if !sub_cluster.is_empty() {
+            split.push(sub_cluster);
+        }
+    }
+
+    split
+}
+
+pub(crate) fn reorder_attribute(
+    attribute: &VertexAttribute,
+    old_vertices: &[usize],
+) -> VertexAttribute {
+    VertexAttribute::new(
+        reorder(attribute.data(), attribute.size() as usize, old_vertices),
+        attribute.size(),
+    )
+}
+
+/// Rearrange per vertex data so that the `n`th vertex is the old `old_vertices[n]`th vertex
+pub(crate) fn reorder<T: Copy>(data: &[T], per_vertex: usize, old_vertices: &[usize]) -> Vec<T> {
+    old_vertices
+        .iter()
+        .flat_map(|old| {
+            data[old * per_vertex..(old + 1) * per_vertex]
+                .iter()
+                .cloned()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::vertex_data::BoneAttributes;
+    use std::collections::HashSet;
+
+    /// A `size` x `size` grid of quads, each split into two triangles, with the triangles
+    /// shuffled so that they have poor vertex cache locality.
+    fn shuffled_grid(size: u32) -> SingleIndexVertexData {
+        let mut single = grid(size, |_, _| 0.0);
+
+        // Walk the triangles with a stride that's coprime with the triangle count
+        let triangles: Vec<&[u32]> = single.indices.chunks(3).collect();
+        let count = triangles.len();
+        let shuffled: Vec<u32> = (0..count)
+            .flat_map(|idx| triangles[(idx * 37) % count].to_vec())
+            .collect();
+        single.indices = shuffled;
+
+        let vertex_count = single.positions.vertex_count();
+        single.uvs = Some(attrib2(
+            (0..vertex_count)
+                .flat_map(|v| vec![v as f32, 0.0])
+                .collect(),
+        ));
+        single.bones = Some(BoneAttributes {
+            bones_per_vertex: 1,
+            bone_indices: (0..vertex_count).map(|v| v as u8).collect(),
+            bone_weights: vec![1.0; vertex_count],
+        });
+
+        single
+    }
+
+    /// Every triangle, as the positions of its corners, so that we can compare triangles across
+    /// reordered vertices
+    fn triangle_positions(single: &SingleIndexVertexData) -> Vec<Vec<(i32, i32, i32)>> {
+        let mut triangles: Vec<Vec<(i32, i32, i32)>> = single
+            .indices
+            .chunks(3)
+            .map(|triangle| {
+                triangle
+                    .iter()
+                    .map(|vertex| {
+                        let (x, y, z) = single.positions.three_components(*vertex as usize);
+                        (x as i32, y as i32, z as i32)
+                    })
+                    .collect()
+            })
+            .collect();
+        triangles.sort();
+        triangles
+    }
+
+    #[test]
+    fn acmr_of_a_strip() {
+        // Every triangle after the first only needs one new vertex
+        let strip = vec![0, 1, 2, 1, 3, 2, 2, 3, 4, 3, 5, 4];
+        assert_eq!(acmr(&strip, 16), 1.5);
+
+        // With a cache that holds only 1 vertex nearly every vertex is a miss
+        assert_eq!(acmr(&strip, 1), 2.75);
+
+        assert_eq!(acmr(&[], 16), 0.0);
+    }
+
+    /// Optimizing improves the ACMR and keeps every triangle, with its winding order
+    #[test]
+    fn optimizing_improves_acmr() {
+        let mut single = shuffled_grid(16);
+        let original_triangles = triangle_positions(&single);
+
+        let report = single.optimize(&OptimizeConfig::default()).unwrap();
+
+        assert!(report.acmr_before > 2.0);
+        assert!(report.acmr_after < 1.0, "{:?}", report);
+        assert_eq!(report.acmr_after, acmr(&single.indices, 16));
+
+        assert_eq!(triangle_positions(&single), original_triangles);
+    }
+
+    /// After reordering, vertices are numbered in the order that the triangles first use them
+    /// and every attribute moves along with its vertex
+    #[test]
+    fn vertices_are_reordered_for_fetch_locality() {
+        let mut single = shuffled_grid(4);
+        let original = triangle_positions(&single);
+
+        single.optimize(&OptimizeConfig::default()).unwrap();
+
+        let mut seen = HashSet::new();
+        let mut next_new_vertex = 0;
+        for vertex in single.indices.iter() {
+            if seen.insert(*vertex) {
+                assert_eq!(*vertex, next_new_vertex);
+                next_new_vertex += 1;
+            }
+        }
+
+        assert_eq!(triangle_positions(&single), original);
+
+        // The uvs and bones of every vertex were set up to match its original position
+        for vertex in 0..single.positions.vertex_count() {
+            let (x, y, _) = single.positions.three_components(vertex);
+            let original_vertex = y * 5.0 + x;
+
+            assert_eq!(
+                single.uvs.as_ref().unwrap().two_components(vertex).0,
+                original_vertex
+            );
+            assert_eq!(
+                single.bones.as_ref().unwrap().bone_indices[vertex],
+                original_vertex as u8
+            );
+        }
+    }
+
+    /// Vertices are only reordered when asked to
+    #[test]
+    fn vertices_keep_their_order() {
+        let mut single = shuffled_grid(4);
+        let positions = single.positions.clone();
+
+        let config = OptimizeConfig {
+            reorder_vertices: false,
+            ..OptimizeConfig::default()
+        };
+        single.optimize(&config).unwrap();
+
+        assert_eq!(single.positions, positions);
+    }
+
+    /// Reordering for overdraw keeps every triangle and draws outward facing clusters first
+    #[test]
+    fn overdraw_clusters_are_sorted_outside_in() {
+        // Two disconnected triangles, one on each side of the mesh's center. The first faces
+        // towards the center (so it's hidden behind the other one), the second faces away.
+        let mut single = SingleIndexVertexData {
+            vertices_in_each_face: vec![3, 3],
+            indices: vec![0, 1, 2, 3, 4, 5],
+            positions: attrib3(vec![
+                0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0, 1.0, 1.0, // Facing +z at z = 1
+                0.0, 0.0, 2.0, 1.0, 0.0, 2.0, 0.0, 1.0, 2.0, // Facing +z at z = 2
+            ]),
+            normals: attrib3(vec![0.0; 18]),
+            ..SingleIndexVertexData::default()
+        };
+
+        let config = OptimizeConfig {
+            overdraw_threshold: Some(1.05),
+            reorder_vertices: false,
+            ..OptimizeConfig::default()
+        };
+        single.optimize(&config).unwrap();
+
+        assert_eq!(single.indices, vec![3, 4, 5, 0, 1, 2]);
+
+        let mut single = shuffled_grid(8);
+        let original = triangle_positions(&single);
+        single.optimize(&config).unwrap();
+        assert_eq!(triangle_positions(&single), original);
+    }
+
+    /// A triangle's material and the positions of its corners
+    type MaterialTriangle = (u16, Vec<(i32, i32, i32)>);
+
+    /// Triangles that use the same material stay together, and keep their material
+    #[test]
+    fn faces_stay_grouped_by_material() {
+        let mut single = shuffled_grid(4);
+        single.face_materials = Some((0..32).map(|triangle| triangle % 2).collect());
+
+        let materials = |single: &SingleIndexVertexData| {
+            let mut materials: Vec<MaterialTriangle> = single
+                .face_materials
+                .as_ref()
+                .unwrap()
+                .iter()
+                .zip(single.indices.chunks(3))
+                .map(|(material, triangle)| {
+                    let positions = triangle
+                        .iter()
+                        .map(|vertex| {
+                            let (x, y, z) = single.positions.three_components(*vertex as usize);
+                            (x as i32, y as i32, z as i32)
+                        })
+                        .collect();
+                    (*material, positions)
+                })
+                .collect();
+            materials.sort();
+            materials
+        };
+        let original = materials(&single);
+
+        single.optimize(&OptimizeConfig::default()).unwrap();
+
+        let face_materials = single.face_materials.as_ref().unwrap();
+        assert!(face_materials.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(materials(&single), original);
+    }
+
+    #[test]
+    fn only_single_indexed_triangles_can_be_optimized() {
+        let mut mesh = BlenderMesh::default();
+        match mesh.optimize(&OptimizeConfig::default()) {
+            Err(OptimizeError::VertexData(VertexDataError::MultipleIndices)) => {}
+            _ => unreachable!(),
+        };
+
+        let mut single = shuffled_grid(1);
+        single.vertices_in_each_face = vec![4, 2];
+        match single.optimize(&OptimizeConfig::default()) {
+            Err(OptimizeError::VertexData(VertexDataError::NotTriangulated {
+                face: 0,
+                vertex_count: 4,
+            })) => {}
+            _ => unreachable!(),
+        };
+    }
+}
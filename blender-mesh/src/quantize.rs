@@ -0,0 +1,921 @@
+//! Storing vertex attributes in fewer bytes than `f32`s, for when memory or bandwidth matters
+//! more than precision.
+//!
+//! - Positions become normalized `i16`s relative to the bounds of the mesh's vertices
+//! - Normals and tangents are octahedral encoded into two normalized `i16`s or `i8`s
+//! - Uvs become half floats, or normalized `u16`s relative to the range of the uvs
+//! - Bone weights become normalized `u8`s or `u16`s that still sum to one
+//!
+//! Every quantized attribute comes with the [`Decoding`] that turns it back into floats, which
+//! you'll usually do in your vertex shader.
+//!
+//! @see http://jcgt.org/published/0003/02/01/
+//!
+//! [`Decoding`]: enum.Decoding.html
+
+use crate::bounding_box::BoundingBox;
+use crate::interleave::ComponentType;
+use crate::vertex_data::{SingleIndexVertexData, VertexAttribute, VertexDataError};
+use crate::BlenderMesh;
+use nalgebra::Vector3;
+
+/// How to store each attribute when quantizing a mesh.
+///
+/// The defaults are the most compact encodings that are precise enough for most meshes.
+#[derive(Debug, Default)]
+pub struct QuantizeConfig {
+    pub positions: PositionEncoding,
+    pub normals: DirectionEncoding,
+    pub tangents: DirectionEncoding,
+    pub uvs: UvEncoding,
+    pub bone_weights: WeightEncoding,
+}
+
+/// How to store positions
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PositionEncoding {
+    F32,
+    /// Normalized `i16`s that span the mesh's bounding box
+    #[default]
+    I16,
+}
+
+/// How to store normals and tangents
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum DirectionEncoding {
+    F32,
+    /// Two normalized `i16`s
+    #[default]
+    Octahedral16,
+    /// Two normalized `i8`s
+    Octahedral8,
+}
+
+/// How to store uvs
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum UvEncoding {
+    F32,
+    /// Half floats. These are most precise near 0, so they lose precision for uvs that wrap
+    /// around a texture many times.
+    F16,
+    /// Normalized `u16`s that span the range of the mesh's uvs
+    #[default]
+    U16,
+}
+
+/// How to store bone weights
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum WeightEncoding {
+    F32,
+    U16,
+    /// Normalized `u8`s
+    #[default]
+    U8,
+}
+
+/// The components of a quantized attribute
+#[derive(Debug, PartialEq, Clone)]
+pub enum QuantizedData {
+    F32(Vec<f32>),
+    /// The bits of each half float
+    F16(Vec<u16>),
+    I16(Vec<i16>),
+    U16(Vec<u16>),
+    I8(Vec<i8>),
+    U8(Vec<u8>),
+}
+
+impl QuantizedData {
+    /// The type that each component is stored as
+    pub fn component_type(&self) -> ComponentType {
+        match self {
+            QuantizedData::F32(_) => ComponentType::F32,
+            QuantizedData::F16(_) => ComponentType::F16,
+            QuantizedData::I16(_) => ComponentType::I16,
+            QuantizedData::U16(_) => ComponentType::U16,
+            QuantizedData::I8(_) => ComponentType::I8,
+            QuantizedData::U8(_) => ComponentType::U8,
+        }
+    }
+
+    /// Every component as little endian bytes, ready to be uploaded to the GPU
+    pub fn to_le_bytes(&self) -> Vec<u8> {
+        match self {
+            QuantizedData::F32(data) => data.iter().flat_map(|c| c.to_le_bytes()).collect(),
+            QuantizedData::F16(data) | QuantizedData::U16(data) => {
+                data.iter().flat_map(|c| c.to_le_bytes()).collect()
+            }
+            QuantizedData::I16(data) => data.iter().flat_map(|c| c.to_le_bytes()).collect(),
+            QuantizedData::I8(data) => data.iter().flat_map(|c| c.to_le_bytes()).collect(),
+            QuantizedData::U8(data) => data.clone(),
+        }
+    }
+
+    /// Every component as the float that a GPU would read, normalizing integers the same way
+    /// that `vertexAttribPointer` does.
+    fn to_f32(&self) -> Vec<f32> {
+        match self {
+            QuantizedData::F32(data) => data.clone(),
+            QuantizedData::F16(data) => data.iter().map(|c| f16_to_f32(*c)).collect(),
+            QuantizedData::I16(data) => data
+                .iter()
+                .map(|c| (*c as f32 / i16::MAX as f32).max(-1.0))
+                .collect(),
+            QuantizedData::U16(data) => data.iter().map(|c| *c as f32 / u16::MAX as f32).collect(),
+            QuantizedData::I8(data) => data
+                .iter()
+                .map(|c| (*c as f32 / i8::MAX as f32).max(-1.0))
+                .collect(),
+            QuantizedData::U8(data) => data.iter().map(|c| *c as f32 / u8::MAX as f32).collect(),
+        }
+    }
+}
+
+/// How to turn the components of a quantized attribute back into the original values.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Decoding {
+    /// `original = offset + scale * component` for each component, where integer components
+    /// have already been normalized by the GPU.
+    Linear { offset: Vec<f32>, scale: Vec<f32> },
+    /// The first two components are an octahedral encoded unit vector.
+    ///
+    /// ```glsl
+    /// vec3 decode(vec2 e) {
+    ///     vec3 v = vec3(e.xy, 1.0 - abs(e.x) - abs(e.y));
+    ///     if (v.z < 0.0) v.xy = (1.0 - abs(v.yx)) * sign(v.xy);
+    ///     return normalize(v);
+    /// }
+    /// ```
+    ///
+    /// Tangents with a handedness have a third component with the handedness (`-1.0` or `1.0`).
+    Octahedral,
+}
+
+/// One quantized vertex attribute
+#[derive(Debug, PartialEq, Clone)]
+pub struct QuantizedAttribute {
+    pub data: QuantizedData,
+    /// The number of components per vertex
+    pub components: u8,
+    /// Whether integer components need to be normalized when they're read, i.e. the
+    /// `normalized` argument to `vertexAttribPointer`
+    pub normalized: bool,
+    pub decoding: Decoding,
+}
+
+impl QuantizedAttribute {
+    /// Decode every component back into floats, the same way that a shader would.
+    pub fn dequantize(&self) -> Vec<f32> {
+        let components = self.components as usize;
+        let data = self.data.to_f32();
+
+        match &self.decoding {
+            Decoding::Linear { offset, scale } => data
+                .chunks(components)
+                .flat_map(|vertex| {
+                    vertex
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, component)| offset[idx] + scale[idx] * component)
+                        .collect::<Vec<f32>>()
+                })
+                .collect(),
+            Decoding::Octahedral => data
+                .chunks(components)
+                .flat_map(|vertex| {
+                    let mut decoded = octahedral_decode(vertex[0], vertex[1]).to_vec();
+                    decoded.extend_from_slice(&vertex[2..]);
+                    decoded
+                })
+                .collect(),
+        }
+    }
+
+    fn unquantized(attribute: &VertexAttribute) -> QuantizedAttribute {
+        let components = attribute.size() as usize;
+
+        QuantizedAttribute {
+            data: QuantizedData::F32(attribute.data().clone()),
+            components: components as u8,
+            normalized: false,
+            decoding: Decoding::Linear {
+                offset: vec![0.0; components],
+                scale: vec![1.0; components],
+            },
+        }
+    }
+}
+
+/// A mesh's vertex attributes after quantization.
+///
+/// These are in the same order as the mesh's vertices, so the mesh's index buffer still applies.
+#[derive(Debug, PartialEq)]
+pub struct QuantizedVertexData {
+    pub positions: QuantizedAttribute,
+    pub normals: QuantizedAttribute,
+    pub uvs: Option<QuantizedAttribute>,
+    pub tangents: Option<QuantizedAttribute>,
+    pub bone_weights: Option<QuantizedAttribute>,
+}
+
+/// An error while quantizing a mesh
+#[derive(Debug, Fail)]
+pub enum QuantizeError {
+    #[fail(display = "Could not quantize the mesh: {}", _0)]
+    VertexData(#[cause] VertexDataError),
+}
+
+impl From<VertexDataError> for QuantizeError {
+    fn from(error: VertexDataError) -> Self {
+        QuantizeError::VertexData(error)
+    }
+}
+
+impl BlenderMesh {
+    /// Quantize the mesh's vertex attributes into more compact encodings.
+    ///
+    /// Positions and uvs are quantized relative to the bounds of the vertices that the indices
+    /// use. Unused vertices (such as the placeholders left behind by `combine_vertex_indices`)
+    /// are written as zeros.
+    pub fn quantize(&self, config: &QuantizeConfig) -> Result<QuantizedVertexData, QuantizeError> {
+        let single = self.single_index_vertex_data_or_err()?;
+        let indexed = single.indexed_vertices();
+
+        let positions = match config.positions {
+            PositionEncoding::F32 => QuantizedAttribute::unquantized(&single.positions),
+            PositionEncoding::I16 => quantize_positions(single, &indexed),
+        };
+
+        let quantize_directions = |attribute: &VertexAttribute, encoding| match encoding {
+            DirectionEncoding::F32 => QuantizedAttribute::unquantized(attribute),
+            DirectionEncoding::Octahedral16 => quantize_directions(attribute, i16::MAX),
+            DirectionEncoding::Octahedral8 => quantize_directions(attribute, i8::MAX as i16),
+        };
+
+        let uvs = single.uvs.as_ref().map(|uvs| match config.uvs {
+            UvEncoding::F32 => QuantizedAttribute::unquantized(uvs),
+            UvEncoding::F16 => QuantizedAttribute {
+                data: QuantizedData::F16(
+                    uvs.data()
+                        .chunks(2)
+                        .zip(indexed.iter())
+                        .flat_map(|(uv, indexed)| {
+                            uv.iter()
+                                .map(move |uv| f32_to_f16(if *indexed { *uv } else { 0.0 }))
+                        })
+                        .collect(),
+                ),
+                components: 2,
+                normalized: false,
+                decoding: Decoding::Linear {
+                    offset: vec![0.0; 2],
+                    scale: vec![1.0; 2],
+                },
+            },
+            UvEncoding::U16 => quantize_uvs(uvs, &indexed),
+        });
+
+        let bone_weights = single.bones.as_ref().map(|bones| {
+            let bones_per_vertex = bones.bones_per_vertex as usize;
+
+            let data = match config.bone_weights {
+                WeightEncoding::F32 => QuantizedData::F32(bones.bone_weights.clone()),
+                WeightEncoding::U16 => QuantizedData::U16(
+                    quantize_weights(&bones.bone_weights, bones_per_vertex, u16::MAX as u32)
+                        .into_iter()
+                        .map(|weight| weight as u16)
+                        .collect(),
+                ),
+                WeightEncoding::U8 => QuantizedData::U8(
+                    quantize_weights(&bones.bone_weights, bones_per_vertex, u8::MAX as u32)
+                        .into_iter()
+                        .map(|weight| weight as u8)
+                        .collect(),
+                ),
+            };
+
+            QuantizedAttribute {
+                normalized: config.bone_weights != WeightEncoding::F32,
+                data,
+                components: bones_per_vertex as u8,
+                decoding: Decoding::Linear {
+                    offset: vec![0.0; bones_per_vertex],
+                    scale: vec![1.0; bones_per_vertex],
+                },
+            }
+        });
+
+        Ok(QuantizedVertexData {
+            positions,
+            normals: quantize_directions(&single.normals, config.normals),
+            uvs,
+            tangents: single
+                .tangents
+                .as_ref()
+                .map(|tangents| quantize_directions(tangents, config.tangents)),
+            bone_weights,
+        })
+    }
+}
+
+/// Map the bounds of the indexed positions onto -1.0..=1.0 along each axis.
+///
+/// We don't use the mesh's `bounding_box` since it is exported in world space while the positions
+/// are in object space.
+fn quantize_positions(single: &SingleIndexVertexData, indexed: &[bool]) -> QuantizedAttribute {
+    let bounds = BoundingBox::from_positions(&single.indexed_positions()).unwrap_or_default();
+    let (min, max) = (bounds.min_corner.coords, bounds.max_corner.coords);
+
+    let center = (min + max) / 2.0;
+    let half_extent = (max - min) / 2.0;
+
+    let mut data = Vec::with_capacity(single.positions.data().len());
+
+    for (position, indexed) in single.positions.data().chunks(3).zip(indexed.iter()) {
+        for axis in 0..3 {
+            let normalized = if *indexed && half_extent[axis] > 0.0 {
+                (position[axis] - center[axis]) / half_extent[axis]
+            } else {
+                0.0
+            };
+            data.push(snorm(normalized, i16::MAX));
+        }
+    }
+
+    QuantizedAttribute {
+        data: QuantizedData::I16(data),
+        components: 3,
+        normalized: true,
+        decoding: Decoding::Linear {
+            offset: center.iter().cloned().collect(),
+            scale: half_extent.iter().cloned().collect(),
+        },
+    }
+}
+
+/// Octahedral encode each direction into two signed integers between `-max` and `max`, keeping
+/// any fourth component (a tangent's handedness) as a third component.
+fn quantize_directions(attribute: &VertexAttribute, max: i16) -> QuantizedAttribute {
+    let size = attribute.size() as usize;
+    let components = if size == 4 { 3 } else { 2 };
+
+    let mut data = Vec::with_capacity(attribute.vertex_count() * components);
+
+    for vertex in attribute.data().chunks(size) {
+        let direction = Vector3::new(vertex[0], vertex[1], vertex[2]);
+        let [x, y] = octahedral_quantize(direction, max);
+        data.push(x);
+        data.push(y);
+
+        if size == 4 {
+            data.push(if vertex[3] < 0.0 { -max } else { max });
+        }
+    }
+
+    QuantizedAttribute {
+        data: if max == i16::MAX {
+            QuantizedData::I16(data)
+        } else {
+            QuantizedData::I8(data.into_iter().map(|c| c as i8).collect())
+        },
+        components: components as u8,
+        normalized: true,
+        decoding: Decoding::Octahedral,
+    }
+}
+
+/// Map the range of the indexed uvs onto 0.0..=1.0
+fn quantize_uvs(uvs: &VertexAttribute, indexed: &[bool]) -> QuantizedAttribute {
+    let mut min = [f32::INFINITY; 2];
+    let mut max = [f32::NEG_INFINITY; 2];
+
+    for (uv, _) in uvs
+        .data()
+        .chunks(2)
+        .zip(indexed.iter())
+        .filter(|(_, i)| **i)
+    {
+        for axis in 0..2 {
+            min[axis] = min[axis].min(uv[axis]);
+            max[axis] = max[axis].max(uv[axis]);
+        }
+    }
+    if !min[0].is_finite() {
+        min = [0.0; 2];
+        max = [1.0; 2];
+    }
+
+    let scale: Vec<f32> = (0..2).map(|axis| max[axis] - min[axis]).collect();
+
+    let data = uvs
+        .data()
+        .chunks(2)
+        .zip(indexed.iter())
+        .flat_map(|(uv, indexed)| {
+            (0..2)
+                .map(|axis| {
+                    let normalized = if *indexed && scale[axis] > 0.0 {
+                        (uv[axis] - min[axis]) / scale[axis]
+                    } else {
+                        0.0
+                    };
+                    (normalized.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16
+                })
+                .collect::<Vec<u16>>()
+        })
+        .collect();
+
+    QuantizedAttribute {
+        data: QuantizedData::U16(data),
+        components: 2,
+        normalized: true,
+        decoding: Decoding::Linear {
+            offset: min.to_vec(),
+            scale,
+        },
+    }
+}
+
+/// Quantize each vertex's weights to integers out of `max`.
+///
+/// Rounding each weight on its own can leave the weights summing to slightly more or less than
+/// `max`, so we round down and then hand the remainder to the weights that lost the most.
+pub(crate) fn quantize_weights(weights: &[f32], bones_per_vertex: usize, max: u32) -> Vec<u32> {
+    if bones_per_vertex == 0 {
+        return vec![];
+    }
+
+    weights
+        .chunks(bones_per_vertex)
+        .flat_map(|weights| {
+            let scaled: Vec<f32> = weights
+                .iter()
+                .map(|weight| weight.clamp(0.0, 1.0) * max as f32)
+                .collect();
+
+            let mut quantized: Vec<u32> =
+                scaled.iter().map(|weight| weight.floor() as u32).collect();
+
+            let total = scaled.iter().sum::<f32>().round().min(max as f32) as u32;
+            let mut remaining = total.saturating_sub(quantized.iter().sum());
+
+            let mut by_remainder: Vec<usize> = (0..weights.len()).collect();
+            by_remainder.sort_by(|a, b| {
+                let remainder = |idx: usize| scaled[idx] - scaled[idx].floor();
+                remainder(*b)
+                    .partial_cmp(&remainder(*a))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+
+            for idx in by_remainder {
+                if remaining == 0 {
+                    break;
+                }
+                if quantized[idx] < max {
+                    quantized[idx] += 1;
+                    remaining -= 1;
+                }
+            }
+
+            quantized
+        })
+        .collect()
+}
+
+/// A float between -1.0 and 1.0 as a signed normalized integer between `-max` and `max`
+fn snorm(value: f32, max: i16) -> i16 {
+    (value.clamp(-1.0, 1.0) * max as f32).round() as i16
+}
+
+/// Octahedral encode a direction, trying each way of rounding the encoding and keeping the one
+/// that decodes closest to the original direction.
+fn octahedral_quantize(direction: Vector3<f32>, max: i16) -> [i16; 2] {
+    let direction = direction
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(Vector3::z);
+    let [x, y] = octahedral_encode(direction);
+
+    let scale = max as f32;
+    let (floor_x, floor_y) = ((x * scale).floor(), (y * scale).floor());
+
+    let mut best = [snorm(x, max), snorm(y, max)];
+    let mut best_dot = f32::NEG_INFINITY;
+
+    for (offset_x, offset_y) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)].iter() {
+        let candidate = [
+            (floor_x + offset_x).clamp(-scale, scale),
+            (floor_y + offset_y).clamp(-scale, scale),
+        ];
+        let decoded = octahedral_decode(candidate[0] / scale, candidate[1] / scale);
+        let dot = Vector3::from(decoded).dot(&direction);
+
+        if dot > best_dot {
+            best_dot = dot;
+            best = [candidate[0] as i16, candidate[1] as i16];
+        }
+    }
+
+    best
+}
+
+/// Project a unit vector onto an octahedron and unfold it into a square from -1.0 to 1.0
+fn octahedral_encode(direction: Vector3<f32>) -> [f32; 2] {
+    let l1_norm = direction.x.abs() + direction.y.abs() + direction.z.abs();
+    let (x, y) = (direction.x / l1_norm, direction.y / l1_norm);
+
+    if direction.z >= 0.0 {
+        [x, y]
+    } else {
+        [
+            (1.0 - y.abs()) * sign_not_zero(x),
+            (1.0 - x.abs()) * sign_not_zero(y),
+        ]
+    }
+}
+
+fn octahedral_decode(x: f32, y: f32) -> [f32; 3] {
+    let z = 1.0 - x.abs() - y.abs();
+    let (x, y) = if z < 0.0 {
+        (
+            (1.0 - y.abs()) * sign_not_zero(x),
+            (1.0 - x.abs()) * sign_not_zero(y),
+        )
+    } else {
+        (x, y)
+    };
+
+    let decoded = Vector3::new(x, y, z).normalize();
+    [decoded.x, decoded.y, decoded.z]
+}
+
+fn sign_not_zero(value: f32) -> f32 {
+    if value < 0.0 {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+/// The bits of the half float closest to `value`, rounding ties to even
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    // Infinity and NaN
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa == 0 { 0 } else { 0x200 };
+    }
+
+    let half_exponent = exponent - 127 + 15;
+
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // Too small for a normal half float, so we need a subnormal (or zero)
+    if half_exponent <= 0 {
+        if half_exponent < -10 {
+            return sign;
+        }
+
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - half_exponent) as u32;
+        return sign | round_shifted(mantissa, shift) as u16;
+    }
+
+    // Rounding can carry into the exponent, which is still the correctly rounded half float
+    let half = ((half_exponent as u32) << 10) + round_shifted(mantissa, 13);
+    sign | half as u16
+}
+
+/// Shift `value` right by `shift` bits, rounding to the nearest even
+fn round_shifted(value: u32, shift: u32) -> u32 {
+    let shifted = value >> shift;
+    let remainder = value & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+
+    if remainder > halfway || (remainder == halfway && shifted & 1 == 1) {
+        shifted + 1
+    } else {
+        shifted
+    }
+}
+
+pub(crate) fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combine_indices::EASILY_RECOGNIZABLE_NUMBER;
+    use crate::test_utils::*;
+    use crate::vertex_data::{AttributeSize, BoneAttributes, VertexData};
+    use nalgebra::Point3;
+
+    /// Directions spread evenly over the sphere
+    fn sphere_directions(count: usize) -> Vec<f32> {
+        let golden_angle = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+
+        (0..count)
+            .flat_map(|idx| {
+                let z = 1.0 - 2.0 * (idx as f32 + 0.5) / count as f32;
+                let radius = (1.0 - z * z).sqrt();
+                let angle = golden_angle * idx as f32;
+                vec![radius * angle.cos(), radius * angle.sin(), z]
+            })
+            .collect()
+    }
+
+    fn mesh() -> BlenderMesh {
+        let vertex_count = 500;
+
+        let positions: Vec<f32> = sphere_directions(vertex_count)
+            .iter()
+            .enumerate()
+            .map(|(idx, component)| component * [4.0, 1.0, 0.5][idx % 3] + 2.0)
+            .collect();
+
+        let mut tangents = vec![];
+        for (idx, tangent) in sphere_directions(vertex_count).chunks(3).enumerate() {
+            tangents.extend_from_slice(tangent);
+            tangents.push(if idx % 3 == 0 { -1.0 } else { 1.0 });
+        }
+
+        BlenderMesh {
+            bounding_box: BoundingBox {
+                min_corner: Point3::new(-2.0, 1.0, 1.5),
+                max_corner: Point3::new(6.0, 3.0, 2.5),
+            },
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                indices: (0..vertex_count as u32).collect(),
+                vertices_in_each_face: vec![3; vertex_count / 3],
+                positions: attrib3(positions),
+                normals: attrib3(sphere_directions(vertex_count)),
+                uvs: Some(attrib2(
+                    (0..vertex_count * 2)
+                        .map(|idx| (idx as f32 * 0.618).fract() * 3.0 - 1.0)
+                        .collect(),
+                )),
+                tangents: Some(VertexAttribute::new(tangents, AttributeSize::Four)),
+                bones: Some(BoneAttributes {
+                    bones_per_vertex: 3,
+                    bone_indices: vec![0; vertex_count * 3],
+                    bone_weights: (0..vertex_count)
+                        .flat_map(|idx| {
+                            let a = (idx as f32 * 0.37).fract();
+                            let b = (1.0 - a) * (idx as f32 * 0.71).fract();
+                            vec![a, b, 1.0 - a - b]
+                        })
+                        .collect(),
+                }),
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        }
+    }
+
+    fn single(mesh: &BlenderMesh) -> &SingleIndexVertexData {
+        mesh.single_index_vertex_data().unwrap()
+    }
+
+    fn max_error(original: &[f32], decoded: &[f32]) -> f32 {
+        assert_eq!(original.len(), decoded.len());
+
+        original
+            .iter()
+            .zip(decoded.iter())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max)
+    }
+
+    /// The largest angle in degrees between the original and decoded directions
+    fn max_angle(original: &[f32], decoded: &[f32], stride: usize) -> f32 {
+        original
+            .chunks(stride)
+            .zip(decoded.chunks(stride))
+            .map(|(a, b)| {
+                let a = Vector3::new(a[0], a[1], a[2]).normalize();
+                let b = Vector3::new(b[0], b[1], b[2]);
+                // acos loses too much precision for tiny angles
+                a.cross(&b).norm().atan2(a.dot(&b)).to_degrees()
+            })
+            .fold(0.0, f32::max)
+    }
+
+    fn linear_decoding(attribute: &QuantizedAttribute) -> (Vec<f32>, Vec<f32>) {
+        match &attribute.decoding {
+            Decoding::Linear { offset, scale } => (offset.clone(), scale.clone()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Positions are off by at most half of a quantization step across the bounds of the
+    /// positions
+    #[test]
+    fn positions_within_half_a_step() {
+        let mesh = mesh();
+        let quantized = mesh.quantize(&QuantizeConfig::default()).unwrap();
+
+        assert_eq!(
+            quantized.positions.data.component_type(),
+            ComponentType::I16
+        );
+
+        let bounds = BoundingBox::from_positions(single(&mesh).positions.data()).unwrap();
+        let (offset, scale) = linear_decoding(&quantized.positions);
+        let half_extent = (bounds.max_corner - bounds.min_corner) / 2.0;
+        assert!(max_error(&offset, bounds.center().coords.as_slice()) < 1e-6);
+        assert!(max_error(&scale, half_extent.as_slice()) < 1e-6);
+
+        let decoded = quantized.positions.dequantize();
+        let original = single(&mesh).positions.data();
+        for (axis, scale) in scale.iter().enumerate() {
+            let step = scale / i16::MAX as f32;
+            let original: Vec<f32> = original.iter().skip(axis).step_by(3).cloned().collect();
+            let decoded: Vec<f32> = decoded.iter().skip(axis).step_by(3).cloned().collect();
+
+            assert!(max_error(&original, &decoded) <= step * 0.5 + 1e-6);
+        }
+    }
+
+    /// The exported bounding box is in world space, so an object that isn't at the origin still
+    /// quantizes its object space positions without any extra error
+    #[test]
+    fn ignores_the_world_space_bounding_box() {
+        let mut mesh = mesh();
+        let expected = mesh.quantize(&QuantizeConfig::default()).unwrap();
+
+        mesh.bounding_box = BoundingBox {
+            min_corner: Point3::new(100.0, 100.0, 100.0),
+            max_corner: Point3::new(108.0, 102.0, 101.0),
+        };
+        let quantized = mesh.quantize(&QuantizeConfig::default()).unwrap();
+
+        assert_eq!(quantized.positions, expected.positions);
+    }
+
+    /// The placeholders that combining indices leaves in unused vertices don't stretch the
+    /// quantization range and get written as zeros
+    #[test]
+    fn unused_vertices_are_written_as_zeros() {
+        let mut mesh = mesh();
+        let expected = mesh.quantize(&QuantizeConfig::default()).unwrap();
+
+        let single = mesh.vertex_data.single_index_mut_or_err().unwrap();
+        let mut positions = single.positions.data().clone();
+        positions.extend_from_slice(&[EASILY_RECOGNIZABLE_NUMBER; 3]);
+        single.positions = attrib3(positions);
+        let mut uvs = single.uvs.as_ref().unwrap().data().clone();
+        uvs.extend_from_slice(&[EASILY_RECOGNIZABLE_NUMBER; 2]);
+        single.uvs = Some(attrib2(uvs));
+
+        let quantized = mesh.quantize(&QuantizeConfig::default()).unwrap();
+
+        let positions = quantized.positions.dequantize();
+        let (offset, _) = linear_decoding(&quantized.positions);
+        assert_eq!(positions[positions.len() - 3..], offset[..]);
+        assert_eq!(
+            linear_decoding(&quantized.positions),
+            linear_decoding(&expected.positions)
+        );
+
+        let uvs = quantized.uvs.unwrap();
+        match &uvs.data {
+            QuantizedData::U16(data) => assert_eq!(data[data.len() - 2..], [0, 0]),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            linear_decoding(&uvs),
+            linear_decoding(expected.uvs.as_ref().unwrap())
+        );
+    }
+
+    /// Octahedral encoded normals stay within a small angle of the originals
+    #[test]
+    fn octahedral_normals() {
+        let mesh = mesh();
+        let original = single(&mesh).normals.data();
+
+        for (encoding, max_degrees) in [
+            (DirectionEncoding::Octahedral16, 0.01),
+            (DirectionEncoding::Octahedral8, 1.0),
+        ]
+        .iter()
+        {
+            let config = QuantizeConfig {
+                normals: *encoding,
+                ..QuantizeConfig::default()
+            };
+            let normals = mesh.quantize(&config).unwrap().normals;
+
+            assert_eq!(normals.components, 2);
+            assert_eq!(normals.decoding, Decoding::Octahedral);
+
+            let angle = max_angle(original, &normals.dequantize(), 3);
+            assert!(angle < *max_degrees, "{:?} {}", encoding, angle);
+        }
+    }
+
+    /// Tangents keep their handedness as a third component
+    #[test]
+    fn octahedral_tangents_keep_their_handedness() {
+        let mesh = mesh();
+        let original = single(&mesh).tangents.as_ref().unwrap().data();
+
+        let tangents = mesh.quantize(&QuantizeConfig::default()).unwrap().tangents;
+        let tangents = tangents.unwrap();
+        assert_eq!(tangents.components, 3);
+
+        let decoded = tangents.dequantize();
+        assert!(max_angle(original, &decoded, 4) < 0.01);
+
+        let original_handedness: Vec<f32> = original.iter().skip(3).step_by(4).cloned().collect();
+        let decoded_handedness: Vec<f32> = decoded.iter().skip(3).step_by(4).cloned().collect();
+        assert_eq!(decoded_handedness, original_handedness);
+    }
+
+    /// Uvs stored as u16s are off by at most half of a step across the range of the uvs, and
+    /// half floats by at most half of a step at their exponent
+    #[test]
+    fn uvs() {
+        let mesh = mesh();
+        let original = single(&mesh).uvs.as_ref().unwrap().data();
+
+        let uvs = mesh.quantize(&QuantizeConfig::default()).unwrap().uvs;
+        let uvs = uvs.unwrap();
+        let (_, scale) = linear_decoding(&uvs);
+        let step = scale[0].max(scale[1]) / u16::MAX as f32;
+        assert!(max_error(original, &uvs.dequantize()) <= step * 0.5 + 1e-6);
+
+        let config = QuantizeConfig {
+            uvs: UvEncoding::F16,
+            ..QuantizeConfig::default()
+        };
+        let uvs = mesh.quantize(&config).unwrap().uvs.unwrap();
+        assert_eq!(uvs.data.component_type(), ComponentType::F16);
+
+        // Our uvs are all between -1.0 and 2.0, where half floats have 11 bits of precision
+        let step = 2.0 / 2048.0;
+        assert!(max_error(original, &uvs.dequantize()) <= step * 0.5);
+    }
+
+    /// Weights that sum to one still sum to one after quantization
+    #[test]
+    fn bone_weights_sum_to_one() {
+        let mesh = mesh();
+        let original = &single(&mesh).bones.as_ref().unwrap().bone_weights;
+
+        let weights = mesh.quantize(&QuantizeConfig::default()).unwrap();
+        let weights = weights.bone_weights.unwrap();
+
+        let bytes = match &weights.data {
+            QuantizedData::U8(bytes) => bytes.clone(),
+            _ => unreachable!(),
+        };
+        for vertex in bytes.chunks(3) {
+            assert_eq!(vertex.iter().map(|w| *w as u32).sum::<u32>(), 255);
+        }
+
+        assert!(max_error(original, &weights.dequantize()) <= 1.0 / 255.0);
+    }
+
+    #[test]
+    fn half_floats() {
+        for value in [0.0, 1.0, -2.5, 0.333_333_3, 65504.0, 1e-6, -1e-7, 1e10].iter() {
+            let half = f32_to_f16(*value);
+            let relative_error = ((f16_to_f32(half) - value) / value).abs();
+
+            if value.abs() < 6.1e-5 || value.abs() > 65504.0 {
+                continue;
+            }
+            assert!(relative_error <= 1.0 / 2048.0, "{}", value);
+        }
+
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(-2.0), 0xc000);
+        assert_eq!(f32_to_f16(65504.0), 0x7bff);
+        assert_eq!(f32_to_f16(1e10), 0x7c00);
+        assert_eq!(f32_to_f16(5.960_464_5e-8), 0x0001);
+        assert_eq!(f32_to_f16(0.0), 0);
+    }
+
+    #[test]
+    fn multi_indexed_meshes_can_not_be_quantized() {
+        match BlenderMesh::default().quantize(&QuantizeConfig::default()) {
+            Err(QuantizeError::VertexData(VertexDataError::MultipleIndices)) => {}
+            _ => unreachable!(),
+        };
+    }
+}
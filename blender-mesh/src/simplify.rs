@@ -0,0 +1,1100 @@
+//! Reducing the number of triangles in a mesh by collapsing edges, so that we can generate
+//! levels of detail instead of authoring them by hand.
+//!
+//! Every vertex has a quadric that measures the squared distance to the planes of the triangles
+//! that it used to be a part of. We repeatedly collapse whichever vertex would move the surface
+//! the least onto one of its neighbours.
+//!
+//! Collapsed vertices are moved onto one of their neighbouring vertices (rather than some new
+//! position in between) so the vertices that are left keep their exact uvs, normals, tangents
+//! and bone weights. This lets every level of detail share the mesh's vertex buffers.
+//!
+//! Several vertices can share a position, such as along uv seams or the hard edges of flat
+//! shaded meshes. They're collapsed together, each onto the vertex at the new position that it
+//! (or another vertex with the same uvs) shares an edge with, so seams stay intact.
+//!
+//! Vertices on the boundaries between materials never move.
+//!
+//! @see https://www.cs.cmu.edu/~./garland/Papers/quadrics.pdf
+
+use crate::combine_indices::IndexBuffer;
+use crate::draw_group::{draw_groups, DrawGroup};
+use crate::half_edge::sorted_edge;
+use crate::vertex_data::{ensure_triangulated, SingleIndexVertexData, VertexDataError};
+use crate::BlenderMesh;
+use nalgebra::{Matrix4, Vector3, Vector4};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Border edges constrain the vertices along them more strongly than faces do, so that the
+/// silhouette of open meshes doesn't get eaten away.
+static BORDER_WEIGHT: f64 = 10.0;
+
+/// Configuration for [`BlenderMesh.simplify`] and [`BlenderMesh.lod_chain`]
+///
+/// [`BlenderMesh.simplify`]: struct.BlenderMesh.html#method.simplify
+/// [`BlenderMesh.lod_chain`]: struct.BlenderMesh.html#method.lod_chain
+#[derive(Debug)]
+pub struct SimplifyConfig {
+    /// Don't move vertices on the border of an open mesh
+    pub lock_borders: bool,
+    /// The furthest apart that two vertices' bone weights can be for one to be collapsed onto the
+    /// other.
+    ///
+    /// This is the total weight that would need to move between bones to turn one vertex's
+    /// weights into the other's, so `0.0` only collapses vertices with the exact same weights
+    /// and `1.0` ignores bone weights entirely.
+    pub max_bone_weight_change: f32,
+}
+
+impl Default for SimplifyConfig {
+    fn default() -> Self {
+        SimplifyConfig {
+            lock_borders: false,
+            max_bone_weight_change: 0.25,
+        }
+    }
+}
+
+/// How far to simplify a mesh.
+///
+/// We stop simplifying as soon as either of these are reached.
+#[derive(Debug)]
+pub struct LodTarget {
+    /// The fraction of the mesh's triangles to keep, from `0.0` to `1.0`
+    pub triangle_ratio: f32,
+    /// The largest geometric error that we'll introduce, in the same units as the mesh's
+    /// positions. `None` to only simplify until we reach the `triangle_ratio`.
+    pub max_error: Option<f32>,
+}
+
+impl Default for LodTarget {
+    fn default() -> Self {
+        LodTarget {
+            triangle_ratio: 0.5,
+            max_error: None,
+        }
+    }
+}
+
+/// One level of detail of a mesh, which indexes into the mesh's vertex data.
+#[derive(Debug, PartialEq)]
+pub struct Lod {
+    /// The triangles of this level of detail, with the same index width as the mesh
+    pub index_buffer: IndexBuffer,
+    /// The range of the index buffer that uses each material.
+    ///
+    /// Triangles keep their order while simplifying, so call
+    /// [`BlenderMesh.split_by_material`] first for one draw group per material.
+    ///
+    /// [`BlenderMesh.split_by_material`]: struct.BlenderMesh.html#method.split_by_material
+    pub draw_groups: Vec<DrawGroup>,
+    /// Roughly the furthest that the simplified surface has moved away from the original
+    /// surface, in the same units as the mesh's positions.
+    ///
+    /// Project this onto the screen to pick a level of detail based on screen space error.
+    pub error: f32,
+}
+
+/// An error while simplifying a mesh
+#[derive(Debug, Fail)]
+pub enum SimplifyError {
+    #[fail(display = "Could not simplify the mesh: {}", _0)]
+    VertexData(#[cause] VertexDataError),
+}
+
+impl From<VertexDataError> for SimplifyError {
+    fn from(error: VertexDataError) -> Self {
+        SimplifyError::VertexData(error)
+    }
+}
+
+impl BlenderMesh {
+    /// Simplify the mesh in place, returning the geometric error that was introduced.
+    ///
+    /// Vertices that are no longer used stay in the vertex data, use [`BlenderMesh.optimize`] to
+    /// move them to the end.
+    ///
+    /// [`BlenderMesh.optimize`]: struct.BlenderMesh.html#method.optimize
+    pub fn simplify(
+        &mut self,
+        target: &LodTarget,
+        config: &SimplifyConfig,
+    ) -> Result<f32, SimplifyError> {
+        let single = self.vertex_data.single_index_mut_or_err()?;
+
+        let mut simplifier = Simplifier::new(single, config)?;
+        simplifier.simplify(target);
+
+        single.indices = simplifier.indices();
+        single.vertices_in_each_face = vec![3; single.indices.len() / 3];
+        single.face_materials = simplifier.face_materials.clone();
+
+        Ok(simplifier.error())
+    }
+
+    /// Generate a chain of increasingly simplified levels of detail.
+    ///
+    /// Each level of detail is simplified further from the previous one, so their triangle
+    /// counts go down and their errors go up in the same order as the `targets`.
+    pub fn lod_chain(
+        &self,
+        targets: &[LodTarget],
+        config: &SimplifyConfig,
+    ) -> Result<Vec<Lod>, SimplifyError> {
+        let single = self.single_index_vertex_data_or_err()?;
+
+        let mut simplifier = Simplifier::new(single, config)?;
+
+        let lods = targets
+            .iter()
+            .map(|target| {
+                simplifier.simplify(target);
+
+                Lod {
+                    index_buffer: IndexBuffer::new(&simplifier.indices(), single.index_width),
+                    draw_groups: draw_groups(
+                        &vec![3; simplifier.triangles.len()],
+                        simplifier.face_materials.as_deref(),
+                        &self.material_slots,
+                    ),
+                    error: simplifier.error(),
+                }
+            })
+            .collect();
+
+        Ok(lods)
+    }
+}
+
+/// A symmetric matrix that measures the weighted sum of squared distances from a point to a set
+/// of planes.
+#[derive(Debug, Clone, Copy)]
+struct Quadric {
+    matrix: Matrix4<f64>,
+    /// The total weight of all of the planes, so that we can turn the error back into a distance
+    weight: f64,
+}
+
+impl Quadric {
+    fn zero() -> Quadric {
+        Quadric {
+            matrix: Matrix4::zeros(),
+            weight: 0.0,
+        }
+    }
+
+    fn add_plane(&mut self, normal: Vector3<f64>, point: Vector3<f64>, weight: f64) {
+        let plane = Vector4::new(normal.x, normal.y, normal.z, -normal.dot(&point));
+
+        self.matrix += plane * plane.transpose() * weight;
+        self.weight += weight;
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        self.matrix += other.matrix;
+        self.weight += other.weight;
+    }
+
+    /// The weighted average squared distance from the point to our planes
+    fn error(&self, point: Vector3<f64>) -> f64 {
+        if self.weight == 0.0 {
+            return 0.0;
+        }
+
+        let point = Vector4::new(point.x, point.y, point.z, 1.0);
+        let error = (point.transpose() * self.matrix * point)[0];
+
+        (error / self.weight).max(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VertexKind {
+    /// Can be collapsed onto any neighbour
+    Manifold,
+    /// On the border of an open mesh, can only be collapsed along the border
+    Border,
+    /// Can't be collapsed. For example, vertices on the boundary between two materials.
+    Locked,
+}
+
+/// A position that would like to be collapsed onto one of its neighbours
+struct Collapse {
+    from: u32,
+    to: u32,
+    /// Each vertex at the `from` position and the vertex at the `to` position that it moves onto
+    moves: Vec<(u32, u32)>,
+    error: f64,
+}
+
+struct Simplifier {
+    positions: Vec<Vector3<f64>>,
+    kinds: Vec<VertexKind>,
+    quadrics: Vec<Quadric>,
+    /// The same number for every vertex that has the same position
+    position_ids: Vec<u32>,
+    /// The uvs of each vertex, which need to match for vertices that share a position to be
+    /// moved onto a vertex that they don't share an edge with
+    uvs: Option<Vec<f32>>,
+    /// The bones and weights that influence each vertex
+    bone_weights: Vec<Vec<(u8, f32)>>,
+    max_bone_weight_change: f32,
+    triangles: Vec<[u32; 3]>,
+    /// The material slot of each triangle, if the mesh has per face materials
+    face_materials: Option<Vec<u16>>,
+    original_triangle_count: usize,
+    /// The largest squared error of any collapse so far
+    error: f64,
+}
+
+impl Simplifier {
+    fn new(
+        single: &SingleIndexVertexData,
+        config: &SimplifyConfig,
+    ) -> Result<Simplifier, SimplifyError> {
+        ensure_triangulated(&single.vertices_in_each_face)?;
+
+        let vertex_count = single.positions.vertex_count();
+
+        let positions: Vec<Vector3<f64>> = (0..vertex_count)
+            .map(|vertex| {
+                let (x, y, z) = single.positions.three_components(vertex);
+                Vector3::new(x as f64, y as f64, z as f64)
+            })
+            .collect();
+
+        let triangles: Vec<[u32; 3]> = single
+            .indices
+            .chunks(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+
+        let position_ids = single.positions.first_vertex_at_same_position();
+
+        // Positions that are used by faces with different materials
+        let mut position_materials = HashMap::new();
+        let mut material_boundaries = HashSet::new();
+        if let Some(face_materials) = single.face_materials.as_ref() {
+            for (triangle, material) in triangles.iter().zip(face_materials.iter()) {
+                for vertex in triangle.iter() {
+                    let position_id = position_ids[*vertex as usize];
+                    if *position_materials.entry(position_id).or_insert(*material) != *material {
+                        material_boundaries.insert(position_id);
+                    }
+                }
+            }
+        }
+
+        let mut kinds: Vec<VertexKind> = (0..vertex_count)
+            .map(|vertex| {
+                if material_boundaries.contains(&position_ids[vertex]) {
+                    VertexKind::Locked
+                } else {
+                    VertexKind::Manifold
+                }
+            })
+            .collect();
+
+        let mut quadrics = vec![Quadric::zero(); vertex_count];
+
+        for triangle in triangles.iter() {
+            let [a, b, c] = triangle_positions(&positions, triangle);
+            let normal = (b - a).cross(&(c - a));
+            let double_area = normal.norm();
+            if double_area == 0.0 {
+                continue;
+            }
+
+            for vertex in triangle.iter() {
+                quadrics[*vertex as usize].add_plane(normal / double_area, a, double_area / 2.0);
+            }
+        }
+
+        let edges = edge_triangles(&triangles, &position_ids);
+        let mut border_edges_per_position = vec![0; vertex_count];
+
+        for triangle_edges in edges.values() {
+            match triangle_edges.as_slice() {
+                [(triangle, from, to)] => {
+                    let [a, b, c] = triangle_positions(&positions, &triangles[*triangle]);
+                    let normal = (b - a).cross(&(c - a));
+
+                    let (from, to) = (*from as usize, *to as usize);
+                    let edge = positions[to] - positions[from];
+
+                    // A plane through the edge that is perpendicular to the triangle
+                    if let Some(perpendicular) = edge.cross(&normal).try_normalize(f64::EPSILON) {
+                        let weight = edge.norm_squared() * BORDER_WEIGHT;
+                        quadrics[from].add_plane(perpendicular, positions[from], weight);
+                        quadrics[to].add_plane(perpendicular, positions[from], weight);
+                    }
+
+                    border_edges_per_position[position_ids[from] as usize] += 1;
+                    border_edges_per_position[position_ids[to] as usize] += 1;
+                }
+                [_, _] => {}
+                // Non manifold edges
+                _ => {
+                    for (_, from, to) in triangle_edges.iter() {
+                        kinds[*from as usize] = VertexKind::Locked;
+                        kinds[*to as usize] = VertexKind::Locked;
+                    }
+                }
+            }
+        }
+
+        // Every vertex at a position is collapsed together, so they all need the same kind
+        let mut position_kinds = HashMap::new();
+        for (vertex, kind) in kinds.iter().enumerate() {
+            if *kind == VertexKind::Locked {
+                position_kinds.insert(position_ids[vertex], VertexKind::Locked);
+            }
+        }
+
+        for (vertex, kind) in kinds.iter_mut().enumerate() {
+            if let Some(position_kind) = position_kinds.get(&position_ids[vertex]) {
+                *kind = *position_kind;
+            }
+
+            let border_edges = border_edges_per_position[position_ids[vertex] as usize];
+            if *kind != VertexKind::Manifold || border_edges == 0 {
+                continue;
+            }
+
+            // Vertices where several borders meet can't move along all of them at once
+            *kind = if config.lock_borders || border_edges > 2 {
+                VertexKind::Locked
+            } else {
+                VertexKind::Border
+            };
+        }
+
+        let bone_weights = match single.bones.as_ref() {
+            Some(bones) => {
+                let bones_per_vertex = bones.bones_per_vertex as usize;
+                (0..vertex_count)
+                    .map(|vertex| {
+                        let range = vertex * bones_per_vertex..(vertex + 1) * bones_per_vertex;
+                        bones.bone_indices[range.clone()]
+                            .iter()
+                            .cloned()
+                            .zip(bones.bone_weights[range].iter().cloned())
+                            .collect()
+                    })
+                    .collect()
+            }
+            None => vec![vec![]; vertex_count],
+        };
+
+        Ok(Simplifier {
+            positions,
+            kinds,
+            quadrics,
+            position_ids,
+            uvs: single.uvs.as_ref().map(|uvs| uvs.data().clone()),
+            bone_weights,
+            max_bone_weight_change: config.max_bone_weight_change,
+            original_triangle_count: triangles.len(),
+            triangles,
+            face_materials: single.face_materials.clone(),
+            error: 0.0,
+        })
+    }
+
+    /// Collapse vertices until we reach the target.
+    ///
+    /// Every pass picks the cheapest collapse for each vertex and then performs them from
+    /// cheapest to most expensive, skipping any that touch a triangle that has already changed
+    /// during this pass.
+    fn simplify(&mut self, target: &LodTarget) {
+        let target_ratio = target.triangle_ratio.clamp(0.0, 1.0) as f64;
+        let target_count = (self.original_triangle_count as f64 * target_ratio) as usize;
+        let max_error = target
+            .max_error
+            .map(|error| (error as f64) * (error as f64))
+            .unwrap_or(f64::INFINITY);
+
+        while self.triangles.len() > target_count {
+            let vertex_triangles = self.vertex_triangles();
+
+            let mut collapses = self.pick_collapses(&vertex_triangles);
+            collapses.sort_by(|a, b| {
+                a.error
+                    .partial_cmp(&b.error)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+
+            // Each collapse removes about two triangles. Don't make collapses that are much
+            // worse than the ones that we need to reach the target, since the next pass might
+            // find cheaper ones.
+            let collapses_needed = (self.triangles.len() - target_count).div_ceil(2);
+            let error_goal = collapses
+                .get(collapses_needed.min(collapses.len()).saturating_sub(1))
+                .map(|collapse| collapse.error * 1.5)
+                .unwrap_or(0.0);
+            let error_limit = max_error.min(error_goal);
+
+            let mut triangle_count = self.triangles.len();
+            let mut touched = vec![false; self.positions.len()];
+            let mut removed = vec![false; self.triangles.len()];
+            let mut collapsed_any = false;
+
+            for collapse in collapses {
+                if triangle_count <= target_count || collapse.error > error_limit {
+                    break;
+                }
+
+                let from_position = self.position_ids[collapse.from as usize];
+                let to_position = self.position_ids[collapse.to as usize];
+                if touched[from_position as usize] || touched[to_position as usize] {
+                    continue;
+                }
+
+                let triangles: Vec<usize> = collapse
+                    .moves
+                    .iter()
+                    .flat_map(|(from, _)| vertex_triangles[*from as usize].iter().cloned())
+                    .collect();
+                if self.collapse_flips_triangle(&triangles, from_position, collapse.to) {
+                    continue;
+                }
+
+                for triangle in triangles {
+                    for vertex in self.triangles[triangle].iter() {
+                        touched[self.position_ids[*vertex as usize] as usize] = true;
+                    }
+
+                    if self.triangle_has_position(triangle, to_position) {
+                        removed[triangle] = true;
+                        triangle_count -= 1;
+                    } else {
+                        for vertex in self.triangles[triangle].iter_mut() {
+                            if let Some((_, to)) =
+                                collapse.moves.iter().find(|(from, _)| from == vertex)
+                            {
+                                *vertex = *to;
+                            }
+                        }
+                    }
+                }
+
+                for (from, to) in collapse.moves.iter() {
+                    let from_quadric = self.quadrics[*from as usize];
+                    self.quadrics[*to as usize].add(&from_quadric);
+                }
+                self.error = self.error.max(collapse.error);
+                collapsed_any = true;
+            }
+
+            if let Some(face_materials) = self.face_materials.as_mut() {
+                let mut removed = removed.iter();
+                face_materials.retain(|_| !removed.next().unwrap());
+            }
+            let mut removed = removed.into_iter();
+            self.triangles.retain(|_| !removed.next().unwrap());
+
+            if !collapsed_any {
+                break;
+            }
+        }
+    }
+
+    /// The cheapest valid collapse for every position that can be collapsed
+    fn pick_collapses(&self, vertex_triangles: &[Vec<usize>]) -> Vec<Collapse> {
+        let border_edges = self.border_edges();
+
+        // The vertices at each position that are still in use
+        let mut vertices_at_position: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for (vertex, triangles) in vertex_triangles.iter().enumerate() {
+            if !triangles.is_empty() {
+                vertices_at_position
+                    .entry(self.position_ids[vertex])
+                    .or_default()
+                    .push(vertex as u32);
+            }
+        }
+
+        let mut collapses = vec![];
+
+        for (from_position, from_vertices) in vertices_at_position.iter() {
+            let from = from_vertices[0];
+            let kind = self.kinds[from as usize];
+            if kind == VertexKind::Locked {
+                continue;
+            }
+
+            let mut quadric = Quadric::zero();
+            for vertex in from_vertices.iter() {
+                quadric.add(&self.quadrics[*vertex as usize]);
+            }
+
+            let mut best: Option<Collapse> = None;
+
+            let neighbours = from_vertices
+                .iter()
+                .flat_map(|vertex| vertex_triangles[*vertex as usize].iter())
+                .flat_map(|triangle| self.triangles[*triangle].iter());
+            for neighbour in neighbours {
+                let to_position = self.position_ids[*neighbour as usize];
+                if to_position == *from_position
+                    || best.as_ref().is_some_and(|best| best.to == to_position)
+                {
+                    continue;
+                }
+
+                if kind == VertexKind::Border
+                    && !border_edges.contains(&sorted_edge(*from_position, to_position))
+                {
+                    continue;
+                }
+
+                let moves = match self.moves(
+                    from_vertices,
+                    &vertices_at_position[&to_position],
+                    vertex_triangles,
+                ) {
+                    Some(moves) => moves,
+                    None => continue,
+                };
+
+                let error = quadric.error(self.positions[*neighbour as usize]);
+                if best.as_ref().is_none_or(|best| error < best.error) {
+                    best = Some(Collapse {
+                        from,
+                        to: to_position,
+                        moves,
+                        error,
+                    });
+                }
+            }
+
+            collapses.extend(best);
+        }
+
+        collapses
+    }
+
+    /// The vertex at the new position that each of the vertices at the old position moves onto,
+    /// or `None` if one of them has nowhere to go.
+    ///
+    /// Vertices move onto the vertex that they share an edge with, so that both sides of a seam
+    /// stay on their own side. Vertices that don't share an edge with the new position follow a
+    /// vertex with the same uvs that does, which lets the hard edges of flat shaded meshes
+    /// collapse.
+    fn moves(
+        &self,
+        from_vertices: &[u32],
+        to_vertices: &[u32],
+        vertex_triangles: &[Vec<usize>],
+    ) -> Option<Vec<(u32, u32)>> {
+        let same_uvs = |a: u32, b: u32| match self.uvs.as_ref() {
+            Some(uvs) => {
+                let uv = |vertex: u32| uvs.get(vertex as usize * 2..vertex as usize * 2 + 2);
+                match (uv(a), uv(b)) {
+                    (Some(a), Some(b)) => a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5),
+                    _ => false,
+                }
+            }
+            None => true,
+        };
+
+        let along_edges: Vec<(u32, Option<u32>)> = from_vertices
+            .iter()
+            .map(|from| {
+                let to = to_vertices.iter().find(|to| {
+                    vertex_triangles[*from as usize]
+                        .iter()
+                        .any(|triangle| self.triangles[*triangle].contains(to))
+                });
+                (*from, to.cloned())
+            })
+            .collect();
+
+        along_edges
+            .iter()
+            .map(|(from, to)| {
+                let to = to.or_else(|| {
+                    along_edges
+                        .iter()
+                        .find(|(other, to)| to.is_some() && same_uvs(*from, *other))
+                        .and_then(|(_, to)| *to)
+                })?;
+
+                if self.bone_weight_change(*from as usize, to as usize)
+                    > self.max_bone_weight_change
+                {
+                    return None;
+                }
+
+                Some((*from, to))
+            })
+            .collect()
+    }
+
+    fn triangle_has_position(&self, triangle: usize, position: u32) -> bool {
+        self.triangles[triangle]
+            .iter()
+            .any(|vertex| self.position_ids[*vertex as usize] == position)
+    }
+
+    /// Whether moving the vertices at the `from` position onto `to` would turn any of their
+    /// other triangles upside down
+    fn collapse_flips_triangle(&self, triangles: &[usize], from: u32, to: u32) -> bool {
+        let to_position = self.position_ids[to as usize];
+
+        triangles.iter().any(|triangle| {
+            if self.triangle_has_position(*triangle, to_position) {
+                return false;
+            }
+            let triangle = &self.triangles[*triangle];
+
+            let [a, b, c] = triangle_positions(&self.positions, triangle);
+            let before = (b - a).cross(&(c - a));
+
+            let mut moved = *triangle;
+            for vertex in moved.iter_mut() {
+                if self.position_ids[*vertex as usize] == from {
+                    *vertex = to;
+                }
+            }
+            let [a, b, c] = triangle_positions(&self.positions, &moved);
+            let after = (b - a).cross(&(c - a));
+
+            before.dot(&after) <= 0.0
+        })
+    }
+
+    /// The total weight that would need to move between bones to turn one vertex's bone weights
+    /// into the other's
+    fn bone_weight_change(&self, from: usize, to: usize) -> f32 {
+        let weight = |weights: &[(u8, f32)], bone: u8| -> f32 {
+            weights
+                .iter()
+                .filter(|(other, _)| *other == bone)
+                .map(|(_, weight)| weight)
+                .sum()
+        };
+
+        let (from, to) = (&self.bone_weights[from], &self.bone_weights[to]);
+
+        let mut bones: Vec<u8> = from
+            .iter()
+            .chain(to.iter())
+            .map(|(bone, _)| *bone)
+            .collect();
+        bones.sort();
+        bones.dedup();
+
+        bones
+            .into_iter()
+            .map(|bone| (weight(from, bone) - weight(to, bone)).abs())
+            .sum::<f32>()
+            / 2.0
+    }
+
+    /// The triangles that each vertex is a part of
+    fn vertex_triangles(&self) -> Vec<Vec<usize>> {
+        let mut vertex_triangles = vec![vec![]; self.positions.len()];
+
+        for (idx, triangle) in self.triangles.iter().enumerate() {
+            for vertex in triangle.iter() {
+                vertex_triangles[*vertex as usize].push(idx);
+            }
+        }
+
+        vertex_triangles
+    }
+
+    fn border_edges(&self) -> HashSet<(u32, u32)> {
+        edge_triangles(&self.triangles, &self.position_ids)
+            .into_iter()
+            .filter(|(_, triangles)| triangles.len() == 1)
+            .map(|(edge, _)| edge)
+            .collect()
+    }
+
+    fn indices(&self) -> Vec<u32> {
+        self.triangles.iter().flatten().cloned().collect()
+    }
+
+    fn error(&self) -> f32 {
+        self.error.sqrt() as f32
+    }
+}
+
+type EdgeTriangles = HashMap<(u32, u32), Vec<(usize, u32, u32)>>;
+
+/// The triangles on either side of each edge, along with the vertices at either end of the edge
+/// in that triangle.
+///
+/// Edges are keyed by position, so that edges along seams (which use different vertices on
+/// either side) aren't mistaken for borders.
+fn edge_triangles(triangles: &[[u32; 3]], position_ids: &[u32]) -> EdgeTriangles {
+    let mut edges = EdgeTriangles::new();
+
+    for (idx, triangle) in triangles.iter().enumerate() {
+        for corner in 0..3 {
+            let from = triangle[corner];
+            let to = triangle[(corner + 1) % 3];
+
+            let edge = sorted_edge(position_ids[from as usize], position_ids[to as usize]);
+            edges.entry(edge).or_default().push((idx, from, to));
+        }
+    }
+
+    edges
+}
+
+fn triangle_positions(positions: &[Vector3<f64>], triangle: &[u32; 3]) -> [Vector3<f64>; 3] {
+    [
+        positions[triangle[0] as usize],
+        positions[triangle[1] as usize],
+        positions[triangle[2] as usize],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combine_indices::IndexWidth;
+    use crate::test_utils::*;
+    use crate::vertex_data::{BoneAttributes, VertexData};
+
+    /// A `size` x `size` grid of quads in the xy plane, with each vertex raised by `height`
+    fn grid_mesh(size: u32, height: impl Fn(f32, f32) -> f32) -> BlenderMesh {
+        BlenderMesh {
+            vertex_data: VertexData::Single(grid(size, height)),
+            ..BlenderMesh::default()
+        }
+    }
+
+    fn bumpy(x: f32, y: f32) -> f32 {
+        (x * 0.7).sin() + (y * 0.9).cos()
+    }
+
+    fn single(mesh: &BlenderMesh) -> &SingleIndexVertexData {
+        mesh.single_index_vertex_data().unwrap()
+    }
+
+    fn single_mut(mesh: &mut BlenderMesh) -> &mut SingleIndexVertexData {
+        match &mut mesh.vertex_data {
+            VertexData::Single(single) => single,
+            VertexData::Multi(_) => unreachable!(),
+        }
+    }
+
+    /// The total area of the mesh's triangles
+    fn area(single: &SingleIndexVertexData) -> f32 {
+        single
+            .indices
+            .chunks(3)
+            .map(|triangle| {
+                let position = |idx: usize| {
+                    let (x, y, z) = single.positions.three_components(triangle[idx] as usize);
+                    Vector3::new(x, y, z)
+                };
+                (position(1) - position(0))
+                    .cross(&(position(2) - position(0)))
+                    .norm()
+                    / 2.0
+            })
+            .sum()
+    }
+
+    /// A flat grid can be simplified down to a couple of triangles without any error, and
+    /// without eating into its border
+    #[test]
+    fn flat_grid_simplifies_without_error() {
+        let mut mesh = grid_mesh(8, |_, _| 0.0);
+
+        let target = LodTarget {
+            triangle_ratio: 0.1,
+            max_error: None,
+        };
+        let error = mesh.simplify(&target, &SimplifyConfig::default()).unwrap();
+
+        let single = single(&mesh);
+        assert!(single.indices.len() / 3 <= 12, "{}", single.indices.len());
+        assert_eq!(single.vertices_in_each_face.len(), single.indices.len() / 3);
+        assert!(error < 1e-4);
+        assert!((area(single) - 64.0).abs() < 1e-3);
+    }
+
+    /// Borders stay exactly where they are when they're locked
+    #[test]
+    fn locked_borders() {
+        let mut mesh = grid_mesh(8, |_, _| 0.0);
+
+        let target = LodTarget {
+            triangle_ratio: 0.0,
+            max_error: None,
+        };
+        let config = SimplifyConfig {
+            lock_borders: true,
+            ..SimplifyConfig::default()
+        };
+        mesh.simplify(&target, &config).unwrap();
+
+        let single = single(&mesh);
+        for border_vertex in (0..81).filter(|v| v % 9 == 0 || v % 9 == 8 || *v < 9 || *v > 71) {
+            assert!(single.indices.contains(&border_vertex));
+        }
+    }
+
+    /// Every level of detail has fewer triangles and a larger error than the last
+    #[test]
+    fn lod_chain() {
+        let mesh = grid_mesh(16, bumpy);
+
+        let targets: Vec<LodTarget> = [0.5, 0.25, 0.1]
+            .iter()
+            .map(|ratio| LodTarget {
+                triangle_ratio: *ratio,
+                max_error: None,
+            })
+            .collect();
+        let lods = mesh
+            .lod_chain(&targets, &SimplifyConfig::default())
+            .unwrap();
+
+        let mut last_triangle_count = 16 * 16 * 2;
+        let mut last_error = 0.0;
+        for (lod, target) in lods.iter().zip(targets.iter()) {
+            let triangle_count = lod.index_buffer.len() / 3;
+
+            assert!(triangle_count < last_triangle_count);
+            assert!(triangle_count as f32 <= 512.0 * target.triangle_ratio);
+            assert!(lod.error > last_error);
+            assert_eq!(lod.index_buffer.index_width(), IndexWidth::U16);
+
+            last_triangle_count = triangle_count;
+            last_error = lod.error;
+        }
+
+        // The mesh itself isn't changed
+        assert_eq!(single(&mesh).indices.len(), 16 * 16 * 2 * 3);
+    }
+
+    /// We stop simplifying before we introduce more than the max error
+    #[test]
+    fn max_error() {
+        let mut mesh = grid_mesh(16, bumpy);
+
+        let target = LodTarget {
+            triangle_ratio: 0.0,
+            max_error: Some(0.05),
+        };
+        let error = mesh.simplify(&target, &SimplifyConfig::default()).unwrap();
+
+        let triangle_count = single(&mesh).indices.len() / 3;
+        assert!(error <= 0.05);
+        assert!(triangle_count < 512);
+        assert!(triangle_count > 2);
+    }
+
+    /// Vertices along a uv seam share positions with other vertices. They can only be collapsed
+    /// along the seam, and each side of the seam stays on its own side.
+    #[test]
+    fn uv_seams_are_preserved() {
+        let mut mesh = grid_mesh(8, |_, _| 0.0);
+
+        // Split the grid down the middle column, as if it were a uv seam
+        {
+            let single = single_mut(&mut mesh);
+            let seam: Vec<u32> = (0..9).map(|row| row * 9 + 4).collect();
+
+            let mut duplicates = HashMap::new();
+            for (idx, seam_vertex) in seam.iter().enumerate() {
+                let (x, y, z) = single.positions.three_components(*seam_vertex as usize);
+                single.positions.data_mut().extend_from_slice(&[x, y, z]);
+                single
+                    .normals
+                    .data_mut()
+                    .extend_from_slice(&[0.0, 0.0, 1.0]);
+                // The other side of the seam is in a different part of the texture
+                let uvs = single.uvs.as_mut().unwrap().data_mut();
+                uvs.extend_from_slice(&[x / 8.0 + 1.0, y / 8.0]);
+                duplicates.insert(*seam_vertex, 81 + idx as u32);
+            }
+
+            // Triangles to the right of the seam use the duplicated vertices
+            for triangle in single.indices.chunks_mut(3) {
+                let right = triangle.iter().any(|vertex| vertex % 9 > 4);
+                if right {
+                    for vertex in triangle.iter_mut() {
+                        if let Some(duplicate) = duplicates.get(vertex) {
+                            *vertex = *duplicate;
+                        }
+                    }
+                }
+            }
+        }
+
+        let target = LodTarget {
+            triangle_ratio: 0.0,
+            max_error: Some(1e-3),
+        };
+        mesh.simplify(&target, &SimplifyConfig::default()).unwrap();
+
+        let single = single(&mesh);
+        let on_left = |vertex: &u32| *vertex < 81 && vertex % 9 <= 4;
+        for triangle in single.indices.chunks(3) {
+            assert!(triangle.iter().all(on_left) || !triangle.iter().any(on_left));
+        }
+        // The ends of the seam are on the border, and can't move along it without the other side
+        for seam_end in [4, 76, 81, 89].iter() {
+            assert!(single.indices.contains(seam_end));
+        }
+        assert!(single.indices.len() / 3 < 32);
+        assert!((area(single) - 64.0).abs() < 1e-3);
+    }
+
+    /// Every triangle of a flat shaded mesh has its own vertices, which are collapsed together
+    #[test]
+    fn flat_shaded_meshes_simplify() {
+        let mut mesh = grid_mesh(16, bumpy);
+
+        {
+            let single = single_mut(&mut mesh);
+            let corners = std::mem::take(&mut single.indices);
+
+            let mut positions = vec![];
+            let mut normals = vec![];
+            let mut uvs = vec![];
+            for triangle in corners.chunks(3) {
+                let position = |corner: usize| {
+                    let (x, y, z) = single.positions.three_components(triangle[corner] as usize);
+                    Vector3::new(x, y, z)
+                };
+                let normal = (position(1) - position(0))
+                    .cross(&(position(2) - position(0)))
+                    .normalize();
+
+                for (corner, vertex) in triangle.iter().enumerate() {
+                    positions.extend_from_slice(position(corner).as_slice());
+                    normals.extend_from_slice(normal.as_slice());
+                    let (u, v) = single
+                        .uvs
+                        .as_ref()
+                        .unwrap()
+                        .two_components(*vertex as usize);
+                    uvs.extend_from_slice(&[u, v]);
+                }
+            }
+
+            single.indices = (0..corners.len() as u32).collect();
+            single.positions = attrib3(positions);
+            single.normals = attrib3(normals);
+            single.uvs = Some(attrib2(uvs));
+        }
+        let original_area = area(single(&mesh));
+
+        let target = LodTarget {
+            triangle_ratio: 0.25,
+            max_error: None,
+        };
+        mesh.simplify(&target, &SimplifyConfig::default()).unwrap();
+
+        let single = single(&mesh);
+        assert!(single.indices.len() / 3 <= 128, "{}", single.indices.len());
+        // The bumps are smoothed out a little, but the surface stays about the same size
+        assert!((area(single) / original_area - 1.0).abs() < 0.05);
+    }
+
+    /// Vertices on the boundary between two materials are kept, and every triangle keeps the
+    /// material of the triangles that it was simplified from
+    #[test]
+    fn material_boundaries_are_preserved() {
+        let mut mesh = grid_mesh(8, |_, _| 0.0);
+        mesh.material_slots = vec![Some("Left".to_string()), Some("Right".to_string())];
+
+        // The left half of the grid uses the first material, the right half the second
+        let face_materials = single(&mesh)
+            .indices
+            .chunks(3)
+            .map(|triangle| triangle.iter().any(|vertex| vertex % 9 > 4) as u16)
+            .collect();
+        single_mut(&mut mesh).face_materials = Some(face_materials);
+        mesh.split_by_material().unwrap();
+
+        let target = LodTarget {
+            triangle_ratio: 0.0,
+            max_error: Some(1e-3),
+        };
+        let lods = mesh
+            .lod_chain(&[target], &SimplifyConfig::default())
+            .unwrap();
+
+        let groups = &lods[0].draw_groups;
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1].material, Some("Right".to_string()));
+        assert_eq!(
+            groups[0].count + groups[1].count,
+            lods[0].index_buffer.len()
+        );
+
+        let indices = match &lods[0].index_buffer {
+            IndexBuffer::U16(indices) => indices.clone(),
+            IndexBuffer::U32(_) => unreachable!(),
+        };
+        let left = &indices[..groups[0].count];
+        let right = &indices[groups[1].start..];
+
+        for boundary_vertex in (0..9).map(|row| row * 9 + 4) {
+            assert!(left.contains(&boundary_vertex));
+            assert!(right.contains(&boundary_vertex));
+        }
+        assert!(left.iter().all(|vertex| vertex % 9 <= 4));
+        assert!(right.iter().all(|vertex| vertex % 9 >= 4));
+        assert!(indices.len() / 3 < 128);
+    }
+
+    /// Vertices aren't collapsed onto vertices with different bone weights
+    #[test]
+    fn bone_weights_are_preserved() {
+        let mut mesh = grid_mesh(4, |_, _| 0.0);
+
+        // Every vertex is driven by a different bone
+        single_mut(&mut mesh).bones = Some(BoneAttributes {
+            bones_per_vertex: 1,
+            bone_indices: (0..25).collect(),
+            bone_weights: vec![1.0; 25],
+        });
+
+        let target = LodTarget {
+            triangle_ratio: 0.0,
+            max_error: None,
+        };
+        let error = mesh.simplify(&target, &SimplifyConfig::default()).unwrap();
+
+        assert_eq!(single(&mesh).indices.len(), 4 * 4 * 2 * 3);
+        assert_eq!(error, 0.0);
+
+        // Ignoring bone weights lets us simplify again
+        let config = SimplifyConfig {
+            max_bone_weight_change: 1.0,
+            ..SimplifyConfig::default()
+        };
+        mesh.simplify(&target, &config).unwrap();
+        assert!(single(&mesh).indices.len() < 4 * 4 * 2 * 3);
+    }
+
+    #[test]
+    fn only_single_indexed_triangles_can_be_simplified() {
+        let mut mesh = BlenderMesh::default();
+        match mesh.simplify(&LodTarget::default(), &SimplifyConfig::default()) {
+            Err(SimplifyError::VertexData(VertexDataError::MultipleIndices)) => {}
+            _ => unreachable!(),
+        };
+
+        let mut mesh = grid_mesh(1, |_, _| 0.0);
+        single_mut(&mut mesh).vertices_in_each_face = vec![4, 2];
+        match mesh.lod_chain(&[LodTarget::default()], &SimplifyConfig::default()) {
+            Err(SimplifyError::VertexData(VertexDataError::NotTriangulated {
+                face: 0,
+                vertex_count: 4,
+            })) => {}
+            _ => unreachable!(),
+        };
+    }
+}
@@ -0,0 +1,345 @@
+//! Deforming a skinned mesh on the CPU, for when you need the posed geometry outside of a shader
+//! (collision, bounds, thumbnails, hit detection on a server...).
+
+use crate::vertex_data::{AttributeSize, VertexAttribute, VertexDataError};
+use crate::BlenderMesh;
+use blender_armature::{dual_quat, BlenderArmature, Bone};
+use nalgebra::{Matrix3, Matrix4, Point3, Vector3};
+use std::collections::HashMap;
+
+/// Configuration for [`BlenderMesh.skin`]
+///
+/// [`BlenderMesh.skin`]: struct.BlenderMesh.html#method.skin
+#[derive(Debug, Default)]
+pub struct SkinConfig {
+    pub method: SkinningMethod,
+}
+
+/// How each vertex's bones are blended together
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SkinningMethod {
+    /// Blend each bone's matrix by its weight.
+    ///
+    /// Supports scaled bones, but joints that twist lose volume.
+    LinearBlend,
+    /// Blend each bone's dual quaternion by its weight, the same way that the mesh visualizer's
+    /// `dual-quat-vertex.glsl` does.
+    ///
+    /// Like the shader, we don't flip quaternions that are in opposite hemispheres, so give
+    /// this the bones from `BlenderArmature.interpolate_bones` without changing their signs.
+    #[default]
+    DualQuaternion,
+}
+
+/// A skinned mesh's vertex data after it has been deformed by its bones
+#[derive(Debug, PartialEq)]
+pub struct SkinnedVertices {
+    pub positions: VertexAttribute,
+    pub normals: VertexAttribute,
+    /// The handedness of four component tangents is kept as it is
+    pub tangents: Option<VertexAttribute>,
+}
+
+/// An error while skinning a mesh
+#[derive(Debug, Fail)]
+pub enum SkinError {
+    #[fail(display = "Could not skin the mesh: {}", _0)]
+    VertexData(#[cause] VertexDataError),
+    #[fail(display = "The mesh has no bone influences, so it can't be skinned")]
+    NoBoneInfluences,
+    #[fail(
+        display = "Vertex {} is influenced by bone {} but there is no bone with that index",
+        vertex, bone
+    )]
+    MissingBone { vertex: usize, bone: u8 },
+}
+
+impl From<VertexDataError> for SkinError {
+    fn from(error: VertexDataError) -> Self {
+        SkinError::VertexData(error)
+    }
+}
+
+impl BlenderMesh {
+    /// Deform the mesh's positions, normals and tangents by the bones, which are usually the
+    /// bones returned by `BlenderArmature.interpolate_bones`.
+    ///
+    /// The bones are expected to already have the inverse bind poses applied, and matrix bones
+    /// need to be column major, so call `BlenderArmature.apply_inverse_bind_poses` and
+    /// `BlenderArmature.transpose_actions` first.
+    ///
+    /// Bones with a weight of zero are ignored, and vertices without any weight keep their
+    /// bind pose.
+    pub fn skin(
+        &self,
+        bones: &HashMap<u8, Bone>,
+        config: &SkinConfig,
+    ) -> Result<SkinnedVertices, SkinError> {
+        let single = self.single_index_vertex_data_or_err()?;
+        let attributes = single.bones.as_ref().ok_or(SkinError::NoBoneInfluences)?;
+        let bones_per_vertex = attributes.bones_per_vertex as usize;
+
+        let vertex_count = single.positions.vertex_count();
+        let mut positions = Vec::with_capacity(vertex_count * 3);
+        let mut normals = Vec::with_capacity(vertex_count * 3);
+        let mut tangents = single
+            .tangents
+            .as_ref()
+            .map(|tangents| Vec::with_capacity(tangents.data().len()));
+
+        for vertex in 0..vertex_count {
+            let influences = vertex * bones_per_vertex..(vertex + 1) * bones_per_vertex;
+            let influences: Vec<(&Bone, f32)> = attributes.bone_indices[influences.clone()]
+                .iter()
+                .zip(attributes.bone_weights[influences].iter())
+                .filter(|(_, weight)| **weight != 0.0)
+                .map(|(bone, weight)| match bones.get(bone) {
+                    Some(pose) => Ok((pose, *weight)),
+                    None => Err(SkinError::MissingBone {
+                        vertex,
+                        bone: *bone,
+                    }),
+                })
+                .collect::<Result<_, _>>()?;
+
+            let matrix = if influences.is_empty() {
+                Matrix4::identity()
+            } else {
+                match config.method {
+                    SkinningMethod::LinearBlend => linear_blend(&influences),
+                    SkinningMethod::DualQuaternion => dual_quaternion_blend(&influences),
+                }
+            };
+            let rotation_and_scale: Matrix3<f32> = matrix
+                .fixed_slice::<nalgebra::U3, nalgebra::U3>(0, 0)
+                .into();
+            // Dual quaternions are rigid so their rotation already works for normals, but blended
+            // matrices might scale or shear.
+            let normal_matrix = match config.method {
+                SkinningMethod::LinearBlend => rotation_and_scale
+                    .try_inverse()
+                    .map(|inverse| inverse.transpose())
+                    .unwrap_or(rotation_and_scale),
+                SkinningMethod::DualQuaternion => rotation_and_scale,
+            };
+
+            let (x, y, z) = single.positions.three_components(vertex);
+            let position = matrix.transform_point(&Point3::new(x, y, z));
+            positions.extend_from_slice(&[position.x, position.y, position.z]);
+
+            let (x, y, z) = single.normals.three_components(vertex);
+            let normal = normalize(normal_matrix * Vector3::new(x, y, z));
+            normals.extend_from_slice(&[normal.x, normal.y, normal.z]);
+
+            if let (Some(skinned), Some(original)) = (tangents.as_mut(), single.tangents.as_ref()) {
+                let size = original.size() as usize;
+                let components = &original.data()[vertex * size..(vertex + 1) * size];
+
+                let tangent = Vector3::new(components[0], components[1], components[2]);
+                let tangent = normalize(rotation_and_scale * tangent);
+                skinned.extend_from_slice(&[tangent.x, tangent.y, tangent.z]);
+                // Handedness
+                skinned.extend_from_slice(&components[3..]);
+            }
+        }
+
+        Ok(SkinnedVertices {
+            positions: VertexAttribute::new(positions, AttributeSize::Three),
+            normals: VertexAttribute::new(normals, AttributeSize::Three),
+            tangents: tangents
+                .map(|data| VertexAttribute::new(data, single.tangents.as_ref().unwrap().size())),
+        })
+    }
+}
+
+fn linear_blend(influences: &[(&Bone, f32)]) -> Matrix4<f32> {
+    influences
+        .iter()
+        .fold(Matrix4::zeros(), |blended, (bone, weight)| {
+            let matrix = match bone {
+                Bone::Matrix(matrix) => *matrix,
+                Bone::DualQuat(_) => match BlenderArmature::dual_quat_to_matrix(bone) {
+                    Bone::Matrix(matrix) => matrix,
+                    Bone::DualQuat(_) => unreachable!(),
+                },
+            };
+
+            blended + Matrix4::from_column_slice(&matrix) * *weight
+        })
+}
+
+/// The same blend as `dual-quat-vertex.glsl`, turned into a matrix
+fn dual_quaternion_blend(influences: &[(&Bone, f32)]) -> Matrix4<f32> {
+    let mut blended = [0.0; 8];
+
+    for (bone, weight) in influences.iter() {
+        let dual_quat = match bone {
+            Bone::Matrix(_) => match BlenderArmature::matrix_to_dual_quat(bone) {
+                Bone::DualQuat(dual_quat) => dual_quat,
+                Bone::Matrix(_) => unreachable!(),
+            },
+            Bone::DualQuat(dual_quat) => *dual_quat,
+        };
+
+        for (blended, component) in blended.iter_mut().zip(dual_quat.iter()) {
+            *blended += component * weight;
+        }
+    }
+
+    match BlenderArmature::dual_quat_to_matrix(&Bone::DualQuat(dual_quat::normalize(&blended))) {
+        Bone::Matrix(matrix) => Matrix4::from_column_slice(&matrix),
+        Bone::DualQuat(_) => unreachable!(),
+    }
+}
+
+fn normalize(vector: Vector3<f32>) -> Vector3<f32> {
+    vector.try_normalize(f32::EPSILON).unwrap_or(vector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::vertex_data::{BoneAttributes, SingleIndexVertexData, VertexData};
+
+    /// One vertex at (1, 0, 0) that is influenced equally by two bones
+    fn two_bone_vertex() -> BlenderMesh {
+        BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                vertices_in_each_face: vec![],
+                positions: attrib3(vec![1.0, 0.0, 0.0]),
+                normals: attrib3(vec![1.0, 0.0, 0.0]),
+                tangents: Some(VertexAttribute::new(
+                    vec![0.0, 1.0, 0.0, -1.0],
+                    AttributeSize::Four,
+                )),
+                bones: Some(BoneAttributes {
+                    bones_per_vertex: 3,
+                    bone_indices: vec![0, 1, 7],
+                    bone_weights: vec![0.5, 0.5, 0.0],
+                }),
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        }
+    }
+
+    fn matrix_bone(matrix: Matrix4<f32>) -> Bone {
+        let mut bone = [0.0; 16];
+        bone.copy_from_slice(matrix.as_slice());
+        Bone::Matrix(bone)
+    }
+
+    /// The identity, and a quarter turn around the z axis
+    fn bones() -> HashMap<u8, Bone> {
+        let quarter_turn =
+            Matrix4::from_axis_angle(&Vector3::z_axis(), std::f32::consts::FRAC_PI_2);
+
+        let mut bones = HashMap::new();
+        bones.insert(0, matrix_bone(Matrix4::identity()));
+        bones.insert(
+            1,
+            BlenderArmature::matrix_to_dual_quat(&matrix_bone(quarter_turn)),
+        );
+        bones
+    }
+
+    fn assert_close(attribute: &VertexAttribute, expected: &[f32]) {
+        for (actual, expected) in attribute.data().iter().zip(expected.iter()) {
+            assert!((actual - expected).abs() < 1e-5, "{:?}", attribute.data());
+        }
+    }
+
+    /// Linear blend skinning averages where each bone would put the vertex, which pulls it
+    /// towards the joint
+    #[test]
+    fn linear_blend_skinning() {
+        let config = SkinConfig {
+            method: SkinningMethod::LinearBlend,
+        };
+        let skinned = two_bone_vertex().skin(&bones(), &config).unwrap();
+
+        let half = 0.5f32.sqrt();
+        assert_close(&skinned.positions, &[0.5, 0.5, 0.0]);
+        assert_close(&skinned.normals, &[half, half, 0.0]);
+        assert_close(
+            skinned.tangents.as_ref().unwrap(),
+            &[-half, half, 0.0, -1.0],
+        );
+    }
+
+    /// Dual quaternion skinning rotates the vertex half way, keeping its distance from the joint
+    #[test]
+    fn dual_quaternion_skinning() {
+        let skinned = two_bone_vertex()
+            .skin(&bones(), &SkinConfig::default())
+            .unwrap();
+
+        let half = 0.5f32.sqrt();
+        assert_close(&skinned.positions, &[half, half, 0.0]);
+        assert_close(&skinned.normals, &[half, half, 0.0]);
+        assert_close(
+            skinned.tangents.as_ref().unwrap(),
+            &[-half, half, 0.0, -1.0],
+        );
+    }
+
+    /// Both methods move vertices with the bone's translation
+    #[test]
+    fn translated_bone() {
+        let mut mesh = two_bone_vertex();
+        if let VertexData::Single(single) = &mut mesh.vertex_data {
+            single.bones.as_mut().unwrap().bone_weights = vec![0.0, 1.0, 0.0];
+        }
+
+        let mut bones = HashMap::new();
+        bones.insert(
+            1,
+            matrix_bone(Matrix4::new_translation(&Vector3::new(2.0, 3.0, 4.0))),
+        );
+
+        for method in [SkinningMethod::LinearBlend, SkinningMethod::DualQuaternion].iter() {
+            let skinned = mesh.skin(&bones, &SkinConfig { method: *method }).unwrap();
+
+            assert_close(&skinned.positions, &[3.0, 3.0, 4.0]);
+            assert_close(&skinned.normals, &[1.0, 0.0, 0.0]);
+        }
+    }
+
+    /// Normals stay perpendicular to the surface when linear blend skinning scales the mesh
+    #[test]
+    fn scaled_normals() {
+        let mut mesh = two_bone_vertex();
+        if let VertexData::Single(single) = &mut mesh.vertex_data {
+            single.normals = attrib3(vec![0.6, 0.8, 0.0]);
+            single.bones.as_mut().unwrap().bone_weights = vec![1.0, 0.0, 0.0];
+        }
+
+        let mut bones = HashMap::new();
+        bones.insert(
+            0,
+            matrix_bone(Matrix4::new_nonuniform_scaling(&Vector3::new(
+                2.0, 1.0, 1.0,
+            ))),
+        );
+
+        let config = SkinConfig {
+            method: SkinningMethod::LinearBlend,
+        };
+        let skinned = mesh.skin(&bones, &config).unwrap();
+
+        let expected = Vector3::new(0.3, 0.8, 0.0).normalize();
+        assert_close(&skinned.normals, expected.as_slice());
+    }
+
+    #[test]
+    fn missing_bone() {
+        let mut bones = bones();
+        bones.remove(&1);
+
+        match two_bone_vertex().skin(&bones, &SkinConfig::default()) {
+            Err(SkinError::MissingBone { vertex: 0, bone: 1 }) => {}
+            _ => unreachable!(),
+        };
+    }
+}
@@ -0,0 +1,523 @@
+use crate::vertex_data::{AttributeSize, MultiIndexVertexData, VertexAttribute, VertexData};
+use crate::BlenderMesh;
+use nalgebra::Vector3;
+use std::collections::HashMap;
+
+/// Configuration for generating smooth vertex normals
+#[derive(Debug, Default)]
+pub struct SmoothNormalsConfig {
+    /// How much each face contributes to the normals of its vertices
+    pub weighting: NormalWeighting,
+    /// Edges between two faces whose normals differ by more than this angle (in radians) stay
+    /// sharp, just like Blender's auto smooth.
+    ///
+    /// `None` smooths every edge between two smooth shaded faces, no matter how sharp it is.
+    pub auto_smooth_angle: Option<f32>,
+}
+
+/// How much each face contributes to the normal of a vertex that it shares with other faces
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NormalWeighting {
+    /// Weight each face by its area, so that large faces dominate small ones
+    Area,
+    /// Weight each face by the angle of its corner at the vertex. This is what Blender does, and
+    /// doesn't depend on how the surrounding faces happen to be subdivided.
+    #[default]
+    Angle,
+    /// Weight each face by both its area and its corner angle
+    AreaAndAngle,
+}
+
+/// An error while generating smooth normals for a mesh
+#[derive(Debug, Fail)]
+pub enum SmoothNormalsError {
+    #[fail(display = "Normals can only be generated before combining vertex indices")]
+    AlreadySingleIndexed,
+}
+
+impl BlenderMesh {
+    /// Blender exports one normal per face, so without smooth normals every mesh renders flat
+    /// shaded.
+    ///
+    /// Here we replace the mesh's normals and normal indices with smooth normals, respecting
+    /// each face's smooth shading flag and the `auto_smooth_angle`.
+    ///
+    /// This needs to happen before [`BlenderMesh.combine_vertex_indices`].
+    ///
+    /// [`BlenderMesh.combine_vertex_indices`]: struct.BlenderMesh.html#method.combine_vertex_indices
+    pub fn calculate_smooth_normals(
+        &mut self,
+        config: &SmoothNormalsConfig,
+    ) -> Result<(), SmoothNormalsError> {
+        match &mut self.vertex_data {
+            VertexData::Multi(multi) => {
+                multi.calculate_smooth_normals(config);
+                Ok(())
+            }
+            VertexData::Single(_) => Err(SmoothNormalsError::AlreadySingleIndexed),
+        }
+    }
+}
+
+impl MultiIndexVertexData {
+    /// Every face corner that shares a position with the corners of the neighbouring faces
+    /// across smooth edges gets the same normal - the weighted average of those faces' normals.
+    ///
+    /// Flat shaded faces use their face normal for all of their corners.
+    pub(crate) fn calculate_smooth_normals(&mut self, config: &SmoothNormalsConfig) {
+        let faces = self.faces();
+
+        let min_cos_angle = config.auto_smooth_angle.map(|angle| angle.cos());
+
+        // Corners in the same smoothing group share a normal
+        let mut smoothing_groups: Vec<usize> = (0..self.position_indices.len()).collect();
+
+        let mut corners_on_each_edge: HashMap<(u32, u32), Vec<(usize, usize)>> = HashMap::new();
+
+        for face in faces.iter() {
+            if !face.smooth {
+                for corner in face.corners() {
+                    union(&mut smoothing_groups, face.start, corner);
+                }
+                continue;
+            }
+
+            for corner in face.corners() {
+                let next_corner = face.next_corner(corner);
+
+                let pos_idx = self.position_indices[corner];
+                let next_pos_idx = self.position_indices[next_corner];
+
+                let edge = if pos_idx < next_pos_idx {
+                    ((pos_idx, next_pos_idx), (corner, next_corner))
+                } else {
+                    ((next_pos_idx, pos_idx), (next_corner, corner))
+                };
+
+                corners_on_each_edge.entry(edge.0).or_default().push(edge.1);
+            }
+        }
+
+        // Join the corners on either end of every smooth edge
+        for (_, corners) in corners_on_each_edge.iter() {
+            for (idx, (a_start, a_end)) in corners.iter().enumerate() {
+                for (b_start, b_end) in corners.iter().skip(idx + 1) {
+                    let a_normal = faces[self.face_at_corner(&faces, *a_start)].normal;
+                    let b_normal = faces[self.face_at_corner(&faces, *b_start)].normal;
+
+                    let is_sharp = match min_cos_angle {
+                        Some(min_cos_angle) => a_normal.dot(&b_normal) < min_cos_angle,
+                        None => false,
+                    };
+
+                    if !is_sharp {
+                        union(&mut smoothing_groups, *a_start, *b_start);
+                        union(&mut smoothing_groups, *a_end, *b_end);
+                    }
+                }
+            }
+        }
+
+        let corner_normals = self.corner_normals(&mut smoothing_groups, config.weighting);
+
+        // Shape keys move the positions, so each one gets its own normals, smoothed across the
+        // same edges as the base mesh
+        let mut target_corner_normals = Vec::with_capacity(self.morph_targets.len());
+        for target in 0..self.morph_targets.len() {
+            let mut morphed = self.positions.data().clone();
+            for (component, delta) in morphed
+                .iter_mut()
+                .zip(self.morph_targets[target].position_deltas.data().iter())
+            {
+                *component += delta;
+            }
+
+            let morphed = VertexAttribute::new(morphed, AttributeSize::Three);
+            let positions = std::mem::replace(&mut self.positions, morphed);
+            target_corner_normals
+                .push(self.corner_normals(&mut smoothing_groups, config.weighting));
+            self.positions = positions;
+        }
+
+        let mut normals = vec![];
+        let mut normal_deltas = vec![vec![]; self.morph_targets.len()];
+        let mut normal_indices = Vec::with_capacity(self.position_indices.len());
+        // Many corners end up with the same normal (such as every corner of a flat face), so we
+        // only store each normal once.
+        let mut encountered_normals: HashMap<Vec<u32>, u32> = HashMap::new();
+
+        for (corner, normal) in corner_normals.iter().enumerate() {
+            let target_normals = target_corner_normals.iter().map(|normals| normals[corner]);
+
+            let key = std::iter::once(*normal)
+                .chain(target_normals.clone())
+                .flat_map(|normal| vec![normal.x.to_bits(), normal.y.to_bits(), normal.z.to_bits()])
+                .collect();
+            let normal_idx = *encountered_normals.entry(key).or_insert_with(|| {
+                normals.extend_from_slice(&[normal.x, normal.y, normal.z]);
+                for (deltas, target_normal) in normal_deltas.iter_mut().zip(target_normals) {
+                    let delta = target_normal - normal;
+                    deltas.extend_from_slice(&[delta.x, delta.y, delta.z]);
+                }
+                (normals.len() / 3 - 1) as u32
+            });
+
+            normal_indices.push(normal_idx);
+        }
+
+        self.normals = VertexAttribute::new(normals, AttributeSize::Three);
+        self.normal_indices = normal_indices;
+        for (target, deltas) in self.morph_targets.iter_mut().zip(normal_deltas) {
+            target.normal_deltas = VertexAttribute::new(deltas, AttributeSize::Three);
+        }
+    }
+
+    /// The smooth normal of every face corner, the weighted average of the normals of the faces
+    /// in its smoothing group
+    fn corner_normals(
+        &self,
+        smoothing_groups: &mut [usize],
+        weighting: NormalWeighting,
+    ) -> Vec<Vector3<f32>> {
+        let faces = self.faces();
+
+        let mut group_normals: HashMap<usize, Vector3<f32>> = HashMap::new();
+
+        for face in faces.iter() {
+            for corner in face.corners() {
+                let weight = match weighting {
+                    NormalWeighting::Area => face.area,
+                    NormalWeighting::Angle => self.corner_angle(face, corner),
+                    NormalWeighting::AreaAndAngle => face.area * self.corner_angle(face, corner),
+                };
+
+                *group_normals
+                    .entry(find(smoothing_groups, corner))
+                    .or_insert_with(Vector3::zeros) += face.normal * weight;
+            }
+        }
+
+        faces
+            .iter()
+            .flat_map(|face| face.corners().map(move |corner| (face, corner)))
+            .map(|(face, corner)| {
+                // Degenerate faces contribute nothing, so fall back to the face normal
+                group_normals[&find(smoothing_groups, corner)]
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or(face.normal)
+            })
+            .collect()
+    }
+
+    /// The position of every face's corners and whether it's smooth shaded
+    fn faces(&self) -> Vec<Face> {
+        let mut faces = Vec::with_capacity(self.vertices_in_each_face.len());
+
+        let mut start = 0;
+
+        for (face_idx, vertices_in_face) in self.vertices_in_each_face.iter().enumerate() {
+            let vertices_in_face = *vertices_in_face as usize;
+
+            // Newell's method, which works for concave and slightly non planar faces.
+            // The length of the result is twice the face's area.
+            let mut newell = Vector3::zeros();
+            for corner in start..start + vertices_in_face {
+                let current = self.corner_position(corner);
+                let next = self.corner_position(start + (corner - start + 1) % vertices_in_face);
+
+                newell += current.cross(&next);
+            }
+
+            faces.push(Face {
+                start,
+                vertices_in_face,
+                normal: newell
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(Vector3::zeros),
+                area: newell.norm() / 2.0,
+                smooth: self
+                    .smooth_faces
+                    .as_ref()
+                    .is_none_or(|smooth_faces| smooth_faces[face_idx]),
+            });
+
+            start += vertices_in_face;
+        }
+
+        faces
+    }
+
+    /// The face that a corner belongs to
+    fn face_at_corner(&self, faces: &[Face], corner: usize) -> usize {
+        match faces.binary_search_by_key(&corner, |face| face.start) {
+            Ok(face) => face,
+            Err(next_face) => next_face - 1,
+        }
+    }
+
+    /// The angle (in radians) between the two edges of a face that meet at a corner
+    fn corner_angle(&self, face: &Face, corner: usize) -> f32 {
+        let position = self.corner_position(corner);
+
+        let to_previous = self.corner_position(face.previous_corner(corner)) - position;
+        let to_next = self.corner_position(face.next_corner(corner)) - position;
+
+        match (
+            to_previous.try_normalize(f32::EPSILON),
+            to_next.try_normalize(f32::EPSILON),
+        ) {
+            (Some(to_previous), Some(to_next)) => to_previous.dot(&to_next).clamp(-1.0, 1.0).acos(),
+            _ => 0.0,
+        }
+    }
+
+    fn corner_position(&self, corner: usize) -> Vector3<f32> {
+        let (x, y, z) = self.vertex_pos_at_idx(self.position_indices[corner]);
+        Vector3::new(x, y, z)
+    }
+}
+
+struct Face {
+    /// Where the face's corners start in the mesh's index vectors
+    start: usize,
+    vertices_in_face: usize,
+    /// Unit length, or zero for faces with no area
+    normal: Vector3<f32>,
+    area: f32,
+    smooth: bool,
+}
+
+impl Face {
+    fn corners(&self) -> std::ops::Range<usize> {
+        self.start..self.start + self.vertices_in_face
+    }
+
+    fn next_corner(&self, corner: usize) -> usize {
+        self.start + (corner - self.start + 1) % self.vertices_in_face
+    }
+
+    fn previous_corner(&self, corner: usize) -> usize {
+        self.start + (corner - self.start + self.vertices_in_face - 1) % self.vertices_in_face
+    }
+}
+
+/// Find the smoothing group that a corner belongs to
+fn find(groups: &mut [usize], mut corner: usize) -> usize {
+    while groups[corner] != corner {
+        groups[corner] = groups[groups[corner]];
+        corner = groups[corner];
+    }
+
+    corner
+}
+
+/// Put two corners into the same smoothing group
+fn union(groups: &mut [usize], a: usize, b: usize) {
+    let a = find(groups, a);
+    let b = find(groups, b);
+
+    groups[a.max(b)] = a.min(b);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::morph_target::MorphTarget;
+    use crate::test_utils::*;
+    use std::f32::consts::PI;
+
+    /// Two faces of a cube that meet at a right angle, sharing the edge between positions 1 and 2.
+    ///
+    /// The first face faces +z, the second faces +x.
+    fn cube_corner(smooth_faces: Option<Vec<bool>>) -> BlenderMesh {
+        BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                positions: attrib3(vec![
+                    0.0, 0.0, 1.0, // 0
+                    1.0, 0.0, 1.0, // 1
+                    1.0, 1.0, 1.0, // 2
+                    0.0, 1.0, 1.0, // 3
+                    1.0, 0.0, 0.0, // 4
+                    1.0, 1.0, 0.0, // 5
+                ]),
+                position_indices: vec![0, 1, 2, 3, 1, 4, 5, 2],
+                normals: attrib3(vec![0.0, 0.0, 1.0, 1.0, 0.0, 0.0]),
+                normal_indices: vec![0, 0, 0, 0, 1, 1, 1, 1],
+                vertices_in_each_face: vec![4, 4],
+                smooth_faces,
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        }
+    }
+
+    /// Corners on the shared edge get the average of both face normals, the rest keep their
+    /// face's normal.
+    #[test]
+    fn shared_positions_are_smoothed() {
+        let mut mesh = cube_corner(None);
+
+        mesh.calculate_smooth_normals(&SmoothNormalsConfig::default())
+            .unwrap();
+
+        let multi = mesh.multi_index_vertex_data().unwrap();
+        assert_eq!(multi.normal_indices(), &vec![0, 1, 1, 0, 1, 2, 2, 1]);
+
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert_normals_eq(multi, 0, [0.0, 0.0, 1.0]);
+        assert_normals_eq(multi, 1, [half, 0.0, half]);
+        assert_normals_eq(multi, 2, [1.0, 0.0, 0.0]);
+    }
+
+    /// An edge sharper than the auto smooth angle isn't smoothed
+    #[test]
+    fn edges_sharper_than_auto_smooth_angle_stay_sharp() {
+        let mut mesh = cube_corner(None);
+
+        let config = SmoothNormalsConfig {
+            auto_smooth_angle: Some(PI / 4.0),
+            ..SmoothNormalsConfig::default()
+        };
+        mesh.calculate_smooth_normals(&config).unwrap();
+
+        let multi = mesh.multi_index_vertex_data().unwrap();
+        assert_eq!(multi.normal_indices(), &vec![0, 0, 0, 0, 1, 1, 1, 1]);
+        assert_normals_eq(multi, 0, [0.0, 0.0, 1.0]);
+        assert_normals_eq(multi, 1, [1.0, 0.0, 0.0]);
+
+        // 90 degrees is within a 100 degree auto smooth angle
+        let mut mesh = cube_corner(None);
+
+        let config = SmoothNormalsConfig {
+            auto_smooth_angle: Some(100.0_f32.to_radians()),
+            ..SmoothNormalsConfig::default()
+        };
+        mesh.calculate_smooth_normals(&config).unwrap();
+
+        let multi = mesh.multi_index_vertex_data().unwrap();
+        assert_eq!(multi.normal_indices(), &vec![0, 1, 1, 0, 1, 2, 2, 1]);
+    }
+
+    /// Flat shaded faces use their face normal and don't contribute to their neighbours' normals
+    #[test]
+    fn flat_faces_keep_their_face_normal() {
+        let mut mesh = cube_corner(Some(vec![true, false]));
+
+        mesh.calculate_smooth_normals(&SmoothNormalsConfig::default())
+            .unwrap();
+
+        let multi = mesh.multi_index_vertex_data().unwrap();
+        assert_eq!(multi.normal_indices(), &vec![0, 0, 0, 0, 1, 1, 1, 1]);
+        assert_normals_eq(multi, 0, [0.0, 0.0, 1.0]);
+        assert_normals_eq(multi, 1, [1.0, 0.0, 0.0]);
+    }
+
+    /// A large face pulls an area weighted normal towards it, while an angle weighted normal
+    /// only depends on the corner angles.
+    #[test]
+    fn area_and_angle_weighting() {
+        // A 1x1 triangle facing +z and a 1x18 triangle facing +x that share the edge between
+        // positions 0 and 2. Both have a right angle at position 0.
+        let mesh = || BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                positions: attrib3(vec![
+                    0.0, 0.0, 0.0, // 0
+                    1.0, 0.0, 0.0, // 1
+                    0.0, 1.0, 0.0, // 2
+                    0.0, 0.0, 18.0, // 3
+                ]),
+                position_indices: vec![0, 1, 2, 0, 2, 3],
+                normal_indices: vec![0; 6],
+                vertices_in_each_face: vec![3, 3],
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        let normal_at_position_0 = |weighting| {
+            let mut mesh = mesh();
+            let config = SmoothNormalsConfig {
+                weighting,
+                ..SmoothNormalsConfig::default()
+            };
+            mesh.calculate_smooth_normals(&config).unwrap();
+
+            let multi = mesh.multi_index_vertex_data().unwrap();
+            multi.vertex_normal_at_idx(multi.normal_indices()[0])
+        };
+
+        let angle = normal_at_position_0(NormalWeighting::Angle);
+        assert!((angle.0 - angle.2).abs() < 1e-6);
+
+        // The second face has 18 times the area of the first
+        let area = normal_at_position_0(NormalWeighting::Area);
+        assert!((area.0 - area.2 * 18.0).abs() < 1e-5);
+        assert!(area.2 > 0.0);
+
+        let area_and_angle = normal_at_position_0(NormalWeighting::AreaAndAngle);
+        assert!((area_and_angle.0 - area_and_angle.2 * 18.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn normals_are_generated_before_combining_indices() {
+        let mut mesh = BlenderMesh {
+            vertex_data: VertexData::Single(Default::default()),
+            ..BlenderMesh::default()
+        };
+
+        match mesh.calculate_smooth_normals(&SmoothNormalsConfig::default()) {
+            Err(SmoothNormalsError::AlreadySingleIndexed) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    /// Shape keys get normal deltas that turn the base mesh's smooth normals into the normals of
+    /// the moved positions
+    #[test]
+    fn morph_target_normals_are_smoothed() {
+        let mut mesh = cube_corner(None);
+        // Tilt the +x face outwards so that it faces +x +z
+        if let VertexData::Multi(multi) = &mut mesh.vertex_data {
+            let mut position_deltas = vec![0.0; 18];
+            position_deltas[12] = 1.0;
+            position_deltas[15] = 1.0;
+
+            multi.morph_targets = vec![MorphTarget::new(
+                "Tilt".to_string(),
+                0.0,
+                position_deltas,
+                vec![0.0; 6],
+            )];
+        }
+
+        mesh.calculate_smooth_normals(&SmoothNormalsConfig::default())
+            .unwrap();
+
+        let multi = mesh.multi_index_vertex_data().unwrap();
+        assert_eq!(multi.normal_indices(), &vec![0, 1, 1, 0, 1, 2, 2, 1]);
+        assert_eq!(multi.morph_targets()[0].normal_deltas().vertex_count(), 3);
+
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let shared = Vector3::new(half, 0.0, 1.0 + half).normalize();
+
+        let morphed = mesh.morph(&[1.0]).unwrap();
+        let expected = [0.0, 0.0, 1.0, shared.x, 0.0, shared.z, half, 0.0, half];
+        for (actual, expected) in morphed.normals.data().iter().zip(expected.iter()) {
+            assert!((actual - expected).abs() < 1e-6);
+        }
+    }
+
+    fn assert_normals_eq(multi: &MultiIndexVertexData, normal_idx: u32, expected: [f32; 3]) {
+        let normal = multi.vertex_normal_at_idx(normal_idx);
+        let normal = [normal.0, normal.1, normal.2];
+
+        for (actual, expected) in normal.iter().zip(expected.iter()) {
+            assert!(
+                (actual - expected).abs() < 1e-6,
+                "{:?} != {:?}",
+                normal,
+                expected
+            );
+        }
+    }
+}
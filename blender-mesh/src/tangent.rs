@@ -1,23 +1,4 @@
-use crate::BlenderMesh;
-
-impl BlenderMesh {
-    /// Get the tangent vector for each vertex
-    pub fn vertex_tangents(&self) -> Option<&Vec<f32>> {
-        if let Some(tangents) = self.per_vertex_tangents.as_ref() {
-            return Some(tangents.data());
-        }
-
-        None
-    }
-
-    pub(crate) fn vertex_tangents_mut(&mut self) -> Option<&mut Vec<f32>> {
-        if let Some(tangents) = self.per_vertex_tangents.as_mut() {
-            return Some(tangents.data_mut());
-        }
-
-        None
-    }
-}
+use crate::vertex_data::MultiIndexVertexData;
 
 /// Indicates an error while calculating the tangents for a mesh's verticies
 #[derive(Debug, Fail)]
@@ -26,7 +7,29 @@ pub enum TangentError {
     NoVertexUvs,
 }
 
-impl BlenderMesh {
+/// How [`BlenderMesh.combine_vertex_indices`] calculates vertex tangents
+///
+/// [`BlenderMesh.combine_vertex_indices`]: struct.BlenderMesh.html#method.combine_vertex_indices
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TangentAlgorithm {
+    /// Sum the tangent of every face that a vertex is a part of.
+    ///
+    /// Each face's tangent comes from its first three vertices. The resulting 3 component tangents
+    /// are not normalized and have no handedness.
+    #[default]
+    FaceTangents,
+    /// Generate unit length 4 component tangents - `(x, y, z)` plus the bitangent sign in `w`,
+    /// where `bitangent = w * cross(normal, tangent)` - the same way that Blender bakes normal
+    /// maps.
+    ///
+    /// Vertices that are shared by faces with mirrored uvs get split in two, since they need
+    /// different tangents.
+    ///
+    /// @see http://www.mikktspace.com/
+    MikkTSpace,
+}
+
+impl MultiIndexVertexData {
     /// Calculate the tangent for each face in the mesh - useful for normal mapping where you'll
     /// typically want to do lighting calculations in tangent space.
     ///
@@ -35,12 +38,10 @@ impl BlenderMesh {
     ///
     /// This is useful for normal mapping.
     ///
-    /// We'll push tangents to `.face_tangents`
-    ///
-    /// Then later in when combining indices we'll use these `.face_tangents` in order to
-    /// generate `per_vertex_tangents`
-    pub(crate) fn calculate_face_tangents(&mut self) -> Result<(), TangentError> {
-        if self.vertex_uvs.is_none() {
+    /// Later, when combining indices, we'll use these face tangents in order to generate
+    /// per vertex tangents.
+    pub(crate) fn calculate_face_tangents(&self) -> Result<Vec<f32>, TangentError> {
+        if self.uvs.is_none() {
             return Err(TangentError::NoVertexUvs)?;
         }
 
@@ -49,20 +50,20 @@ impl BlenderMesh {
         let mut face_tangents = vec![];
 
         // Iterate over each face and calculate the tangent for that face.
-        for vertices_in_face in self.num_vertices_in_each_face.iter() {
+        for vertices_in_face in self.vertices_in_each_face.iter() {
             let vertices_in_face = *vertices_in_face;
 
-            let idx = total_indices_processed as usize;
+            let idx = total_indices_processed;
 
             // Get the first three vertex indices for this face
-            let pos_idx_0 = self.vertex_position_indices[idx];
-            let pos_idx_1 = self.vertex_position_indices[idx + 1];
-            let pos_idx_2 = self.vertex_position_indices[idx + 2];
+            let pos_idx_0 = self.position_indices[idx];
+            let pos_idx_1 = self.position_indices[idx + 1];
+            let pos_idx_2 = self.position_indices[idx + 2];
 
             // Get the three UV indices for this face
-            let uv_idx_0 = self.vertex_uv_indices.as_ref().unwrap()[idx];
-            let uv_idx_1 = self.vertex_uv_indices.as_ref().unwrap()[idx + 1];
-            let uv_idx_2 = self.vertex_uv_indices.as_ref().unwrap()[idx + 2];
+            let uv_idx_0 = self.uv_indices.as_ref().unwrap()[idx];
+            let uv_idx_1 = self.uv_indices.as_ref().unwrap()[idx + 1];
+            let uv_idx_2 = self.uv_indices.as_ref().unwrap()[idx + 2];
 
             let pos0 = self.vertex_pos_at_idx(pos_idx_0);
             let pos1 = self.vertex_pos_at_idx(pos_idx_1);
@@ -88,26 +89,10 @@ impl BlenderMesh {
             face_tangents.push(tangent_y);
             face_tangents.push(tangent_z);
 
-            total_indices_processed += vertices_in_face as u16;
+            total_indices_processed += vertices_in_face as usize;
         }
 
-        self.face_tangents = Some(face_tangents);
-
-        Ok(())
-    }
-}
-
-impl BlenderMesh {
-    /// Given a face idx, get the corresponding tangent
-    pub(crate) fn face_tangent_at_idx(&self, face_idx: usize) -> (f32, f32, f32) {
-        let face_idx = face_idx as usize;
-        let face_tangents = self.face_tangents.as_ref().unwrap();
-
-        (
-            face_tangents[face_idx * 3],
-            face_tangents[face_idx * 3 + 1],
-            face_tangents[face_idx * 3 + 2],
-        )
+        Ok(face_tangents)
     }
 }
 
@@ -123,9 +108,9 @@ mod tests {
     /// Ensure that a mesh with no uvs returns TangentError::NoVertexUvs
     #[test]
     fn no_vertex_uvs() {
-        let mut mesh: BlenderMesh = BlenderMesh::default();
+        let vertex_data = MultiIndexVertexData::default();
 
-        match mesh.calculate_face_tangents() {
+        match vertex_data.calculate_face_tangents() {
             Ok(_) => unreachable!(),
             Err(TangentError::NoVertexUvs) => {}
         }
@@ -134,72 +119,75 @@ mod tests {
     /// Properly calculates tangents for a mesh that has one triangle
     #[test]
     fn calculate_tangents_1_triangle() {
-        let mut mesh: BlenderMesh = BlenderMesh {
-            vertex_positions: concat_vecs!(v(0), vec![1.0, 0.0, 0.0], vec![1.0, 1.0, 0.0]),
-            vertex_position_indices: vec![0, 1, 2],
-            vertex_uvs: Some(concat_vecs!(v2(0), vec![0.5, 0.0], v2(1))),
-            vertex_uv_indices: Some(vec![0, 1, 2]),
-            num_vertices_in_each_face: vec![3],
-            ..BlenderMesh::default()
+        let vertex_data = MultiIndexVertexData {
+            positions: attrib3(concat_vecs!(v(0), vec![1.0, 0.0, 0.0], vec![1.0, 1.0, 0.0])),
+            position_indices: vec![0, 1, 2],
+            uvs: Some(attrib2(concat_vecs!(v2(0), vec![0.5, 0.0], v2(1)))),
+            uv_indices: Some(vec![0, 1, 2]),
+            vertices_in_each_face: vec![3],
+            ..MultiIndexVertexData::default()
         };
 
-        mesh.calculate_face_tangents().unwrap();
-
         assert_eq!(
-            mesh.face_tangents.as_ref().unwrap(),
+            vertex_data.calculate_face_tangents().unwrap(),
             // One face (a triangle) so only one face tangent vector
-            &vec![2., 0., 0.]
+            vec![2., 0., 0.]
         )
     }
 
     #[test]
     fn calculate_tangents_2_triangle() {
-        let mut mesh: BlenderMesh = BlenderMesh {
-            vertex_positions: concat_vecs!(
+        let vertex_data = MultiIndexVertexData {
+            positions: attrib3(concat_vecs!(
                 v(0),
                 vec![1.0, 0.0, 0.0],
                 vec![1.0, 1.0, 0.0],
                 vec![0., 1., 0.]
-            ),
-            vertex_position_indices: vec![0, 1, 2, 0, 2, 3],
-            vertex_uvs: Some(concat_vecs!(v2(0), vec![0.5, 0.0], v2(1), vec![0., 1.])),
-            vertex_uv_indices: Some(vec![0, 1, 2, 0, 2, 3]),
-            num_vertices_in_each_face: vec![3, 3],
-            ..BlenderMesh::default()
+            )),
+            position_indices: vec![0, 1, 2, 0, 2, 3],
+            uvs: Some(attrib2(concat_vecs!(
+                v2(0),
+                vec![0.5, 0.0],
+                v2(1),
+                vec![0., 1.]
+            ))),
+            uv_indices: Some(vec![0, 1, 2, 0, 2, 3]),
+            vertices_in_each_face: vec![3, 3],
+            ..MultiIndexVertexData::default()
         };
 
-        mesh.calculate_face_tangents().unwrap();
-
         assert_eq!(
-            mesh.face_tangents.as_ref().unwrap(),
+            vertex_data.calculate_face_tangents().unwrap(),
             // Two faces (two triangles) so two tangent vectors
-            &vec![2., 0., 0., 1., 0., 0.]
+            vec![2., 0., 0., 1., 0., 0.]
         )
     }
 
     #[test]
     fn calculate_tangents_1_quad() {
-        let mut mesh: BlenderMesh = BlenderMesh {
-            vertex_positions: concat_vecs!(
+        let vertex_data = MultiIndexVertexData {
+            positions: attrib3(concat_vecs!(
                 v(0),
                 vec![1.0, 0.0, 0.0],
                 vec![1.0, 1.0, 0.0],
                 vec![0., 1., 0.]
-            ),
-            vertex_position_indices: vec![0, 1, 2, 3],
-            vertex_uvs: Some(concat_vecs!(v2(0), vec![0.5, 0.0], v2(1), vec![0., 1.])),
-            vertex_uv_indices: Some(vec![0, 1, 2, 3]),
-            num_vertices_in_each_face: vec![4],
-            ..BlenderMesh::default()
+            )),
+            position_indices: vec![0, 1, 2, 3],
+            uvs: Some(attrib2(concat_vecs!(
+                v2(0),
+                vec![0.5, 0.0],
+                v2(1),
+                vec![0., 1.]
+            ))),
+            uv_indices: Some(vec![0, 1, 2, 3]),
+            vertices_in_each_face: vec![4],
+            ..MultiIndexVertexData::default()
         };
 
-        mesh.calculate_face_tangents().unwrap();
-
         assert_eq!(
-            mesh.face_tangents.as_ref().unwrap(),
+            vertex_data.calculate_face_tangents().unwrap(),
             // Two faces (two triangles) so two tangent vectors
-            &vec![2., 0., 0.]
+            vec![2., 0., 0.]
         )
     }
-
 }
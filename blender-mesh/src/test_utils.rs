@@ -1,4 +1,5 @@
 use crate::concat_vecs;
+use crate::vertex_data::{AttributeSize, SingleIndexVertexData, VertexAttribute};
 
 /// Create a 3 dimensional vector with all three values the same.
 /// Useful for quickly generating some fake vertex data.
@@ -31,3 +32,67 @@ pub fn v3_x4(v1: u8, v2: u8, v3: u8, v4: u8) -> Vec<f32> {
 pub fn v3_x3(v1: u8, v2: u8, v3: u8) -> Vec<f32> {
     concat_vecs!(v(v1), v(v2), v(v3))
 }
+
+/// A vertex attribute with three components per vertex, such as positions or normals
+pub fn attrib3(data: Vec<f32>) -> VertexAttribute {
+    VertexAttribute::new(data, AttributeSize::Three)
+}
+
+/// A vertex attribute with two components per vertex, such as uvs
+pub fn attrib2(data: Vec<f32>) -> VertexAttribute {
+    VertexAttribute::new(data, AttributeSize::Two)
+}
+
+/// The corners of every quad in a `size` x `size` grid of quads, counter clockwise and row by
+/// row. Vertex `x + y * (size + 1)` is at `(x, y)`.
+///
+/// ```text
+/// 3 - 4 - 5
+/// |   |   |
+/// 0 - 1 - 2
+/// ```
+pub fn grid_quads(size: u32) -> Vec<u32> {
+    let row = size + 1;
+
+    let mut quads = vec![];
+    for y in 0..size {
+        for x in 0..size {
+            let corner = y * row + x;
+            quads.extend_from_slice(&[corner, corner + 1, corner + row + 1, corner + row]);
+        }
+    }
+
+    quads
+}
+
+/// A `size` x `size` grid of quads in the xy plane with each vertex raised by `height`, and each
+/// quad split into two triangles. Normals face +z and uvs go from 0.0 to 1.0 across the grid.
+pub fn grid(size: u32, height: impl Fn(f32, f32) -> f32) -> SingleIndexVertexData {
+    let row = size + 1;
+
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+    for y in 0..row {
+        for x in 0..row {
+            let (x, y) = (x as f32, y as f32);
+            positions.extend_from_slice(&[x, y, height(x, y)]);
+            normals.extend_from_slice(&[0.0, 0.0, 1.0]);
+            uvs.extend_from_slice(&[x / size as f32, y / size as f32]);
+        }
+    }
+
+    let indices: Vec<u32> = grid_quads(size)
+        .chunks(4)
+        .flat_map(|quad| vec![quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]])
+        .collect();
+
+    SingleIndexVertexData {
+        vertices_in_each_face: vec![3; indices.len() / 3],
+        indices,
+        positions: attrib3(positions),
+        normals: attrib3(normals),
+        uvs: Some(attrib2(uvs)),
+        ..SingleIndexVertexData::default()
+    }
+}
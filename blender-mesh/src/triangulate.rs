@@ -1,87 +1,594 @@
+use crate::vertex_data::{VertexAttribute, VertexData};
 use crate::BlenderMesh;
 
+/// Configuration for splitting a mesh's faces into triangles
+#[derive(Debug, Default)]
+pub struct TriangulateConfig {
+    /// Split each quad along whichever of its two diagonals is shorter.
+    ///
+    /// By default quads are split from their first vertex to their third. Choosing the shorter
+    /// diagonal avoids long, thin (sliver) triangles on stretched quads.
+    pub shortest_quad_diagonal: bool,
+}
+
+/// One triangle that was cut out of a face.
+///
+/// `corners` are offsets into the face's vertices, so `[0, 2, 3]` is the face's first, third and
+/// fourth vertex.
+#[derive(Debug, PartialEq)]
+pub(crate) struct FaceTriangle {
+    /// The face that this triangle was cut out of
+    pub(crate) face: usize,
+    /// Where the face's vertices start in the mesh's index vectors
+    pub(crate) face_start: usize,
+    pub(crate) corners: [usize; 3],
+}
+
 impl BlenderMesh {
     /// When exporting a mesh from Blender, faces will usually have 4 vertices (quad) but some
-    /// faces might have 3 (triangle).
+    /// faces might have 3 (triangle) or more (n-gons, such as the caps of a cylinder).
     ///
-    /// We read `self.num_vertices_in_each_face` to check how
+    /// We read the vertex data's `vertices_in_each_face` to check how
     /// many vertices each face has.
     ///
-    /// If a face has 4 vertices we convert it into two triangles, each with 3 vertices.
+    /// Faces with more than 3 vertices are split into triangles using
+    /// [`BlenderMesh.triangulate_with_config`] with the default configuration.
     ///
-    /// # Panics
-    ///
-    /// Panics if a face has more than 4 vertices. In the future we might support 5+ vertices,
-    /// but I haven't run into that yet. Not even sure if Blender can have faces with 5 vertices..
+    /// [`BlenderMesh.triangulate_with_config`]: struct.BlenderMesh.html#method.triangulate_with_config
     pub fn triangulate(&mut self) {
-        let mut triangulated_position_indices = vec![];
-        let mut triangulated_face_vertex_counts = vec![];
+        self.triangulate_with_config(&TriangulateConfig::default());
+    }
 
-        let mut face_pointer = 0;
+    /// Split every face with more than 3 vertices into triangles.
+    ///
+    /// Each face is projected onto its best fit plane and then split using ear clipping, so
+    /// concave faces are triangulated without any triangles falling outside of the face.
+    ///
+    /// The position, normal and uv indices all get split the same way, so this works both
+    /// before and after `combine_vertex_indices`.
+    pub fn triangulate_with_config(&mut self, config: &TriangulateConfig) {
+        match &mut self.vertex_data {
+            VertexData::Multi(multi) => {
+                let triangles = face_triangles(
+                    &multi.vertices_in_each_face,
+                    &multi.position_indices,
+                    &multi.positions,
+                    config,
+                );
 
-        for num_verts_in_face in self.num_vertices_in_each_face.iter() {
-            match num_verts_in_face {
-                &3 => {
-                    triangulated_face_vertex_counts.push(3);
+                multi.position_indices = triangulate_indices(&triangles, &multi.position_indices);
+                multi.normal_indices = triangulate_indices(&triangles, &multi.normal_indices);
+                if let Some(uv_indices) = multi.uv_indices.as_ref() {
+                    multi.uv_indices = Some(triangulate_indices(&triangles, uv_indices));
+                }
+                if let Some(smooth_faces) = multi.smooth_faces.as_ref() {
+                    multi.smooth_faces = Some(per_triangle(&triangles, smooth_faces));
+                }
+                if let Some(face_materials) = multi.face_materials.as_ref() {
+                    multi.face_materials = Some(per_triangle(&triangles, face_materials));
+                }
 
-                    triangulated_position_indices.push(self.vertex_position_indices[face_pointer]);
-                    triangulated_position_indices
-                        .push(self.vertex_position_indices[face_pointer + 1]);
-                    triangulated_position_indices
-                        .push(self.vertex_position_indices[face_pointer + 2]);
+                multi.vertices_in_each_face = vec![3; triangles.len()];
+            }
+            VertexData::Single(single) => {
+                let triangles = face_triangles(
+                    &single.vertices_in_each_face,
+                    &single.indices,
+                    &single.positions,
+                    config,
+                );
 
-                    face_pointer += 3;
+                single.indices = triangulate_indices(&triangles, &single.indices);
+                if let Some(face_materials) = single.face_materials.as_ref() {
+                    single.face_materials = Some(per_triangle(&triangles, face_materials));
                 }
-                &4 => {
-                    triangulated_face_vertex_counts.push(3);
-                    triangulated_face_vertex_counts.push(3);
-
-                    triangulated_position_indices.push(self.vertex_position_indices[face_pointer]);
-                    triangulated_position_indices
-                        .push(self.vertex_position_indices[face_pointer + 1]);
-                    triangulated_position_indices
-                        .push(self.vertex_position_indices[face_pointer + 2]);
-                    triangulated_position_indices.push(self.vertex_position_indices[face_pointer]);
-                    triangulated_position_indices
-                        .push(self.vertex_position_indices[face_pointer + 2]);
-                    triangulated_position_indices
-                        .push(self.vertex_position_indices[face_pointer + 3]);
-
-                    face_pointer += 4;
-                }
-                _ => {
-                    panic!("blender-mesh currently only supports triangulating faces with 3 or 4 vertices");
+
+                single.vertices_in_each_face = vec![3; triangles.len()];
+            }
+        };
+    }
+}
+
+/// The indices of every triangle's corners
+fn triangulate_indices(triangles: &[FaceTriangle], indices: &[u32]) -> Vec<u32> {
+    triangles
+        .iter()
+        .flat_map(|triangle| {
+            let start = triangle.face_start;
+            triangle.corners.iter().map(move |c| indices[start + c])
+        })
+        .collect()
+}
+
+/// Per face data for every triangle, taken from the face that the triangle was split from
+fn per_triangle<T: Copy>(triangles: &[FaceTriangle], faces: &[T]) -> Vec<T> {
+    triangles
+        .iter()
+        .map(|triangle| faces[triangle.face])
+        .collect()
+}
+
+/// Split every face into triangles
+pub(crate) fn face_triangles(
+    vertices_in_each_face: &[u8],
+    position_indices: &[u32],
+    positions: &VertexAttribute,
+    config: &TriangulateConfig,
+) -> Vec<FaceTriangle> {
+    let mut triangles = vec![];
+
+    let mut face_start = 0;
+
+    for (face, num_verts_in_face) in vertices_in_each_face.iter().enumerate() {
+        let num_verts_in_face = *num_verts_in_face as usize;
+
+        let corners = match num_verts_in_face {
+            0..=2 => vec![],
+            3 => vec![[0, 1, 2]],
+            _ => {
+                let positions: Vec<(f32, f32, f32)> = position_indices
+                    [face_start..face_start + num_verts_in_face]
+                    .iter()
+                    .map(|pos_idx| positions.three_components(*pos_idx as usize))
+                    .collect();
+                let polygon = project_onto_best_fit_plane(&positions);
+
+                if num_verts_in_face == 4 && config.shortest_quad_diagonal {
+                    split_quad_along_shortest_diagonal(&positions, &polygon)
+                } else {
+                    ear_clip(&polygon)
                 }
             }
+        };
+
+        for corners in corners {
+            triangles.push(FaceTriangle {
+                face,
+                face_start,
+                corners,
+            });
         }
 
-        self.vertex_position_indices = triangulated_position_indices;
-        self.num_vertices_in_each_face = triangulated_face_vertex_counts;
+        face_start += num_verts_in_face;
+    }
+
+    triangles
+}
+
+/// Project a face's positions onto the plane that best fits them.
+///
+/// We calculate the face normal using Newell's method (which is robust for concave and slightly
+/// non planar faces) and then drop the axis that the normal points along the most.
+///
+/// @see https://www.khronos.org/opengl/wiki/Calculating_a_Surface_Normal#Newell.27s_Method
+fn project_onto_best_fit_plane(positions: &[(f32, f32, f32)]) -> Vec<(f32, f32)> {
+    let mut normal = (0.0, 0.0, 0.0);
+
+    for (idx, current) in positions.iter().enumerate() {
+        let next = positions[(idx + 1) % positions.len()];
+
+        normal.0 += (current.1 - next.1) * (current.2 + next.2);
+        normal.1 += (current.2 - next.2) * (current.0 + next.0);
+        normal.2 += (current.0 - next.0) * (current.1 + next.1);
+    }
+
+    let (x, y, z) = (normal.0.abs(), normal.1.abs(), normal.2.abs());
+
+    positions
+        .iter()
+        .map(|p| {
+            if z >= x && z >= y {
+                (p.0, p.1)
+            } else if x >= y {
+                (p.1, p.2)
+            } else {
+                (p.2, p.0)
+            }
+        })
+        .collect()
+}
+
+/// Triangulate a simple (possibly concave) polygon by repeatedly cutting off ears.
+///
+/// An ear is a convex vertex whose triangle (previous vertex, vertex, next vertex) contains no
+/// other vertex of the polygon. Every simple polygon with more than 3 vertices has at least two.
+///
+/// We start looking for ears at the second vertex, so convex polygons are split into a fan around
+/// the first vertex. A quad becomes `[0, 1, 2]` and `[0, 2, 3]`.
+///
+/// @see https://www.geometrictools.com/Documentation/TriangulationByEarClipping.pdf
+fn ear_clip(polygon: &[(f32, f32)]) -> Vec<[usize; 3]> {
+    let mut triangles = vec![];
+
+    // Works for clockwise and counter clockwise polygons
+    let orientation = if signed_area(polygon) < 0.0 {
+        -1.0
+    } else {
+        1.0
+    };
+
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+
+    while remaining.len() > 3 {
+        let len = remaining.len();
+
+        let corner = |idx: usize| {
+            (
+                remaining[(idx + len - 1) % len],
+                remaining[idx],
+                remaining[(idx + 1) % len],
+            )
+        };
+
+        let ear = (0..len).map(|offset| (offset + 1) % len).find(|idx| {
+            let (prev, current, next) = corner(*idx);
+            is_ear(polygon, &remaining, prev, current, next, orientation)
+        });
+
+        // Degenerate polygons (such as ones with all of their vertices in a line) might not have
+        // any proper ears. We still need to consume a vertex so we clip the next one in line.
+        let ear = ear.unwrap_or(1);
+
+        let (prev, current, next) = corner(ear);
+        triangles.push([prev, current, next]);
+        remaining.remove(ear);
+    }
+
+    if remaining.len() == 3 {
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
     }
+
+    triangles
+}
+
+fn is_ear(
+    polygon: &[(f32, f32)],
+    remaining: &[usize],
+    prev: usize,
+    current: usize,
+    next: usize,
+    orientation: f32,
+) -> bool {
+    let (a, b, c) = (polygon[prev], polygon[current], polygon[next]);
+
+    // Reflex (and collinear) vertices can't be ears
+    if orientation * cross(a, b, c) <= 0.0 {
+        return false;
+    }
+
+    remaining
+        .iter()
+        .filter(|idx| **idx != prev && **idx != current && **idx != next)
+        .map(|idx| polygon[*idx])
+        // Vertices that share a position with the triangle's corners can't be inside of it
+        .filter(|p| *p != a && *p != b && *p != c)
+        .all(|p| !point_in_triangle(p, a, b, c, orientation))
+}
+
+fn split_quad_along_shortest_diagonal(
+    positions: &[(f32, f32, f32)],
+    quad: &[(f32, f32)],
+) -> Vec<[usize; 3]> {
+    let orientation = if signed_area(quad) < 0.0 { -1.0 } else { 1.0 };
+    let is_convex =
+        |idx: usize| orientation * cross(quad[(idx + 3) % 4], quad[idx], quad[(idx + 1) % 4]) > 0.0;
+
+    // A diagonal only lies inside of the quad if the two vertices that it doesn't touch are
+    // convex
+    let can_split_0_2 = is_convex(1) && is_convex(3);
+    let can_split_1_3 = is_convex(0) && is_convex(2);
+
+    let split_1_3 = [[0, 1, 3], [1, 2, 3]];
+    let split_0_2 = [[0, 1, 2], [0, 2, 3]];
+
+    let shorter_1_3 =
+        distance_squared(positions[1], positions[3]) < distance_squared(positions[0], positions[2]);
+
+    if can_split_1_3 && (shorter_1_3 || !can_split_0_2) {
+        split_1_3.to_vec()
+    } else {
+        split_0_2.to_vec()
+    }
+}
+
+/// Twice the signed area of a polygon. Positive when counter clockwise.
+fn signed_area(polygon: &[(f32, f32)]) -> f32 {
+    polygon
+        .iter()
+        .enumerate()
+        .map(|(idx, current)| {
+            let next = polygon[(idx + 1) % polygon.len()];
+            current.0 * next.1 - next.0 * current.1
+        })
+        .sum()
+}
+
+/// The z component of (b - a) x (c - b). Positive when a -> b -> c turns counter clockwise.
+fn cross(a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> f32 {
+    (b.0 - a.0) * (c.1 - b.1) - (b.1 - a.1) * (c.0 - b.0)
+}
+
+/// Whether or not a point lies inside of (or on the edge of) a triangle
+fn point_in_triangle(
+    p: (f32, f32),
+    a: (f32, f32),
+    b: (f32, f32),
+    c: (f32, f32),
+    orientation: f32,
+) -> bool {
+    orientation * cross(a, b, p) >= 0.0
+        && orientation * cross(b, c, p) >= 0.0
+        && orientation * cross(c, a, p) >= 0.0
+}
+
+fn distance_squared(a: (f32, f32, f32), b: (f32, f32, f32)) -> f32 {
+    (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::concat_vecs;
+    use crate::morph_target::MorphTarget;
+    use crate::test_utils::*;
+    use crate::vertex_data::{MultiIndexVertexData, SingleIndexVertexData};
 
     #[test]
     fn triangulate_faces() {
-        let mut start_mesh = BlenderMesh {
-            vertex_position_indices: vec![0, 1, 2, 3, 4, 5, 6, 7],
-            num_vertices_in_each_face: vec![4, 4],
-            ..BlenderMesh::default()
-        };
+        let mut start_mesh = multi_indexed_mesh(
+            concat_vecs!(unit_square(), unit_square()),
+            vec![0, 1, 2, 3, 4, 5, 6, 7],
+            vec![4, 4],
+        );
 
         start_mesh.triangulate();
         let triangulated_mesh = start_mesh;
 
-        let expected_mesh = BlenderMesh {
-            vertex_position_indices: vec![0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7],
-            num_vertices_in_each_face: vec![3, 3, 3, 3],
+        let expected_mesh = multi_indexed_mesh(
+            concat_vecs!(unit_square(), unit_square()),
+            vec![0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7],
+            vec![3, 3, 3, 3],
+        );
+
+        assert_eq!(triangulated_mesh, expected_mesh);
+    }
+
+    /// The normal and uv indices need to be split in the same way as the position indices
+    #[test]
+    fn triangulate_keeps_normal_and_uv_indices_in_sync() {
+        let mut mesh = BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                positions: attrib3(concat_vecs!(unit_square(), vec![0.5, 1.5, 0.0])),
+                position_indices: vec![0, 1, 2, 4, 3],
+                normal_indices: vec![10, 11, 12, 14, 13],
+                uv_indices: Some(vec![20, 21, 22, 24, 23]),
+                vertices_in_each_face: vec![5],
+                ..MultiIndexVertexData::default()
+            }),
             ..BlenderMesh::default()
         };
 
-        assert_eq!(triangulated_mesh, expected_mesh);
+        mesh.triangulate();
+
+        let multi = mesh.multi_index_vertex_data().unwrap();
+        assert_eq!(multi.vertices_in_each_face, vec![3, 3, 3]);
+        assert_eq!(multi.position_indices, vec![0, 1, 2, 0, 2, 4, 0, 4, 3]);
+        assert_eq!(
+            multi.normal_indices,
+            vec![10, 11, 12, 10, 12, 14, 10, 14, 13]
+        );
+        assert_eq!(
+            multi.uv_indices.as_ref().unwrap(),
+            &vec![20, 21, 22, 20, 22, 24, 20, 24, 23]
+        );
+    }
+
+    /// Every triangle keeps the smooth shading flag and material of the face that it was cut
+    /// out of
+    #[test]
+    fn triangulate_keeps_per_face_data_in_sync() {
+        let mut mesh = multi_indexed_mesh(
+            concat_vecs!(unit_square(), unit_square()),
+            vec![0, 1, 2, 4, 5, 6, 7],
+            vec![3, 4],
+        );
+        if let VertexData::Multi(multi) = &mut mesh.vertex_data {
+            multi.smooth_faces = Some(vec![false, true]);
+            multi.face_materials = Some(vec![1, 0]);
+        }
+
+        mesh.triangulate();
+
+        let multi = mesh.multi_index_vertex_data().unwrap();
+        assert_eq!(multi.smooth_faces(), Some(&vec![false, true, true]));
+        assert_eq!(multi.face_materials(), Some(&vec![1, 0, 0]));
     }
 
+    /// Single indexed meshes are triangulated the same way as multi indexed meshes
+    #[test]
+    fn triangulate_single_indexed_mesh() {
+        let mut mesh = BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                positions: attrib3(unit_square()),
+                indices: vec![0, 1, 2, 3],
+                vertices_in_each_face: vec![4],
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        mesh.triangulate();
+
+        let single = mesh.single_index_vertex_data().unwrap();
+        assert_eq!(single.vertices_in_each_face, vec![3, 3]);
+        assert_eq!(single.indices, vec![0, 1, 2, 0, 2, 3]);
+    }
+
+    /// Triangulating only changes the faces, so morph target deltas still line up with the
+    /// vertices that they move
+    #[test]
+    fn triangulate_keeps_morph_targets() {
+        let mut mesh = multi_indexed_mesh(unit_square(), vec![0, 1, 2, 3], vec![4]);
+        let target = MorphTarget::new(
+            "Raise".to_string(),
+            0.0,
+            vec![0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.0, 0.0, 3.0, 0.0, 0.0, 4.0],
+            vec![0.0; 12],
+        );
+        if let VertexData::Multi(multi) = &mut mesh.vertex_data {
+            multi.normals = attrib3([0.0, 0.0, 1.0].repeat(4));
+            multi.morph_targets = vec![target.clone()];
+        }
+
+        mesh.triangulate();
+        mesh.combine_vertex_indices(&Default::default()).unwrap();
+
+        assert_eq!(
+            mesh.morph_targets()[0].position_deltas(),
+            target.position_deltas()
+        );
+    }
+
+    /// An L shaped hexagon. A fan from the first vertex would create a triangle outside of
+    /// the face.
+    #[test]
+    fn triangulate_concave_ngon() {
+        let mut mesh = multi_indexed_mesh(l_shape_in_xz_plane(), vec![0, 1, 2, 3, 4, 5], vec![6]);
+
+        mesh.triangulate();
+
+        let multi = mesh.multi_index_vertex_data().unwrap();
+        assert_eq!(multi.vertices_in_each_face, vec![3, 3, 3, 3]);
+        assert_triangles_cover_polygon(multi, 3.0);
+    }
+
+    /// Vertex 1 of this quad is reflex, so the quad can only be split along its 1 -> 3 diagonal
+    #[test]
+    fn triangulate_concave_quad() {
+        let mut mesh = multi_indexed_mesh(
+            concat_vecs!(
+                vec![0.0, 0.0, 0.0],
+                vec![1.0, 0.8, 0.0],
+                vec![2.0, 0.0, 0.0],
+                vec![1.0, 2.0, 0.0]
+            ),
+            vec![0, 1, 2, 3],
+            vec![4],
+        );
+
+        mesh.triangulate();
+
+        assert_eq!(
+            mesh.multi_index_vertex_data().unwrap().position_indices,
+            vec![1, 2, 3, 0, 1, 3]
+        );
+    }
+
+    #[test]
+    fn split_quad_along_shortest_diagonal() {
+        // A quad that is much longer along its 0 -> 2 diagonal
+        let positions = concat_vecs!(
+            vec![0.0, 0.0, 0.0],
+            vec![5.0, 0.0, 0.0],
+            vec![6.0, 1.0, 0.0],
+            vec![1.0, 1.0, 0.0]
+        );
+
+        let mut mesh = multi_indexed_mesh(positions.clone(), vec![0, 1, 2, 3], vec![4]);
+        mesh.triangulate();
+        assert_eq!(
+            mesh.multi_index_vertex_data().unwrap().position_indices,
+            vec![0, 1, 2, 0, 2, 3]
+        );
+
+        let mut mesh = multi_indexed_mesh(positions, vec![0, 1, 2, 3], vec![4]);
+        mesh.triangulate_with_config(&TriangulateConfig {
+            shortest_quad_diagonal: true,
+        });
+        assert_eq!(
+            mesh.multi_index_vertex_data().unwrap().position_indices,
+            vec![0, 1, 3, 1, 2, 3]
+        );
+    }
+
+    /// The polygon is projected onto its best fit plane, so faces that are facing any direction
+    /// (and are wound either way) get triangulated
+    #[test]
+    fn project_onto_best_fit_plane_handles_any_facing_direction() {
+        let polygon: Vec<(f32, f32, f32)> = l_shape_in_xz_plane()
+            .chunks(3)
+            .map(|p| (p[0], p[1], p[2]))
+            .collect();
+
+        // Facing -y
+        assert_eq!(ear_clip(&project_onto_best_fit_plane(&polygon)).len(), 4);
+
+        // Facing +y
+        let mut reversed = polygon.clone();
+        reversed.reverse();
+        assert_eq!(ear_clip(&project_onto_best_fit_plane(&reversed)).len(), 4);
+    }
+
+    /// A multi indexed mesh where every face vertex has its own normal index
+    fn multi_indexed_mesh(
+        positions: Vec<f32>,
+        position_indices: Vec<u32>,
+        vertices_in_each_face: Vec<u8>,
+    ) -> BlenderMesh {
+        BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                positions: attrib3(positions),
+                normal_indices: position_indices.clone(),
+                position_indices,
+                vertices_in_each_face,
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        }
+    }
+
+    fn unit_square() -> Vec<f32> {
+        concat_vecs!(
+            v(0),
+            vec![1.0, 0.0, 0.0],
+            vec![1.0, 1.0, 0.0],
+            vec![0.0, 1.0, 0.0]
+        )
+    }
+
+    /// (0,0) -> (2,0) -> (2,1) -> (1,1) -> (1,2) -> (0,2), which has an area of 3
+    fn l_shape_in_xz_plane() -> Vec<f32> {
+        concat_vecs!(
+            vec![0.0, 0.0, 0.0],
+            vec![2.0, 0.0, 0.0],
+            vec![2.0, 0.0, 1.0],
+            vec![1.0, 0.0, 1.0],
+            vec![1.0, 0.0, 2.0],
+            vec![0.0, 0.0, 2.0]
+        )
+    }
+
+    /// When every triangle is inside of the polygon the triangles' areas add up to the polygon's
+    /// area, and they all have the same winding.
+    fn assert_triangles_cover_polygon(multi: &MultiIndexVertexData, polygon_area: f32) {
+        let mut total_area = 0.0;
+
+        for triangle in multi.position_indices.chunks(3) {
+            let a = multi.vertex_pos_at_idx(triangle[0]);
+            let b = multi.vertex_pos_at_idx(triangle[1]);
+            let c = multi.vertex_pos_at_idx(triangle[2]);
+
+            // All of our test polygons are in the xz plane, facing -y
+            let area_neg_y = (b.0 - a.0) * (c.2 - a.2) - (b.2 - a.2) * (c.0 - a.0);
+            assert!(
+                area_neg_y > 0.0,
+                "Triangle {:?} has the wrong winding",
+                triangle
+            );
+
+            total_area += area_neg_y / 2.0;
+        }
+
+        assert!((total_area - polygon_area).abs() < 1e-5);
+    }
 }
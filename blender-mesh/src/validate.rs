@@ -0,0 +1,869 @@
+//! Checking a mesh for data that would cause problems later on, such as indices that point past
+//! the end of an attribute or NaNs in the positions, so that bad exports are caught before
+//! they reach a renderer.
+
+use crate::combine_indices::EASILY_RECOGNIZABLE_NUMBER;
+use crate::interleave::AttributeKind;
+use crate::material::{MaterialInput, PrincipledBSDF};
+use crate::vertex_data::{BoneInfluences, VertexAttribute, VertexData};
+use crate::{BlenderMesh, BoneAttributes};
+use blender_armature::BlenderArmature;
+use nalgebra::Vector3;
+use std::path::PathBuf;
+
+/// Configuration for validating a mesh
+#[derive(Debug)]
+pub struct ValidateConfig<'a> {
+    /// The armature that the mesh is parented to. If set we check that every bone index is one
+    /// of the armature's joints.
+    pub armature: Option<&'a BlenderArmature>,
+    /// The directory that the mesh's textures are in. If set we check that every texture that
+    /// the mesh's materials use is in this directory.
+    pub texture_directory: Option<PathBuf>,
+    /// How far a normal's length can be from `1.0`
+    pub normal_tolerance: f32,
+    /// How far the sum of a vertex's bone weights can be from `1.0`
+    pub bone_weight_tolerance: f32,
+    /// Faces with less area than this are zero area faces
+    pub min_face_area: f32,
+}
+
+impl<'a> Default for ValidateConfig<'a> {
+    fn default() -> Self {
+        ValidateConfig {
+            armature: None,
+            texture_directory: None,
+            normal_tolerance: 1e-3,
+            bone_weight_tolerance: 1e-3,
+            min_face_area: 1e-10,
+        }
+    }
+}
+
+/// How much of a problem an issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The mesh will probably still render, but might not look the way that you expect
+    Warning,
+    /// The mesh will not render correctly, or will cause a panic when it is processed
+    Error,
+}
+
+/// Something wrong with a mesh
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+}
+
+/// The different problems that we look for when validating a mesh.
+///
+/// Vertices are indices into the attribute that the issue is about, and corners are indices
+/// into the mesh's index buffer (or `position_indices`, `normal_indices` or `uv_indices`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueKind {
+    /// An attribute doesn't have one index for every vertex of every face
+    IndexCountMismatch {
+        attribute: AttributeKind,
+        expected: usize,
+        actual: usize,
+    },
+    /// An index points past the end of its attribute
+    IndexOutOfRange {
+        attribute: AttributeKind,
+        corner: usize,
+        index: u32,
+        vertex_count: usize,
+    },
+    /// A single indexed attribute has a different number of vertices than the positions
+    VertexCountMismatch {
+        attribute: AttributeKind,
+        expected: usize,
+        actual: usize,
+    },
+    /// Per face data such as `smooth_faces` or `face_materials` doesn't have one entry per face
+    FaceCountMismatch {
+        field: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    /// A NaN or infinite component
+    NonFinite {
+        attribute: AttributeKind,
+        vertex: usize,
+    },
+    /// A component that still has the placeholder value from combining indices, which means
+    /// that it was never filled in
+    PlaceholderValue {
+        attribute: AttributeKind,
+        vertex: usize,
+    },
+    /// A face that uses the same vertex more than once
+    DegenerateFace { face: usize },
+    /// A face whose vertices are all in a line, or in the same place
+    ZeroAreaFace { face: usize, area: f32 },
+    /// A normal that isn't unit length
+    UnnormalizedNormal { vertex: usize, length: f32 },
+    /// A vertex whose bone weights don't add up to one
+    BoneWeightSum { vertex: usize, sum: f32 },
+    /// A vertex is influenced by a bone that the parent armature doesn't have
+    BoneIndexOutOfRange {
+        vertex: usize,
+        bone: u8,
+        bone_count: usize,
+    },
+    /// Vertices that no face uses, such as the ones that combining indices leaves behind for
+    /// positions without any faces. Their values aren't checked.
+    UnusedVertices { count: usize },
+    /// A face uses a material slot that the mesh doesn't have
+    FaceMaterialOutOfRange {
+        face: usize,
+        slot: u16,
+        slot_count: usize,
+    },
+    /// A material slot names a material that wasn't exported with the mesh
+    MissingMaterial { slot: usize, material: String },
+    /// A material uses a texture that has no name or isn't in the texture directory
+    MissingTexture { material: String, texture: String },
+}
+
+impl IssueKind {
+    /// How much of a problem this kind of issue is
+    pub fn severity(&self) -> Severity {
+        match self {
+            IssueKind::DegenerateFace { .. }
+            | IssueKind::ZeroAreaFace { .. }
+            | IssueKind::UnnormalizedNormal { .. }
+            | IssueKind::BoneWeightSum { .. }
+            | IssueKind::UnusedVertices { .. }
+            | IssueKind::MissingMaterial { .. }
+            | IssueKind::MissingTexture { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl From<IssueKind> for ValidationIssue {
+    fn from(kind: IssueKind) -> Self {
+        ValidationIssue {
+            severity: kind.severity(),
+            kind,
+        }
+    }
+}
+
+impl BlenderMesh {
+    /// Check the mesh for problems, returning every issue that we find.
+    ///
+    /// An empty list means that the mesh looks good.
+    pub fn validate(&self, config: &ValidateConfig) -> Vec<ValidationIssue> {
+        let mut issues = vec![];
+        let mut issue = |kind: IssueKind| issues.push(ValidationIssue::from(kind));
+
+        match &self.vertex_data {
+            VertexData::Multi(multi) => {
+                let corner_count = count_corners(&multi.vertices_in_each_face);
+
+                let mut indexed = vec![(
+                    AttributeKind::Position,
+                    &multi.positions,
+                    &multi.position_indices,
+                )];
+                if !multi.normals.data().is_empty() || !multi.normal_indices.is_empty() {
+                    indexed.push((AttributeKind::Normal, &multi.normals, &multi.normal_indices));
+                }
+                if let (Some(uvs), Some(uv_indices)) = (&multi.uvs, &multi.uv_indices) {
+                    indexed.push((AttributeKind::Uv, uvs, uv_indices));
+                }
+
+                let mut indices_valid = true;
+                for (attribute, data, indices) in indexed.iter() {
+                    indices_valid &=
+                        validate_indices(*attribute, data, indices, corner_count, &mut issue);
+                }
+
+                if indices_valid {
+                    validate_faces(
+                        &multi.vertices_in_each_face,
+                        &multi.position_indices,
+                        &multi.positions,
+                        config,
+                        &mut issue,
+                    );
+                }
+
+                if let Some(influences) = multi.bone_influences.as_ref() {
+                    validate_bone_influences(
+                        influences,
+                        multi.positions.vertex_count(),
+                        config,
+                        &mut issue,
+                    );
+                }
+
+                validate_values(AttributeKind::Position, &multi.positions, None, &mut issue);
+                validate_values(AttributeKind::Normal, &multi.normals, None, &mut issue);
+                validate_normals(&multi.normals, None, config, &mut issue);
+                if let Some(uvs) = multi.uvs.as_ref() {
+                    validate_values(AttributeKind::Uv, uvs, None, &mut issue);
+                }
+
+                validate_face_data(
+                    &multi.vertices_in_each_face,
+                    multi.smooth_faces.as_ref().map(Vec::len),
+                    multi.face_materials.as_deref(),
+                    &self.material_slots,
+                    &mut issue,
+                );
+            }
+            VertexData::Single(single) => {
+                let vertex_count = single.positions.vertex_count();
+                let corner_count = count_corners(&single.vertices_in_each_face);
+
+                if validate_indices(
+                    AttributeKind::Position,
+                    &single.positions,
+                    &single.indices,
+                    corner_count,
+                    &mut issue,
+                ) {
+                    validate_faces(
+                        &single.vertices_in_each_face,
+                        &single.indices,
+                        &single.positions,
+                        config,
+                        &mut issue,
+                    );
+                }
+
+                // Combining indices leaves placeholder vertices behind for positions that no face
+                // uses, so we only check the values of the vertices that are used
+                let used = single.indexed_vertices();
+                let unused_count = used.iter().filter(|used| !**used).count();
+                if unused_count > 0 {
+                    issue(IssueKind::UnusedVertices {
+                        count: unused_count,
+                    });
+                }
+
+                let mut attributes = vec![
+                    (AttributeKind::Position, &single.positions),
+                    (AttributeKind::Normal, &single.normals),
+                ];
+                if let Some(uvs) = single.uvs.as_ref() {
+                    attributes.push((AttributeKind::Uv, uvs));
+                }
+                if let Some(tangents) = single.tangents.as_ref() {
+                    attributes.push((AttributeKind::Tangent, tangents));
+                }
+
+                for (attribute, data) in attributes {
+                    if data.vertex_count() != vertex_count {
+                        issue(IssueKind::VertexCountMismatch {
+                            attribute,
+                            expected: vertex_count,
+                            actual: data.vertex_count(),
+                        });
+                    }
+                    validate_values(attribute, data, Some(&used), &mut issue);
+                }
+                validate_normals(&single.normals, Some(&used), config, &mut issue);
+
+                if let Some(bones) = single.bones.as_ref() {
+                    validate_bone_attributes(bones, vertex_count, &used, config, &mut issue);
+                }
+
+                validate_face_data(
+                    &single.vertices_in_each_face,
+                    None,
+                    single.face_materials.as_deref(),
+                    &self.material_slots,
+                    &mut issue,
+                );
+            }
+        };
+
+        for (slot, material) in self.material_slots.iter().enumerate() {
+            if let Some(material) = material {
+                if !self.materials.contains_key(material) {
+                    issue(IssueKind::MissingMaterial {
+                        slot,
+                        material: material.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut material_names: Vec<&String> = self.materials.keys().collect();
+        material_names.sort();
+        for name in material_names {
+            for texture in textures(&self.materials[name]) {
+                let missing = match config.texture_directory.as_ref() {
+                    _ if texture.is_empty() => true,
+                    Some(directory) => !directory.join(texture).is_file(),
+                    None => false,
+                };
+
+                if missing {
+                    issue(IssueKind::MissingTexture {
+                        material: name.clone(),
+                        texture: texture.clone(),
+                    });
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+fn count_corners(vertices_in_each_face: &[u8]) -> usize {
+    vertices_in_each_face
+        .iter()
+        .map(|count| *count as usize)
+        .sum()
+}
+
+/// Returns `false` if the indices can't be used to look up the attribute
+fn validate_indices<F: FnMut(IssueKind)>(
+    attribute: AttributeKind,
+    data: &VertexAttribute,
+    indices: &[u32],
+    corner_count: usize,
+    issue: &mut F,
+) -> bool {
+    let mut valid = true;
+
+    if indices.len() != corner_count {
+        valid = false;
+        issue(IssueKind::IndexCountMismatch {
+            attribute,
+            expected: corner_count,
+            actual: indices.len(),
+        });
+    }
+
+    let vertex_count = data.vertex_count();
+    for (corner, index) in indices.iter().enumerate() {
+        if *index as usize >= vertex_count {
+            valid = false;
+            issue(IssueKind::IndexOutOfRange {
+                attribute,
+                corner,
+                index: *index,
+                vertex_count,
+            });
+        }
+    }
+
+    valid
+}
+
+/// Look for faces that repeat a vertex or have no area
+fn validate_faces<F: FnMut(IssueKind)>(
+    vertices_in_each_face: &[u8],
+    indices: &[u32],
+    positions: &VertexAttribute,
+    config: &ValidateConfig,
+    issue: &mut F,
+) {
+    let mut start = 0;
+
+    for (face, vertex_count) in vertices_in_each_face.iter().enumerate() {
+        let end = start + *vertex_count as usize;
+        let face_indices = &indices[start..end];
+        start = end;
+
+        let repeats_vertex = face_indices
+            .iter()
+            .enumerate()
+            .any(|(idx, index)| face_indices[idx + 1..].contains(index));
+        if repeats_vertex {
+            issue(IssueKind::DegenerateFace { face });
+            continue;
+        }
+
+        // Newell's method, which works for any polygon
+        let mut normal = Vector3::<f64>::zeros();
+        for (idx, index) in face_indices.iter().enumerate() {
+            let next = face_indices[(idx + 1) % face_indices.len()];
+            let (ax, ay, az) = positions.three_components(*index as usize);
+            let (bx, by, bz) = positions.three_components(next as usize);
+            let a = Vector3::new(ax as f64, ay as f64, az as f64);
+            let b = Vector3::new(bx as f64, by as f64, bz as f64);
+            normal += a.cross(&b);
+        }
+
+        let area = (normal.norm() / 2.0) as f32;
+        if area.is_finite() && area < config.min_face_area {
+            issue(IssueKind::ZeroAreaFace { face, area });
+        }
+    }
+}
+
+/// Whether a vertex is used by a face, where `None` means that every vertex is used
+fn is_used(used: Option<&[bool]>, vertex: usize) -> bool {
+    used.is_none_or(|used| used.get(vertex).cloned().unwrap_or(true))
+}
+
+fn validate_values<F: FnMut(IssueKind)>(
+    attribute: AttributeKind,
+    data: &VertexAttribute,
+    used: Option<&[bool]>,
+    issue: &mut F,
+) {
+    let size = data.size() as usize;
+
+    for (vertex, components) in data.data().chunks(size).enumerate() {
+        if !is_used(used, vertex) {
+            continue;
+        }
+
+        if components.iter().any(|component| !component.is_finite()) {
+            issue(IssueKind::NonFinite { attribute, vertex });
+        } else if components.contains(&EASILY_RECOGNIZABLE_NUMBER) {
+            issue(IssueKind::PlaceholderValue { attribute, vertex });
+        }
+    }
+}
+
+fn validate_normals<F: FnMut(IssueKind)>(
+    normals: &VertexAttribute,
+    used: Option<&[bool]>,
+    config: &ValidateConfig,
+    issue: &mut F,
+) {
+    for vertex in (0..normals.vertex_count()).filter(|vertex| is_used(used, *vertex)) {
+        let (x, y, z) = normals.three_components(vertex);
+        let length = Vector3::new(x, y, z).norm();
+
+        if length.is_finite() && (length - 1.0).abs() > config.normal_tolerance {
+            issue(IssueKind::UnnormalizedNormal { vertex, length });
+        }
+    }
+}
+
+fn validate_bone_influences<F: FnMut(IssueKind)>(
+    influences: &BoneInfluences,
+    vertex_count: usize,
+    config: &ValidateConfig,
+    issue: &mut F,
+) {
+    let each_vertex = influences.influences_of_each_vertex();
+
+    if each_vertex.len() != vertex_count
+        || influences.bone_indices.len() != influences.bone_weights.len()
+    {
+        issue(IssueKind::VertexCountMismatch {
+            attribute: AttributeKind::BoneWeights,
+            expected: vertex_count,
+            actual: each_vertex.len(),
+        });
+    }
+
+    validate_bone_weights(&each_vertex, None, config, issue);
+}
+
+fn validate_bone_attributes<F: FnMut(IssueKind)>(
+    bones: &BoneAttributes,
+    vertex_count: usize,
+    used: &[bool],
+    config: &ValidateConfig,
+    issue: &mut F,
+) {
+    let bones_per_vertex = bones.bones_per_vertex as usize;
+
+    for (attribute, len) in [
+        (AttributeKind::BoneIndices, bones.bone_indices.len()),
+        (AttributeKind::BoneWeights, bones.bone_weights.len()),
+    ]
+    .iter()
+    {
+        if *len != vertex_count * bones_per_vertex {
+            issue(IssueKind::VertexCountMismatch {
+                attribute: *attribute,
+                expected: vertex_count,
+                actual: len / bones_per_vertex.max(1),
+            });
+        }
+    }
+
+    let each_vertex: Vec<Vec<(u8, f32)>> = bones
+        .bone_indices
+        .chunks(bones_per_vertex.max(1))
+        .zip(bones.bone_weights.chunks(bones_per_vertex.max(1)))
+        .map(|(indices, weights)| {
+            indices
+                .iter()
+                .cloned()
+                .zip(weights.iter().cloned())
+                .collect()
+        })
+        .collect();
+
+    validate_bone_weights(&each_vertex, Some(used), config, issue);
+}
+
+fn validate_bone_weights<F: FnMut(IssueKind)>(
+    each_vertex: &[Vec<(u8, f32)>],
+    used: Option<&[bool]>,
+    config: &ValidateConfig,
+    issue: &mut F,
+) {
+    let bone_count = config.armature.map(|armature| armature.joint_index.len());
+
+    for (vertex, influences) in each_vertex.iter().enumerate() {
+        if !is_used(used, vertex) {
+            continue;
+        }
+
+        let sum: f32 = influences.iter().map(|(_, weight)| weight).sum();
+        if sum.is_finite() && (sum - 1.0).abs() > config.bone_weight_tolerance {
+            issue(IssueKind::BoneWeightSum { vertex, sum });
+        }
+
+        if let Some(bone_count) = bone_count {
+            for (bone, weight) in influences.iter() {
+                // Padding influences with no weight don't use their bone
+                if *bone as usize >= bone_count && *weight != 0.0 {
+                    issue(IssueKind::BoneIndexOutOfRange {
+                        vertex,
+                        bone: *bone,
+                        bone_count,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn validate_face_data<F: FnMut(IssueKind)>(
+    vertices_in_each_face: &[u8],
+    smooth_face_count: Option<usize>,
+    face_materials: Option<&[u16]>,
+    material_slots: &[Option<String>],
+    issue: &mut F,
+) {
+    let face_count = vertices_in_each_face.len();
+
+    if let Some(actual) = smooth_face_count {
+        if actual != face_count {
+            issue(IssueKind::FaceCountMismatch {
+                field: "smooth_faces",
+                expected: face_count,
+                actual,
+            });
+        }
+    }
+
+    if let Some(face_materials) = face_materials {
+        if face_materials.len() != face_count {
+            issue(IssueKind::FaceCountMismatch {
+                field: "face_materials",
+                expected: face_count,
+                actual: face_materials.len(),
+            });
+        }
+
+        // Meshes without material slots are drawn with a default material
+        if !material_slots.is_empty() {
+            for (face, slot) in face_materials.iter().enumerate() {
+                if *slot as usize >= material_slots.len() {
+                    issue(IssueKind::FaceMaterialOutOfRange {
+                        face,
+                        slot: *slot,
+                        slot_count: material_slots.len(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// The names of every texture that the material uses
+fn textures(material: &PrincipledBSDF) -> Vec<&String> {
+    let mut textures = vec![];
+
+    if let MaterialInput::ImageTexture(texture) = material.base_color() {
+        textures.push(texture);
+    }
+    if let MaterialInput::ImageTexture((texture, _)) = material.roughness() {
+        textures.push(texture);
+    }
+    if let MaterialInput::ImageTexture((texture, _)) = material.metallic() {
+        textures.push(texture);
+    }
+    if let Some(normal_map) = material.normal_map() {
+        textures.push(normal_map);
+    }
+
+    textures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bone::BoneInfluencesPerVertex;
+    use crate::test_utils::*;
+    use crate::vertex_data::{MultiIndexVertexData, SingleIndexVertexData};
+    use crate::CreateSingleIndexConfig;
+    use std::collections::HashMap;
+
+    fn kinds(issues: Vec<ValidationIssue>) -> Vec<IssueKind> {
+        issues.into_iter().map(|issue| issue.kind).collect()
+    }
+
+    /// A quad and a triangle with nothing wrong with them
+    fn valid_multi() -> MultiIndexVertexData {
+        MultiIndexVertexData {
+            vertices_in_each_face: vec![4, 3],
+            positions: attrib3(vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 2.0, 0.0, 0.0,
+            ]),
+            position_indices: vec![0, 1, 2, 3, 1, 4, 2],
+            normals: attrib3(vec![0.0, 0.0, 1.0]),
+            normal_indices: vec![0; 7],
+            uvs: Some(attrib2(vec![0.0, 0.0, 1.0, 1.0])),
+            uv_indices: Some(vec![0, 1, 1, 0, 0, 1, 1]),
+            ..MultiIndexVertexData::default()
+        }
+    }
+
+    fn multi_mesh(multi: MultiIndexVertexData) -> BlenderMesh {
+        BlenderMesh {
+            vertex_data: VertexData::Multi(multi),
+            ..BlenderMesh::default()
+        }
+    }
+
+    #[test]
+    fn valid_mesh() {
+        let issues = multi_mesh(valid_multi()).validate(&ValidateConfig::default());
+        assert_eq!(issues, vec![]);
+    }
+
+    #[test]
+    fn index_problems() {
+        let mut multi = valid_multi();
+        multi.normal_indices.pop();
+        multi.uv_indices.as_mut().unwrap()[3] = 7;
+        multi.position_indices[6] = 9;
+
+        let issues = multi_mesh(multi).validate(&ValidateConfig::default());
+
+        assert!(issues.iter().all(|issue| issue.severity == Severity::Error));
+        assert_eq!(
+            kinds(issues),
+            vec![
+                IssueKind::IndexOutOfRange {
+                    attribute: AttributeKind::Position,
+                    corner: 6,
+                    index: 9,
+                    vertex_count: 5,
+                },
+                IssueKind::IndexCountMismatch {
+                    attribute: AttributeKind::Normal,
+                    expected: 7,
+                    actual: 6,
+                },
+                IssueKind::IndexOutOfRange {
+                    attribute: AttributeKind::Uv,
+                    corner: 3,
+                    index: 7,
+                    vertex_count: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_values_and_faces() {
+        let mut multi = valid_multi();
+        // Vertex 4 ends up in line with vertices 1 and 2
+        multi.positions.data_mut()[12] = 1.0;
+        multi.positions.data_mut()[13] = 0.5;
+        multi.position_indices[3] = 0;
+        multi.normals = attrib3(vec![0.0, 0.0, 1.0, f32::NAN, 0.0, 0.0, 0.0, 0.0, 2.0]);
+        multi.uvs = Some(attrib2(vec![0.0, 0.0, EASILY_RECOGNIZABLE_NUMBER, 1.0]));
+
+        let issues = multi_mesh(multi).validate(&ValidateConfig::default());
+
+        assert_eq!(
+            kinds(issues),
+            vec![
+                IssueKind::DegenerateFace { face: 0 },
+                IssueKind::ZeroAreaFace { face: 1, area: 0.0 },
+                IssueKind::NonFinite {
+                    attribute: AttributeKind::Normal,
+                    vertex: 1,
+                },
+                IssueKind::UnnormalizedNormal {
+                    vertex: 2,
+                    length: 2.0
+                },
+                IssueKind::PlaceholderValue {
+                    attribute: AttributeKind::Uv,
+                    vertex: 1,
+                },
+            ]
+        );
+    }
+
+    /// Combining indices leaves a placeholder vertex behind for a loose vertex, which is only
+    /// worth a warning
+    #[test]
+    fn unused_vertices_after_combining_indices() {
+        let mut multi = valid_multi();
+        // Position 3 isn't used by any face
+        multi.positions = attrib3(vec![
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 5.0, 5.0, 5.0, 0.0, 1.0, 0.0, 2.0, 0.0,
+            0.0,
+        ]);
+        multi.position_indices = vec![0, 1, 2, 4, 1, 5, 2];
+        multi.bone_influences = Some(BoneInfluences {
+            bones_per_vertex: BoneInfluencesPerVertex::Uniform(1),
+            bone_indices: vec![0; 6],
+            bone_weights: vec![1.0; 6],
+        });
+
+        let mut mesh = multi_mesh(multi);
+        let config = CreateSingleIndexConfig {
+            bone_influences_per_vertex: Some(2),
+            ..CreateSingleIndexConfig::default()
+        };
+        mesh.combine_vertex_indices(&config).unwrap();
+
+        let issues = mesh.validate(&ValidateConfig::default());
+
+        assert_eq!(
+            issues,
+            vec![ValidationIssue {
+                severity: Severity::Warning,
+                kind: IssueKind::UnusedVertices { count: 1 },
+            }]
+        );
+    }
+
+    #[test]
+    fn bone_problems() {
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                vertices_in_each_face: vec![3],
+                indices: vec![0, 1, 2],
+                positions: attrib3(vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]),
+                normals: attrib3(vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]),
+                bones: Some(BoneAttributes {
+                    bones_per_vertex: 2,
+                    bone_indices: vec![0, 1, 2, 0, 1, 5],
+                    bone_weights: vec![0.5, 0.5, 1.0, 0.0, 0.25, 0.5],
+                }),
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        let armature = BlenderArmature {
+            joint_index: vec![("Root".to_string(), 0), ("Arm".to_string(), 1)]
+                .into_iter()
+                .collect(),
+            inverse_bind_poses: vec![],
+            bone_parents: vec![],
+            local_rest_poses: vec![],
+            actions: HashMap::new(),
+        };
+        let config = ValidateConfig {
+            armature: Some(&armature),
+            ..ValidateConfig::default()
+        };
+
+        let issues = mesh.validate(&config);
+
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue {
+                    severity: Severity::Error,
+                    kind: IssueKind::BoneIndexOutOfRange {
+                        vertex: 1,
+                        bone: 2,
+                        bone_count: 2,
+                    },
+                },
+                ValidationIssue {
+                    severity: Severity::Warning,
+                    kind: IssueKind::BoneWeightSum {
+                        vertex: 2,
+                        sum: 0.75
+                    },
+                },
+                ValidationIssue {
+                    severity: Severity::Error,
+                    kind: IssueKind::BoneIndexOutOfRange {
+                        vertex: 2,
+                        bone: 5,
+                        bone_count: 2,
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn material_problems() {
+        let mut multi = valid_multi();
+        multi.face_materials = Some(vec![0, 3]);
+
+        let mut materials = HashMap::new();
+        materials.insert(
+            "Skin".to_string(),
+            serde_json::from_value(serde_json::json!({
+                "base_color": {"ImageTexture": "skin.png"},
+                "roughness": {"Uniform": 0.5},
+                "metallic": {"Uniform": 0.0},
+                "normal_map": "",
+            }))
+            .unwrap(),
+        );
+
+        let mesh = BlenderMesh {
+            vertex_data: VertexData::Multi(multi),
+            materials,
+            material_slots: vec![Some("Skin".to_string()), Some("Hair".to_string())],
+            ..BlenderMesh::default()
+        };
+
+        let expected_without_directory = vec![
+            IssueKind::FaceMaterialOutOfRange {
+                face: 1,
+                slot: 3,
+                slot_count: 2,
+            },
+            IssueKind::MissingMaterial {
+                slot: 1,
+                material: "Hair".to_string(),
+            },
+            IssueKind::MissingTexture {
+                material: "Skin".to_string(),
+                texture: "".to_string(),
+            },
+        ];
+        assert_eq!(
+            kinds(mesh.validate(&ValidateConfig::default())),
+            expected_without_directory
+        );
+
+        let config = ValidateConfig {
+            texture_directory: Some(std::env::temp_dir().join("no-textures-here")),
+            ..ValidateConfig::default()
+        };
+        let issues = kinds(mesh.validate(&config));
+        assert_eq!(issues.len(), 4);
+        assert_eq!(
+            issues[2],
+            IssueKind::MissingTexture {
+                material: "Skin".to_string(),
+                texture: "skin.png".to_string(),
+            }
+        );
+    }
+}
@@ -1,6 +1,10 @@
 mod vertex_attribute;
 
-pub(crate) use self::vertex_attribute::{AttributeSize, BoneAttributes, VertexAttribute};
+use crate::bone::BoneInfluencesPerVertex;
+use crate::combine_indices::{IndexBuffer, IndexWidth};
+use crate::morph_target::MorphTarget;
+
+pub use self::vertex_attribute::{AttributeSize, BoneAttributes, VertexAttribute};
 
 /// Per vertex data from the BlenderMesh.
 ///
@@ -22,10 +26,66 @@ impl Default for VertexData {
     }
 }
 
+/// The mesh's vertex data isn't in the shape that an operation needs
+#[derive(Debug, Fail)]
+pub enum VertexDataError {
+    /// The operation needs one index per vertex
+    #[fail(
+        display = "The mesh still has multiple indices. Call BlenderMesh.combine_vertex_indices first"
+    )]
+    MultipleIndices,
+    /// The operation needs a mesh made of triangles
+    #[fail(
+        display = "Face {} has {} vertices but the mesh needs to be made of triangles. Call BlenderMesh.triangulate first",
+        face, vertex_count
+    )]
+    NotTriangulated { face: usize, vertex_count: u8 },
+}
+
+impl VertexData {
+    /// The single indexed vertex data, or an error if the indices haven't been combined yet
+    pub(crate) fn single_index_mut_or_err(
+        &mut self,
+    ) -> Result<&mut SingleIndexVertexData, VertexDataError> {
+        match self {
+            VertexData::Single(single) => Ok(single),
+            VertexData::Multi(_) => Err(VertexDataError::MultipleIndices),
+        }
+    }
+}
+
+/// An error for the first face that isn't a triangle
+pub(crate) fn ensure_triangulated(vertices_in_each_face: &[u8]) -> Result<(), VertexDataError> {
+    match vertices_in_each_face
+        .iter()
+        .enumerate()
+        .find(|(_, vertex_count)| **vertex_count != 3)
+    {
+        Some((face, vertex_count)) => Err(VertexDataError::NotTriangulated {
+            face,
+            vertex_count: *vertex_count,
+        }),
+        None => Ok(()),
+    }
+}
+
+impl From<MultiIndexVertexData> for VertexData {
+    fn from(multi: MultiIndexVertexData) -> Self {
+        VertexData::Multi(multi)
+    }
+}
+
+impl From<SingleIndexVertexData> for VertexData {
+    fn from(single: SingleIndexVertexData) -> Self {
+        VertexData::Single(single)
+    }
+}
+
 /// Vertex data with multiple indices - not suited for OpenGL and other single index rendering
 /// pipelines, but good for on disk storage as their is less data duplicated when there are
 /// multiple indices.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
 pub struct MultiIndexVertexData {
     /// The number of vertices that comprise each face of the mesh.
     ///
@@ -38,9 +98,9 @@ pub struct MultiIndexVertexData {
     ///
     /// - Calculating vertex tangents, where all vertices in the same face will have the same
     ///   tangent.
-    vertices_in_each_face: Vec<u8>,
+    pub(crate) vertices_in_each_face: Vec<u8>,
     /// The amount that the bones in the arent armature influence each vertex
-    bone_influences: Option<BoneInfluences>,
+    pub(crate) bone_influences: Option<BoneInfluences>,
     /// All of the x, y and z positions of the vertices in this mesh, indexed by `position_indices`.
     ///
     /// For example, [0., 1., 2., 3., 4. 5.] would mean that there are two vertex positions.
@@ -48,7 +108,7 @@ pub struct MultiIndexVertexData {
     ///
     /// This does not, however, mean that there are two vertices. There could be multiple vertices
     /// that happened to have the same positions.
-    positions: VertexAttribute,
+    pub(crate) positions: VertexAttribute,
     /// Indices of each vertex positions for this mesh. Position index 0 would be the 0 index
     /// in the positions vector.
     ///
@@ -61,15 +121,32 @@ pub struct MultiIndexVertexData {
     /// give you the positions for the triangle for the first face, then the next 4 position indices
     /// give you the quad for the next face, then the next three give you positions for the next
     /// triangle.
-    position_indices: Vec<u16>,
+    pub(crate) position_indices: Vec<u32>,
     /// All of the normals, indexed by `normal_indices`
-    normals: VertexAttribute,
+    pub(crate) normals: VertexAttribute,
     /// Indices into the normals
-    normal_indices: Vec<u16>,
+    pub(crate) normal_indices: Vec<u32>,
     /// All of the uvs, indexed by uv_indices
-    uvs: Option<VertexAttribute>,
+    pub(crate) uvs: Option<VertexAttribute>,
     /// Indices into the uvs
-    uv_indices: Option<Vec<u16>>,
+    pub(crate) uv_indices: Option<Vec<u32>>,
+    /// Whether each face is smooth shaded (`true`) or flat shaded (`false`).
+    ///
+    /// Only used when generating normals with [`BlenderMesh.calculate_smooth_normals`].
+    /// `None` means that every face is smooth shaded.
+    ///
+    /// [`BlenderMesh.calculate_smooth_normals`]: struct.BlenderMesh.html#method.calculate_smooth_normals
+    pub(crate) smooth_faces: Option<Vec<bool>>,
+    /// The material that each face uses, as an index into [`BlenderMesh.material_slots`].
+    ///
+    /// `None` means that every face uses the first material slot.
+    ///
+    /// [`BlenderMesh.material_slots`]: struct.BlenderMesh.html#method.material_slots
+    pub(crate) face_materials: Option<Vec<u16>>,
+    /// The mesh's shape keys, with one position delta per position and one normal delta
+    /// per normal
+    #[serde(default)]
+    pub(crate) morph_targets: Vec<MorphTarget>,
 }
 
 /// Most 3D model file formats export vertex data with multiple indices.
@@ -80,12 +157,39 @@ pub struct MultiIndexVertexData {
 ///
 /// When we've run [`BlenderMesh.combine_vertex_indices`] we'll end up generating
 /// `SingleIndexVertexData`
+///
+/// [`BlenderMesh.combine_vertex_indices`]: struct.BlenderMesh.html#method.combine_vertex_indices
 #[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
 pub struct SingleIndexVertexData {
-    positions: VertexAttribute,
-    normals: VertexAttribute,
-    uvs: Option<VertexAttribute>,
-    bones: Option<BoneAttributes>,
+    /// The number of vertices that comprise each face of the mesh.
+    pub(crate) vertices_in_each_face: Vec<u8>,
+    /// One index per face vertex, into every one of the vertex attributes
+    pub(crate) indices: Vec<u32>,
+    /// The integer type that [`SingleIndexVertexData.index_buffer`] hands back our indices as
+    ///
+    /// [`SingleIndexVertexData.index_buffer`]: struct.SingleIndexVertexData.html#method.index_buffer
+    pub(crate) index_width: IndexWidth,
+    pub(crate) positions: VertexAttribute,
+    pub(crate) normals: VertexAttribute,
+    pub(crate) uvs: Option<VertexAttribute>,
+    /// Tangent vectors per vertex, useful for normal mapping.
+    ///
+    /// These get set during [`BlenderMesh.combine_vertex_indices`] when
+    /// `CreateSingleIndexConfig.calculate_vertex_tangents` is set.
+    ///
+    /// [`BlenderMesh.combine_vertex_indices`]: struct.BlenderMesh.html#method.combine_vertex_indices
+    pub(crate) tangents: Option<VertexAttribute>,
+    pub(crate) bones: Option<BoneAttributes>,
+    /// The material that each face uses, as an index into [`BlenderMesh.material_slots`].
+    ///
+    /// `None` means that every face uses the first material slot.
+    ///
+    /// [`BlenderMesh.material_slots`]: struct.BlenderMesh.html#method.material_slots
+    pub(crate) face_materials: Option<Vec<u16>>,
+    /// The mesh's shape keys, with one position delta and one normal delta per vertex
+    #[serde(default)]
+    pub(crate) morph_targets: Vec<MorphTarget>,
 }
 
 /// The amount that each bone in the mesh's parent armature influences each vertex.
@@ -95,15 +199,171 @@ pub struct SingleIndexVertexData {
 /// the first vertex is influenced by bone 0 by 0.2, bone 1 by 0.4 and bone 2 by 0.2.
 ///
 /// Then the second vertex is influenced by bone 2 by 0.5 and bone 5 by 0.5
-#[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Default, Clone)]
 pub struct BoneInfluences {
     /// The number of bones that affect each vertex.
     ///
     /// Example: [3, 5, 2] would mean that the first vertex is influenced by 3 bones, second by
     /// 5, and third by 2
-    bones_per_vertex: Vec<u8>,
+    pub(crate) bones_per_vertex: BoneInfluencesPerVertex,
     /// The indices of the bones that affect each vertex.
-    bone_indices: Vec<u8>,
+    pub(crate) bone_indices: Vec<u8>,
     /// The corresponding weights of each bone index
-    bone_weights: Vec<f32>,
+    pub(crate) bone_weights: Vec<f32>,
+}
+
+impl MultiIndexVertexData {
+    /// The number of vertices in each face
+    pub fn vertices_in_each_face(&self) -> &Vec<u8> {
+        &self.vertices_in_each_face
+    }
+
+    /// The amount that each bone influences each of the vertex positions
+    pub fn bone_influences(&self) -> Option<&BoneInfluences> {
+        self.bone_influences.as_ref()
+    }
+
+    /// Every vertex position, indexed by `position_indices`
+    pub fn positions(&self) -> &VertexAttribute {
+        &self.positions
+    }
+
+    /// The position index for every vertex of every face
+    pub fn position_indices(&self) -> &Vec<u32> {
+        &self.position_indices
+    }
+
+    /// Every vertex normal, indexed by `normal_indices`
+    pub fn normals(&self) -> &VertexAttribute {
+        &self.normals
+    }
+
+    /// The normal index for every vertex of every face
+    pub fn normal_indices(&self) -> &Vec<u32> {
+        &self.normal_indices
+    }
+
+    /// Every uv coordinate, indexed by `uv_indices`
+    pub fn uvs(&self) -> Option<&VertexAttribute> {
+        self.uvs.as_ref()
+    }
+
+    /// The uv index for every vertex of every face
+    pub fn uv_indices(&self) -> Option<&Vec<u32>> {
+        self.uv_indices.as_ref()
+    }
+
+    /// Whether each face is smooth shaded
+    pub fn smooth_faces(&self) -> Option<&Vec<bool>> {
+        self.smooth_faces.as_ref()
+    }
+
+    /// The material slot that each face uses
+    pub fn face_materials(&self) -> Option<&Vec<u16>> {
+        self.face_materials.as_ref()
+    }
+
+    /// The mesh's shape keys
+    pub fn morph_targets(&self) -> &Vec<MorphTarget> {
+        &self.morph_targets
+    }
+}
+
+impl SingleIndexVertexData {
+    /// The number of vertices in each face
+    pub fn vertices_in_each_face(&self) -> &Vec<u8> {
+        &self.vertices_in_each_face
+    }
+
+    /// The index of every vertex of every face
+    pub fn indices(&self) -> &Vec<u32> {
+        &self.indices
+    }
+
+    /// The integer type that the indices were combined to fit in
+    pub fn index_width(&self) -> IndexWidth {
+        self.index_width
+    }
+
+    /// Our indices as the integer type that was chosen when combining indices.
+    pub fn index_buffer(&self) -> IndexBuffer {
+        IndexBuffer::new(&self.indices, self.index_width)
+    }
+
+    /// The position of every vertex
+    pub fn positions(&self) -> &VertexAttribute {
+        &self.positions
+    }
+
+    /// The normal of every vertex
+    pub fn normals(&self) -> &VertexAttribute {
+        &self.normals
+    }
+
+    /// The uv coordinates of every vertex
+    pub fn uvs(&self) -> Option<&VertexAttribute> {
+        self.uvs.as_ref()
+    }
+
+    /// The tangent of every vertex
+    pub fn tangents(&self) -> Option<&VertexAttribute> {
+        self.tangents.as_ref()
+    }
+
+    /// The bones that influence every vertex
+    pub fn bones(&self) -> Option<&BoneAttributes> {
+        self.bones.as_ref()
+    }
+
+    /// The material slot that each face uses
+    pub fn face_materials(&self) -> Option<&Vec<u16>> {
+        self.face_materials.as_ref()
+    }
+
+    /// The mesh's shape keys
+    pub fn morph_targets(&self) -> &Vec<MorphTarget> {
+        &self.morph_targets
+    }
+
+    /// Whether or not each vertex is used by the indices. Combining indices can leave unused
+    /// vertices behind, and their attributes are filled with placeholder values.
+    pub(crate) fn indexed_vertices(&self) -> Vec<bool> {
+        let mut indexed = vec![false; self.positions.vertex_count()];
+
+        for index in self.indices.iter() {
+            if let Some(indexed) = indexed.get_mut(*index as usize) {
+                *indexed = true;
+            }
+        }
+
+        indexed
+    }
+
+    /// The positions of the vertices that are used by the indices, as `[x, y, z, x, y, z, ...]`
+    pub(crate) fn indexed_positions(&self) -> Vec<f32> {
+        self.positions
+            .data()
+            .chunks(3)
+            .zip(self.indexed_vertices())
+            .filter(|(_, indexed)| *indexed)
+            .flat_map(|(position, _)| position.to_vec())
+            .collect()
+    }
+}
+
+impl BoneInfluences {
+    /// The number of bones that affect each vertex
+    pub fn bones_per_vertex(&self) -> &BoneInfluencesPerVertex {
+        &self.bones_per_vertex
+    }
+
+    /// The indices of the bones that affect each vertex
+    pub fn bone_indices(&self) -> &Vec<u8> {
+        &self.bone_indices
+    }
+
+    /// The corresponding weights of each bone index
+    pub fn bone_weights(&self) -> &Vec<f32> {
+        &self.bone_weights
+    }
 }
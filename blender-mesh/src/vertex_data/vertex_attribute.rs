@@ -1,20 +1,24 @@
+use std::collections::HashMap;
+
 /// Data for an individual vertex attribute such as positions, normals or uvs.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Default, Clone)]
 pub struct VertexAttribute {
     /// The underlying vector of floats for this data
     data: Vec<f32>,
     /// Positions and normals have a size of 3 (x, y, z)
     /// Uvs have a size of 2 (u, v)
+    /// MikkTSpace tangents have a size of 4 (x, y, z, handedness)
     size: AttributeSize,
 }
 
 /// The number of components per vertex.
 ///
 /// For example - a position would have 3 components per vertex, a uv would have 2.
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
 pub enum AttributeSize {
     Two = 2,
     Three = 3,
+    Four = 4,
 }
 
 impl Default for AttributeSize {
@@ -23,11 +27,15 @@ impl Default for AttributeSize {
     }
 }
 
-/// Used for vertex skinning
-#[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+/// Used for vertex skinning.
+///
+/// Every vertex is influenced by the same number of bones, so the `n`th vertex's bones start at
+/// `n * bones_per_vertex`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Default, Clone)]
 pub struct BoneAttributes {
-    bone_indices: VertexAttribute,
-    bone_weights: VertexAttribute,
+    pub(crate) bones_per_vertex: u8,
+    pub(crate) bone_indices: Vec<u8>,
+    pub(crate) bone_weights: Vec<f32>,
 }
 
 impl VertexAttribute {
@@ -46,9 +54,39 @@ impl VertexAttribute {
     pub(crate) fn data_mut(&mut self) -> &mut Vec<f32> {
         &mut self.data
     }
+
+    /// The number of components per vertex
+    pub fn size(&self) -> AttributeSize {
+        self.size
+    }
+
+    /// The number of vertices that this attribute has data for
+    pub fn vertex_count(&self) -> usize {
+        self.data.len() / self.size as usize
+    }
 }
 
 impl VertexAttribute {
+    /// Get the data for a vertex of an attribute with 3 components per vertex
+    pub(crate) fn three_components(&self, idx: usize) -> (f32, f32, f32) {
+        match self.size {
+            AttributeSize::Three => (
+                self.data[idx * 3],
+                self.data[idx * 3 + 1],
+                self.data[idx * 3 + 2],
+            ),
+            _ => panic!("Does not have exactly three components"),
+        }
+    }
+
+    /// Get the data for a vertex of an attribute with 2 components per vertex
+    pub(crate) fn two_components(&self, idx: usize) -> (f32, f32) {
+        match self.size {
+            AttributeSize::Two => (self.data[idx * 2], self.data[idx * 2 + 1]),
+            _ => panic!("Does not have exactly two components"),
+        }
+    }
+
     /// Set vertex data for an attribute with 3 components per vertex
     pub(crate) fn set_three_components(&mut self, idx: usize, comp1: f32, comp2: f32, comp3: f32) {
         match self.size {
@@ -81,6 +119,23 @@ impl VertexAttribute {
         };
     }
 
+    /// For every vertex of an attribute with 3 components per vertex, the first vertex that has
+    /// exactly the same components.
+    ///
+    /// Combining indices splits a position into one vertex per normal and uv, so this finds the
+    /// vertices that are really the same position.
+    pub(crate) fn first_vertex_at_same_position(&self) -> Vec<u32> {
+        let mut first_at_position = HashMap::new();
+
+        (0..self.vertex_count())
+            .map(|vertex| {
+                let (x, y, z) = self.three_components(vertex);
+                let key = (x.to_bits(), y.to_bits(), z.to_bits());
+                *first_at_position.entry(key).or_insert(vertex as u32)
+            })
+            .collect()
+    }
+
     /// Set vertex data for an attribute with 2 components per vertex
     pub(crate) fn set_two_components(&mut self, idx: usize, comp1: f32, comp2: f32) {
         match self.size {
@@ -91,9 +146,21 @@ impl VertexAttribute {
             _ => panic!("Does not have exactly two components"),
         };
     }
+}
+
+impl BoneAttributes {
+    /// The number of bones that influence each vertex
+    pub fn bones_per_vertex(&self) -> u8 {
+        self.bones_per_vertex
+    }
+
+    /// The indices of the bones that influence each vertex, `bones_per_vertex` per vertex
+    pub fn bone_indices(&self) -> &Vec<u8> {
+        &self.bone_indices
+    }
 
-    /// Push data to the end of the underlying vector
-    pub(crate) fn push(&mut self, val: f32) {
-        self.data.push(val)
+    /// The weight of each of the bones in `bone_indices`
+    pub fn bone_weights(&self) -> &Vec<f32> {
+        &self.bone_weights
     }
 }
@@ -0,0 +1,491 @@
+//! Merging vertices that are (almost) in the same place, such as the coincident positions that
+//! imported meshes and boolean modifiers tend to leave behind.
+//!
+//! Vertices are put into a spatial hash with cells that are as large as the epsilon, so finding
+//! the vertices that are close to a vertex only needs to look at the neighboring cells.
+
+use crate::bone::BoneInfluencesPerVertex;
+use crate::optimize::{reorder, reorder_attribute};
+use crate::vertex_data::{
+    BoneInfluences, MultiIndexVertexData, SingleIndexVertexData, VertexAttribute, VertexData,
+};
+use crate::BlenderMesh;
+use std::collections::HashMap;
+
+/// Configuration for [`BlenderMesh.weld`]
+///
+/// Two vertices are merged when every component of each attribute is within that attribute's
+/// epsilon. An epsilon of `0.0` only merges exact duplicates.
+///
+/// [`BlenderMesh.weld`]: struct.BlenderMesh.html#method.weld
+#[derive(Debug)]
+pub struct WeldConfig {
+    pub position_epsilon: f32,
+    /// Also used for tangents
+    pub normal_epsilon: f32,
+    pub uv_epsilon: f32,
+    /// Vertices are only merged if they're influenced by the same bones, with weights that
+    /// are within this epsilon
+    pub bone_weight_epsilon: f32,
+}
+
+impl Default for WeldConfig {
+    fn default() -> Self {
+        WeldConfig {
+            position_epsilon: 1e-5,
+            normal_epsilon: 1e-3,
+            uv_epsilon: 1e-5,
+            bone_weight_epsilon: 1e-3,
+        }
+    }
+}
+
+/// How many vertices were merged into other vertices while welding
+#[derive(Debug, PartialEq, Default)]
+pub struct WeldReport {
+    /// For meshes with multiple indices this is the number of positions that were merged
+    pub vertices_merged: usize,
+    /// Meshes with multiple indices weld their normals separately. This is always `0` for
+    /// meshes with a single index.
+    pub normals_merged: usize,
+    /// Meshes with multiple indices weld their uvs separately. This is always `0` for
+    /// meshes with a single index.
+    pub uvs_merged: usize,
+}
+
+impl BlenderMesh {
+    /// Merge vertices that are within the [`WeldConfig`]'s epsilons of each other, and update
+    /// every index and bone influence to use the vertices that are left.
+    ///
+    /// Each vertex is merged into the first vertex that it is close to, so the kept vertices
+    /// keep their original values.
+    ///
+    /// Welding can collapse small faces, which [`BlenderMesh.validate`] reports as degenerate.
+    ///
+    /// [`WeldConfig`]: struct.WeldConfig.html
+    /// [`BlenderMesh.validate`]: struct.BlenderMesh.html#method.validate
+    pub fn weld(&mut self, config: &WeldConfig) -> WeldReport {
+        match &mut self.vertex_data {
+            VertexData::Multi(multi) => multi.weld(config),
+            VertexData::Single(single) => single.weld(config),
+        }
+    }
+}
+
+impl MultiIndexVertexData {
+    fn weld(&mut self, config: &WeldConfig) -> WeldReport {
+        let mut report = WeldReport::default();
+
+        let influences = self.bone_influences.as_ref().map(sorted_influences);
+        let positions = &self.positions;
+        let welded = weld_vertices(positions, config.position_epsilon, |a, b| {
+            within(positions, a, b, config.position_epsilon)
+                && influences.as_ref().is_none_or(|influences| {
+                    same_influences(&influences[a], &influences[b], config.bone_weight_epsilon)
+                })
+                && self
+                    .morph_targets
+                    .iter()
+                    .all(|target| within(&target.position_deltas, a, b, config.position_epsilon))
+        });
+        report.vertices_merged = welded.merged();
+        remap(&mut self.position_indices, &welded);
+        self.positions = reorder_attribute(&self.positions, &welded.old_vertices);
+        if let Some(bone_influences) = self.bone_influences.as_mut() {
+            *bone_influences = reorder_influences(bone_influences, &welded.old_vertices);
+        }
+        for target in self.morph_targets.iter_mut() {
+            target.position_deltas =
+                reorder_attribute(&target.position_deltas, &welded.old_vertices);
+        }
+
+        let normals = &self.normals;
+        let welded = weld_vertices(normals, config.normal_epsilon, |a, b| {
+            within(normals, a, b, config.normal_epsilon)
+                && self
+                    .morph_targets
+                    .iter()
+                    .all(|target| within(&target.normal_deltas, a, b, config.normal_epsilon))
+        });
+        report.normals_merged = welded.merged();
+        remap(&mut self.normal_indices, &welded);
+        self.normals = reorder_attribute(&self.normals, &welded.old_vertices);
+        for target in self.morph_targets.iter_mut() {
+            target.normal_deltas = reorder_attribute(&target.normal_deltas, &welded.old_vertices);
+        }
+
+        if let (Some(uvs), Some(uv_indices)) = (self.uvs.as_mut(), self.uv_indices.as_mut()) {
+            let welded = {
+                let uvs = &*uvs;
+                weld_vertices(uvs, config.uv_epsilon, |a, b| {
+                    within(uvs, a, b, config.uv_epsilon)
+                })
+            };
+            report.uvs_merged = welded.merged();
+            remap(uv_indices, &welded);
+            *uvs = reorder_attribute(uvs, &welded.old_vertices);
+        }
+
+        report
+    }
+}
+
+impl SingleIndexVertexData {
+    fn weld(&mut self, config: &WeldConfig) -> WeldReport {
+        let welded = weld_vertices(&self.positions, config.position_epsilon, |a, b| {
+            within(&self.positions, a, b, config.position_epsilon)
+                && within(&self.normals, a, b, config.normal_epsilon)
+                && self
+                    .uvs
+                    .as_ref()
+                    .is_none_or(|uvs| within(uvs, a, b, config.uv_epsilon))
+                && self
+                    .tangents
+                    .as_ref()
+                    .is_none_or(|tangents| within(tangents, a, b, config.normal_epsilon))
+                && self.bones.as_ref().is_none_or(|bones| {
+                    let count = bones.bones_per_vertex as usize;
+                    let a = a * count..(a + 1) * count;
+                    let b = b * count..(b + 1) * count;
+
+                    bones.bone_indices[a.clone()] == bones.bone_indices[b.clone()]
+                        && bones.bone_weights[a]
+                            .iter()
+                            .zip(bones.bone_weights[b].iter())
+                            .all(|(a, b)| (a - b).abs() <= config.bone_weight_epsilon)
+                })
+                && self.morph_targets.iter().all(|target| {
+                    within(&target.position_deltas, a, b, config.position_epsilon)
+                        && within(&target.normal_deltas, a, b, config.normal_epsilon)
+                })
+        });
+
+        remap(&mut self.indices, &welded);
+
+        let mut attributes = vec![&mut self.positions, &mut self.normals];
+        attributes.extend(self.uvs.as_mut());
+        attributes.extend(self.tangents.as_mut());
+        for target in self.morph_targets.iter_mut() {
+            attributes.push(&mut target.position_deltas);
+            attributes.push(&mut target.normal_deltas);
+        }
+        for attribute in attributes {
+            *attribute = reorder_attribute(attribute, &welded.old_vertices);
+        }
+
+        if let Some(bones) = self.bones.as_mut() {
+            let bones_per_vertex = bones.bones_per_vertex as usize;
+
+            bones.bone_indices =
+                reorder(&bones.bone_indices, bones_per_vertex, &welded.old_vertices);
+            bones.bone_weights =
+                reorder(&bones.bone_weights, bones_per_vertex, &welded.old_vertices);
+        }
+
+        WeldReport {
+            vertices_merged: welded.merged(),
+            ..WeldReport::default()
+        }
+    }
+}
+
+/// The result of welding an attribute's vertices
+struct Welded {
+    /// The new index of every old vertex
+    new_indices: Vec<u32>,
+    /// The old index of every vertex that was kept, in order
+    old_vertices: Vec<usize>,
+}
+
+impl Welded {
+    fn merged(&self) -> usize {
+        self.new_indices.len() - self.old_vertices.len()
+    }
+}
+
+/// Merge every vertex into the first vertex before it that `same_vertex` says that it matches.
+///
+/// Vertices are hashed by their first (up to) three components of `hashed`, and only vertices
+/// in neighboring cells are compared, so `same_vertex` must only match vertices whose hashed
+/// components are within `epsilon` of each other.
+fn weld_vertices<F: Fn(usize, usize) -> bool>(
+    hashed: &VertexAttribute,
+    epsilon: f32,
+    same_vertex: F,
+) -> Welded {
+    let vertex_count = hashed.vertex_count();
+    let size = hashed.size() as usize;
+    let dimensions = size.min(3);
+
+    let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    let mut new_indices = Vec::with_capacity(vertex_count);
+    let mut old_vertices = vec![];
+
+    for (vertex, components) in hashed.data().chunks(size).enumerate() {
+        let mut cell = [0; 3];
+        for (axis, component) in components[..dimensions].iter().enumerate() {
+            cell[axis] = cell_coordinate(*component, epsilon);
+        }
+
+        let existing = neighboring_cells(cell, dimensions, epsilon)
+            .into_iter()
+            .filter_map(|neighbor| cells.get(&neighbor))
+            .flat_map(|kept| kept.iter())
+            .filter(|kept| same_vertex(vertex, old_vertices[**kept]))
+            .min()
+            .cloned();
+
+        match existing {
+            Some(kept) => new_indices.push(kept as u32),
+            None => {
+                let kept = old_vertices.len();
+                old_vertices.push(vertex);
+                new_indices.push(kept as u32);
+                cells.entry(cell).or_default().push(kept);
+            }
+        }
+    }
+
+    Welded {
+        new_indices,
+        old_vertices,
+    }
+}
+
+/// The cell that a component falls into. With an epsilon of zero only equal components share
+/// a cell.
+fn cell_coordinate(component: f32, epsilon: f32) -> i64 {
+    if epsilon > 0.0 {
+        (component / epsilon).floor() as i64
+    } else {
+        // Adding zero turns -0.0 into 0.0
+        (component + 0.0).to_bits() as i64
+    }
+}
+
+/// The cell and every cell next to it, since a vertex within epsilon of another vertex can be
+/// in the next cell over
+fn neighboring_cells(cell: [i64; 3], dimensions: usize, epsilon: f32) -> Vec<[i64; 3]> {
+    let mut neighbors = vec![cell];
+
+    if epsilon > 0.0 {
+        for axis in 0..dimensions {
+            neighbors = neighbors
+                .into_iter()
+                .flat_map(|neighbor| {
+                    (-1..=1).map(move |offset| {
+                        let mut neighbor = neighbor;
+                        neighbor[axis] += offset;
+                        neighbor
+                    })
+                })
+                .collect();
+        }
+    }
+
+    neighbors
+}
+
+fn within(attribute: &VertexAttribute, a: usize, b: usize, epsilon: f32) -> bool {
+    let size = attribute.size() as usize;
+    let data = attribute.data();
+
+    data[a * size..(a + 1) * size]
+        .iter()
+        .zip(data[b * size..(b + 1) * size].iter())
+        .all(|(a, b)| (a - b).abs() <= epsilon)
+}
+
+fn remap(indices: &mut [u32], welded: &Welded) {
+    for index in indices.iter_mut() {
+        *index = welded.new_indices[*index as usize];
+    }
+}
+
+/// Each vertex's bone influences, sorted by bone so that they can be compared
+fn sorted_influences(influences: &BoneInfluences) -> Vec<Vec<(u8, f32)>> {
+    let mut each_vertex = influences.influences_of_each_vertex();
+
+    for influences in each_vertex.iter_mut() {
+        influences.sort_by_key(|(bone, _)| *bone);
+    }
+
+    each_vertex
+}
+
+fn same_influences(a: &[(u8, f32)], b: &[(u8, f32)], epsilon: f32) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b.iter())
+            .all(|(a, b)| a.0 == b.0 && (a.1 - b.1).abs() <= epsilon)
+}
+
+fn reorder_influences(influences: &BoneInfluences, old_vertices: &[usize]) -> BoneInfluences {
+    let each_vertex = influences.influences_of_each_vertex();
+    let kept: Vec<&Vec<(u8, f32)>> = old_vertices.iter().map(|old| &each_vertex[*old]).collect();
+
+    let bones_per_vertex = match &influences.bones_per_vertex {
+        BoneInfluencesPerVertex::Uniform(count) => BoneInfluencesPerVertex::Uniform(*count),
+        BoneInfluencesPerVertex::NonUniform(_) => BoneInfluencesPerVertex::NonUniform(
+            kept.iter()
+                .map(|influences| influences.len() as u8)
+                .collect(),
+        ),
+    };
+
+    BoneInfluences {
+        bones_per_vertex,
+        bone_indices: kept.iter().flat_map(|v| v.iter().map(|i| i.0)).collect(),
+        bone_weights: kept.iter().flat_map(|v| v.iter().map(|i| i.1)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::vertex_data::BoneAttributes;
+
+    /// Two triangles that share an edge, but each has its own copy of the edge's positions,
+    /// normals and uvs
+    fn split_quad() -> MultiIndexVertexData {
+        MultiIndexVertexData {
+            vertices_in_each_face: vec![3, 3],
+            positions: attrib3(vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, //
+                0.0, 0.0, 0.0, 1.0, 1.000_001, 0.0, 0.0, 1.0, 0.0,
+            ]),
+            position_indices: vec![0, 1, 2, 3, 4, 5],
+            normals: attrib3(vec![0.0, 0.0, 1.0, 0.0, 0.0, 0.9999]),
+            normal_indices: vec![0, 0, 0, 1, 1, 1],
+            uvs: Some(attrib2(vec![
+                0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0,
+            ])),
+            uv_indices: Some(vec![0, 1, 2, 3, 2, 4]),
+            bone_influences: Some(BoneInfluences {
+                bones_per_vertex: BoneInfluencesPerVertex::NonUniform(vec![1; 6]),
+                bone_indices: vec![0; 6],
+                bone_weights: vec![1.0; 6],
+            }),
+            ..MultiIndexVertexData::default()
+        }
+    }
+
+    #[test]
+    fn weld_multiple_indices() {
+        let mut mesh = BlenderMesh {
+            vertex_data: VertexData::Multi(split_quad()),
+            ..BlenderMesh::default()
+        };
+
+        let report = mesh.weld(&WeldConfig::default());
+
+        assert_eq!(
+            report,
+            WeldReport {
+                vertices_merged: 2,
+                normals_merged: 1,
+                uvs_merged: 1,
+            }
+        );
+
+        let multi = match &mesh.vertex_data {
+            VertexData::Multi(multi) => multi,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(multi.position_indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(
+            multi.positions.data(),
+            &vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0]
+        );
+        assert_eq!(multi.normal_indices, vec![0; 6]);
+        assert_eq!(multi.normals.data(), &vec![0.0, 0.0, 1.0]);
+        assert_eq!(multi.uv_indices, Some(vec![0, 1, 2, 0, 2, 3]));
+        assert_eq!(multi.uvs.as_ref().unwrap().vertex_count(), 4);
+        assert_eq!(
+            multi.bone_influences,
+            Some(BoneInfluences {
+                bones_per_vertex: BoneInfluencesPerVertex::NonUniform(vec![1, 1, 1, 1]),
+                bone_indices: vec![0, 0, 0, 0],
+                bone_weights: vec![1.0, 1.0, 1.0, 1.0],
+            })
+        );
+    }
+
+    /// Vertices that have different bone influences stay separate, even when they're in the
+    /// same place
+    #[test]
+    fn different_bone_influences_are_not_welded() {
+        let mut multi = split_quad();
+        let influences = multi.bone_influences.as_mut().unwrap();
+        influences.bone_indices[3] = 2;
+
+        let mut mesh = BlenderMesh {
+            vertex_data: VertexData::Multi(multi),
+            ..BlenderMesh::default()
+        };
+
+        assert_eq!(mesh.weld(&WeldConfig::default()).vertices_merged, 1);
+    }
+
+    #[test]
+    fn weld_single_index() {
+        let mut mesh = BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                vertices_in_each_face: vec![3, 3],
+                indices: vec![0, 1, 2, 3, 4, 5],
+                positions: attrib3(vec![
+                    0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, //
+                    0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0,
+                ]),
+                normals: attrib3(vec![
+                    0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, //
+                    0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0,
+                ]),
+                // The second vertex at (1, 1, 0) has a different uv, so it is a uv seam
+                uvs: Some(attrib2(vec![
+                    0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.5, 0.5, 0.0, 1.0,
+                ])),
+                bones: Some(BoneAttributes {
+                    bones_per_vertex: 1,
+                    bone_indices: vec![0, 1, 2, 0, 2, 3],
+                    bone_weights: vec![1.0; 6],
+                }),
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        let report = mesh.weld(&WeldConfig::default());
+        assert_eq!(report.vertices_merged, 1);
+
+        let single = match &mesh.vertex_data {
+            VertexData::Single(single) => single,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(single.indices, vec![0, 1, 2, 0, 3, 4]);
+        assert_eq!(single.positions.vertex_count(), 5);
+        assert_eq!(single.uvs.as_ref().unwrap().vertex_count(), 5);
+        assert_eq!(
+            single.bones.as_ref().unwrap().bone_indices,
+            vec![0, 1, 2, 2, 3]
+        );
+    }
+
+    /// Vertices that straddle a cell boundary are still found
+    #[test]
+    fn weld_across_cells() {
+        let positions = attrib3(vec![
+            0.999_99, 0.0, 0.0, 1.000_01, 0.0, 0.0, 1.000_1, 0.0, 0.0, -0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0,
+        ]);
+
+        let welded = weld_vertices(&positions, 1e-4, |a, b| within(&positions, a, b, 1e-4));
+        assert_eq!(welded.new_indices, vec![0, 0, 1, 2, 2]);
+
+        let welded = weld_vertices(&positions, 0.0, |a, b| within(&positions, a, b, 0.0));
+        assert_eq!(welded.new_indices, vec![0, 1, 2, 3, 3]);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::vertex_data::{VertexAttribute, VertexData};
 use crate::BlenderMesh;
 
 static Y: usize = 1;
@@ -13,31 +14,52 @@ impl BlenderMesh {
     /// into here.
     /// https://github.com/chinedufn/change-mat4-coordinate-system/blob/master/change-mat4-coordinate-system.js
     pub fn y_up(&mut self) {
-        for vert_num in 0..(self.vertex_positions.len() / 3) {
-            let y_index = vert_num * 3 + 1;
-            let z_index = y_index + 1;
+        let morph_targets = match &mut self.vertex_data {
+            VertexData::Multi(multi) => {
+                y_up_attribute(&mut multi.positions);
+                y_up_attribute(&mut multi.normals);
+                &mut multi.morph_targets
+            }
+            VertexData::Single(single) => {
+                y_up_attribute(&mut single.positions);
+                y_up_attribute(&mut single.normals);
+                if let Some(tangents) = single.tangents.as_mut() {
+                    y_up_attribute(tangents);
+                }
+                &mut single.morph_targets
+            }
+        };
 
-            let new_z = -self.vertex_positions[y_index];
-            self.vertex_positions[y_index] = self.vertex_positions[z_index];
-            self.vertex_positions[z_index] = new_z;
+        for target in morph_targets.iter_mut() {
+            y_up_attribute(&mut target.position_deltas);
+            y_up_attribute(&mut target.normal_deltas);
         }
 
-        for vert_num in 0..(self.vertex_normals.len() / 3) {
-            let y_index = vert_num * 3 + 1;
-            let z_index = y_index + 1;
-
-            let new_z = -self.vertex_normals[y_index];
-            self.vertex_normals[y_index] = self.vertex_normals[z_index];
-            self.vertex_normals[z_index] = new_z;
-        }
+        // Negating y flips which corner has the lowest value, so the new min z comes from the
+        // old max y
+        let min_z = -self.bounding_box.max_corner[Y];
+        let max_z = -self.bounding_box.min_corner[Y];
 
-        let new_z = -self.bounding_box.min_corner[Y];
         self.bounding_box.min_corner[Y] = self.bounding_box.min_corner[Z];
-        self.bounding_box.min_corner[Z] = new_z;
+        self.bounding_box.min_corner[Z] = min_z;
 
-        let new_z = -self.bounding_box.max_corner[Y];
         self.bounding_box.max_corner[Y] = self.bounding_box.max_corner[Z];
-        self.bounding_box.max_corner[Z] = new_z;
+        self.bounding_box.max_corner[Z] = max_z;
+    }
+}
+
+/// Flip the y and z components of every vertex in an attribute
+fn y_up_attribute(attribute: &mut VertexAttribute) {
+    let size = attribute.size() as usize;
+    let data = attribute.data_mut();
+
+    for vert_num in 0..(data.len() / size) {
+        let y_index = vert_num * size + Y;
+        let z_index = vert_num * size + Z;
+
+        let new_z = -data[y_index];
+        data[y_index] = data[z_index];
+        data[z_index] = new_z;
     }
 }
 
@@ -45,13 +67,19 @@ impl BlenderMesh {
 mod tests {
     use super::*;
     use crate::bounding_box::BoundingBox;
+    use crate::morph_target::MorphTarget;
+    use crate::test_utils::*;
+    use crate::vertex_data::{MultiIndexVertexData, SingleIndexVertexData};
     use nalgebra::Point3;
 
     #[test]
     fn z_up_to_y_up() {
         let mut start_mesh = BlenderMesh {
-            vertex_positions: vec![0.0, 1.0, 2.0, 0.0, 1.0, 2.0],
-            vertex_normals: vec![0.0, 1.0, 2.0],
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                positions: attrib3(vec![0.0, 1.0, 2.0, 0.0, 1.0, 2.0]),
+                normals: attrib3(vec![0.0, 1.0, 2.0]),
+                ..MultiIndexVertexData::default()
+            }),
             bounding_box: BoundingBox {
                 min_corner: Point3::new(1.0, 2.0, 3.0),
                 max_corner: Point3::new(5.0, 6.0, 7.0),
@@ -63,15 +91,63 @@ mod tests {
         let y_up_mesh = start_mesh;
 
         let expected_mesh = BlenderMesh {
-            vertex_positions: vec![0.0, 2.0, -1.0, 0.0, 2.0, -1.0],
-            vertex_normals: vec![0.0, 2.0, -1.0],
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                positions: attrib3(vec![0.0, 2.0, -1.0, 0.0, 2.0, -1.0]),
+                normals: attrib3(vec![0.0, 2.0, -1.0]),
+                ..MultiIndexVertexData::default()
+            }),
             bounding_box: BoundingBox {
-                min_corner: Point3::new(1.0, 3.0, -2.0),
-                max_corner: Point3::new(5.0, 7.0, -6.0),
+                min_corner: Point3::new(1.0, 3.0, -6.0),
+                max_corner: Point3::new(5.0, 7.0, -2.0),
             },
             ..BlenderMesh::default()
         };
 
         assert_eq!(y_up_mesh, expected_mesh);
     }
+
+    /// Tangents of single indexed meshes are rotated along with the normals
+    #[test]
+    fn single_indexed_tangents_are_y_up() {
+        let mut mesh = BlenderMesh {
+            vertex_data: VertexData::Single(SingleIndexVertexData {
+                positions: attrib3(vec![0.0, 1.0, 2.0]),
+                normals: attrib3(vec![0.0, 1.0, 2.0]),
+                tangents: Some(attrib3(vec![3.0, 4.0, 5.0])),
+                ..SingleIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        mesh.y_up();
+
+        let single = mesh.single_index_vertex_data().unwrap();
+        assert_eq!(single.positions().data(), &vec![0.0, 2.0, -1.0]);
+        assert_eq!(single.tangents().unwrap().data(), &vec![3.0, 5.0, -4.0]);
+    }
+
+    /// Morph target deltas are rotated along with the positions and normals that they move
+    #[test]
+    fn morph_target_deltas_are_y_up() {
+        let mut mesh = BlenderMesh {
+            vertex_data: VertexData::Multi(MultiIndexVertexData {
+                positions: attrib3(vec![0.0, 1.0, 2.0]),
+                normals: attrib3(vec![0.0, 1.0, 2.0]),
+                morph_targets: vec![MorphTarget::new(
+                    "Smile".to_string(),
+                    0.0,
+                    vec![1.0, 2.0, 3.0],
+                    vec![4.0, 5.0, 6.0],
+                )],
+                ..MultiIndexVertexData::default()
+            }),
+            ..BlenderMesh::default()
+        };
+
+        mesh.y_up();
+
+        let target = &mesh.morph_targets()[0];
+        assert_eq!(target.position_deltas().data(), &vec![1.0, 3.0, -2.0]);
+        assert_eq!(target.normal_deltas().data(), &vec![4.0, 6.0, -5.0]);
+    }
 }
@@ -49,6 +49,8 @@ fn expected_mesh_data() -> String {
             "vertex_positions": [ 1.0, 0.99999994, -1.0, 1.0, -1.0, -1.0, -1.0000001, -0.9999998, -1.0, -0.99999964, 1.0000004, -1.0, 1.0000005, 0.99999946, 1.0, 0.99999934, -1.0000006, 1.0, -1.0000004, -0.99999964, 1.0, -0.99999994, 1.0, 1.0 ],
             "vertex_position_indices": [ 0, 1, 2, 3, 4, 7, 6, 5, 0, 4, 5, 1, 1, 5, 6, 2, 2, 6, 7, 3, 4, 0, 3, 7 ],
             "num_vertices_in_each_face": [ 4, 4, 4, 4, 4, 4 ],
+            "smooth_faces": [ false, false, false, false, false, false ],
+            "face_materials": [ 0, 0, 0, 0, 0, 0 ],
             "vertex_normals": [ 0.0, 0.0, -1.0, 0.0, -0.0, 1.0, 1.0, -0.00000028312206, 0.000000044703413, -0.00000028312206, -1.0, -0.00000010430819, -1.0, 0.00000022351745, -0.00000013411044, 0.00000023841858, 1.0, 0.00000020861626 ],
             "vertex_normal_indices": [ 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5 ],
             "armature_name": null,
@@ -65,7 +67,152 @@ fn expected_mesh_data() -> String {
                     "roughness": {"Uniform": 0.5},
                     "normal_map": "1x1-green-pixel.png"
                 }
-            }
+            },
+            "material_slots": ["Material"]
         }
     "#.to_string()
 }
+
+#[cfg(test)]
+mod mikktspace {
+    use super::*;
+    use blender_mesh::{
+        CombineIndicesError, CreateSingleIndexConfig, TangentAlgorithm, TangentError,
+    };
+    use serde_json::Value;
+    use std::fs::File;
+    use std::io::Write;
+
+    // --python-expr wasn't working in travis-ci on linux so writing the script to disk
+    // and using using --python instead
+    static UNWRAP_AND_PRINT_TANGENTS_SCRIPT: &str = "/tmp/unwrap-and-print-tangents.py";
+
+    /// Shades the fixture's cube smooth and projects each face's uvs onto the plane of the axis
+    /// that it faces. Opposite faces share a projection, so half of the faces end up with mirrored
+    /// uvs.
+    ///
+    /// Then prints the normal and the MikkTSpace tangent that Blender calculates for every
+    /// corner (loop).
+    static UNWRAP_AND_PRINT_TANGENTS: &str = r#"
+import bpy
+import json
+
+mesh = bpy.data.objects['Cube'].data
+
+for polygon in mesh.polygons:
+    polygon.use_smooth = True
+
+if not mesh.uv_layers:
+    mesh.uv_layers.new(name='UVMap')
+uvs = mesh.uv_layers.active.data
+
+for polygon in mesh.polygons:
+    normal_axis = max(range(3), key=lambda axis: abs(polygon.normal[axis]))
+    u_axis, v_axis = [axis for axis in range(3) if axis != normal_axis]
+
+    for loop_index in polygon.loop_indices:
+        position = mesh.vertices[mesh.loops[loop_index].vertex_index].co
+        uvs[loop_index].uv = (position[u_axis] * 0.25 + 0.5, position[v_axis] * 0.25 + 0.5)
+
+mesh.calc_tangents()
+
+reference = {'normals': [], 'tangents': []}
+for polygon in mesh.polygons:
+    for loop_index in polygon.loop_indices:
+        loop = mesh.loops[loop_index]
+        reference['normals'] += [loop.normal.x, loop.normal.y, loop.normal.z]
+        reference['tangents'] += [loop.tangent.x, loop.tangent.y, loop.tangent.z, loop.bitangent_sign]
+
+print('START_MIKKTSPACE_REFERENCE')
+print(json.dumps(reference))
+print('END_MIKKTSPACE_REFERENCE')
+"#;
+
+    /// The normal map fixture was exported without uvs, so it can't have MikkTSpace tangents
+    #[test]
+    fn mikktspace_tangents_need_uvs() {
+        let mut mesh: BlenderMesh = serde_json::from_str(&expected_mesh_data()).unwrap();
+
+        match mesh.combine_vertex_indices(&mikktspace_config()) {
+            Err(CombineIndicesError::Tangents(TangentError::NoVertexUvs)) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    /// Compare our tangents against the ones that Blender calculates for every corner of the
+    /// fixture's cube, once it is smooth shaded and has mirrored uvs
+    #[test]
+    fn mikktspace_tangents_match_blender() {
+        let normal_map_blend = &rel_workspace_string(
+            &"crates/blender-export-test/src/principled_material_normal_map.blend",
+        );
+        let run_addon = &rel_workspace_string(&"run-addon.py");
+
+        let mut script = File::create(UNWRAP_AND_PRINT_TANGENTS_SCRIPT).unwrap();
+        script
+            .write_all(UNWRAP_AND_PRINT_TANGENTS.as_bytes())
+            .unwrap();
+
+        let blender_output = Command::new("blender")
+            .arg(normal_map_blend)
+            .arg("--background")
+            .args(["--python", UNWRAP_AND_PRINT_TANGENTS_SCRIPT])
+            .args(["--python", run_addon])
+            .arg("-noaudio")
+            .arg("--")
+            .output()
+            .expect("Failed to execute Blender process");
+
+        let stdout = String::from_utf8(blender_output.stdout).unwrap();
+
+        let reference: Value =
+            serde_json::from_str(line_after(&stdout, "START_MIKKTSPACE_REFERENCE")).unwrap();
+        let corner_count = reference["tangents"].as_array().unwrap().len() / 4;
+
+        // Use Blender's (smooth) normals so that we start from the same tangent planes
+        let mut exported: Value =
+            serde_json::from_str(line_after(&stdout, "START_MESH_JSON")).unwrap();
+        exported["vertex_normals"] = reference["normals"].clone();
+        exported["vertex_normal_indices"] = (0..corner_count).collect();
+
+        let mut mesh: BlenderMesh = serde_json::from_value(exported).unwrap();
+        mesh.combine_vertex_indices(&mikktspace_config()).unwrap();
+
+        let single = mesh.single_index_vertex_data().unwrap();
+        let tangents = single.tangents().unwrap().data();
+
+        for (corner, vertex) in single.indices().iter().enumerate() {
+            let vertex = *vertex as usize;
+            let tangent = &tangents[vertex * 4..vertex * 4 + 4];
+
+            for component in 0..4 {
+                let expected = reference["tangents"][corner * 4 + component]
+                    .as_f64()
+                    .unwrap() as f32;
+
+                assert!(
+                    (tangent[component] - expected).abs() < 1e-4,
+                    "Corner {}: {:?} != {}",
+                    corner,
+                    tangent,
+                    reference["tangents"]
+                );
+            }
+        }
+    }
+
+    /// The line after the first line in Blender's output that starts with `marker`
+    fn line_after<'a>(stdout: &'a str, marker: &str) -> &'a str {
+        let mut lines = stdout.lines();
+        lines.find(|line| line.starts_with(marker)).unwrap();
+        lines.next().unwrap()
+    }
+
+    fn mikktspace_config() -> CreateSingleIndexConfig {
+        CreateSingleIndexConfig {
+            calculate_vertex_tangents: true,
+            tangent_algorithm: TangentAlgorithm::MikkTSpace,
+            ..CreateSingleIndexConfig::default()
+        }
+    }
+}
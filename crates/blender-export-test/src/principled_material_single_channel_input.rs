@@ -49,6 +49,8 @@ fn expected_mesh_data() -> String {
             "vertex_positions": [ 1.0, 0.99999994, -1.0, 1.0, -1.0, -1.0, -1.0000001, -0.9999998, -1.0, -0.99999964, 1.0000004, -1.0, 1.0000005, 0.99999946, 1.0, 0.99999934, -1.0000006, 1.0, -1.0000004, -0.99999964, 1.0, -0.99999994, 1.0, 1.0 ],
             "vertex_position_indices": [ 0, 1, 2, 3, 4, 7, 6, 5, 0, 4, 5, 1, 1, 5, 6, 2, 2, 6, 7, 3, 4, 0, 3, 7 ],
             "num_vertices_in_each_face": [ 4, 4, 4, 4, 4, 4 ],
+            "smooth_faces": [ false, false, false, false, false, false ],
+            "face_materials": [ 0, 0, 0, 0, 0, 0 ],
             "vertex_normals": [ 0.0, 0.0, -1.0, 0.0, -0.0, 1.0, 1.0, -0.00000028312206, 0.000000044703413, -0.00000028312206, -1.0, -0.00000010430819, -1.0, 0.00000022351745, -0.00000013411044, 0.00000023841858, 1.0, 0.00000020861626 ],
             "vertex_normal_indices": [ 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5 ],
             "armature_name": null,
@@ -64,7 +66,8 @@ fn expected_mesh_data() -> String {
                     "metallic": {"ImageTexture": ["1x1-green-pixel.png", "G"]},
                     "roughness": {"ImageTexture": ["1x1-green-pixel.png", "R"]}
                 }
-            }
+            },
+            "material_slots": ["Material"]
         }
     "#.to_string()
 }
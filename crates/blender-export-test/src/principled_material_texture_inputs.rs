@@ -49,6 +49,8 @@ fn expected_mesh_data() -> String {
             "vertex_positions": [ 1.0, 0.99999994, -1.0, 1.0, -1.0, -1.0, -1.0000001, -0.9999998, -1.0, -0.99999964, 1.0000004, -1.0, 1.0000005, 0.99999946, 1.0, 0.99999934, -1.0000006, 1.0, -1.0000004, -0.99999964, 1.0, -0.99999994, 1.0, 1.0 ],
             "vertex_position_indices": [ 0, 1, 2, 3, 4, 7, 6, 5, 0, 4, 5, 1, 1, 5, 6, 2, 2, 6, 7, 3, 4, 0, 3, 7 ],
             "num_vertices_in_each_face": [ 4, 4, 4, 4, 4, 4 ],
+            "smooth_faces": [ false, false, false, false, false, false ],
+            "face_materials": [ 0, 0, 0, 0, 0, 0 ],
             "vertex_normals": [ 0.0, 0.0, -1.0, 0.0, -0.0, 1.0, 1.0, -0.00000028312206, 0.000000044703413, -0.00000028312206, -1.0, -0.00000010430819, -1.0, 0.00000022351745, -0.00000013411044, 0.00000023841858, 1.0, 0.00000020861626 ],
             "vertex_normal_indices": [ 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5 ],
             "vertex_uvs": [0.000100158795, 0.52753544, 0.00009998002, 0.27758548, 0.25005, 0.27758533, 0.25005004, 0.5275353, 0.74995005, 0.52753514, 0.5, 0.5275352, 0.49999994, 0.27758527, 0.7499499, 0.27758518, 0.99990004, 0.5275351, 0.74995005, 0.52753514, 0.7499499, 0.27758518, 0.9998998, 0.27758515, 0.25004995, 0.52773535, 0.2500499, 0.7776853, 0.00009998002, 0.7776853, 0.00009998002, 0.52773535, 0.25005, 0.27758533, 0.49999994, 0.27758527, 0.5, 0.5275352, 0.25005004, 0.5275353, 0.5001999, 0.52773535, 0.5001999, 0.7776853, 0.25024998, 0.7776853, 0.2502499, 0.5277354],
@@ -64,7 +66,8 @@ fn expected_mesh_data() -> String {
                     "metallic": {"ImageTexture": ["metal-material.jpg", "G"]},
                     "roughness": {"ImageTexture": ["metal-material.jpg", "R"]}
                 }
-            }
+            },
+            "material_slots": ["Metal"]
         }
     "#.to_string()
 }
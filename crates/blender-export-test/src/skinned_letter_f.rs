@@ -120,6 +120,8 @@ fn expected_mesh_data() -> String {
         "vertex_positions":[0.125,0.12499999,0.0,0.125,-0.125,0.0,-0.12500001,-0.12499998,0.0,-0.124999955,0.12500004,0.0,0.12500006,0.12499993,1.0,0.12499992,-0.12500007,1.0,-0.12500004,-0.124999955,1.0,-0.12499999,0.125,1.0,0.12500004,0.12499995,0.79263926,0.12499993,-0.12500006,0.79263926,-0.12500004,-0.12499996,0.79263926,-0.124999985,0.12500001,0.79263926,0.12500006,0.12499994,0.8963196,0.124999926,-0.12500006,0.8963196,-0.12500004,-0.124999955,0.8963196,-0.124999985,0.125,0.8963196,0.12500004,0.124999955,0.66119784,0.12499995,-0.12500004,0.66119784,-0.12500004,-0.12499996,0.66119784,-0.12499998,0.12500001,0.66119784,0.12500004,0.12499996,0.5595808,0.124999955,-0.12500004,0.5595808,-0.12500004,-0.12499996,0.5595808,-0.12499998,0.12500001,0.5595808,-0.50342923,-0.12499996,0.5595808,-0.50342923,-0.12499996,0.66119784,-0.5034292,0.12500001,0.5595808,-0.5034292,0.12500001,0.66119784,-0.5135834,-0.124999836,0.8963196,-0.5135834,-0.124999836,1.0,-0.51358336,0.12500012,0.8963196,-0.51358336,0.12500012,1.0,-0.1790613,0.12500001,0.5595808,-0.23312262,0.12500001,0.5595808,-0.28718394,0.12500001,0.5595808,-0.34124523,0.12500001,0.5595808,-0.39530656,0.12500001,0.5595808,-0.44936788,0.12500001,0.5595808,-0.44936785,0.12500001,0.66119784,-0.39530653,0.12500001,0.66119784,-0.3412452,0.12500001,0.66119784,-0.28718388,0.12500001,0.66119784,-0.23312257,0.12500001,0.66119784,-0.17906128,0.12500001,0.66119784,-0.17906135,-0.12499996,0.5595808,-0.23312268,-0.12499996,0.5595808,-0.287184,-0.12499996,0.5595808,-0.3412453,-0.12499996,0.5595808,-0.39530662,-0.12499996,0.5595808,-0.44936794,-0.12499996,0.5595808,-0.4493679,-0.12499996,0.66119784,-0.3953066,-0.12499996,0.66119784,-0.3412453,-0.12499996,0.66119784,-0.28718397,-0.12499996,0.66119784,-0.23312266,-0.12499996,0.66119784,-0.17906135,-0.12499996,0.66119784,-0.18976389,0.12500001,1.0,-0.25452778,0.12500003,1.0,-0.31929168,0.12500006,1.0,-0.38405558,0.12500007,1.0,-0.44881946,0.12500009,1.0,-0.44881952,-0.12499986,1.0,-0.3840556,-0.12499988,1.0,-0.3192917,-0.124999896,1.0,-0.2545278,-0.12499992,1.0,-0.18976393,-0.12499994,1.0,-0.18976387,0.12500001,0.8963196,-0.25452778,0.12500003,0.8963196,-0.31929168,0.12500006,0.8963196,-0.38405558,0.12500007,0.8963196,-0.44881946,0.12500009,0.8963196,-0.18976393,-0.12499993,0.8963196,-0.25452784,-0.12499991,0.8963196,-0.31929174,-0.124999896,0.8963196,-0.38405564,-0.12499987,0.8963196,-0.44881952,-0.12499985,0.8963196,0.075,0.125,0.0,0.025000013,0.12500001,0.0,-0.02499998,0.12500003,0.0,-0.07499997,0.12500003,0.0,0.075,-0.12499999,0.0,0.024999999,-0.124999985,0.0,-0.025000006,-0.124999985,0.0,-0.07500001,-0.124999985,0.0,0.07500005,0.12499995,1.0,0.025000036,0.12499996,1.0,-0.024999976,0.12499998,1.0,-0.07499999,0.124999985,1.0,0.07499993,-0.12500004,1.0,0.024999935,-0.12500003,1.0,-0.025000058,-0.125,1.0,-0.07500005,-0.12499998,1.0,0.07500003,0.12499996,0.79263926,0.025000028,0.12499998,0.79263926,-0.024999976,0.12499999,0.79263926,-0.07499998,0.125,0.79263926,0.07499994,-0.12500004,0.79263926,0.024999946,-0.12500003,0.79263926,-0.02500005,-0.125,0.79263926,-0.07500005,-0.124999985,0.79263926,0.07500005,0.124999955,0.8963196,0.02500004,0.12499997,0.8963196,-0.024999969,0.12499998,0.8963196,-0.07499997,0.124999985,0.8963196,0.07499993,-0.12500004,0.8963196,0.024999935,-0.12500003,0.8963196,-0.025000058,-0.125,0.8963196,-0.07500005,-0.12499998,0.8963196,0.07499994,-0.12500003,0.66119784,0.024999946,-0.12500001,0.66119784,-0.02500005,-0.125,0.66119784,-0.07500005,-0.124999985,0.66119784,0.07500003,0.12499997,0.66119784,0.025000028,0.124999985,0.66119784,-0.024999976,0.12499999,0.66119784,-0.07499997,0.125,0.66119784,0.07499996,-0.12500003,0.5595808,0.024999958,-0.12500001,0.5595808,-0.025000047,-0.125,0.5595808,-0.07500005,-0.124999985,0.5595808,0.07500003,0.12499997,0.5595808,0.025000028,0.124999985,0.5595808,-0.024999976,0.12499999,0.5595808,-0.07499997,0.125,0.5595808,0.125,0.124999985,0.0699476,0.125,0.124999985,0.1398952,0.125,0.124999985,0.2098428,0.12500001,0.12499998,0.2797904,0.12500003,0.12499997,0.349738,0.12500003,0.12499997,0.4196856,0.12500003,0.12499997,0.4896332,0.12499999,-0.125,0.0699476,0.124999985,-0.125,0.1398952,0.12499998,-0.125,0.2098428,0.12499997,-0.12500001,0.2797904,0.12499997,-0.12500003,0.349738,0.12499996,-0.12500003,0.4196856,0.124999955,-0.12500003,0.4896332,-0.12500001,-0.12499998,0.0699476,-0.12500001,-0.12499998,0.1398952,-0.12500001,-0.12499998,0.2098428,-0.12500001,-0.12499998,0.2797904,-0.12500003,-0.12499997,0.349738,-0.12500003,-0.12499997,0.4196856,-0.12500003,-0.12499997,0.4896332,-0.124999955,0.12500004,0.0699476,-0.124999955,0.12500004,0.1398952,-0.124999955,0.12500004,0.2098428,-0.12499996,0.12500004,0.2797904,-0.12499997,0.12500003,0.349738,-0.12499997,0.12500003,0.4196856,-0.12499997,0.12500003,0.4896332,0.074999966,-0.12500003,0.4896332,0.07499997,-0.12500003,0.4196856,0.07499998,-0.12500003,0.349738,0.07499999,-0.12500003,0.2797904,0.07499999,-0.12500001,0.2098428,0.074999996,-0.125,0.1398952,0.075,-0.125,0.0699476,0.024999963,-0.12500001,0.4896332,0.024999969,-0.12500001,0.4196856,0.024999974,-0.12500001,0.349738,0.02499998,-0.12500001,0.2797904,0.024999984,-0.125,0.2098428,0.02499999,-0.12499999,0.1398952,0.024999995,-0.124999985,0.0699476,-0.025000041,-0.125,0.4896332,-0.025000036,-0.125,0.4196856,-0.02500003,-0.125,0.349738,-0.025000025,-0.125,0.2797904,-0.02500002,-0.125,0.2098428,-0.025000015,-0.12499999,0.1398952,-0.02500001,-0.124999985,0.0699476,-0.07500004,-0.124999985,0.4896332,-0.07500003,-0.124999985,0.4196856,-0.07500003,-0.124999985,0.349738,-0.075000025,-0.124999985,0.2797904,-0.07500002,-0.124999985,0.2098428,-0.07500002,-0.124999985,0.1398952,-0.07500002,-0.124999985,0.0699476,0.075,0.125,0.0699476,0.07500001,0.12499999,0.1398952,0.07500002,0.124999985,0.2098428,0.07500002,0.124999985,0.2797904,0.07500002,0.124999985,0.349738,0.075000025,0.12499998,0.4196856,0.07500003,0.12499997,0.4896332,0.025000015,0.12500001,0.0699476,0.025000017,0.12500001,0.1398952,0.025000019,0.12500001,0.2098428,0.02500002,0.12500001,0.2797904,0.025000023,0.125,0.349738,0.025000025,0.12499999,0.4196856,0.025000026,0.124999985,0.4896332,-0.02499998,0.12500003,0.0699476,-0.02499998,0.12500003,0.1398952,-0.02499998,0.12500003,0.2098428,-0.02499998,0.12500003,0.2797904,-0.02499998,0.12500001,0.349738,-0.024999978,0.125,0.4196856,-0.024999976,0.125,0.4896332,-0.07499997,0.12500003,0.0699476,-0.07499997,0.12500003,0.1398952,-0.07499997,0.12500003,0.2098428,-0.07499997,0.12500003,0.2797904,-0.07499997,0.12500003,0.349738,-0.07499997,0.12500001,0.4196856,-0.07499997,0.125,0.4896332],
         "vertex_position_indices":[79,83,2,3,87,7,6,91,12,4,5,13,107,91,6,14,61,60,31,29,201,79,3,145,103,95,11,15,18,10,11,19,111,99,10,18,16,8,9,17,87,103,15,7,10,14,15,11,99,107,14,10,8,12,13,9,20,16,17,21,119,111,18,22,38,37,26,27,95,115,19,11,144,22,23,151,173,119,22,144,130,20,21,137,115,123,23,19,24,25,27,26,37,49,24,26,50,38,27,25,49,50,25,24,28,29,31,30,60,70,30,31,70,75,28,30,75,61,29,28,22,18,55,44,44,55,54,45,45,54,53,46,46,53,52,47,47,52,51,48,48,51,50,49,18,19,43,55,55,43,42,54,54,42,41,53,53,41,40,52,52,40,39,51,51,39,38,50,23,22,44,32,32,44,45,33,33,45,46,34,34,46,47,35,35,47,48,36,36,48,49,37,19,23,32,43,43,32,33,42,42,33,34,41,41,34,35,40,40,35,36,39,39,36,37,38,14,6,65,71,71,65,64,72,72,64,63,73,73,63,62,74,74,62,61,75,15,14,71,66,66,71,72,67,67,72,73,68,68,73,74,69,69,74,75,70,7,15,66,56,56,66,67,57,57,67,68,58,58,68,69,59,59,69,70,60,6,7,56,65,65,56,57,64,64,57,58,63,63,58,59,62,62,59,60,61,16,20,120,112,112,120,121,113,113,121,122,114,114,122,123,115,137,21,116,152,152,116,117,159,159,117,118,166,166,118,119,173,8,16,112,92,92,112,113,93,93,113,114,94,94,114,115,95,21,17,108,116,116,108,109,117,117,109,110,118,118,110,111,119,9,13,104,96,96,104,105,97,97,105,106,98,98,106,107,99,4,12,100,84,84,100,101,85,85,101,102,86,86,102,103,87,17,9,96,108,108,96,97,109,109,97,98,110,110,98,99,111,12,8,92,100,100,92,93,101,101,93,94,102,102,94,95,103,124,0,76,180,180,76,77,187,187,77,78,194,194,78,79,201,13,5,88,104,104,88,89,105,105,89,90,106,106,90,91,107,4,84,88,5,84,85,89,88,85,86,90,89,86,87,91,90,0,1,80,76,76,80,81,77,77,81,82,78,78,82,83,79,122,200,207,123,200,199,206,207,199,198,205,206,198,197,204,205,197,196,203,204,196,195,202,203,195,194,201,202,121,193,200,122,193,192,199,200,192,191,198,199,191,190,197,198,190,189,196,197,189,188,195,196,188,187,194,195,120,186,193,121,186,185,192,193,185,184,191,192,184,183,190,191,183,182,189,190,182,181,188,189,181,180,187,188,20,130,186,120,130,129,185,186,129,128,184,185,128,127,183,184,127,126,182,183,126,125,181,182,125,124,180,181,82,172,179,83,172,171,178,179,171,170,177,178,170,169,176,177,169,168,175,176,168,167,174,175,167,166,173,174,81,165,172,82,165,164,171,172,164,163,170,171,163,162,169,170,162,161,168,169,161,160,167,168,160,159,166,167,80,158,165,81,158,157,164,165,157,156,163,164,156,155,162,163,155,154,161,162,154,153,160,161,153,152,159,160,1,131,158,80,131,132,157,158,132,133,156,157,133,134,155,156,134,135,154,155,135,136,153,154,136,137,152,153,0,124,131,1,124,125,132,131,125,126,133,132,126,127,134,133,127,128,135,134,128,129,136,135,129,130,137,136,83,179,138,2,179,178,139,138,178,177,140,139,177,176,141,140,176,175,142,141,175,174,143,142,174,173,144,143,2,138,145,3,138,139,146,145,139,140,147,146,140,141,148,147,141,142,149,148,142,143,150,149,143,144,151,150,123,207,151,23,207,206,150,151,206,205,149,150,205,204,148,149,204,203,147,148,203,202,146,147,202,201,145,146],
         "num_vertices_in_each_face":[4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4],
+        "smooth_faces": [false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false,false],
+        "face_materials": [0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
         "vertex_normals":[0.0,0.0,-1.0,0.0,-0.0,1.0,1.0,-5.5134296e-7,3.5930526e-8,-4.4703484e-7,-1.0,0.0,-0.0,0.0,1.0,2.9802334e-7,1.0,0.0,2.9802317e-7,1.0,1.4372209e-7,-1.0,2.5331977e-7,-2.8341834e-8,-4.4703484e-7,-0.99999994,0.0,1.0,-4.172325e-7,5.668366e-8,2.9802317e-7,1.0,3.2124385e-14,-0.99999994,2.384186e-7,0.0,-4.4703484e-7,-1.0,7.1861045e-8,1.0,-4.917383e-7,-3.5930526e-8,0.99999994,-3.7252903e-7,3.6660094e-8,-4.4703486e-7,-1.0,0.0,-0.0,1.0,0.0,2.980232e-7,1.0,1.6893046e-14,-1.0,2.5331974e-7,-1.5977486e-7,-3.725291e-7,-1.0,5.3258244e-8,1.0,-3.2782555e-7,-1.065166e-7,2.9802322e-7,1.0,0.0,-1.0,2.384186e-7,0.0,0.0,0.0,-0.99999994,-0.0,0.0,1.0,0.0,-1.0,0.0,-0.99999994,2.384186e-7,0.0,4.6016874e-7,1.0,0.0,0.0,0.0,-1.0,-2.8760547e-7,-1.0,-3.593053e-8,0.0,-1.0,0.0,0.0,-1.0,0.0,0.0,-1.0,0.0,0.0,-1.0,0.0,0.0,-1.0,0.0,0.0,-1.0,0.0,-0.0,0.0,0.99999994,-0.0,0.0,1.0,-0.0,0.0,1.0,-0.0,0.0,1.0,-0.0,0.0,1.0,-0.0,0.0,1.0,0.0,0.0,-1.0,0.0,0.0,-1.0,0.0,0.0,-1.0,0.0,0.0,-0.99999994,0.0,0.0,-1.0,0.0,0.0,-1.0,-0.0,1.0,0.0,-0.0,0.99999994,0.0,-0.0,1.0,0.0,-0.0,1.0,0.0,-0.0,1.0,0.0,-0.0,1.0,0.0,-2.8760553e-7,-1.0,-3.5930526e-8,-3.4512664e-7,-1.0,-7.1861e-8,-2.8760547e-7,-1.0,-3.5930444e-8,-2.8760547e-7,-1.0,-3.5930444e-8,-3.4512664e-7,-1.0,-7.1861e-8,0.0,0.0,-1.0,0.0,0.0,-1.0,0.0,0.0,-1.0,0.0,0.0,-1.0,0.0,0.0,-1.0,2.300844e-7,1.0,2.480116e-14,2.300844e-7,1.0,1.6534107e-14,4.6016874e-7,1.0,0.0,2.3008437e-7,1.0,0.0,2.3008447e-7,1.0,0.0,-0.0,0.0,1.0,-0.0,0.0,1.0,-0.0,0.0,1.0,-0.0,0.0,1.0,-0.0,0.0,1.0,2.2351736e-7,1.0,3.6660094e-8,2.9802322e-7,1.0,0.0,1.4901161e-7,1.0,0.0,1.4901163e-7,1.0,0.0,-1.4901163e-7,-0.99999994,-1.065166e-7,-2.9802322e-7,-1.0,-2.3808314e-14,-2.9802322e-7,-1.0,-3.174442e-14,-2.9802325e-7,-1.0,-2.3808317e-14,2.9802317e-7,1.0,5.6683664e-8,2.980232e-7,1.0,5.6683657e-8,2.2351739e-7,1.0,2.834183e-8,1.4901161e-7,1.0,4.223262e-15,-2.9802325e-7,-1.0,-3.2776682e-14,-2.9802325e-7,-1.0,-3.823946e-14,-2.9802325e-7,-1.0,-2.185112e-14,-2.9802325e-7,-1.0,-5.46278e-15,-2.9802325e-7,-1.0,-3.2124395e-14,-2.9802325e-7,-1.0,-3.7478464e-14,-5.960465e-7,-1.0,-5.354066e-14,-3.7252906e-7,-1.0,3.5930515e-8,2.9802317e-7,1.0,7.186105e-8,2.9802317e-7,1.0,7.186105e-8,2.2351736e-7,1.0,3.5930537e-8,1.4901158e-7,1.0,1.6062193e-14,-2.9802322e-7,-0.99999994,-1.1336733e-7,-2.9802322e-7,-0.99999994,-1.13367314e-7,-4.4703484e-7,-0.99999994,-5.6683657e-8,-2.9802322e-7,-0.99999994,0.0,2.9802317e-7,1.0,7.186101e-8,2.9802317e-7,1.0,7.186101e-8,2.2351739e-7,1.0,1.0779156e-7,1.490116e-7,1.0,1.4372209e-7,2.2351743e-7,1.0,5.32583e-8,2.9802328e-7,1.0,0.0,2.9802325e-7,1.0,-7.936106e-15,-0.0,0.99999994,0.0,-4.4703486e-7,-1.0,-7.1861066e-8,-2.9802325e-7,-1.0,0.0,-5.960465e-7,-1.0,0.0,-4.4703492e-7,-0.99999994,0.0,0.0,-0.0,1.0,0.0,-0.0,1.0,0.0,-0.0,1.0,0.0,-0.0,1.0,0.0,0.0,-1.0,0.0,0.0,-1.0,0.0,0.0,-1.0,0.0,0.0,-1.0,7.450581e-8,1.0,5.32583e-8,1.4901163e-7,1.0,1.065166e-7,2.9802325e-7,1.0,2.1303319e-7,1.4901163e-7,0.99999994,1.06516595e-7,-0.0,0.99999994,0.0,-0.0,0.99999994,0.0,-0.0,0.99999994,0.0,2.2351742e-7,1.0,5.3258304e-8,2.2351742e-7,1.0,5.3258297e-8,2.2351742e-7,1.0,1.597749e-7,2.9802322e-7,1.0,2.130332e-7,2.9802325e-7,1.0,-7.936106e-15,2.9802325e-7,1.0,0.0,2.9802325e-7,1.0,0.0,2.9802322e-7,1.0,0.0,2.9802322e-7,1.0,1.0651657e-7,2.9802325e-7,1.0,1.0651658e-7,4.4703486e-7,1.0,1.065166e-7,5.960465e-7,1.0,0.0,5.215407e-7,1.0,5.3258255e-8,3.725291e-7,1.0,5.3258276e-8,7.4505806e-8,1.0,5.3258297e-8,7.4505806e-8,1.0,5.3258297e-8,2.2351739e-7,1.0,5.325829e-8,2.2351742e-7,1.0,5.325828e-8,7.450582e-8,1.0,5.325829e-8,7.450583e-8,1.0,5.3258297e-8,2.2351743e-7,0.99999994,5.3258287e-8,0.0,-1.0,0.0,-7.4505806e-8,-1.0,-5.3258308e-8,-2.2351742e-7,-1.0,-5.325831e-8,-2.9802325e-7,-1.0,-2.3808317e-14,-2.9802322e-7,-1.0,-2.3808314e-14,-2.9802322e-7,-1.0,-1.587221e-14,-2.9802325e-7,-1.0,-3.1744423e-14,0.0,-1.0,0.0,0.0,-1.0,-1.0651661e-7,0.0,-1.0,-1.0651661e-7,-1.4901161e-7,-1.0,-1.06516616e-7,-2.9802322e-7,-1.0,-3.174442e-14,-2.9802322e-7,-1.0,-3.174442e-14,-2.9802322e-7,-1.0,-3.174442e-14,-2.2351742e-7,-1.0,-5.325831e-8,-2.2351742e-7,-1.0,-5.3258322e-8,-2.2351742e-7,-1.0,-1.5977493e-7,-2.9802322e-7,-1.0,-2.1303323e-7,-2.9802322e-7,-1.0,-2.3808314e-14,-2.9802322e-7,-1.0,-3.174442e-14,-2.9802322e-7,-1.0,-3.174442e-14,-7.450581e-8,-0.99999994,-5.32583e-8,0.0,-1.0,0.0,1.4901164e-7,-1.0,-1.0651658e-7,2.980233e-7,-1.0,-2.1303319e-7,1.4901165e-7,-1.0,-1.06516595e-7,0.0,-1.0,0.0,0.0,-1.0,0.0,1.0,-1.4901161e-8,5.3258297e-8,1.0,-4.4703487e-8,5.32583e-8,1.0,-7.450581e-8,5.3258297e-8,1.0,-1.3411045e-7,-5.3258326e-8,1.0,-2.0861626e-7,-1.0651663e-7,1.0,-2.5331974e-7,5.32583e-8,1.0,-2.8312206e-7,5.32583e-8,-1.4901161e-7,-1.0,-7.936105e-15,-1.4901163e-7,-1.0,0.0,-1.4901163e-7,-1.0,0.0,-1.4901163e-7,-0.99999994,-7.936106e-15,-2.2351746e-7,-1.0,5.325827e-8,-2.9802325e-7,-1.0,0.0,-2.9802325e-7,-0.99999994,-1.5872212e-14,-1.0,2.3841855e-7,0.0,-1.0,2.3841855e-7,0.0,-1.0,2.3841855e-7,0.0,-1.0,2.2351739e-7,-5.3258294e-8,-1.0,2.2351742e-7,-1.597749e-7,-1.0,2.3841858e-7,0.0,-1.0,2.3841858e-7,0.0,4.4703484e-7,1.0,1.0651662e-7,4.4703486e-7,1.0,1.065166e-7,1.4901163e-7,1.0,1.065166e-7,1.4901163e-7,0.99999994,1.065166e-7,2.980233e-7,1.0,1.5872215e-14,2.9802334e-7,1.0,0.0,2.9802334e-7,1.0,0.0],
         "vertex_normal_indices":[0,0,0,0,1,1,1,1,2,2,2,2,3,3,3,3,4,4,4,4,5,5,5,5,6,6,6,6,7,7,7,7,8,8,8,8,9,9,9,9,10,10,10,10,11,11,11,11,12,12,12,12,13,13,13,13,14,14,14,14,15,15,15,15,16,16,16,16,17,17,17,17,18,18,18,18,19,19,19,19,20,20,20,20,21,21,21,21,22,22,22,22,23,23,23,23,24,24,24,24,25,25,25,25,26,26,26,26,27,27,27,27,28,28,28,28,29,29,29,29,30,30,30,30,31,31,31,31,32,32,32,32,33,33,33,33,34,34,34,34,35,35,35,35,36,36,36,36,37,37,37,37,38,38,38,38,39,39,39,39,40,40,40,40,41,41,41,41,42,42,42,42,43,43,43,43,44,44,44,44,45,45,45,45,46,46,46,46,47,47,47,47,48,48,48,48,49,49,49,49,50,50,50,50,51,51,51,51,52,52,52,52,53,53,53,53,54,54,54,54,55,55,55,55,56,56,56,56,57,57,57,57,58,58,58,58,59,59,59,59,60,60,60,60,61,61,61,61,62,62,62,62,63,63,63,63,64,64,64,64,65,65,65,65,66,66,66,66,67,67,67,67,68,68,68,68,69,69,69,69,70,70,70,70,71,71,71,71,72,72,72,72,73,73,73,73,74,74,74,74,75,75,75,75,76,76,76,76,77,77,77,77,78,78,78,78,79,79,79,79,80,80,80,80,81,81,81,81,82,82,82,82,83,83,83,83,84,84,84,84,85,85,85,85,86,86,86,86,87,87,87,87,88,88,88,88,89,89,89,89,90,90,90,90,91,91,91,91,92,92,92,92,93,93,93,93,94,94,94,94,95,95,95,95,96,96,96,96,97,97,97,97,98,98,98,98,99,99,99,99,100,100,100,100,101,101,101,101,102,102,102,102,103,103,103,103,104,104,104,104,105,105,105,105,106,106,106,106,107,107,107,107,108,108,108,108,109,109,109,109,110,110,110,110,111,111,111,111,112,112,112,112,113,113,113,113,114,114,114,114,115,115,115,115,116,116,116,116,117,117,117,117,118,118,118,118,119,119,119,119,120,120,120,120,121,121,121,121,122,122,122,122,123,123,123,123,124,124,124,124,125,125,125,125,126,126,126,126,127,127,127,127,128,128,128,128,129,129,129,129,130,130,130,130,131,131,131,131,132,132,132,132,133,133,133,133,134,134,134,134,135,135,135,135,136,136,136,136,137,137,137,137,138,138,138,138,139,139,139,139,140,140,140,140,141,141,141,141,142,142,142,142,143,143,143,143,144,144,144,144,145,145,145,145,146,146,146,146,147,147,147,147,148,148,148,148,149,149,149,149,150,150,150,150,151,151,151,151,152,152,152,152,153,153,153,153,154,154,154,154,155,155,155,155,156,156,156,156,157,157,157,157,158,158,158,158,159,159,159,159,160,160,160,160,161,161,161,161,162,162,162,162,163,163,163,163,164,164,164,164,165,165,165,165,166,166,166,166,167,167,167,167,168,168,168,168,169,169,169,169,170,170,170,170,171,171,171,171,172,172,172,172,173,173,173,173,174,174,174,174,175,175,175,175,176,176,176,176,177,177,177,177,178,178,178,178,179,179,179,179,180,180,180,180,181,181,181,181,182,182,182,182,183,183,183,183,184,184,184,184,185,185,185,185,186,186,186,186,187,187,187,187,188,188,188,188,189,189,189,189,190,190,190,190,191,191,191,191,192,192,192,192,193,193,193,193,194,194,194,194,195,195,195,195,196,196,196,196,197,197,197,197,198,198,198,198,199,199,199,199,200,200,200,200,201,201,201,201,202,202,202,202,203,203,203,203,204,204,204,204,205,205,205,205],
         "armature_name": "LetterFArmature",
@@ -133,7 +135,8 @@ fn expected_mesh_data() -> String {
             "min_corner": [-0.5135834217071533, -0.12500007450580597, 0.0],
             "max_corner": [0.12500005960464478, 0.12500011920928955, 1.0]
         },
-        "materials": {}
+        "materials": {},
+        "material_slots": []
     }
         "#.to_string()
 }
@@ -46,6 +46,8 @@ fn expected_mesh_data() -> String {
             "vertex_positions": [ 1.0, 0.99999994, -1.0, 1.0, -1.0, -1.0, -1.0000001, -0.9999998, -1.0, -0.99999964, 1.0000004, -1.0, 1.0000005, 0.99999946, 1.0, 0.99999934, -1.0000006, 1.0, -1.0000004, -0.99999964, 1.0, -0.99999994, 1.0, 1.0 ],
             "vertex_position_indices": [ 0, 1, 2, 3, 4, 7, 6, 5, 0, 4, 5, 1, 1, 5, 6, 2, 2, 6, 7, 3, 4, 0, 3, 7 ],
             "num_vertices_in_each_face": [ 4, 4, 4, 4, 4, 4 ],
+            "smooth_faces": [ false, false, false, false, false, false ],
+            "face_materials": [ 0, 0, 0, 0, 0, 0 ],
             "vertex_normals": [ 0.0, 0.0, -1.0, 0.0, -0.0, 1.0, 1.0, -0.00000028312206, 0.000000044703413, -0.00000028312206, -1.0, -0.00000010430819, -1.0, 0.00000022351745, -0.00000013411044, 0.00000023841858, 1.0, 0.00000020861626 ],
             "vertex_normal_indices": [ 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5 ],
             "vertex_uvs": [0.33313355, 0.66646683, 0.00019979005, 0.6664669, 0.00019976028, 0.3335331, 0.33313352, 0.33353317, 0.33313367, 0.33313346, 0.00019984959, 0.33313358, 0.00019976028, 0.00019987935, 0.33313346, 0.00019976028, 0.66646695, 0.00019976028, 0.6664669, 0.33313346, 0.33353317, 0.33313352, 0.3335333, 0.00019979005, 0.66646695, 0.3335331, 0.66646683, 0.66646683, 0.33353317, 0.6664669, 0.33353317, 0.33353314, 0.66686654, 0.33313352, 0.66686654, 0.00019976028, 0.9998003, 0.00019979996, 0.9998003, 0.33313352, 0.33313355, 0.6668665, 0.33313355, 0.99980015, 0.0001998595, 0.99980015, 0.00019976028, 0.66686654],
@@ -61,7 +63,8 @@ fn expected_mesh_data() -> String {
                     "metallic": {"Uniform": 0.0},
                     "roughness": {"Uniform": 0.5}
                 }
-            }
+            },
+            "material_slots": ["Default"]
         }
     "#.to_string()
 }
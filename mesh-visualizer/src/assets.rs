@@ -4,7 +4,7 @@
 use crate::state_wrapper::{Msg, StateWrapper};
 use bincode;
 use blender_armature::BlenderArmature;
-use blender_mesh::{BlenderMesh, CreateSingleIndexConfig};
+use blender_mesh::{BlenderMesh, CreateSingleIndexConfig, IndexWidth, TangentAlgorithm};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -50,10 +50,15 @@ impl Assets {
 
             for (mesh_name, mut mesh) in meshes {
                 info!("{}", mesh_name);
+                // WebGL 1 only supports u16 indices without the OES_element_index_uint extension
                 mesh.combine_vertex_indices(&CreateSingleIndexConfig {
                     calculate_vertex_tangents: false,
+                    tangent_algorithm: TangentAlgorithm::FaceTangents,
                     bone_influences_per_vertex: None,
-                });
+                    index_width: Some(IndexWidth::U16),
+                    ..CreateSingleIndexConfig::default()
+                })
+                .unwrap();
                 mesh.triangulate();
                 mesh.y_up();
 
@@ -5,7 +5,10 @@ use crate::shader::ShaderKind::NonSkinnedNonTextured;
 use crate::shader::ShaderSystem;
 use crate::state_wrapper::State;
 use blender_armature::BlenderArmature;
-use blender_mesh::{BlenderMesh, MaterialInput};
+use blender_mesh::{
+    AttributeKind, AttributeLayout, BlenderMesh, ComponentType, IndexBuffer,
+    InterleavedVertexBuffer, MaterialInput, SingleIndexVertexData, VertexLayout,
+};
 use js_sys::WebAssembly;
 use nalgebra::Perspective3;
 use nalgebra::{Isometry3, Point3, Vector3};
@@ -65,6 +68,13 @@ struct NonSkinnedMesh<'a> {
     name: String,
 }
 
+impl<'a> NonSkinnedMesh<'a> {
+    /// Meshes have their vertex indices combined when they're loaded
+    fn vertex_data(&self) -> &SingleIndexVertexData {
+        self.blender_mesh.single_index_vertex_data().unwrap()
+    }
+}
+
 impl<'a> Renderable for NonSkinnedMesh<'a> {
     fn shader_kind(&self) -> ShaderKind {
         //        if let Some(_) = self.armature_name {
@@ -72,7 +82,7 @@ impl<'a> Renderable for NonSkinnedMesh<'a> {
         //        } else {
         //            ShaderKind::NonSkinned
         //        }
-        if let Some(_) = self.blender_mesh.vertex_uvs {
+        if let Some(_) = self.vertex_data().uvs() {
             ShaderKind::NonSkinnedWithTexture
         } else {
             ShaderKind::NonSkinnedNonTextured
@@ -92,20 +102,34 @@ impl<'a> Renderable for NonSkinnedMesh<'a> {
         gl.enable_vertex_attrib_array(pos_attrib as u32);
         gl.enable_vertex_attrib_array(normal_attrib as u32);
 
-        if let Some(ref uvs) = self.blender_mesh.vertex_uvs.as_ref() {
+        let vertex_data = self.vertex_data();
+
+        let mut layout = VertexLayout::default();
+        let mut attrib_locations = vec![
+            (AttributeKind::Position, pos_attrib as u32),
+            (AttributeKind::Normal, normal_attrib as u32),
+        ];
+
+        if let Some(_) = vertex_data.uvs() {
             let uv_attrib =
                 gl.get_attrib_location(shader.program.as_ref().unwrap(), "aTextureCoord");
             gl.enable_vertex_attrib_array(uv_attrib as u32);
 
-            GpuBufferer::buffer_f32_data(&gl, &uvs[..], uv_attrib as u32, 2);
+            layout.attributes.push(AttributeLayout {
+                attribute: AttributeKind::Uv,
+                component_type: ComponentType::F32,
+                normalized: false,
+            });
+            attrib_locations.push((AttributeKind::Uv, uv_attrib as u32));
         }
 
-        let mesh = self.blender_mesh;
-
-        GpuBufferer::buffer_f32_data(&gl, &mesh.vertex_positions[..], pos_attrib as u32, 3);
-        GpuBufferer::buffer_f32_data(&gl, &mesh.vertex_normals[..], normal_attrib as u32, 3);
+        let interleaved = vertex_data.interleave(&layout).unwrap();
+        GpuBufferer::buffer_interleaved(&gl, &interleaved, &attrib_locations);
 
-        GpuBufferer::buffer_u16_indices(&gl, &mesh.vertex_position_indices[..]);
+        match vertex_data.index_buffer() {
+            IndexBuffer::U16(indices) => GpuBufferer::buffer_u16_indices(&gl, &indices[..]),
+            IndexBuffer::U32(_) => panic!("Meshes are combined with u16 indices for WebGL 1"),
+        };
     }
 
     fn set_uniforms(
@@ -176,7 +200,7 @@ impl<'a> Renderable for NonSkinnedMesh<'a> {
             gl.get_uniform_location(shader.program.as_ref().unwrap(), "lightColor");
         gl.uniform3fv_with_f32_array(light_color_uni.as_ref(), &[1.0, 1.0, 1.0]);
 
-        let num_indices = self.blender_mesh.vertex_position_indices.len() as i32;
+        let num_indices = self.vertex_data().indices().len() as i32;
         RenderInstructions::DrawElements { num_indices }
     }
 }
@@ -268,6 +292,46 @@ impl GpuBufferer {
         gl.vertex_attrib_pointer_with_i32(attrib, size, GL::FLOAT, false, 0, 0);
     }
 
+    /// Upload an interleaved vertex buffer and point each attribute location at its attribute
+    pub fn buffer_interleaved(
+        gl: &GL,
+        interleaved: &InterleavedVertexBuffer,
+        attrib_locations: &[(AttributeKind, u32)],
+    ) {
+        let memory_buffer = wasm_bindgen::memory()
+            .dyn_into::<WebAssembly::Memory>()
+            .unwrap()
+            .buffer();
+
+        let bytes = interleaved.bytes();
+        let data_location = bytes.as_ptr() as u32;
+
+        let data_array = js_sys::Uint8Array::new(&memory_buffer)
+            .subarray(data_location, data_location + bytes.len() as u32);
+
+        let buffer = gl.create_buffer().unwrap();
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&buffer));
+        gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &data_array, GL::STATIC_DRAW);
+
+        for attribute in interleaved.attributes() {
+            let location = attrib_locations
+                .iter()
+                .find(|(kind, _)| *kind == attribute.attribute);
+
+            if let Some((_, location)) = location {
+                gl.vertex_attrib_pointer_with_i32(
+                    *location,
+                    attribute.components as i32,
+                    attribute.component_type.gl_enum(),
+                    attribute.normalized,
+                    interleaved.stride() as i32,
+                    attribute.offset as i32,
+                );
+            }
+        }
+    }
+
     pub fn buffer_u8_data(gl: &GL, data: &[u8], attrib: u32, size: i32) {
         let memory_buffer = wasm_bindgen::memory()
             .dyn_into::<WebAssembly::Memory>()